@@ -0,0 +1,196 @@
+//! 识别调试输出：保存标注了 ROI、最佳匹配位置、模板和置信度的截图，以及匹配热力图
+
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use opencv::core::{Mat, MatTraitConst, Point, Rect, Scalar, Vector, CV_8U};
+use opencv::{imgcodecs, imgproc};
+use serde::{Deserialize, Serialize};
+
+/// 调试输出配置
+///
+/// 识别失败，或成功但置信度低于 `threshold + margin` 时，在 `dir` 下保存：
+/// - `<action>-<时间戳>-<序号>.png`：截图，标出 ROI（蓝）、最佳匹配位置（成功为绿，失败为红）及置信度，
+///   左上角贴上模板
+/// - `<action>-<时间戳>-<序号>-heatmap.png`：ROI 内每个位置的匹配置信度，越红越接近模板
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct DebugOutputConfig {
+    /// 保存目录，不存在时自动创建
+    pub dir: String,
+    /// 置信度高于阈值不到这么多时也保存，默认 0 即只保存识别失败的截图
+    #[serde(default)]
+    #[cfg_attr(feature = "schema", schemars(range(min = 0.0, max = 1.0)))]
+    pub margin: f64,
+    /// 是否同时保存匹配热力图
+    #[serde(default = "default_heatmap")]
+    pub heatmap: bool,
+}
+
+fn default_heatmap() -> bool {
+    true
+}
+
+impl DebugOutputConfig {
+    /// 该次识别结果是否需要保存
+    pub fn wants(&self, matched: bool, confidence: f64, threshold: f64) -> bool {
+        !matched || confidence < threshold + self.margin
+    }
+}
+
+/// 一次匹配的全部信息，用于标注
+pub(crate) struct Annotation<'a> {
+    pub action: &'a str,
+    pub screenshot: &'a Mat,
+    pub template: &'a Mat,
+    pub roi: Option<[u64; 4]>,
+    /// `match_template` 的输出，ROI 内每个位置的置信度
+    pub scores: &'a Mat,
+    /// 最佳匹配位置，相对于 ROI
+    pub location: Point,
+    pub confidence: f64,
+    pub threshold: f64,
+    pub matched: bool,
+}
+
+/// 同一毫秒内多次保存时区分文件名
+static SEQUENCE: AtomicU64 = AtomicU64::new(0);
+
+const BLUE: (f64, f64, f64) = (255.0, 0.0, 0.0);
+const GREEN: (f64, f64, f64) = (0.0, 200.0, 0.0);
+const RED: (f64, f64, f64) = (0.0, 0.0, 255.0);
+const YELLOW: (f64, f64, f64) = (0.0, 220.0, 220.0);
+
+fn color((blue, green, red): (f64, f64, f64)) -> Scalar {
+    Scalar::new(blue, green, red, 0.0)
+}
+
+impl Annotation<'_> {
+    /// 保存标注后的截图（及热力图），返回截图路径
+    pub fn save(&self, config: &DebugOutputConfig) -> Result<PathBuf, String> {
+        let dir = Path::new(&config.dir);
+        std::fs::create_dir_all(dir)
+            .map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+        let millis = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis();
+        let sequence = SEQUENCE.fetch_add(1, Ordering::Relaxed);
+        let stem = format!("{}-{millis}-{sequence}", file_name(self.action));
+
+        let path = dir.join(format!("{stem}.png"));
+        write(&path, &self.annotate().map_err(|e| e.to_string())?)?;
+        if config.heatmap {
+            let heatmap = self.heatmap().map_err(|e| e.to_string())?;
+            write(&dir.join(format!("{stem}-heatmap.png")), &heatmap)?;
+        }
+        Ok(path)
+    }
+
+    fn annotate(&self) -> opencv::Result<Mat> {
+        let mut canvas = self.screenshot.try_clone()?;
+        let origin = match self.roi {
+            Some([x, y, width, height]) => {
+                let roi = Rect::new(x as i32, y as i32, width as i32, height as i32);
+                rectangle(&mut canvas, roi, BLUE)?;
+                Point::new(roi.x, roi.y)
+            }
+            None => Point::new(0, 0),
+        };
+
+        let found = Rect::new(
+            origin.x + self.location.x,
+            origin.y + self.location.y,
+            self.template.cols(),
+            self.template.rows(),
+        );
+        let outcome = if self.matched { GREEN } else { RED };
+        rectangle(&mut canvas, found, outcome)?;
+        let label = format!(
+            "{} {:.3} / {:.2}",
+            self.action, self.confidence, self.threshold
+        );
+        // 标在匹配位置上方，贴近顶边时标在下方
+        let baseline = if found.y >= 24 {
+            found.y - 8
+        } else {
+            found.y + found.height + 20
+        };
+        imgproc::put_text(
+            &mut canvas,
+            &label,
+            Point::new(found.x, baseline),
+            imgproc::FONT_HERSHEY_SIMPLEX,
+            0.6,
+            color(outcome),
+            2,
+            imgproc::LINE_AA,
+            false,
+        )?;
+
+        // 模板不超过截图的一半时贴在左上角，方便对照
+        let (width, height) = (self.template.cols(), self.template.rows());
+        if width * 2 <= canvas.cols() && height * 2 <= canvas.rows() {
+            let inset = Rect::new(0, 0, width, height);
+            self.template
+                .copy_to(&mut Mat::roi_mut(&mut canvas, inset)?)?;
+            rectangle(&mut canvas, inset, YELLOW)?;
+        }
+        Ok(canvas)
+    }
+
+    /// 置信度 -1..1 映射到 JET 色图，不按每张图归一化，不同截图之间可以直接比较
+    fn heatmap(&self) -> opencv::Result<Mat> {
+        let mut gray = Mat::default();
+        self.scores.convert_to(&mut gray, CV_8U, 127.5, 127.5)?;
+        let mut heatmap = Mat::default();
+        imgproc::apply_color_map(&gray, &mut heatmap, imgproc::COLORMAP_JET)?;
+        Ok(heatmap)
+    }
+}
+
+fn rectangle(canvas: &mut Mat, rect: Rect, bgr: (f64, f64, f64)) -> opencv::Result<()> {
+    imgproc::rectangle(canvas, rect, color(bgr), 2, imgproc::LINE_8, 0)
+}
+
+fn write(path: &Path, image: &Mat) -> Result<(), String> {
+    match imgcodecs::imwrite(&path.to_string_lossy(), image, &Vector::new()) {
+        Ok(true) => Ok(()),
+        Ok(false) => Err(format!("Failed to write {}", path.display())),
+        Err(e) => Err(format!("Failed to write {}: {}", path.display(), e)),
+    }
+}
+
+/// Action 名称中不适合出现在文件名里的字符替换为 `_`
+fn file_name(action: &str) -> String {
+    action
+        .chars()
+        .map(|c| match c {
+            'a'..='z' | 'A'..='Z' | '0'..='9' | '-' | '_' | '.' => c,
+            _ => '_',
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_wants() {
+        let config = DebugOutputConfig {
+            dir: "debug".to_string(),
+            margin: 0.05,
+            heatmap: true,
+        };
+        assert!(config.wants(false, 0.5, 0.8));
+        assert!(config.wants(true, 0.82, 0.8));
+        assert!(!config.wants(true, 0.9, 0.8));
+    }
+
+    #[test]
+    fn test_file_name() {
+        assert_eq!(file_name("login/button 1"), "login_button_1");
+    }
+}
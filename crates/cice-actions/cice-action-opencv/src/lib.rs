@@ -1,3 +1,24 @@
+pub mod debug_output;
 pub mod template_match;
 
+use cice_core::pipeline::action::ActionFactories;
+use cice_core::pipeline::lint::Linter;
+use cice_core::runtime::ext::ScreenshotExt;
+
+pub use debug_output::DebugOutputConfig;
 pub use template_match::{TemplateMatchAction, TemplateMatchConfig, TemplateMatchResult};
+
+/// 注册本 crate 提供的全部 Action 工厂，使 Pipeline 可以直接声明这些 Action
+pub fn register<R: ScreenshotExt>(factories: &mut ActionFactories<R>) {
+    factories.register(TemplateMatchAction::TYPE_NAME, |id, config| {
+        TemplateMatchAction::new(id.clone(), config)
+    });
+}
+
+/// 注册本 crate 提供的 Action 的配置检查，供 `cice-debug lint` 使用
+pub fn register_lints(linter: &mut Linter) {
+    linter.action(
+        TemplateMatchAction::TYPE_NAME,
+        |lint, config: TemplateMatchConfig| config.lint(lint),
+    );
+}
@@ -1,27 +1,78 @@
 use async_trait::async_trait;
-use cice_core::action::{Action, ExecError, RecognizeError};
+use cice_core::action::{Action, ExecError, Recognition, RecognizeError};
+use cice_core::pipeline::lint::{ActionLint, Rule};
 use cice_core::runtime::ext::ScreenshotExt;
-use opencv::core::{Mat, MatTraitConst};
+use opencv::core::{Mat, MatTraitConst, Point, Rect};
 use opencv::imgcodecs;
 use serde::{Deserialize, Serialize};
 
+use crate::debug_output::{Annotation, DebugOutputConfig};
+
 /// 模板匹配 Action 配置
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct TemplateMatchConfig {
     /// 模板图像路径
     pub template_path: String,
     /// 匹配置信度阈值（0.0 - 1.0）
     #[serde(default = "default_threshold")]
+    #[cfg_attr(feature = "schema", schemars(range(min = 0.0, max = 1.0)))]
     pub threshold: f64,
     /// 感兴趣区域 [x, y, width, height]
     #[serde(default)]
     pub roi: Option<[u64; 4]>,
+    /// 调试输出，识别失败或置信度接近阈值时保存标注后的截图
+    #[serde(default)]
+    pub debug: Option<DebugOutputConfig>,
 }
 
 fn default_threshold() -> f64 {
     0.8
 }
 
+impl TemplateMatchConfig {
+    /// 不运行 Pipeline 即可发现的配置问题：模板文件不存在、阈值或调试输出的 margin 超出 0..1、ROI 为空或超出屏幕
+    pub fn lint(&self, lint: &mut ActionLint<'_>) {
+        // 与 imread 一致，相对路径相对于当前工作目录
+        if !std::path::Path::new(&self.template_path).is_file() {
+            lint.report(
+                Rule::MissingFile,
+                format!("template {} does not exist", self.template_path),
+            );
+        }
+        if !(0.0..=1.0).contains(&self.threshold) {
+            lint.report(
+                Rule::OutOfRange,
+                format!("threshold {} is outside 0..1", self.threshold),
+            );
+        }
+        if let Some(debug) = &self.debug {
+            if !(0.0..=1.0).contains(&debug.margin) {
+                lint.report(
+                    Rule::OutOfRange,
+                    format!("debug margin {} is outside 0..1", debug.margin),
+                );
+            }
+        }
+        let Some([x, y, width, height]) = self.roi else {
+            return;
+        };
+        if width == 0 || height == 0 {
+            lint.report(Rule::OutOfRange, "roi is empty");
+        }
+        if let Some((screen_width, screen_height)) = lint.screen() {
+            if x.saturating_add(width) > screen_width || y.saturating_add(height) > screen_height {
+                lint.report(
+                    Rule::OutsideScreen,
+                    format!(
+                        "roi [{x}, {y}, {width}, {height}] exceeds the {screen_width}x{screen_height} screen"
+                    ),
+                );
+            }
+        }
+    }
+}
+
 /// 模板匹配结果
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TemplateMatchResult {
@@ -66,14 +117,26 @@ pub struct Size {
 ///         template_path: "/path/to/template.png".to_string(),
 ///         threshold: 0.8,
 ///         roi: None,
+///         debug: None,
 ///     });
 /// ```
 pub struct TemplateMatchAction {
-    _name: String,
+    name: String,
     config: TemplateMatchConfig,
 }
 
+/// `match_template` 的输出及其中的最佳匹配
+struct Scores {
+    /// ROI 内每个位置的置信度
+    map: Mat,
+    best: f64,
+    location: Point,
+}
+
 impl TemplateMatchAction {
+    /// 在 Pipeline 中引用该 Action 配置时使用的类型名
+    pub const TYPE_NAME: &'static str = "opencv.template_match";
+
     /// 创建新的模板匹配 Action
     ///
     /// # 参数
@@ -81,25 +144,49 @@ impl TemplateMatchAction {
     /// - `config`: 模板匹配配置
     pub fn new(name: impl Into<String>, config: TemplateMatchConfig) -> Self {
         Self {
-            _name: name.into(),
+            name: name.into(),
             config,
         }
     }
 
-    /// 执行模板匹配
-    fn template_match(
+    /// 不经过 Runtime，直接在图片 `src` 的 `roi` 内匹配模板并打分
+    ///
+    /// 与 Action 识别时的计算相同，`confidence` 即 `recognize_scored` 报告的得分。
+    pub fn template_match(
         src: Mat,
         template: &Mat,
         roi: Option<[u64; 4]>,
         threshold: f64,
     ) -> Result<TemplateMatchResult, String> {
-        // 应用 ROI（如果指定）
-        let input_roi = if let Some([x, y, width, height]) = roi {
-            let rect = opencv::core::Rect::new(x as i32, y as i32, width as i32, height as i32);
-            opencv::core::Mat::roi(&src, rect).map_err(|e| format!("Failed to apply ROI: {}", e))?
-        } else {
-            src.into()
+        let scores = Self::scores(&src, template, roi)?;
+        Ok(Self::result(&scores, template, threshold))
+    }
+
+    fn result(scores: &Scores, template: &Mat, threshold: f64) -> TemplateMatchResult {
+        TemplateMatchResult {
+            matched: scores.best > threshold,
+            position: Position {
+                x: scores.location.x,
+                y: scores.location.y,
+            },
+            size: Size {
+                width: template.cols(),
+                height: template.rows(),
+            },
+            confidence: scores.best,
+        }
+    }
+
+    /// 在 ROI 内计算每个位置的匹配置信度
+    fn scores(src: &Mat, template: &Mat, roi: Option<[u64; 4]>) -> Result<Scores, String> {
+        // 应用 ROI（如果指定），未指定时为整张截图
+        let rect = match roi {
+            Some([x, y, width, height]) => {
+                Rect::new(x as i32, y as i32, width as i32, height as i32)
+            }
+            None => Rect::new(0, 0, src.cols(), src.rows()),
         };
+        let input_roi = Mat::roi(src, rect).map_err(|e| format!("Failed to apply ROI: {}", e))?;
 
         // 执行模板匹配
         let mut result = opencv::core::Mat::default();
@@ -129,27 +216,26 @@ impl TemplateMatchAction {
         )
         .map_err(|e| format!("Failed to find match location: {}", e))?;
 
-        Ok(TemplateMatchResult {
-            matched: max_val > threshold,
-            position: Position {
-                x: max_loc.x,
-                y: max_loc.y,
-            },
-            size: Size {
-                width: template.cols(),
-                height: template.rows(),
-            },
-            confidence: max_val,
+        Ok(Scores {
+            map: result,
+            best: max_val,
+            location: max_loc,
         })
     }
-}
 
-#[async_trait]
-impl<R> Action<R> for TemplateMatchAction
-where
-    R: ScreenshotExt,
-{
-    async fn recognize(&self, runtime: &R) -> Result<(), RecognizeError> {
+    /// 匹配位置（相对于 ROI）换算为截图中模板中心的坐标
+    fn center(&self, result: &TemplateMatchResult) -> (u32, u32) {
+        let [left, top, _, _] = self.config.roi.unwrap_or_default();
+        let x = left as i64 + (result.position.x + result.size.width / 2) as i64;
+        let y = top as i64 + (result.position.y + result.size.height / 2) as i64;
+        (x.max(0) as u32, y.max(0) as u32)
+    }
+
+    /// 截图并在截图中匹配模板
+    async fn match_screen<R: ScreenshotExt>(
+        &self,
+        runtime: &R,
+    ) -> Result<TemplateMatchResult, RecognizeError> {
         // 获取屏幕截图
         let screenshot = runtime
             .screenshot()
@@ -168,19 +254,61 @@ where
                 })?;
 
         // 执行模板匹配
-        let result = Self::template_match(
-            src_mat,
-            &template_mat,
-            self.config.roi,
-            self.config.threshold,
-        )
-        .map_err(|e| RecognizeError::RecognizeFailed { reason: e })?;
+        let scores = Self::scores(&src_mat, &template_mat, self.config.roi)
+            .map_err(|e| RecognizeError::RecognizeFailed { reason: e })?;
+        let result = Self::result(&scores, &template_mat, self.config.threshold);
+
+        if let Some(debug) = &self.config.debug {
+            if debug.wants(result.matched, result.confidence, self.config.threshold) {
+                let annotation = Annotation {
+                    action: &self.name,
+                    screenshot: &src_mat,
+                    template: &template_mat,
+                    roi: self.config.roi,
+                    scores: &scores.map,
+                    location: scores.location,
+                    confidence: result.confidence,
+                    threshold: self.config.threshold,
+                    matched: result.matched,
+                };
+                // 调试输出失败不影响识别结果
+                match annotation.save(debug) {
+                    Ok(path) => log::info!("{}: saved {}", self.name, path.display()),
+                    Err(e) => log::warn!("{}: debug output failed: {}", self.name, e),
+                }
+            }
+        }
+        Ok(result)
+    }
+}
+
+#[async_trait]
+impl<R> Action<R> for TemplateMatchAction
+where
+    R: ScreenshotExt,
+{
+    async fn recognize(&self, runtime: &R) -> Result<(), RecognizeError> {
+        self.recognize_scored(runtime).await.result
+    }
 
-        // 检查是否匹配
-        if result.matched {
-            Ok(())
-        } else {
-            Err(RecognizeError::UnRecognized)
+    /// 匹配置信度作为识别得分，供任务守卫读取；匹配成功时模板中心作为识别位置，供 `"at": "match"` 的输入 Action 使用
+    async fn recognize_scored(&self, runtime: &R) -> Recognition {
+        match self.match_screen(runtime).await {
+            // 检查是否匹配
+            Ok(result) => Recognition {
+                result: if result.matched {
+                    Ok(())
+                } else {
+                    Err(RecognizeError::UnRecognized)
+                },
+                score: Some(result.confidence),
+                location: result.matched.then(|| self.center(&result)),
+            },
+            Err(err) => Recognition {
+                result: Err(err),
+                score: None,
+                location: None,
+            },
         }
     }
 
@@ -200,6 +328,7 @@ mod tests {
             template_path: "test.png".to_string(),
             threshold: default_threshold(),
             roi: None,
+            debug: None,
         };
 
         assert_eq!(config.threshold, 0.8);
@@ -214,13 +343,37 @@ mod tests {
                 template_path: "template.png".to_string(),
                 threshold: 0.9,
                 roi: None,
+                debug: None,
             },
         );
-        assert_eq!(action._name, "test");
+        assert_eq!(action.name, "test");
         assert_eq!(action.config.template_path, "template.png");
         assert_eq!(action.config.threshold, 0.9);
     }
 
+    #[test]
+    fn test_center_includes_roi_offset() {
+        let action = TemplateMatchAction::new(
+            "test",
+            TemplateMatchConfig {
+                template_path: "template.png".to_string(),
+                threshold: 0.8,
+                roi: Some([100, 50, 400, 300]),
+                debug: None,
+            },
+        );
+        let result = TemplateMatchResult {
+            matched: true,
+            position: Position { x: 10, y: 20 },
+            size: Size {
+                width: 30,
+                height: 40,
+            },
+            confidence: 0.9,
+        };
+        assert_eq!(action.center(&result), (125, 90));
+    }
+
     const BASE_DIR: &str = concat!(
         env!("CARGO_MANIFEST_DIR"),
         "/tests/resource/template_match/",
@@ -0,0 +1,124 @@
+use std::collections::VecDeque;
+
+use base64::prelude::{Engine, BASE64_STANDARD};
+use cice_core::message::Message;
+use serde_json::Value;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader, Lines, ReadHalf, WriteHalf};
+use tokio::net::TcpStream;
+
+use crate::server::Connection;
+use crate::{Command, ControlError, Endpoint, Outcome, Request, Screenshot, ServerMessage};
+
+/// Client of a [`ControlServer`](crate::ControlServer).
+///
+/// Events that arrive while waiting for a reply are kept for [`ControlClient::next_event`].
+pub struct ControlClient {
+    lines: Lines<BufReader<ReadHalf<Box<dyn Connection>>>>,
+    writer: WriteHalf<Box<dyn Connection>>,
+    next_id: u64,
+    events: VecDeque<Message>,
+}
+
+impl ControlClient {
+    pub async fn connect(endpoint: &Endpoint) -> Result<Self, ControlError> {
+        let connect_error = |source| ControlError::Connect {
+            endpoint: endpoint.clone(),
+            source,
+        };
+        let connection: Box<dyn Connection> = match endpoint {
+            Endpoint::Tcp(address) => {
+                Box::new(TcpStream::connect(address).await.map_err(connect_error)?)
+            }
+            #[cfg(unix)]
+            Endpoint::Unix(path) => Box::new(
+                tokio::net::UnixStream::connect(path)
+                    .await
+                    .map_err(connect_error)?,
+            ),
+            #[cfg(not(unix))]
+            Endpoint::Unix(_) => {
+                return Err(connect_error(std::io::Error::new(
+                    std::io::ErrorKind::Unsupported,
+                    "unix sockets are not supported on this platform",
+                )))
+            }
+        };
+        let (reader, writer) = tokio::io::split(connection);
+        Ok(Self {
+            lines: BufReader::new(reader).lines(),
+            writer,
+            next_id: 1,
+            events: VecDeque::new(),
+        })
+    }
+
+    /// Sends `command` and waits for its result.
+    pub async fn request(&mut self, command: Command) -> Result<Value, ControlError> {
+        let id = self.next_id;
+        self.next_id += 1;
+        let request = Request {
+            id: Some(id),
+            command,
+        };
+        let mut line =
+            serde_json::to_string(&request).map_err(|source| ControlError::Protocol { source })?;
+        line.push('\n');
+        self.writer
+            .write_all(line.as_bytes())
+            .await
+            .map_err(|source| ControlError::Io { source })?;
+        loop {
+            match self.receive().await? {
+                ServerMessage::Event { event } => self.events.push_back(event),
+                ServerMessage::Reply(reply) if reply.id == Some(id) => {
+                    return match reply.outcome {
+                        Outcome::Result(result) => Ok(result),
+                        Outcome::Error(message) => Err(ControlError::Remote { message }),
+                    }
+                }
+                ServerMessage::Reply(reply) => {
+                    log::warn!("ignoring the reply to another request: {reply:?}")
+                }
+            }
+        }
+    }
+
+    /// The screenshot of [`Command::Screenshot`], decoded.
+    pub async fn screenshot(&mut self) -> Result<Vec<u8>, ControlError> {
+        let result = self.request(Command::Screenshot).await?;
+        let screenshot: Screenshot =
+            serde_json::from_value(result).map_err(|source| ControlError::Protocol { source })?;
+        BASE64_STANDARD
+            .decode(screenshot.data)
+            .map_err(|err| ControlError::Remote {
+                message: format!("invalid screenshot: {err}"),
+            })
+    }
+
+    /// Waits for the next message of the context, `None` once the server disconnects.
+    pub async fn next_event(&mut self) -> Result<Option<Message>, ControlError> {
+        if let Some(event) = self.events.pop_front() {
+            return Ok(Some(event));
+        }
+        loop {
+            match self.receive().await {
+                Ok(ServerMessage::Event { event }) => return Ok(Some(event)),
+                Ok(ServerMessage::Reply(reply)) => {
+                    log::warn!("ignoring a reply without request: {reply:?}")
+                }
+                Err(ControlError::Closed) => return Ok(None),
+                Err(err) => return Err(err),
+            }
+        }
+    }
+
+    async fn receive(&mut self) -> Result<ServerMessage, ControlError> {
+        let line = self
+            .lines
+            .next_line()
+            .await
+            .map_err(|source| ControlError::Io { source })?
+            .ok_or(ControlError::Closed)?;
+        serde_json::from_str(&line).map_err(|source| ControlError::Protocol { source })
+    }
+}
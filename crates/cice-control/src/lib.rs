@@ -0,0 +1,200 @@
+//! Remote control of a running [`Context`] over a local socket.
+//!
+//! A [`ControlServer`] exposes the [`ContextHandler`] of a context on a TCP or Unix socket, so a
+//! debugger in another process can follow and steer the run. The protocol is JSON lines: each
+//! line a client sends is a [`Request`], and each line the server sends is a [`ServerMessage`],
+//! either the reply to a request or an event of the context.
+//!
+//! ```text
+//! > {"id": 1, "command": "set-breakpoint", "task": "login", "stage": "before-exec"}
+//! < {"id": 1, "result": null}
+//! < {"event": {"TaskMessage": {"Enter": {"id": "start"}}}}
+//! < {"event": {"Halted": {"stage": "before-exec", "task": "login", "candidates": []}}}
+//! > {"id": 2, "command": "screenshot"}
+//! < {"id": 2, "result": {"data": "iVBORw0KGgo..."}}
+//! > {"id": 3, "command": "step-task"}
+//! < {"id": 3, "result": null}
+//! ```
+//!
+//! Requests without an `id` get a reply without one. A request that can't be parsed or carried
+//! out gets `{"id": ..., "error": "..."}`.
+//!
+//! [`Context`]: cice_core::context::Context
+//! [`ContextHandler`]: cice_core::context::ContextHandler
+
+use std::fmt;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use cice_core::debug::{Breakpoint, Halt, Stage};
+use cice_core::graph::Edge;
+use cice_core::message::Message;
+use cice_core::task::{TaskConfig, TaskId};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use snafu::Snafu;
+
+mod client;
+mod server;
+
+pub use client::ControlClient;
+pub use server::{ControlServer, Listener};
+
+/// Where a [`ControlServer`] listens, written `tcp:HOST:PORT` or `unix:PATH`; `HOST:PORT` alone
+/// is a TCP address.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Endpoint {
+    Tcp(String),
+    Unix(PathBuf),
+}
+
+impl FromStr for Endpoint {
+    type Err = ControlError;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let endpoint = match text.split_once(':') {
+            Some(("unix", path)) => Self::Unix(path.into()),
+            Some(("tcp", address)) => Self::Tcp(address.to_string()),
+            _ => Self::Tcp(text.to_string()),
+        };
+        let valid = match &endpoint {
+            Self::Tcp(address) => address.contains(':'),
+            Self::Unix(path) => !path.as_os_str().is_empty(),
+        };
+        match valid {
+            true => Ok(endpoint),
+            false => Err(ControlError::InvalidEndpoint {
+                text: text.to_string(),
+            }),
+        }
+    }
+}
+
+impl fmt::Display for Endpoint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Tcp(address) => write!(f, "tcp:{address}"),
+            Self::Unix(path) => write!(f, "unix:{}", path.display()),
+        }
+    }
+}
+
+/// A line sent by a client.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Request {
+    /// Echoed in the reply
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<u64>,
+    #[serde(flatten)]
+    pub command: Command,
+}
+
+/// What a client asks of the context; each replies `null` unless noted.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "command", rename_all = "kebab-case")]
+pub enum Command {
+    /// Cancels the run, halted or not
+    Cancel,
+    Pause,
+    Resume,
+    StepRound,
+    StepTask,
+    /// Replies a [`Status`]
+    Status,
+    /// Replies a [`Graph`]
+    Graph,
+    /// Replies a [`Screenshot`] of the screen the context runs on
+    Screenshot,
+    SetBreakpoint {
+        task: TaskId,
+        stage: Stage,
+    },
+    /// Replies whether the breakpoint was set
+    RemoveBreakpoint {
+        task: TaskId,
+        stage: Stage,
+    },
+    /// Replies the variable of the blackboard, `null` when unset
+    GetVar {
+        name: String,
+    },
+    SetVar {
+        name: String,
+        value: Value,
+    },
+}
+
+/// A line sent by the server.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum ServerMessage {
+    /// A message of the context, sent to every client as it happens
+    Event {
+        event: Message,
+    },
+    Reply(Reply),
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Reply {
+    /// `id` of the request, if it had one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<u64>,
+    #[serde(flatten)]
+    pub outcome: Outcome,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Outcome {
+    Result(Value),
+    Error(String),
+}
+
+/// Reply to [`Command::Status`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Status {
+    pub paused: bool,
+    pub halted: Option<Halt>,
+    pub breakpoints: Vec<Breakpoint>,
+}
+
+/// Reply to [`Command::Graph`], see [`cice_core::graph::TaskGraph`].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Graph {
+    pub tasks: Vec<TaskConfig>,
+    pub entries: Vec<TaskId>,
+    pub edges: Vec<Edge>,
+}
+
+/// Reply to [`Command::Screenshot`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Screenshot {
+    /// The encoded image returned by the runtime, in base64
+    pub data: String,
+}
+
+#[derive(Debug, Snafu)]
+pub enum ControlError {
+    #[snafu(display("invalid endpoint `{text}`, expected tcp:HOST:PORT or unix:PATH"))]
+    InvalidEndpoint { text: String },
+    #[snafu(display("can't listen on {endpoint}: {source}"))]
+    Bind {
+        endpoint: Endpoint,
+        source: std::io::Error,
+    },
+    #[snafu(display("can't connect to {endpoint}: {source}"))]
+    Connect {
+        endpoint: Endpoint,
+        source: std::io::Error,
+    },
+    #[snafu(display("{source}"))]
+    Io { source: std::io::Error },
+    #[snafu(display("invalid line from the server: {source}"))]
+    Protocol { source: serde_json::Error },
+    #[snafu(display("the server closed the connection"))]
+    Closed,
+    /// The server couldn't carry out a request
+    #[snafu(display("{message}"))]
+    Remote { message: String },
+}
@@ -0,0 +1,278 @@
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use base64::prelude::{Engine, BASE64_STANDARD};
+use cice_core::context::ContextHandler;
+use cice_core::graph::TaskGraph;
+use cice_core::message::Message;
+use cice_core::runtime::ext::ScreenshotExt;
+use serde::Serialize;
+use serde_json::Value;
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader};
+use tokio::net::TcpListener;
+use tokio::sync::broadcast;
+
+use crate::{
+    Command, ControlError, Endpoint, Graph, Outcome, Reply, Request, Screenshot, ServerMessage,
+    Status,
+};
+
+/// Events buffered for each client; a client that falls further behind misses some.
+const EVENT_BUFFER: usize = 1024;
+
+/// A stream a client and the server talk over.
+pub(crate) trait Connection: AsyncRead + AsyncWrite + Unpin + Send {}
+
+impl<STREAM: AsyncRead + AsyncWrite + Unpin + Send> Connection for STREAM {}
+
+/// Socket a [`ControlServer`] accepts clients on.
+pub struct Listener(Inner);
+
+enum Inner {
+    Tcp(TcpListener),
+    #[cfg(unix)]
+    Unix(tokio::net::UnixListener, PathBuf),
+}
+
+impl Listener {
+    pub async fn bind(endpoint: &Endpoint) -> Result<Self, ControlError> {
+        let bind_error = |source| ControlError::Bind {
+            endpoint: endpoint.clone(),
+            source,
+        };
+        let inner = match endpoint {
+            Endpoint::Tcp(address) => {
+                Inner::Tcp(TcpListener::bind(address).await.map_err(bind_error)?)
+            }
+            #[cfg(unix)]
+            Endpoint::Unix(path) => Inner::Unix(
+                tokio::net::UnixListener::bind(path).map_err(bind_error)?,
+                path.clone(),
+            ),
+            #[cfg(not(unix))]
+            Endpoint::Unix(_) => {
+                return Err(bind_error(std::io::Error::new(
+                    std::io::ErrorKind::Unsupported,
+                    "unix sockets are not supported on this platform",
+                )))
+            }
+        };
+        Ok(Self(inner))
+    }
+
+    /// Where clients connect, with the port the system chose when bound to port 0.
+    pub fn endpoint(&self) -> Endpoint {
+        match &self.0 {
+            Inner::Tcp(listener) => match listener.local_addr() {
+                Ok(address) => Endpoint::Tcp(address.to_string()),
+                Err(_) => Endpoint::Tcp(String::new()),
+            },
+            #[cfg(unix)]
+            Inner::Unix(_, path) => Endpoint::Unix(path.clone()),
+        }
+    }
+
+    async fn accept(&self) -> std::io::Result<Box<dyn Connection>> {
+        Ok(match &self.0 {
+            Inner::Tcp(listener) => Box::new(listener.accept().await?.0),
+            #[cfg(unix)]
+            Inner::Unix(listener, _) => Box::new(listener.accept().await?.0),
+        })
+    }
+}
+
+impl Drop for Listener {
+    fn drop(&mut self) {
+        // The socket file outlives the listener otherwise, and binding it again would fail
+        #[cfg(unix)]
+        if let Inner::Unix(_, path) = &self.0 {
+            let _ = std::fs::remove_file(path);
+        }
+    }
+}
+
+/// Serves the control protocol of the [crate documentation](crate) for a context.
+///
+/// The server doesn't receive the messages of the context itself, so the application can still
+/// handle them: it passes each one to [`ControlServer::publish`] for the clients to see. Clones
+/// share the same clients.
+///
+/// ```no_run
+/// # use cice_control::{ControlServer, Endpoint, Listener};
+/// # use cice_core::context::Context;
+/// # use cice_core::runtime::ext::TimerExt;
+/// # async fn run<RUNTIME: TimerExt>(context: Context<'_, RUNTIME>) -> Result<(), Box<dyn std::error::Error>> {
+/// let handler = context.get_handler();
+/// let server = ControlServer::new(handler.clone(), context.task_graph());
+/// let listener = Listener::bind(&"unix:/tmp/cice.sock".parse::<Endpoint>()?).await?;
+/// tokio::spawn(server.clone().serve(listener));
+/// let mut run = std::pin::pin!(context.run("start".to_string()));
+/// loop {
+///     tokio::select! {
+///         Ok(message) = handler.recv() => server.publish(&message),
+///         _ = &mut run => break,
+///     }
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct ControlServer {
+    handler: ContextHandler,
+    graph: Arc<TaskGraph>,
+    screenshots: Option<Arc<dyn ScreenshotExt>>,
+    events: broadcast::Sender<Arc<str>>,
+}
+
+impl ControlServer {
+    pub fn new(handler: ContextHandler, graph: TaskGraph) -> Self {
+        Self {
+            handler,
+            graph: Arc::new(graph),
+            screenshots: None,
+            events: broadcast::channel(EVENT_BUFFER).0,
+        }
+    }
+
+    /// Answers [`Command::Screenshot`] with screenshots of `runtime`, usually a clone of the
+    /// runtime of the context.
+    pub fn screenshots(mut self, runtime: impl ScreenshotExt + 'static) -> Self {
+        self.screenshots = Some(Arc::new(runtime));
+        self
+    }
+
+    /// Sends `message` to every connected client.
+    pub fn publish(&self, message: &Message) {
+        if self.events.receiver_count() == 0 {
+            return;
+        }
+        let event = ServerMessage::Event {
+            event: message.clone(),
+        };
+        match serde_json::to_string(&event) {
+            Ok(line) => {
+                let _ = self.events.send(line.into());
+            }
+            Err(err) => log::warn!("can't send {message:?} to control clients: {err}"),
+        }
+    }
+
+    /// Accepts clients on `listener` until accepting fails, serving each on a task of its own.
+    pub async fn serve(self, listener: Listener) -> Result<(), ControlError> {
+        loop {
+            let connection = listener
+                .accept()
+                .await
+                .map_err(|source| ControlError::Io { source })?;
+            let server = self.clone();
+            tokio::spawn(async move {
+                if let Err(err) = server.connection(connection).await {
+                    log::warn!("control client disconnected: {err}");
+                }
+            });
+        }
+    }
+
+    async fn connection(self, connection: Box<dyn Connection>) -> std::io::Result<()> {
+        let (reader, mut writer) = tokio::io::split(connection);
+        let mut lines = BufReader::new(reader).lines();
+        let mut events = self.events.subscribe();
+        loop {
+            let line: Arc<str> = tokio::select! {
+                line = lines.next_line() => match line? {
+                    Some(line) if line.trim().is_empty() => continue,
+                    Some(line) => {
+                        let reply = ServerMessage::Reply(self.reply(&line).await);
+                        serde_json::to_string(&reply)?.into()
+                    }
+                    None => return Ok(()),
+                },
+                event = events.recv() => match event {
+                    Ok(line) => line,
+                    Err(broadcast::error::RecvError::Lagged(missed)) => {
+                        log::warn!("control client missed {missed} events");
+                        continue;
+                    }
+                    Err(broadcast::error::RecvError::Closed) => return Ok(()),
+                },
+            };
+            writer.write_all(line.as_bytes()).await?;
+            writer.write_all(b"\n").await?;
+        }
+    }
+
+    async fn reply(&self, line: &str) -> Reply {
+        match serde_json::from_str::<Request>(line) {
+            Ok(Request { id, command }) => Reply {
+                id,
+                outcome: match self.execute(command).await {
+                    Ok(result) => Outcome::Result(result),
+                    Err(message) => Outcome::Error(message),
+                },
+            },
+            Err(err) => Reply {
+                // Still tell the client which request failed when its id can be read
+                id: serde_json::from_str::<Value>(line)
+                    .ok()
+                    .and_then(|request| request.get("id")?.as_u64()),
+                outcome: Outcome::Error(format!("invalid request: {err}")),
+            },
+        }
+    }
+
+    async fn execute(&self, command: Command) -> Result<Value, String> {
+        let handler = &self.handler;
+        match command {
+            Command::Cancel => {
+                let _ = handler.try_cancel();
+                // A paused context only notices the cancellation between rounds
+                handler.resume();
+            }
+            Command::Pause => handler.pause(),
+            Command::Resume => handler.resume(),
+            Command::StepRound => handler.step_round(),
+            Command::StepTask => handler.step_task(),
+            Command::Status => {
+                return to_value(Status {
+                    paused: handler.is_paused(),
+                    halted: handler.halted(),
+                    breakpoints: handler.breakpoints(),
+                })
+            }
+            Command::Graph => {
+                let graph = &self.graph;
+                return to_value(Graph {
+                    tasks: graph.tasks().cloned().collect(),
+                    entries: graph.entries().into_iter().map(str::to_string).collect(),
+                    edges: graph.edges(),
+                });
+            }
+            Command::Screenshot => {
+                let runtime = self
+                    .screenshots
+                    .as_ref()
+                    .ok_or("this context doesn't share its screen")?;
+                let data = runtime
+                    .screenshot()
+                    .await
+                    .ok_or("the runtime has no screenshot")?;
+                return to_value(Screenshot {
+                    data: BASE64_STANDARD.encode(data),
+                });
+            }
+            Command::SetBreakpoint { task, stage } => handler.set_breakpoint(task, stage),
+            Command::RemoveBreakpoint { task, stage } => {
+                return Ok(handler.remove_breakpoint(&task, stage).into())
+            }
+            Command::GetVar { name } => {
+                return Ok(handler.blackboard().get(&name).unwrap_or_default())
+            }
+            Command::SetVar { name, value } => handler.blackboard().set(name, value),
+        }
+        Ok(Value::Null)
+    }
+}
+
+fn to_value(reply: impl Serialize) -> Result<Value, String> {
+    serde_json::to_value(reply).map_err(|err| err.to_string())
+}
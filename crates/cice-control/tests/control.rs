@@ -0,0 +1,267 @@
+use std::pin::pin;
+use std::sync::mpsc;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use cice_control::{
+    Command, ControlClient, ControlServer, Endpoint, Graph, Listener, ServerMessage, Status,
+};
+use cice_core::context::ContextBuilder;
+use cice_core::debug::{Breakpoint, Stage};
+use cice_core::graph::EdgeKind;
+use cice_core::message::task::TaskMessage;
+use cice_core::message::Message;
+use cice_core::runtime::ext::ScreenshotExt;
+use cice_core::runtime::Runtime;
+use cice_core::task::{TaskConfig, TaskResult};
+use cice_tests_common::action::{ScriptedAction, TestRuntime};
+use cice_tests_common::harness;
+use futures::FutureExt;
+use serde_json::json;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+
+const SCREEN: &[u8] = b"\x89PNG not really";
+
+/// Runtime whose screen never changes.
+struct Screen;
+
+impl Runtime for Screen {}
+
+#[async_trait]
+impl ScreenshotExt for Screen {
+    async fn screenshot(&self) -> Option<Vec<u8>> {
+        Some(SCREEN.to_vec())
+    }
+}
+
+fn task_config(name: &str, next_task: &[&str]) -> TaskConfig {
+    TaskConfig {
+        task_name: name.to_string(),
+        action_name: name.to_string(),
+        next_task: next_task.iter().map(|id| id.to_string()).collect(),
+        interrupt_task: vec![],
+        guards: Default::default(),
+        timeout: Duration::from_secs(10),
+        max_retry: 20,
+    }
+}
+
+async fn serve(server: ControlServer, endpoint: &str) -> Endpoint {
+    let listener = Listener::bind(&endpoint.parse().unwrap()).await.unwrap();
+    let endpoint = listener.endpoint();
+    tokio::spawn(server.serve(listener));
+    endpoint
+}
+
+#[test]
+fn endpoints_parse() {
+    assert_eq!(
+        "unix:/tmp/cice.sock".parse::<Endpoint>().unwrap(),
+        Endpoint::Unix("/tmp/cice.sock".into())
+    );
+    for text in ["tcp:127.0.0.1:7000", "127.0.0.1:7000"] {
+        let endpoint = text.parse::<Endpoint>().unwrap();
+        assert_eq!(endpoint, Endpoint::Tcp("127.0.0.1:7000".to_string()));
+        assert_eq!(endpoint.to_string(), "tcp:127.0.0.1:7000");
+    }
+    assert!("unix:".parse::<Endpoint>().is_err());
+    assert!("localhost".parse::<Endpoint>().is_err());
+}
+
+#[tokio::test]
+async fn commands_drive_the_handler() {
+    let (entry, login) = (ScriptedAction::new("entry"), ScriptedAction::new("login"));
+    let mut builder = ContextBuilder::new(TestRuntime::new());
+    builder
+        .add_task(task_config("entry", &["login"]), &entry)
+        .unwrap();
+    builder.add_task(task_config("login", &[]), &login).unwrap();
+    let context = builder.build();
+    let handler = context.get_handler();
+    let server = ControlServer::new(handler.clone(), context.task_graph()).screenshots(Screen);
+    let mut client = ControlClient::connect(&serve(server, "127.0.0.1:0").await)
+        .await
+        .unwrap();
+
+    client.request(Command::Pause).await.unwrap();
+    assert!(handler.is_paused());
+    let breakpoint = Command::SetBreakpoint {
+        task: "login".to_string(),
+        stage: Stage::AfterExec,
+    };
+    client.request(breakpoint).await.unwrap();
+    let status: Status =
+        serde_json::from_value(client.request(Command::Status).await.unwrap()).unwrap();
+    assert_eq!(
+        status,
+        Status {
+            paused: true,
+            halted: None,
+            breakpoints: vec![Breakpoint {
+                task: "login".to_string(),
+                stage: Stage::AfterExec
+            }],
+        }
+    );
+    let remove = Command::RemoveBreakpoint {
+        task: "login".to_string(),
+        stage: Stage::AfterExec,
+    };
+    assert_eq!(client.request(remove.clone()).await.unwrap(), json!(true));
+    assert_eq!(client.request(remove).await.unwrap(), json!(false));
+
+    let set = Command::SetVar {
+        name: "mode".to_string(),
+        value: json!("shop"),
+    };
+    client.request(set).await.unwrap();
+    assert_eq!(handler.blackboard().get("mode"), Some(json!("shop")));
+    let get = |name: &str| Command::GetVar {
+        name: name.to_string(),
+    };
+    assert_eq!(client.request(get("mode")).await.unwrap(), json!("shop"));
+    assert_eq!(client.request(get("unset")).await.unwrap(), json!(null));
+
+    let graph: Graph =
+        serde_json::from_value(client.request(Command::Graph).await.unwrap()).unwrap();
+    assert_eq!(graph.entries, ["entry"]);
+    assert_eq!(graph.tasks.len(), 2);
+    assert!(graph
+        .edges
+        .iter()
+        .any(|edge| edge.from == "entry" && edge.to == "login" && edge.kind == EdgeKind::Next));
+
+    assert_eq!(client.screenshot().await.unwrap(), SCREEN);
+}
+
+#[tokio::test]
+async fn invalid_requests_get_errors() {
+    let context = ContextBuilder::new(TestRuntime::new()).build();
+    let server = ControlServer::new(context.get_handler(), context.task_graph());
+    let Endpoint::Tcp(address) = serve(server, "127.0.0.1:0").await else {
+        unreachable!()
+    };
+    let mut stream = BufReader::new(tokio::net::TcpStream::connect(address).await.unwrap());
+
+    assert_eq!(
+        reply(&mut stream, r#"{"command": "resume"}"#).await,
+        json!({ "result": null })
+    );
+    let unknown = reply(&mut stream, r#"{"id": 7, "command": "explode"}"#).await;
+    assert_eq!(unknown["id"], 7);
+    assert!(unknown["error"]
+        .as_str()
+        .unwrap()
+        .starts_with("invalid request: unknown variant `explode`"));
+    let screenshot = reply(&mut stream, r#"{"id": 8, "command": "screenshot"}"#).await;
+    assert_eq!(
+        screenshot,
+        json!({ "id": 8, "error": "this context doesn't share its screen" })
+    );
+    assert!(reply(&mut stream, "not json").await["error"].is_string());
+}
+
+/// Sends a raw request line and returns the reply as JSON.
+async fn reply(stream: &mut BufReader<tokio::net::TcpStream>, line: &str) -> serde_json::Value {
+    stream
+        .write_all(format!("{line}\n").as_bytes())
+        .await
+        .unwrap();
+    let mut reply = String::new();
+    stream.read_line(&mut reply).await.unwrap();
+    match serde_json::from_str(&reply).unwrap() {
+        ServerMessage::Reply(reply) => serde_json::to_value(reply).unwrap(),
+        ServerMessage::Event { event } => panic!("unexpected event {event:?}"),
+    }
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn clients_follow_events_and_steer_halted_runs() {
+    let socket = std::env::temp_dir().join(format!("cice-control-{}.sock", std::process::id()));
+    let (handler_sender, handler_receiver) = mpsc::channel();
+    let (server_sender, server_receiver) = mpsc::channel::<ControlServer>();
+    // The context runs in virtual time, so on a thread of its own
+    let run = std::thread::spawn(move || {
+        let runtime = TestRuntime::new();
+        let clock = runtime.clock().clone();
+        let (entry, login, home) = (
+            ScriptedAction::new("entry"),
+            ScriptedAction::new("login"),
+            ScriptedAction::new("home"),
+        );
+        let mut builder = ContextBuilder::new(runtime);
+        builder
+            .add_task(task_config("entry", &["login"]), &entry)
+            .unwrap();
+        builder
+            .add_task(task_config("login", &["home"]), &login)
+            .unwrap();
+        builder.add_task(task_config("home", &[]), &home).unwrap();
+        let context = builder.build();
+        let handler = context.get_handler();
+        handler_sender
+            .send((handler.clone(), context.task_graph()))
+            .unwrap();
+        let server = server_receiver.recv().unwrap();
+        let result = harness::block_on(&clock, async {
+            let mut run = pin!(context.run("entry".to_string()).fuse());
+            loop {
+                futures::select_biased! {
+                    message = handler.recv().fuse() => {
+                        if let Ok(message) = message {
+                            server.publish(&message);
+                        }
+                    },
+                    result = run => break result,
+                }
+            }
+        });
+        while let Ok(message) = handler.try_recv() {
+            server.publish(&message);
+        }
+        (result, login.exec_count())
+    });
+
+    let (handler, graph) = handler_receiver.recv().unwrap();
+    let server = ControlServer::new(handler, graph);
+    let endpoint = serve(server.clone(), &format!("unix:{}", socket.display())).await;
+    let mut client = ControlClient::connect(&endpoint).await.unwrap();
+    let breakpoint = Command::SetBreakpoint {
+        task: "login".to_string(),
+        stage: Stage::BeforeExec,
+    };
+    client.request(breakpoint).await.unwrap();
+    server_sender.send(server).unwrap();
+
+    let mut events = vec![];
+    loop {
+        let event = client.next_event().await.unwrap().unwrap();
+        match &event {
+            Message::Halted(halt) => {
+                assert_eq!(
+                    (halt.stage, halt.task.as_str()),
+                    (Stage::BeforeExec, "login")
+                );
+                let status: Status =
+                    serde_json::from_value(client.request(Command::Status).await.unwrap()).unwrap();
+                assert_eq!(status.halted.as_ref(), Some(halt));
+                client.request(Command::Resume).await.unwrap();
+            }
+            Message::TaskMessage(TaskMessage::ExecSuccess { id }) if id == "home" => break,
+            _ => {}
+        }
+        events.push(event);
+    }
+    let (result, login_execs) = run.join().unwrap();
+    assert!(matches!(result, Ok(TaskResult::NoPendingTask)));
+    assert_eq!(login_execs, 1);
+    let halted_at = events
+        .iter()
+        .position(|event| matches!(event, Message::Halted(_)))
+        .unwrap();
+    assert!(events[..halted_at].iter().all(|event| !matches!(
+        event,
+        Message::TaskMessage(TaskMessage::TryExec { id }) if id == "login"
+    )));
+}
@@ -0,0 +1,207 @@
+//! Actions sending input through [`InputExt`], registered by [`register`]:
+//!
+//! ```json
+//! {
+//!   "type": "compose",
+//!   "recognize": { "type": "opencv.template_match", "template_path": "login.png" },
+//!   "exec": { "type": "input.click", "at": "match" }
+//! }
+//! ```
+//!
+//! They are always recognized, so they are meant to be executed after another action recognized,
+//! as the `exec` of a [`COMPOSE_TYPE`](crate::pipeline::action::COMPOSE_TYPE) action. Pointer
+//! actions aim at a [`Target`], either fixed coordinates or the location that action matched.
+
+use alloc::boxed::Box;
+use alloc::format;
+use alloc::string::{String, ToString};
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+
+use super::{Action, ExecError, RecognizeError};
+use crate::pipeline::action::ActionFactories;
+use crate::runtime::ext::{InputError, InputExt};
+
+/// Registers the factories of the input actions.
+pub fn register<RUNTIME: InputExt + 'static>(factories: &mut ActionFactories<RUNTIME>) {
+    factories
+        .register(Click::TYPE_NAME, |_, click: Click| click)
+        .register(MoveMouse::TYPE_NAME, |_, move_mouse: MoveMouse| move_mouse)
+        .register(TypeText::TYPE_NAME, |_, type_text: TypeText| type_text)
+        .register(PressKey::TYPE_NAME, |_, press_key: PressKey| press_key);
+}
+
+fn exec_failed(err: InputError) -> ExecError {
+    ExecError::ExecFailed {
+        reason: err.to_string(),
+    }
+}
+
+/// Where a pointer action aims, configured as `[x, y]` or `"match"`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "TargetRepr", into = "TargetRepr")]
+pub enum Target {
+    Point(u32, u32),
+    /// The [`Recognition::location`](super::Recognition::location) of the task
+    Match,
+}
+
+impl Target {
+    /// Name of [`Target::Match`] in configs.
+    pub const MATCH: &'static str = "match";
+
+    /// Coordinates to aim at, given the location the task was recognized at.
+    pub fn resolve(self, location: Option<(u32, u32)>) -> Result<(u32, u32), ExecError> {
+        match self {
+            Target::Point(x, y) => Ok((x, y)),
+            Target::Match => location.ok_or_else(|| ExecError::ExecFailed {
+                reason: "the recognition matched no location to aim at".into(),
+            }),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+enum TargetRepr {
+    Point([u32; 2]),
+    Name(String),
+}
+
+impl TryFrom<TargetRepr> for Target {
+    type Error = String;
+
+    fn try_from(repr: TargetRepr) -> Result<Self, Self::Error> {
+        match repr {
+            TargetRepr::Point([x, y]) => Ok(Target::Point(x, y)),
+            TargetRepr::Name(name) if name == Target::MATCH => Ok(Target::Match),
+            TargetRepr::Name(name) => Err(format!(
+                "unknown target `{name}`, expected [x, y] or \"{}\"",
+                Target::MATCH
+            )),
+        }
+    }
+}
+
+impl From<Target> for TargetRepr {
+    fn from(target: Target) -> Self {
+        match target {
+            Target::Point(x, y) => TargetRepr::Point([x, y]),
+            Target::Match => TargetRepr::Name(Target::MATCH.into()),
+        }
+    }
+}
+
+/// Clicks at a [`Target`], see [`InputExt::click`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Click {
+    pub at: Target,
+    /// Left button by default
+    #[serde(default = "Click::default_button")]
+    pub button: u8,
+}
+
+impl Click {
+    pub const TYPE_NAME: &'static str = "input.click";
+
+    fn default_button() -> u8 {
+        1
+    }
+}
+
+#[async_trait]
+impl<RUNTIME: InputExt> Action<RUNTIME> for Click {
+    async fn recognize(&self, _runtime: &RUNTIME) -> Result<(), RecognizeError> {
+        Ok(())
+    }
+
+    async fn exec(&self, runtime: &RUNTIME) -> Result<(), ExecError> {
+        self.exec_at(runtime, None).await
+    }
+
+    async fn exec_at(
+        &self,
+        runtime: &RUNTIME,
+        location: Option<(u32, u32)>,
+    ) -> Result<(), ExecError> {
+        let (x, y) = self.at.resolve(location)?;
+        runtime.click(x, y, self.button).await.map_err(exec_failed)
+    }
+}
+
+/// Moves the pointer to a [`Target`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct MoveMouse {
+    pub at: Target,
+}
+
+impl MoveMouse {
+    pub const TYPE_NAME: &'static str = "input.move";
+}
+
+#[async_trait]
+impl<RUNTIME: InputExt> Action<RUNTIME> for MoveMouse {
+    async fn recognize(&self, _runtime: &RUNTIME) -> Result<(), RecognizeError> {
+        Ok(())
+    }
+
+    async fn exec(&self, runtime: &RUNTIME) -> Result<(), ExecError> {
+        self.exec_at(runtime, None).await
+    }
+
+    async fn exec_at(
+        &self,
+        runtime: &RUNTIME,
+        location: Option<(u32, u32)>,
+    ) -> Result<(), ExecError> {
+        let (x, y) = self.at.resolve(location)?;
+        runtime.move_mouse(x, y).await.map_err(exec_failed)
+    }
+}
+
+/// Types `text`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TypeText {
+    pub text: String,
+}
+
+impl TypeText {
+    pub const TYPE_NAME: &'static str = "input.type";
+}
+
+#[async_trait]
+impl<RUNTIME: InputExt> Action<RUNTIME> for TypeText {
+    async fn recognize(&self, _runtime: &RUNTIME) -> Result<(), RecognizeError> {
+        Ok(())
+    }
+
+    async fn exec(&self, runtime: &RUNTIME) -> Result<(), ExecError> {
+        runtime.type_text(&self.text).await.map_err(exec_failed)
+    }
+}
+
+/// Presses and releases a key, given as X11 keysym, e.g. `65293` for Return.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PressKey {
+    pub key: u32,
+}
+
+impl PressKey {
+    pub const TYPE_NAME: &'static str = "input.key";
+}
+
+#[async_trait]
+impl<RUNTIME: InputExt> Action<RUNTIME> for PressKey {
+    async fn recognize(&self, _runtime: &RUNTIME) -> Result<(), RecognizeError> {
+        Ok(())
+    }
+
+    async fn exec(&self, runtime: &RUNTIME) -> Result<(), ExecError> {
+        runtime.press_key(self.key).await.map_err(exec_failed)
+    }
+}
@@ -1,8 +1,13 @@
+use alloc::boxed::Box;
+use alloc::string::String;
+
 use async_trait::async_trait;
 use snafu::Snafu;
 
 use crate::runtime::Runtime;
 
+pub mod input;
+
 pub type ActionId = String;
 
 /// Action is the behavior that a Task will perform.Basically it contains two stages:
@@ -17,6 +22,72 @@ pub type ActionId = String;
 pub trait Action<RUNTIME: Runtime>: Send + Sync {
     async fn recognize(&self, runtime: &RUNTIME) -> Result<(), RecognizeError>;
     async fn exec(&self, runtime: &RUNTIME) -> Result<(), ExecError>;
+
+    /// Recognizes like `recognize`, also reporting a score that task guards can read as
+    /// `score.task`, see [`crate::guard`]. Actions with a confidence, such as template matching,
+    /// override it; the others report no score.
+    async fn recognize_scored(&self, runtime: &RUNTIME) -> Recognition {
+        Recognition {
+            result: self.recognize(runtime).await,
+            score: None,
+            location: None,
+        }
+    }
+
+    /// Executes like `exec`, given the [`Recognition::location`] the task was recognized at.
+    /// Actions aiming at a point, such as [`input::Click`], override it to aim at the match.
+    async fn exec_at(
+        &self,
+        runtime: &RUNTIME,
+        _location: Option<(u32, u32)>,
+    ) -> Result<(), ExecError> {
+        self.exec(runtime).await
+    }
+}
+
+/// Result of [`Action::recognize_scored`].
+#[derive(Debug)]
+pub struct Recognition {
+    pub result: Result<(), RecognizeError>,
+    /// Score of the recognition whether it succeeded or not, e.g. a match confidence
+    pub score: Option<f64>,
+    /// Where on screen it recognized, e.g. the centre of a template match
+    pub location: Option<(u32, u32)>,
+}
+
+/// Recognizes with one action and executes another, e.g. find a button then click it.
+pub struct Compose<RUNTIME: Runtime> {
+    recognize: Box<dyn Action<RUNTIME>>,
+    exec: Box<dyn Action<RUNTIME>>,
+}
+
+impl<RUNTIME: Runtime> Compose<RUNTIME> {
+    pub fn new(recognize: Box<dyn Action<RUNTIME>>, exec: Box<dyn Action<RUNTIME>>) -> Self {
+        Self { recognize, exec }
+    }
+}
+
+#[async_trait]
+impl<RUNTIME: Runtime> Action<RUNTIME> for Compose<RUNTIME> {
+    async fn recognize(&self, runtime: &RUNTIME) -> Result<(), RecognizeError> {
+        self.recognize.recognize(runtime).await
+    }
+
+    async fn recognize_scored(&self, runtime: &RUNTIME) -> Recognition {
+        self.recognize.recognize_scored(runtime).await
+    }
+
+    async fn exec(&self, runtime: &RUNTIME) -> Result<(), ExecError> {
+        self.exec.exec(runtime).await
+    }
+
+    async fn exec_at(
+        &self,
+        runtime: &RUNTIME,
+        location: Option<(u32, u32)>,
+    ) -> Result<(), ExecError> {
+        self.exec.exec_at(runtime, location).await
+    }
 }
 
 #[derive(Debug, Snafu)]
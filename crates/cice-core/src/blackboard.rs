@@ -0,0 +1,107 @@
+//! State shared by the tasks of a [`Context`](crate::context::Context), which task guards are
+//! evaluated against, see [`crate::guard`].
+
+use alloc::collections::BTreeMap;
+use alloc::string::String;
+use alloc::sync::Arc;
+
+use serde_json::Value;
+
+use crate::sync::Mutex;
+use crate::task::TaskId;
+
+/// Variables set by the application, plus how many times each task was entered and the outcome
+/// score and location of its last recognition.
+///
+/// Clones share the same state, so a [`ContextHandler`](crate::context::ContextHandler) can set
+/// variables while the context runs.
+#[derive(Clone, Default)]
+pub struct Blackboard(Arc<Mutex<State>>);
+
+#[derive(Default)]
+struct State {
+    vars: BTreeMap<String, Value>,
+    runs: BTreeMap<TaskId, usize>,
+    scores: BTreeMap<TaskId, f64>,
+    locations: BTreeMap<TaskId, (u32, u32)>,
+    recognized: BTreeMap<TaskId, bool>,
+}
+
+impl Blackboard {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn set(&self, name: impl Into<String>, value: impl Into<Value>) {
+        self.0.lock().vars.insert(name.into(), value.into());
+    }
+
+    pub fn get(&self, name: &str) -> Option<Value> {
+        self.0.lock().vars.get(name).cloned()
+    }
+
+    pub fn remove(&self, name: &str) -> Option<Value> {
+        self.0.lock().vars.remove(name)
+    }
+
+    /// How many times the task `id` was entered.
+    pub fn runs(&self, id: &str) -> usize {
+        self.0.lock().runs.get(id).copied().unwrap_or(0)
+    }
+
+    /// Score of the last recognition of the task `id`, if it reported one, see
+    /// [`Action::recognize_scored`](crate::action::Action::recognize_scored).
+    pub fn score(&self, id: &str) -> Option<f64> {
+        self.0.lock().scores.get(id).copied()
+    }
+
+    /// Where the last recognition of the task `id` matched, if it reported it; the task is
+    /// executed at that location, see [`Action::exec_at`](crate::action::Action::exec_at).
+    pub fn location(&self, id: &str) -> Option<(u32, u32)> {
+        self.0.lock().locations.get(id).copied()
+    }
+
+    /// Whether the last recognition of the task `id` succeeded, if it was recognized at all.
+    pub fn recognized(&self, id: &str) -> Option<bool> {
+        self.0.lock().recognized.get(id).copied()
+    }
+
+    pub(crate) fn record_run(&self, id: &str) {
+        *self.0.lock().runs.entry(id.into()).or_default() += 1;
+    }
+
+    /// Records the score of the last recognition, clearing the previous one when it reported none.
+    pub(crate) fn record_score(&self, id: &str, score: Option<f64>) {
+        let mut state = self.0.lock();
+        match score {
+            Some(score) => state.scores.insert(id.into(), score),
+            None => state.scores.remove(id),
+        };
+    }
+
+    /// Records the location of the last recognition, clearing the previous one like scores.
+    pub(crate) fn record_location(&self, id: &str, location: Option<(u32, u32)>) {
+        let mut state = self.0.lock();
+        match location {
+            Some(location) => state.locations.insert(id.into(), location),
+            None => state.locations.remove(id),
+        };
+    }
+
+    pub(crate) fn record_recognized(&self, id: &str, recognized: bool) {
+        self.0.lock().recognized.insert(id.into(), recognized);
+    }
+}
+
+impl core::fmt::Debug for Blackboard {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        let state = self.0.lock();
+        f.debug_struct("Blackboard")
+            .field("vars", &state.vars)
+            .field("runs", &state.runs)
+            .field("scores", &state.scores)
+            .field("locations", &state.locations)
+            .field("recognized", &state.recognized)
+            .finish()
+    }
+}
@@ -1,9 +1,32 @@
-use crate::action::Action;
+use crate::action::{Action, RecognizeError};
+use crate::blackboard::Blackboard;
+use crate::debug::{Breakpoint, Checkpoint, Debugger, Halt, Stage, Step};
+use crate::graph::TaskGraph;
 use crate::message::Message;
+use crate::runtime::executor::Spawner;
+use crate::runtime::ext::TimerExt;
 use crate::runtime::Runtime;
+use crate::sync::Mutex;
 use crate::task::{Task, TaskConfig, TaskError, TaskId, TaskResult};
-use std::collections::HashMap;
-use std::sync::Arc;
+use crate::trace::{TraceEvent, Tracer};
+use alloc::boxed::Box;
+use alloc::collections::btree_map::Entry;
+use alloc::collections::BTreeMap;
+use alloc::string::ToString;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::sync::atomic::{AtomicBool, Ordering};
+use core::time::Duration;
+use futures::channel::oneshot;
+use futures::future::{AbortHandle, Abortable, BoxFuture};
+use futures::FutureExt;
+use snafu::Snafu;
+
+#[derive(Debug, Snafu)]
+pub enum ContextError {
+    #[snafu(display("task {id} is added twice"))]
+    DuplicateTask { id: TaskId },
+}
 
 #[derive(Clone)]
 pub struct ContextHandler(Arc<ContextHandlerInner>);
@@ -11,8 +34,14 @@ pub struct ContextHandler(Arc<ContextHandlerInner>);
 struct ContextHandlerInner {
     cancel_sender: async_channel::Sender<()>,
     message_recv: async_channel::Receiver<Message>,
+    blackboard: Blackboard,
+    paused: AtomicBool,
+    debugger: Mutex<Debugger>,
 }
 
+/// How often a halted context checks whether it was resumed.
+const HALT_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
 impl ContextHandler {
     pub async fn cancel(&self) -> Result<(), async_channel::SendError<()>> {
         self.0.cancel_sender.send(()).await
@@ -29,51 +58,158 @@ impl ContextHandler {
     pub fn try_recv(&self) -> Result<Message, async_channel::TryRecvError> {
         self.0.message_recv.try_recv()
     }
+
+    /// State that task guards read, where variables can be set while the context runs.
+    pub fn blackboard(&self) -> &Blackboard {
+        &self.0.blackboard
+    }
+
+    /// Holds the running task before its next recognition round until [`ContextHandler::resume`].
+    ///
+    /// Time spent paused doesn't count against the task's timeout, and the context can still be
+    /// cancelled while paused. A paused context halts, see [`crate::debug`].
+    pub fn pause(&self) {
+        self.0.paused.store(true, Ordering::Release);
+    }
+
+    /// Runs on until the next breakpoint.
+    pub fn resume(&self) {
+        self.continue_with(None);
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.0.paused.load(Ordering::Acquire)
+    }
+
+    /// Runs on until the next recognition round, or an earlier breakpoint.
+    pub fn step_round(&self) {
+        self.continue_with(Some(Step::Round));
+    }
+
+    /// Runs on until the next task is chosen, before it is executed, or an earlier breakpoint.
+    pub fn step_task(&self) {
+        self.continue_with(Some(Step::Task));
+    }
+
+    fn continue_with(&self, step: Option<Step>) {
+        let mut debugger = self.0.debugger.lock();
+        debugger.step = step;
+        debugger.halt = None;
+        self.0.paused.store(false, Ordering::Release);
+    }
+
+    /// Where the context is halted, if it is.
+    pub fn halted(&self) -> Option<Halt> {
+        self.0.debugger.lock().halt.clone()
+    }
+
+    pub fn set_breakpoint(&self, task: impl Into<TaskId>, stage: Stage) {
+        let breakpoint = Breakpoint {
+            task: task.into(),
+            stage,
+        };
+        self.0.debugger.lock().breakpoints.insert(breakpoint);
+    }
+
+    /// Removes a breakpoint, returning whether it was set.
+    pub fn remove_breakpoint(&self, task: &str, stage: Stage) -> bool {
+        let breakpoint = Breakpoint {
+            task: task.into(),
+            stage,
+        };
+        self.0.debugger.lock().breakpoints.remove(&breakpoint)
+    }
+
+    pub fn breakpoints(&self) -> Vec<Breakpoint> {
+        self.0.debugger.lock().breakpoints.iter().cloned().collect()
+    }
 }
 
 //could have a 'context lifetime specifier which is longer than 'task if needed
 pub struct ContextBuilder<'task, RUNTIME: Runtime> {
     runtime: RUNTIME,
-    tasks: HashMap<TaskId, Task<'task, RUNTIME>>,
+    tasks: BTreeMap<TaskId, Task<'task, RUNTIME>>,
     context_handler: ContextHandler,
     cancel_recv: async_channel::Receiver<()>,
     message_sender: async_channel::Sender<Message>,
+    spawn_recognize: Option<Box<SpawnRecognize<'task, RUNTIME>>>,
+    tracer: Option<Arc<dyn Tracer>>,
 }
 
+/// Recognizes a task on its own executor task, see [`ContextBuilder::spawner`].
+pub(crate) type SpawnRecognize<'task, RUNTIME> = dyn Fn(
+        &Context<'task, RUNTIME>,
+        &Task<'task, RUNTIME>,
+    ) -> BoxFuture<'task, Result<(), RecognizeError>>
+    + Send
+    + Sync
+    + 'task;
+
 impl<'task, RUNTIME: Runtime> ContextBuilder<'task, RUNTIME> {
     pub fn new(runtime: RUNTIME) -> Self {
         let (cancel_sender, cancel_recv) = async_channel::bounded(1); //Cancel signal should be sent only once
         let (message_sender, message_recv) = async_channel::bounded(20);
         Self {
             runtime,
-            tasks: HashMap::new(),
+            tasks: BTreeMap::new(),
             context_handler: ContextHandler(Arc::new(ContextHandlerInner {
                 cancel_sender,
                 message_recv,
+                blackboard: Blackboard::new(),
+                paused: AtomicBool::new(false),
+                debugger: Mutex::new(Debugger::default()),
             })),
             cancel_recv,
             message_sender,
+            spawn_recognize: None,
+            tracer: None,
         }
     }
+
+    /// Adds a task, keyed by its `task_name`.
+    ///
+    /// Fails with [`ContextError::DuplicateTask`] if a task with the same `task_name` was already
+    /// added, as one of them would never run.
     pub fn add_task(
         &mut self,
         task_config: TaskConfig,
         action: &'task impl Action<RUNTIME>,
-    ) -> &mut Self {
-        self.tasks.insert(
-            task_config.task_name.clone(),
-            Task::new(task_config, action),
-        );
-        self
+    ) -> Result<&mut Self, ContextError> {
+        self.add_dyn_task(task_config, action)
+    }
+
+    pub(crate) fn add_dyn_task(
+        &mut self,
+        task_config: TaskConfig,
+        action: &'task dyn Action<RUNTIME>,
+    ) -> Result<&mut Self, ContextError> {
+        match self.tasks.entry(task_config.task_name.clone()) {
+            Entry::Occupied(entry) => {
+                return Err(ContextError::DuplicateTask {
+                    id: entry.key().clone(),
+                })
+            }
+            Entry::Vacant(entry) => {
+                entry.insert(Task::new(task_config, action));
+            }
+        }
+        Ok(self)
     }
 
+    /// Adds each of `tasks`, stopping at the first one that can't be added.
     pub fn add_tasks(
         &mut self,
         tasks: Vec<(TaskConfig, &'task impl Action<RUNTIME>)>,
-    ) -> &mut Self {
+    ) -> Result<&mut Self, ContextError> {
         for task in tasks {
-            self.add_task(task.0, task.1);
+            self.add_task(task.0, task.1)?;
         }
+        Ok(self)
+    }
+
+    /// Passes what the context does to `tracer`, see [`crate::trace`].
+    pub fn tracer(&mut self, tracer: impl Tracer + 'static) -> &mut Self {
+        self.tracer = Some(Arc::new(tracer));
         self
     }
 
@@ -84,22 +220,86 @@ impl<'task, RUNTIME: Runtime> ContextBuilder<'task, RUNTIME> {
             handler: self.context_handler,
             cancel_recv: self.cancel_recv,
             message_sender: self.message_sender,
+            spawn_recognize: self.spawn_recognize,
+            tracer: self.tracer,
         }))
     }
 }
 
+impl<RUNTIME: TimerExt + 'static> ContextBuilder<'static, RUNTIME> {
+    /// Recognizes `next_task` candidates on their own tasks of `spawner`, so they run in parallel
+    /// on multi-threaded executors instead of taking turns on the scheduler's task.
+    ///
+    /// Only available with `'static` actions, as a spawned task may outlive any shorter borrow.
+    /// Recognitions that lose the race are aborted.
+    pub fn spawner(&mut self, spawner: impl Spawner + 'static) -> &mut Self {
+        self.spawn_recognize = Some(Box::new(
+            move |context: &Context<'static, RUNTIME>, task: &Task<'static, RUNTIME>| {
+                let (context, task) = (context.clone(), task.clone());
+                let (sender, receiver) = oneshot::channel();
+                let (abort_handle, abort_registration) = AbortHandle::new_pair();
+                let recognize = async move {
+                    let _ = sender.send(task.try_recognize(&context).await);
+                };
+                spawner.spawn(
+                    Abortable::new(recognize, abort_registration)
+                        .map(|_| ())
+                        .boxed(),
+                );
+                let abort_on_drop = AbortOnDrop(abort_handle);
+                async move {
+                    let _abort_on_drop = abort_on_drop;
+                    receiver.await.unwrap_or_else(|_| {
+                        Err(RecognizeError::RecognizeFailed {
+                            reason: "spawned recognition was dropped".into(),
+                        })
+                    })
+                }
+                .boxed()
+            },
+        ));
+        self
+    }
+}
+
+struct AbortOnDrop(AbortHandle);
+
+impl Drop for AbortOnDrop {
+    fn drop(&mut self) {
+        self.0.abort();
+    }
+}
+
 struct ContextInner<'task, RUNTIME: Runtime> {
     runtime: RUNTIME, //TODO maybe can extract runtime out of Arc inner thus can use &mut directly
-    tasks: HashMap<TaskId, Task<'task, RUNTIME>>,
+    tasks: BTreeMap<TaskId, Task<'task, RUNTIME>>,
     handler: ContextHandler,
     cancel_recv: async_channel::Receiver<()>,
     message_sender: async_channel::Sender<Message>,
+    spawn_recognize: Option<Box<SpawnRecognize<'task, RUNTIME>>>,
+    tracer: Option<Arc<dyn Tracer>>,
 }
 
 pub struct Context<'task, RUNTIME: Runtime>(Arc<ContextInner<'task, RUNTIME>>);
 
-impl<RUNTIME: Runtime> Context<'_, RUNTIME> {
+impl<RUNTIME: Runtime> Clone for Context<'_, RUNTIME> {
+    fn clone(&self) -> Self {
+        Self(self.0.clone())
+    }
+}
+
+impl<RUNTIME: TimerExt> Context<'_, RUNTIME> {
     pub async fn run(&self, entry: TaskId) -> Result<TaskResult, TaskError> {
+        let result = self.run_tasks(entry).await;
+        self.trace(|| TraceEvent::Finish {
+            at: self.get_runtime().now(),
+            cancelled: matches!(result, Ok(TaskResult::TaskCancelled)),
+            error: result.as_ref().err().map(ToString::to_string),
+        });
+        result
+    }
+
+    async fn run_tasks(&self, entry: TaskId) -> Result<TaskResult, TaskError> {
         if let Some(task) = self.0.tasks.get(&entry) {
             let mut task_res = task.run_with_context(self).await;
             while let Ok(ref res) = task_res {
@@ -119,11 +319,64 @@ impl<RUNTIME: Runtime> Context<'_, RUNTIME> {
         }
     }
 
+    /// Halts at `stage` of `task` if a breakpoint or step says so, or the context was paused, and
+    /// waits until it is resumed.
+    pub(crate) async fn checkpoint(
+        &self,
+        stage: Stage,
+        task: &TaskId,
+        candidates: Vec<TaskId>,
+    ) -> Checkpoint {
+        let handler = &self.0.handler;
+        let halt = {
+            let mut debugger = handler.0.debugger.lock();
+            if !debugger.should_halt(stage, task, &candidates) && !handler.is_paused() {
+                return Checkpoint::Passed;
+            }
+            let halt = Halt {
+                stage,
+                task: task.clone(),
+                candidates,
+            };
+            debugger.halt = Some(halt.clone());
+            handler.pause();
+            halt
+        };
+        if let Err(e) = self.try_send_message(Message::Halted(halt)) {
+            log::error!("Failed to send message {e}");
+        }
+        let timer = self.get_runtime();
+        let mut cancel_signal = self.get_cancel_signal().boxed().fuse();
+        let mut checkpoint = Checkpoint::Resumed;
+        while handler.is_paused() {
+            futures::select_biased! {
+                _ = cancel_signal => {
+                    checkpoint = Checkpoint::Cancelled;
+                    break;
+                },
+                _ = timer.sleep(HALT_POLL_INTERVAL).fuse() => {},
+            }
+        }
+        handler.0.debugger.lock().halt = None;
+        checkpoint
+    }
+}
+
+impl<'task, RUNTIME: Runtime> Context<'task, RUNTIME> {
     pub fn get_handler(&self) -> ContextHandler {
         self.0.handler.clone()
     }
 
-    pub(crate) fn get_task(&self, id: &TaskId) -> Option<&Task<RUNTIME>> {
+    pub fn blackboard(&self) -> &Blackboard {
+        self.0.handler.blackboard()
+    }
+
+    /// The graph of the tasks of this context, see [`crate::graph`].
+    pub fn task_graph(&self) -> TaskGraph {
+        TaskGraph::new(self.0.tasks.values().map(Task::config))
+    }
+
+    pub(crate) fn get_task(&self, id: &TaskId) -> Option<&Task<'task, RUNTIME>> {
         self.0.tasks.get(id)
     }
 
@@ -131,10 +384,21 @@ impl<RUNTIME: Runtime> Context<'_, RUNTIME> {
         self.0.cancel_recv.recv().await
     }
 
+    /// Passes the event built by `event` to the tracer, if there is one.
+    pub(crate) fn trace(&self, event: impl FnOnce() -> TraceEvent) {
+        if let Some(tracer) = &self.0.tracer {
+            tracer.record(event());
+        }
+    }
+
     pub(crate) fn get_runtime(&self) -> &RUNTIME {
         &self.0.runtime
     }
 
+    pub(crate) fn get_spawn_recognize(&self) -> Option<&SpawnRecognize<'task, RUNTIME>> {
+        self.0.spawn_recognize.as_deref()
+    }
+
     // Always drop message if channel is full in try_send_message
     pub(crate) fn try_send_message(
         &self,
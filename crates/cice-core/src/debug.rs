@@ -0,0 +1,126 @@
+//! Breakpoints and stepping, driven through a [`ContextHandler`].
+//!
+//! The scheduler passes a checkpoint at each [`Stage`] of a task. It halts there when a
+//! [`Breakpoint`] matches, when a step requested with [`ContextHandler::step_round`] or
+//! [`ContextHandler::step_task`] ends, or when the context was [paused](crate::context::ContextHandler::pause).
+//! A halted context is paused: [`ContextHandler::halted`] tells where it stopped and which tasks
+//! it is about to recognize, the [blackboard](crate::context::ContextHandler::blackboard) can be read and changed,
+//! and [`ContextHandler::resume`] runs on to the next breakpoint.
+//!
+//! ```no_run
+//! # use cice_core::context::ContextHandler;
+//! # use cice_core::debug::Stage;
+//! # fn debug(handler: ContextHandler) {
+//! handler.set_breakpoint("login", Stage::BeforeExec);
+//! // ... once the context halts
+//! if let Some(halt) = handler.halted() {
+//!     println!("halted {} {}", halt.stage, halt.task);
+//!     handler.blackboard().set("retries", 0);
+//!     handler.step_round();
+//! }
+//! # }
+//! ```
+//!
+//! [`ContextHandler`]: crate::context::ContextHandler
+//! [`ContextHandler::step_round`]: crate::context::ContextHandler::step_round
+//! [`ContextHandler::step_task`]: crate::context::ContextHandler::step_task
+//! [`ContextHandler::halted`]: crate::context::ContextHandler::halted
+//! [`ContextHandler::resume`]: crate::context::ContextHandler::resume
+
+use alloc::collections::BTreeSet;
+use alloc::vec::Vec;
+use core::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use crate::task::TaskId;
+
+/// Where in the run of a task the scheduler can halt.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Stage {
+    /// Before a recognition round over the `next_task` of a task
+    BeforeRecognize,
+    /// Before the action of a recognized task is executed
+    BeforeExec,
+    /// After the action of a task was executed
+    AfterExec,
+}
+
+impl fmt::Display for Stage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::BeforeRecognize => "before recognize",
+            Self::BeforeExec => "before exec",
+            Self::AfterExec => "after exec",
+        })
+    }
+}
+
+/// Halts the scheduler at `stage` of `task`.
+///
+/// A [`Stage::BeforeRecognize`] breakpoint halts before every round that recognizes `task`, that
+/// is while one of its predecessors waits and `task` is among the candidates.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct Breakpoint {
+    pub task: TaskId,
+    pub stage: Stage,
+}
+
+/// Where a context halted.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Halt {
+    pub stage: Stage,
+    /// Task waiting for its `next_task` before a round, or the task executed
+    pub task: TaskId,
+    /// Tasks the round is about to recognize, those of `next_task` whose guard holds; empty for
+    /// the other stages
+    pub candidates: Vec<TaskId>,
+}
+
+/// How far to run before halting again.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Step {
+    /// To the next recognition round
+    Round,
+    /// To the next task chosen, before it is executed
+    Task,
+}
+
+/// How a checkpoint was passed.
+pub(crate) enum Checkpoint {
+    /// Without halting
+    Passed,
+    /// After halting, so the blackboard may have changed
+    Resumed,
+    /// The context was cancelled while halted
+    Cancelled,
+}
+
+#[derive(Debug, Default)]
+pub(crate) struct Debugger {
+    pub(crate) breakpoints: BTreeSet<Breakpoint>,
+    pub(crate) step: Option<Step>,
+    pub(crate) halt: Option<Halt>,
+}
+
+impl Debugger {
+    /// Whether to halt at `stage` of `task`, consuming the step that ends there.
+    pub(crate) fn should_halt(&mut self, stage: Stage, task: &str, candidates: &[TaskId]) -> bool {
+        let step_ends = matches!(
+            (self.step, stage),
+            (Some(Step::Round), Stage::BeforeRecognize) | (Some(Step::Task), Stage::BeforeExec)
+        );
+        let breakpoint = self.breakpoints.iter().any(|breakpoint| {
+            breakpoint.stage == stage
+                && match stage {
+                    Stage::BeforeRecognize => candidates.contains(&breakpoint.task),
+                    Stage::BeforeExec | Stage::AfterExec => breakpoint.task == task,
+                }
+        });
+        if step_ends || breakpoint {
+            self.step = None;
+        }
+        step_ends || breakpoint
+    }
+}
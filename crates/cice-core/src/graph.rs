@@ -0,0 +1,400 @@
+//! The task graph of a pipeline or context, rendered as Graphviz DOT or Mermaid.
+//!
+//! ```
+//! # use cice_core::graph::TaskGraph;
+//! # use cice_core::pipeline::Pipeline;
+//! let pipeline = Pipeline::from_json(r#"{
+//!     "login": { "action_name": "click_login", "next_task": ["home"], "interrupt_task": ["popup"] },
+//!     "home": { "action_name": "find_home" },
+//!     "popup": { "action_name": "close_popup" }
+//! }"#).unwrap();
+//! let mut graph = TaskGraph::from(&pipeline);
+//! graph.entry("login");
+//! let dot = graph.to_dot();
+//! assert!(dot.contains(r#""login" -> "home""#));
+//! ```
+//!
+//! `next_task` edges are solid and labelled with their guard, see [`crate::guard`];
+//! `interrupt_task` edges are dashed. Tasks that wait on `next_task` also get an error edge to
+//! a shared `error` node, taken when recognition times out. Entry tasks are highlighted, terminal
+//! tasks, which have no `next_task`, are rounded, and references to undefined tasks are drawn as
+//! red dangling nodes.
+
+use alloc::collections::{BTreeMap, BTreeSet};
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::fmt::Write;
+
+use serde::{Deserialize, Serialize};
+
+use crate::pipeline::Pipeline;
+use crate::task::{duration, TaskConfig, TaskId};
+
+/// Node that error edges point to; `$` can't start a task id.
+pub const ERROR_NODE: &str = "$error";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum EdgeKind {
+    /// To one of `next_task`
+    Next,
+    /// To one of `interrupt_task`
+    Interrupt,
+    /// To [`ERROR_NODE`], when no `next_task` is recognized in time
+    Error,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Edge {
+    pub from: TaskId,
+    pub to: TaskId,
+    pub kind: EdgeKind,
+    /// Guard of a `next_task` edge, or the timeout of an error edge
+    pub label: Option<String>,
+}
+
+/// Tasks and the edges between them, see the [module documentation](self).
+#[derive(Debug, Clone, Default)]
+pub struct TaskGraph {
+    tasks: BTreeMap<TaskId, TaskConfig>,
+    entries: BTreeSet<TaskId>,
+}
+
+impl TaskGraph {
+    pub fn new<'c>(tasks: impl IntoIterator<Item = &'c TaskConfig>) -> Self {
+        Self {
+            tasks: tasks
+                .into_iter()
+                .map(|config| (config.task_name.clone(), config.clone()))
+                .collect(),
+            entries: BTreeSet::new(),
+        }
+    }
+
+    /// Marks `id` as a task the context is run from.
+    pub fn entry(&mut self, id: impl Into<TaskId>) -> &mut Self {
+        self.entries.insert(id.into());
+        self
+    }
+
+    /// Configs of the tasks, by id.
+    pub fn tasks(&self) -> impl Iterator<Item = &TaskConfig> {
+        self.tasks.values()
+    }
+
+    /// Tasks marked with [`TaskGraph::entry`], or else the tasks no other task leads to.
+    pub fn entries(&self) -> BTreeSet<&str> {
+        if !self.entries.is_empty() {
+            return self.entries.iter().map(TaskId::as_str).collect();
+        }
+        let targets: BTreeSet<&str> = self
+            .tasks
+            .values()
+            .flat_map(|config| config.next_task.iter().chain(&config.interrupt_task))
+            .map(TaskId::as_str)
+            .collect();
+        self.tasks
+            .keys()
+            .map(TaskId::as_str)
+            .filter(|id| !targets.contains(id))
+            .collect()
+    }
+
+    /// Tasks without `next_task`, where a run ends.
+    pub fn terminals(&self) -> BTreeSet<&str> {
+        self.tasks
+            .values()
+            .filter(|config| config.next_task.is_empty())
+            .map(|config| config.task_name.as_str())
+            .collect()
+    }
+
+    /// Referenced tasks that aren't defined, entries included.
+    pub fn dangling(&self) -> BTreeSet<&str> {
+        self.tasks
+            .values()
+            .flat_map(|config| config.next_task.iter().chain(&config.interrupt_task))
+            .chain(&self.entries)
+            .map(TaskId::as_str)
+            .filter(|id| !self.tasks.contains_key(*id))
+            .collect()
+    }
+
+    /// Defined tasks that can't be reached from the entries, following both kinds of edges.
+    pub fn unreachable(&self) -> BTreeSet<&str> {
+        let mut reached = BTreeSet::new();
+        let mut pending: Vec<&str> = self.entries().into_iter().collect();
+        while let Some(id) = pending.pop() {
+            let Some(config) = self.tasks.get(id) else {
+                continue;
+            };
+            if reached.insert(id) {
+                let targets = config.next_task.iter().chain(&config.interrupt_task);
+                pending.extend(targets.map(TaskId::as_str));
+            }
+        }
+        self.tasks
+            .keys()
+            .map(TaskId::as_str)
+            .filter(|id| !reached.contains(id))
+            .collect()
+    }
+
+    /// Cycles of `next_task` edges that no terminal task can be reached from, so a run entering
+    /// them only ends by timing out. Each cycle is a strongly connected set of tasks.
+    ///
+    /// Interrupts return to the interrupted task and don't count as exits; references to
+    /// undefined tasks do, as they are reported by [`TaskGraph::dangling`].
+    pub fn cycles_without_exit(&self) -> Vec<BTreeSet<&str>> {
+        let next = |id: &str| -> Vec<&str> {
+            self.tasks[id]
+                .next_task
+                .iter()
+                .map(TaskId::as_str)
+                .collect()
+        };
+        let mut ending = self.terminals();
+        loop {
+            let found: Vec<&str> = self
+                .tasks
+                .keys()
+                .map(TaskId::as_str)
+                .filter(|id| !ending.contains(id))
+                .filter(|id| {
+                    next(id)
+                        .iter()
+                        .any(|target| ending.contains(target) || !self.tasks.contains_key(*target))
+                })
+                .collect();
+            if found.is_empty() {
+                break;
+            }
+            ending.extend(found);
+        }
+        // Every stuck task leads to another one, so they all lead into cycles
+        let reach = |from: &str| {
+            let mut reached = BTreeSet::new();
+            let mut pending = next(from);
+            while let Some(id) = pending.pop() {
+                if reached.insert(id) {
+                    pending.extend(next(id));
+                }
+            }
+            reached
+        };
+        let mut cycles: Vec<BTreeSet<&str>> = Vec::new();
+        for id in self.tasks.keys().map(TaskId::as_str) {
+            if ending.contains(id) || cycles.iter().any(|cycle| cycle.contains(id)) {
+                continue;
+            }
+            let reached = reach(id);
+            if reached.contains(id) {
+                let cycle = reached
+                    .into_iter()
+                    .filter(|other| reach(other).contains(id))
+                    .collect();
+                cycles.push(cycle);
+            }
+        }
+        cycles
+    }
+
+    pub fn edges(&self) -> Vec<Edge> {
+        let mut edges = Vec::new();
+        for config in self.tasks.values() {
+            for target in &config.next_task {
+                edges.push(Edge {
+                    from: config.task_name.clone(),
+                    to: target.clone(),
+                    kind: EdgeKind::Next,
+                    label: config
+                        .guards
+                        .get(target)
+                        .map(|guard| match guard.recognize {
+                            true => guard.condition.to_string(),
+                            false => format!("{} (no recognize)", guard.condition),
+                        }),
+                });
+            }
+            for target in &config.interrupt_task {
+                edges.push(Edge {
+                    from: config.task_name.clone(),
+                    to: target.clone(),
+                    kind: EdgeKind::Interrupt,
+                    label: None,
+                });
+            }
+            if !config.next_task.is_empty() {
+                edges.push(Edge {
+                    from: config.task_name.clone(),
+                    to: ERROR_NODE.into(),
+                    kind: EdgeKind::Error,
+                    label: Some(format!("timeout {}", duration::format(config.timeout))),
+                });
+            }
+        }
+        edges
+    }
+
+    /// Renders the graph in the Graphviz DOT language.
+    pub fn to_dot(&self) -> String {
+        let (entries, terminals, dangling) = (self.entries(), self.terminals(), self.dangling());
+        let edges = self.edges();
+        let mut dot = String::from("digraph tasks {\n    node [shape=box];\n");
+        for id in self.tasks.keys() {
+            let mut attributes = Vec::new();
+            if terminals.contains(id.as_str()) {
+                attributes.push("style=\"rounded,filled\", fillcolor=lightgrey");
+            }
+            if entries.contains(id.as_str()) {
+                attributes.push("penwidth=2, color=blue");
+            }
+            let _ = writeln!(dot, "    {}{};", quote(id), list(&attributes));
+        }
+        for id in &dangling {
+            let _ = writeln!(
+                dot,
+                "    {} [label={}, style=dashed, color=red, fontcolor=red];",
+                quote(id),
+                quote(&format!("{id} (undefined)")),
+            );
+        }
+        if edges.iter().any(|edge| edge.kind == EdgeKind::Error) {
+            let _ = writeln!(
+                dot,
+                "    {} [label=\"error\", shape=octagon, color=red];",
+                quote(ERROR_NODE)
+            );
+        }
+        for edge in &edges {
+            let mut attributes = Vec::new();
+            match edge.kind {
+                EdgeKind::Next => {}
+                EdgeKind::Interrupt => attributes.push(String::from("style=dashed")),
+                EdgeKind::Error => attributes.push(String::from("style=dotted, color=red")),
+            }
+            if dangling.contains(edge.to.as_str()) {
+                attributes.push(String::from("color=red"));
+            }
+            if let Some(label) = &edge.label {
+                attributes.push(format!("label={}", quote(label)));
+            }
+            let attributes: Vec<&str> = attributes.iter().map(String::as_str).collect();
+            let _ = writeln!(
+                dot,
+                "    {} -> {}{};",
+                quote(&edge.from),
+                quote(&edge.to),
+                list(&attributes)
+            );
+        }
+        dot.push_str("}\n");
+        dot
+    }
+
+    /// Renders the graph as a Mermaid flowchart.
+    pub fn to_mermaid(&self) -> String {
+        let (entries, terminals, dangling) = (self.entries(), self.terminals(), self.dangling());
+        let edges = self.edges();
+        // Task ids may contain characters Mermaid doesn't accept in node ids
+        let ids: BTreeMap<&str, String> = self
+            .tasks
+            .keys()
+            .map(TaskId::as_str)
+            .chain(dangling.iter().copied())
+            .chain(core::iter::once(ERROR_NODE))
+            .enumerate()
+            .map(|(index, id)| (id, format!("t{index}")))
+            .collect();
+        let mut mermaid = String::from("flowchart TD\n");
+        for id in self.tasks.keys() {
+            let (open, close) = match terminals.contains(id.as_str()) {
+                true => ("([", "])"),
+                false => ("[", "]"),
+            };
+            let _ = writeln!(
+                mermaid,
+                "    {}{open}{}{close}",
+                ids[id.as_str()],
+                label(id)
+            );
+        }
+        for id in &dangling {
+            let _ = writeln!(
+                mermaid,
+                "    {}[{}]",
+                ids[id],
+                label(&format!("{id} (undefined)"))
+            );
+        }
+        if edges.iter().any(|edge| edge.kind == EdgeKind::Error) {
+            let _ = writeln!(mermaid, "    {}{{{{error}}}}", ids[ERROR_NODE]);
+        }
+        for edge in &edges {
+            let arrow = match edge.kind {
+                EdgeKind::Next => "-->",
+                EdgeKind::Interrupt => "-.->",
+                EdgeKind::Error => "-.-x",
+            };
+            let text = edge
+                .label
+                .as_deref()
+                .map_or(String::new(), |text| format!("|{}|", label(text)));
+            let _ = writeln!(
+                mermaid,
+                "    {} {arrow}{text} {}",
+                ids[edge.from.as_str()],
+                ids[edge.to.as_str()]
+            );
+        }
+        let classes = [
+            ("entry", "stroke:#00f,stroke-width:3px", &entries),
+            (
+                "dangling",
+                "stroke:#f00,stroke-dasharray:4,color:#f00",
+                &dangling,
+            ),
+        ];
+        for (class, style, members) in classes {
+            let members: Vec<&str> = members
+                .iter()
+                .filter_map(|id| ids.get(id))
+                .map(String::as_str)
+                .collect();
+            if !members.is_empty() {
+                let _ = writeln!(mermaid, "    classDef {class} {style}");
+                let _ = writeln!(mermaid, "    class {} {class}", members.join(","));
+            }
+        }
+        mermaid
+    }
+}
+
+impl From<&Pipeline> for TaskGraph {
+    fn from(pipeline: &Pipeline) -> Self {
+        Self::new(pipeline.tasks())
+    }
+}
+
+/// A DOT string literal.
+fn quote(text: &str) -> String {
+    format!("\"{}\"", text.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// DOT attributes in brackets, or nothing.
+fn list(attributes: &[&str]) -> String {
+    match attributes.is_empty() {
+        true => String::new(),
+        false => format!(" [{}]", attributes.join(", ")),
+    }
+}
+
+/// A quoted Mermaid label, with the characters Mermaid reads as markup written as entities.
+fn label(text: &str) -> String {
+    let text = text
+        .replace('"', "#quot;")
+        .replace('<', "#lt;")
+        .replace('>', "#gt;");
+    format!("\"{text}\"")
+}
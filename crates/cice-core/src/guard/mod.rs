@@ -0,0 +1,408 @@
+//! Conditions guarding the `next_task` edges of a task.
+//!
+//! A guard is checked at the start of each recognition round, and its target is only recognized
+//! while it holds. With `"recognize": false` the guard decides alone and its target is entered
+//! without calling [`Action::recognize`](crate::action::Action::recognize):
+//!
+//! ```json
+//! {
+//!   "login": {
+//!     "action_name": "click_login",
+//!     "next_task": ["home", "login", "give_up"],
+//!     "guards": {
+//!       "login": "runs.login < 3 && score.home < 0.5",
+//!       "give_up": { "if": "runs.login >= 3", "recognize": false }
+//!     }
+//!   }
+//! }
+//! ```
+//!
+//! Conditions are evaluated against the [`Blackboard`] of the context:
+//!
+//! - `vars.name` is a variable set on the blackboard, or `null`
+//! - `runs.task` is how many times `task` was entered
+//! - `score.task` is the score of the last recognition of `task`, or `null`
+//!
+//! Names that aren't made of letters, digits and `_` separated by `.` or `::` are written
+//! `vars["any name"]`. Values are compared with `==`, `!=`, `<`, `<=`, `>` and `>=`, and combined
+//! with `!`, `&&`, `||` and parentheses. Literals are numbers, `"strings"` or `'strings'`,
+//! `true`, `false` and `null`. Ordering only holds between two numbers or two strings, so a
+//! missing score is neither above nor below any threshold. `false`, `null`, `0`, `""` and empty
+//! collections are false, other values true.
+
+use alloc::boxed::Box;
+use alloc::string::String;
+use core::cmp::Ordering;
+use core::fmt;
+use core::str::FromStr;
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use serde_json::Value;
+use snafu::Snafu;
+
+use crate::blackboard::Blackboard;
+
+mod parser;
+
+/// A condition on a `next_task` edge, see the [module documentation](self).
+#[derive(Debug, Clone, PartialEq)]
+pub struct Guard {
+    pub condition: Condition,
+    /// Whether the target must still be recognized once the condition holds
+    pub recognize: bool,
+}
+
+impl Guard {
+    pub fn new(condition: Condition) -> Self {
+        Self {
+            condition,
+            recognize: true,
+        }
+    }
+
+    /// A guard that enters its target without recognizing it.
+    pub fn without_recognize(condition: Condition) -> Self {
+        Self {
+            condition,
+            recognize: false,
+        }
+    }
+}
+
+// Conditions hold finite numbers only, compared like in `PartialEq`
+impl Eq for Guard {}
+
+/// A parsed condition expression.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Condition(Expr);
+
+#[derive(Debug, Snafu)]
+#[snafu(display("{message} at column {column}"))]
+pub struct ConditionError {
+    pub column: usize,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Expr {
+    Literal(Value),
+    Path(Path),
+    Not(Box<Expr>),
+    Binary(Box<Expr>, BinaryOp, Box<Expr>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Path {
+    Var(String),
+    Runs(String),
+    Score(String),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum BinaryOp {
+    Or,
+    And,
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+impl BinaryOp {
+    fn symbol(self) -> &'static str {
+        match self {
+            Self::Or => "||",
+            Self::And => "&&",
+            Self::Eq => "==",
+            Self::Ne => "!=",
+            Self::Lt => "<",
+            Self::Le => "<=",
+            Self::Gt => ">",
+            Self::Ge => ">=",
+        }
+    }
+
+    fn is_comparison(self) -> bool {
+        !matches!(self, Self::Or | Self::And)
+    }
+
+    /// Binding strength, operands of lower precedence are parenthesized when displayed.
+    fn precedence(self) -> u8 {
+        match self {
+            Self::Or => 1,
+            Self::And => 2,
+            _ => 3,
+        }
+    }
+}
+
+impl Condition {
+    pub fn eval(&self, blackboard: &Blackboard) -> bool {
+        truthy(&self.0.eval(blackboard))
+    }
+
+    /// Tasks whose run count or score the condition reads.
+    pub fn tasks(&self) -> impl Iterator<Item = &str> {
+        let mut tasks = alloc::vec::Vec::new();
+        self.0.visit_paths(&mut |path| {
+            if let Path::Runs(id) | Path::Score(id) = path {
+                tasks.push(id.as_str());
+            }
+        });
+        tasks.into_iter()
+    }
+
+    /// Renames the tasks the condition reads, as for namespaced includes.
+    #[cfg(feature = "std")]
+    pub(crate) fn map_tasks(&mut self, mut map: impl FnMut(&str) -> String) {
+        self.0.map_paths(&mut |path| {
+            if let Path::Runs(id) | Path::Score(id) = path {
+                *id = map(id);
+            }
+        });
+    }
+}
+
+impl Expr {
+    fn eval(&self, blackboard: &Blackboard) -> Value {
+        match self {
+            Self::Literal(value) => value.clone(),
+            Self::Path(Path::Var(name)) => blackboard.get(name).unwrap_or(Value::Null),
+            Self::Path(Path::Runs(id)) => blackboard.runs(id).into(),
+            Self::Path(Path::Score(id)) => blackboard.score(id).map_or(Value::Null, Value::from),
+            Self::Not(expr) => (!truthy(&expr.eval(blackboard))).into(),
+            Self::Binary(left, BinaryOp::And, right) => {
+                (truthy(&left.eval(blackboard)) && truthy(&right.eval(blackboard))).into()
+            }
+            Self::Binary(left, BinaryOp::Or, right) => {
+                (truthy(&left.eval(blackboard)) || truthy(&right.eval(blackboard))).into()
+            }
+            Self::Binary(left, op, right) => {
+                let ordering = compare(&left.eval(blackboard), &right.eval(blackboard));
+                match op {
+                    BinaryOp::Eq => ordering == Some(Ordering::Equal),
+                    BinaryOp::Ne => ordering != Some(Ordering::Equal),
+                    BinaryOp::Lt => ordering == Some(Ordering::Less),
+                    BinaryOp::Le => matches!(ordering, Some(Ordering::Less | Ordering::Equal)),
+                    BinaryOp::Gt => ordering == Some(Ordering::Greater),
+                    BinaryOp::Ge => matches!(ordering, Some(Ordering::Greater | Ordering::Equal)),
+                    BinaryOp::And | BinaryOp::Or => unreachable!("handled above"),
+                }
+                .into()
+            }
+        }
+    }
+
+    fn visit_paths<'e>(&'e self, visit: &mut impl FnMut(&'e Path)) {
+        match self {
+            Self::Literal(_) => {}
+            Self::Path(path) => visit(path),
+            Self::Not(expr) => expr.visit_paths(visit),
+            Self::Binary(left, _, right) => {
+                left.visit_paths(visit);
+                right.visit_paths(visit);
+            }
+        }
+    }
+
+    #[cfg(feature = "std")]
+    fn map_paths(&mut self, map: &mut impl FnMut(&mut Path)) {
+        match self {
+            Self::Literal(_) => {}
+            Self::Path(path) => map(path),
+            Self::Not(expr) => expr.map_paths(map),
+            Self::Binary(left, _, right) => {
+                left.map_paths(map);
+                right.map_paths(map);
+            }
+        }
+    }
+
+    fn precedence(&self) -> u8 {
+        match self {
+            Self::Binary(_, op, _) => op.precedence(),
+            _ => 4,
+        }
+    }
+
+    /// Writes `self` as an operand needing at least `precedence`.
+    fn fmt_operand(&self, f: &mut fmt::Formatter<'_>, precedence: u8) -> fmt::Result {
+        if self.precedence() < precedence {
+            write!(f, "({self})")
+        } else {
+            write!(f, "{self}")
+        }
+    }
+}
+
+/// Orders numbers by value and strings lexically; other values are only equal to themselves.
+fn compare(left: &Value, right: &Value) -> Option<Ordering> {
+    match (left, right) {
+        (Value::Number(left), Value::Number(right)) => left.as_f64()?.partial_cmp(&right.as_f64()?),
+        (Value::String(left), Value::String(right)) => Some(left.cmp(right)),
+        (left, right) => (left == right).then_some(Ordering::Equal),
+    }
+}
+
+fn truthy(value: &Value) -> bool {
+    match value {
+        Value::Null => false,
+        Value::Bool(value) => *value,
+        Value::Number(value) => value.as_f64() != Some(0.0),
+        Value::String(value) => !value.is_empty(),
+        Value::Array(values) => !values.is_empty(),
+        Value::Object(values) => !values.is_empty(),
+    }
+}
+
+impl fmt::Display for Condition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl fmt::Display for Expr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Literal(Value::String(value)) => f.write_str(&parser::quote(value)),
+            Self::Literal(value) => write!(f, "{value}"),
+            Self::Path(path) => {
+                let (root, name) = match path {
+                    Path::Var(name) => ("vars", name),
+                    Path::Runs(id) => ("runs", id),
+                    Path::Score(id) => ("score", id),
+                };
+                if parser::is_plain_name(name) {
+                    write!(f, "{root}.{name}")
+                } else {
+                    write!(f, "{root}[{}]", parser::quote(name))
+                }
+            }
+            Self::Not(expr) => {
+                f.write_str("!")?;
+                expr.fmt_operand(f, 4)
+            }
+            Self::Binary(left, op, right) => {
+                // Comparisons don't chain, and `&&`/`||` are left associative
+                let precedence = op.precedence();
+                let operand = if op.is_comparison() { 4 } else { precedence };
+                left.fmt_operand(f, operand)?;
+                write!(f, " {} ", op.symbol())?;
+                right.fmt_operand(f, operand.max(precedence + 1))
+            }
+        }
+    }
+}
+
+impl FromStr for Condition {
+    type Err = ConditionError;
+
+    fn from_str(source: &str) -> Result<Self, Self::Err> {
+        parser::parse(source).map(Self)
+    }
+}
+
+impl Serialize for Condition {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for Condition {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let source = String::deserialize(deserializer)?;
+        source.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+/// Key of the condition of a guard written as an object.
+const IF_KEY: &str = "if";
+/// Key of the [`Guard::recognize`] flag of a guard written as an object.
+const RECOGNIZE_KEY: &str = "recognize";
+
+/// A guard is written as its condition, or as `{ "if": condition, "recognize": false }`.
+impl Serialize for Guard {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        use serde::ser::SerializeMap;
+
+        if self.recognize {
+            return self.condition.serialize(serializer);
+        }
+        let mut map = serializer.serialize_map(Some(2))?;
+        map.serialize_entry(IF_KEY, &self.condition)?;
+        map.serialize_entry(RECOGNIZE_KEY, &self.recognize)?;
+        map.end()
+    }
+}
+
+impl<'de> Deserialize<'de> for Guard {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        #[serde(deny_unknown_fields)]
+        struct Full {
+            #[serde(rename = "if")]
+            condition: Condition,
+            #[serde(default = "recognize_by_default")]
+            recognize: bool,
+        }
+
+        fn recognize_by_default() -> bool {
+            true
+        }
+
+        struct Visitor;
+
+        impl<'de> serde::de::Visitor<'de> for Visitor {
+            type Value = Guard;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("a condition or { \"if\": condition, \"recognize\": bool }")
+            }
+
+            fn visit_str<E: serde::de::Error>(self, source: &str) -> Result<Guard, E> {
+                source.parse().map(Guard::new).map_err(E::custom)
+            }
+
+            fn visit_map<A: serde::de::MapAccess<'de>>(self, map: A) -> Result<Guard, A::Error> {
+                let full = Full::deserialize(serde::de::value::MapAccessDeserializer::new(map))?;
+                Ok(Guard {
+                    condition: full.condition,
+                    recognize: full.recognize,
+                })
+            }
+        }
+
+        deserializer.deserialize_any(Visitor)
+    }
+}
+
+#[cfg(feature = "schema")]
+impl schemars::JsonSchema for Guard {
+    fn schema_name() -> alloc::borrow::Cow<'static, str> {
+        "Guard".into()
+    }
+
+    fn json_schema(_: &mut schemars::SchemaGenerator) -> schemars::Schema {
+        schemars::json_schema!({
+            "description": "Condition to recognize the task under, see `cice_core::guard`",
+            "oneOf": [
+                { "type": "string" },
+                {
+                    "type": "object",
+                    "properties": {
+                        (IF_KEY): { "type": "string" },
+                        (RECOGNIZE_KEY): {
+                            "description": "Whether the task is still recognized once the condition holds",
+                            "type": "boolean",
+                            "default": true,
+                        },
+                    },
+                    "required": [IF_KEY],
+                    "additionalProperties": false,
+                },
+            ],
+        })
+    }
+}
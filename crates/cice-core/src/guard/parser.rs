@@ -0,0 +1,330 @@
+use alloc::boxed::Box;
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use serde_json::{Number, Value};
+
+use super::{BinaryOp, ConditionError, Expr, Path};
+
+#[derive(Debug, Clone, PartialEq)]
+enum TokenKind {
+    Ident(String),
+    Number(String),
+    Str(String),
+    Dot,
+    PathSeparator,
+    LParen,
+    RParen,
+    LBracket,
+    RBracket,
+    Not,
+    Op(BinaryOp),
+    Eof,
+}
+
+impl core::fmt::Display for TokenKind {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::Ident(name) => write!(f, "`{name}`"),
+            Self::Number(number) => write!(f, "`{number}`"),
+            Self::Str(_) => f.write_str("string"),
+            Self::Dot => f.write_str("`.`"),
+            Self::PathSeparator => f.write_str("`::`"),
+            Self::LParen => f.write_str("`(`"),
+            Self::RParen => f.write_str("`)`"),
+            Self::LBracket => f.write_str("`[`"),
+            Self::RBracket => f.write_str("`]`"),
+            Self::Not => f.write_str("`!`"),
+            Self::Op(op) => write!(f, "`{}`", op.symbol()),
+            Self::Eof => f.write_str("end of condition"),
+        }
+    }
+}
+
+struct Token {
+    kind: TokenKind,
+    /// Byte offset in the source
+    offset: usize,
+}
+
+/// Parses `source`; errors are located by byte offset.
+pub(super) fn parse(source: &str) -> Result<Expr, ConditionError> {
+    let mut parser = Parser {
+        source,
+        tokens: tokenize(source)?,
+        position: 0,
+    };
+    let expr = parser.or()?;
+    match parser.bump() {
+        Token {
+            kind: TokenKind::Eof,
+            ..
+        } => Ok(expr),
+        token => Err(parser.error(
+            token.offset,
+            format!("expected an operator, found {}", token.kind),
+        )),
+    }
+}
+
+fn tokenize(source: &str) -> Result<Vec<Token>, ConditionError> {
+    let error = |offset, message| ConditionError::new(source, offset, message);
+    let mut tokens = Vec::new();
+    let mut chars = source.char_indices().peekable();
+    while let Some((offset, c)) = chars.next() {
+        let mut next_is = |expected| chars.next_if(|&(_, c)| c == expected).is_some();
+        let kind = match c {
+            c if c.is_whitespace() => continue,
+            '.' => TokenKind::Dot,
+            '(' => TokenKind::LParen,
+            ')' => TokenKind::RParen,
+            '[' => TokenKind::LBracket,
+            ']' => TokenKind::RBracket,
+            ':' if next_is(':') => TokenKind::PathSeparator,
+            '&' if next_is('&') => TokenKind::Op(BinaryOp::And),
+            '|' if next_is('|') => TokenKind::Op(BinaryOp::Or),
+            '=' if next_is('=') => TokenKind::Op(BinaryOp::Eq),
+            '!' if next_is('=') => TokenKind::Op(BinaryOp::Ne),
+            '!' => TokenKind::Not,
+            '<' if next_is('=') => TokenKind::Op(BinaryOp::Le),
+            '<' => TokenKind::Op(BinaryOp::Lt),
+            '>' if next_is('=') => TokenKind::Op(BinaryOp::Ge),
+            '>' => TokenKind::Op(BinaryOp::Gt),
+            quote @ ('"' | '\'') => {
+                let mut value = String::new();
+                loop {
+                    match chars.next() {
+                        Some((_, c)) if c == quote => break,
+                        Some((at, '\\')) => match chars.next() {
+                            Some((_, c @ ('"' | '\'' | '\\'))) => value.push(c),
+                            Some((_, 'n')) => value.push('\n'),
+                            Some((_, 't')) => value.push('\t'),
+                            _ => return Err(error(at, "unknown escape".into())),
+                        },
+                        Some((_, c)) => value.push(c),
+                        None => return Err(error(offset, "unterminated string".into())),
+                    }
+                }
+                TokenKind::Str(value)
+            }
+            c if c.is_ascii_digit()
+                || (c == '-' && chars.peek().is_some_and(|(_, c)| c.is_ascii_digit())) =>
+            {
+                let mut number = String::from(c);
+                while let Some((_, c)) = chars.next_if(|&(_, c)| c.is_ascii_digit() || c == '.') {
+                    number.push(c);
+                }
+                TokenKind::Number(number)
+            }
+            c if c.is_alphabetic() || c == '_' => {
+                let mut name = String::from(c);
+                while let Some((_, c)) = chars.next_if(|&(_, c)| c.is_alphanumeric() || c == '_') {
+                    name.push(c);
+                }
+                TokenKind::Ident(name)
+            }
+            c => return Err(error(offset, format!("unexpected character `{c}`"))),
+        };
+        tokens.push(Token { kind, offset });
+    }
+    tokens.push(Token {
+        kind: TokenKind::Eof,
+        offset: source.len(),
+    });
+    Ok(tokens)
+}
+
+struct Parser<'s> {
+    source: &'s str,
+    tokens: Vec<Token>,
+    position: usize,
+}
+
+impl Parser<'_> {
+    fn or(&mut self) -> Result<Expr, ConditionError> {
+        let mut left = self.and()?;
+        while self.peek() == &TokenKind::Op(BinaryOp::Or) {
+            self.bump();
+            left = Expr::Binary(Box::new(left), BinaryOp::Or, Box::new(self.and()?));
+        }
+        Ok(left)
+    }
+
+    fn and(&mut self) -> Result<Expr, ConditionError> {
+        let mut left = self.comparison()?;
+        while self.peek() == &TokenKind::Op(BinaryOp::And) {
+            self.bump();
+            left = Expr::Binary(Box::new(left), BinaryOp::And, Box::new(self.comparison()?));
+        }
+        Ok(left)
+    }
+
+    /// Comparisons don't chain, `a < b < c` is an error.
+    fn comparison(&mut self) -> Result<Expr, ConditionError> {
+        let left = self.unary()?;
+        match self.peek() {
+            TokenKind::Op(op) if op.is_comparison() => {
+                let op = *op;
+                self.bump();
+                Ok(Expr::Binary(Box::new(left), op, Box::new(self.unary()?)))
+            }
+            _ => Ok(left),
+        }
+    }
+
+    fn unary(&mut self) -> Result<Expr, ConditionError> {
+        let token = self.bump();
+        let expr = match token.kind {
+            TokenKind::Not => Expr::Not(Box::new(self.unary()?)),
+            TokenKind::LParen => {
+                let expr = self.or()?;
+                self.expect(TokenKind::RParen)?;
+                expr
+            }
+            TokenKind::Str(value) => Expr::Literal(Value::String(value)),
+            TokenKind::Number(number) => Expr::Literal(self.number(token.offset, &number)?),
+            TokenKind::Ident(name) => match name.as_str() {
+                "true" => Expr::Literal(Value::Bool(true)),
+                "false" => Expr::Literal(Value::Bool(false)),
+                "null" => Expr::Literal(Value::Null),
+                "vars" => Expr::Path(Path::Var(self.path_name()?)),
+                "runs" => Expr::Path(Path::Runs(self.path_name()?)),
+                "score" => Expr::Path(Path::Score(self.path_name()?)),
+                _ => {
+                    return Err(self.error(
+                        token.offset,
+                        format!("unknown name `{name}`, expected `vars`, `runs` or `score`"),
+                    ))
+                }
+            },
+            kind => return Err(self.error(token.offset, format!("expected a value, found {kind}"))),
+        };
+        Ok(expr)
+    }
+
+    fn number(&self, offset: usize, number: &str) -> Result<Value, ConditionError> {
+        if let Ok(value) = number.parse::<i64>() {
+            return Ok(value.into());
+        }
+        number
+            .parse()
+            .ok()
+            .and_then(Number::from_f64)
+            .map(Value::Number)
+            .ok_or_else(|| self.error(offset, format!("invalid number `{number}`")))
+    }
+
+    /// The name after `vars`, `runs` or `score`: `.a.b`, `.login::start` or `["any name"]`.
+    fn path_name(&mut self) -> Result<String, ConditionError> {
+        let token = self.bump();
+        match token.kind {
+            TokenKind::LBracket => {
+                let token = self.bump();
+                let TokenKind::Str(name) = token.kind else {
+                    return Err(self.error(
+                        token.offset,
+                        format!("expected a quoted name, found {}", token.kind),
+                    ));
+                };
+                self.expect(TokenKind::RBracket)?;
+                Ok(name)
+            }
+            TokenKind::Dot => {
+                let mut name = self.segment()?;
+                loop {
+                    let separator = match self.peek() {
+                        TokenKind::Dot => ".",
+                        TokenKind::PathSeparator => "::",
+                        _ => return Ok(name),
+                    };
+                    self.bump();
+                    name.push_str(separator);
+                    name.push_str(&self.segment()?);
+                }
+            }
+            kind => Err(self.error(token.offset, format!("expected `.` or `[`, found {kind}"))),
+        }
+    }
+
+    fn segment(&mut self) -> Result<String, ConditionError> {
+        let token = self.bump();
+        match token.kind {
+            TokenKind::Ident(name) => Ok(name),
+            TokenKind::Number(number) if number.bytes().all(|b| b.is_ascii_digit()) => Ok(number),
+            kind => Err(self.error(token.offset, format!("expected a name, found {kind}"))),
+        }
+    }
+
+    fn expect(&mut self, expected: TokenKind) -> Result<(), ConditionError> {
+        let token = self.bump();
+        if token.kind == expected {
+            Ok(())
+        } else {
+            Err(self.error(
+                token.offset,
+                format!("expected {expected}, found {}", token.kind),
+            ))
+        }
+    }
+
+    fn error(&self, offset: usize, message: String) -> ConditionError {
+        ConditionError::new(self.source, offset, message)
+    }
+
+    fn peek(&self) -> &TokenKind {
+        &self.tokens[self.position].kind
+    }
+
+    /// Moves to the next token, staying on the final [`TokenKind::Eof`].
+    fn bump(&mut self) -> Token {
+        let token = &self.tokens[self.position];
+        if self.position < self.tokens.len() - 1 {
+            self.position += 1;
+        }
+        Token {
+            kind: token.kind.clone(),
+            offset: token.offset,
+        }
+    }
+}
+
+/// Whether `name` reads back as a path after `.`, otherwise it is written `["name"]`.
+pub(super) fn is_plain_name(name: &str) -> bool {
+    !name.is_empty()
+        && name.split("::").all(|part| {
+            part.split('.').all(|segment| {
+                !segment.is_empty()
+                    && (segment.bytes().all(|b| b.is_ascii_digit())
+                        || (!segment.starts_with(|c: char| c.is_ascii_digit())
+                            && segment.chars().all(|c| c.is_alphanumeric() || c == '_')))
+            })
+        })
+}
+
+/// Quotes `value` so the lexer reads it back.
+pub(super) fn quote(value: &str) -> String {
+    let mut quoted = String::from("\"");
+    for c in value.chars() {
+        match c {
+            '"' | '\\' => {
+                quoted.push('\\');
+                quoted.push(c);
+            }
+            '\n' => quoted.push_str("\\n"),
+            '\t' => quoted.push_str("\\t"),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+impl ConditionError {
+    fn new(source: &str, offset: usize, message: String) -> Self {
+        Self {
+            column: source[..offset].chars().count() + 1,
+            message,
+        }
+    }
+}
@@ -1,6 +1,14 @@
+#![cfg_attr(not(feature = "std"), no_std)]
 extern crate alloc;
 pub mod action;
+pub mod blackboard;
 pub mod context;
+pub mod debug;
+pub mod graph;
+pub mod guard;
 pub mod message;
+pub mod pipeline;
 pub mod runtime;
+mod sync;
 pub mod task;
+pub mod trace;
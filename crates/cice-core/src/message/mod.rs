@@ -1,4 +1,6 @@
 use serde::{Deserialize, Serialize};
+
+use crate::debug::Halt;
 use task::TaskMessage;
 
 pub mod task;
@@ -7,4 +9,6 @@ pub mod task;
 #[non_exhaustive] //It's likely to be extended at any time. So keep this for compatibility
 pub enum Message {
     TaskMessage(TaskMessage),
+    /// The context halted at a checkpoint, see [`crate::debug`]
+    Halted(Halt),
 }
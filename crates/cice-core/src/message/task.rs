@@ -3,7 +3,7 @@ use snafu::Snafu;
 
 use crate::{action::ActionId, task::TaskId};
 
-#[derive(Debug, Clone, Serialize, Deserialize, Snafu)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Snafu)]
 pub enum TaskMessage {
     #[snafu(display("enter task {id}"))]
     Enter { id: TaskId },
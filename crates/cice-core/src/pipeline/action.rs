@@ -0,0 +1,246 @@
+//! Actions declared in pipeline documents.
+//!
+//! Instead of referring to an action built in Rust with `action_name`, a task can declare its
+//! action inline by type and config:
+//!
+//! ```json
+//! {
+//!   "login": {
+//!     "action": { "type": "opencv.template_match", "template_path": "login.png", "threshold": 0.9 },
+//!     "next_task": ["home"]
+//!   }
+//! }
+//! ```
+//!
+//! Action crates register a factory for each type in [`ActionFactories`], which builds the
+//! actions of a [`Pipeline`] so the whole graph can be run without custom code. Inline actions
+//! are registered under ids of their own, see [`inline_action_id`].
+//!
+//! The built-in [`COMPOSE_TYPE`] recognizes with one action and executes another, see
+//! [`Compose`]:
+//!
+//! ```json
+//! {
+//!   "type": "compose",
+//!   "recognize": { "type": "opencv.template_match", "template_path": "login.png" },
+//!   "exec": { "type": "input.click", "at": "match" }
+//! }
+//! ```
+
+use alloc::boxed::Box;
+use alloc::collections::BTreeMap;
+use alloc::format;
+use alloc::string::{String, ToString};
+use core::fmt;
+
+use serde::de::{self, DeserializeOwned, MapAccess, Visitor};
+use serde::ser::SerializeMap;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use serde_json::{Map, Value};
+
+use super::{ActionRegistry, Pipeline, PipelineError};
+use crate::action::{Action, ActionId, Compose};
+use crate::runtime::Runtime;
+
+/// Field of an [`ActionConfig`] holding its type.
+pub const TYPE_KEY: &str = "type";
+/// Type of the built-in [`Compose`] action, configured by its [`RECOGNIZE_KEY`] and [`EXEC_KEY`].
+pub const COMPOSE_TYPE: &str = "compose";
+/// Field of a [`COMPOSE_TYPE`] action holding the action to recognize with.
+pub const RECOGNIZE_KEY: &str = "recognize";
+/// Field of a [`COMPOSE_TYPE`] action holding the action to execute.
+pub const EXEC_KEY: &str = "exec";
+/// Start of the ids of inline actions, followed by the id of their task. Task ids can't start
+/// with `$`, so these never collide with the names of actions built in Rust.
+pub const INLINE_ACTION_PREFIX: &str = "$inline::";
+
+/// Id the action declared inline by the task `task` is registered under.
+pub fn inline_action_id(task: &str) -> ActionId {
+    format!("{INLINE_ACTION_PREFIX}{task}")
+}
+
+/// An action declared in a pipeline: the type of action and its config.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ActionConfig {
+    /// Type the action factory was registered with, e.g. `"opencv.template_match"`
+    pub type_name: String,
+    /// The other fields, deserialized by the factory into its config type
+    pub config: Map<String, Value>,
+}
+
+impl Serialize for ActionConfig {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(Some(self.config.len() + 1))?;
+        map.serialize_entry(TYPE_KEY, &self.type_name)?;
+        for (key, value) in &self.config {
+            map.serialize_entry(key, value)?;
+        }
+        map.end()
+    }
+}
+
+impl<'de> Deserialize<'de> for ActionConfig {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct ActionConfigVisitor;
+
+        impl<'de> Visitor<'de> for ActionConfigVisitor {
+            type Value = ActionConfig;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("an action with a \"type\" and its config")
+            }
+
+            fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+                let mut type_name = None;
+                let mut config = Map::new();
+                while let Some(key) = map.next_key::<String>()? {
+                    if key == TYPE_KEY {
+                        type_name = Some(map.next_value()?);
+                    } else {
+                        config.insert(key, map.next_value()?);
+                    }
+                }
+                let type_name = type_name.ok_or_else(|| de::Error::missing_field(TYPE_KEY))?;
+                Ok(ActionConfig { type_name, config })
+            }
+        }
+
+        deserializer.deserialize_map(ActionConfigVisitor)
+    }
+}
+
+type Factory<RUNTIME> =
+    dyn Fn(&ActionId, Map<String, Value>) -> Result<Box<dyn Action<RUNTIME>>, String>;
+
+/// Builds actions from their [`ActionConfig`], by type.
+pub struct ActionFactories<RUNTIME: Runtime> {
+    factories: BTreeMap<String, Box<Factory<RUNTIME>>>,
+}
+
+impl<RUNTIME: Runtime> ActionFactories<RUNTIME> {
+    pub fn new() -> Self {
+        Self {
+            factories: BTreeMap::new(),
+        }
+    }
+
+    /// Registers `factory` for actions of type `type_name`, configured with a `CONFIG`.
+    ///
+    /// The factory gets the id of the action, the [`inline_action_id`] of the task declaring it.
+    pub fn register<CONFIG, ACTION>(
+        &mut self,
+        type_name: impl Into<String>,
+        factory: impl Fn(&ActionId, CONFIG) -> ACTION + 'static,
+    ) -> &mut Self
+    where
+        CONFIG: DeserializeOwned,
+        ACTION: Action<RUNTIME> + 'static,
+    {
+        self.factories.insert(
+            type_name.into(),
+            Box::new(move |id, config| {
+                let config =
+                    serde_json::from_value(Value::Object(config)).map_err(|err| err.to_string())?;
+                Ok(Box::new(factory(id, config)))
+            }),
+        );
+        self
+    }
+
+    pub fn contains(&self, type_name: &str) -> bool {
+        type_name == COMPOSE_TYPE || self.factories.contains_key(type_name)
+    }
+
+    /// Builds every action declared in `pipeline`.
+    pub fn build(&self, pipeline: &Pipeline) -> Result<Actions<RUNTIME>, PipelineError>
+    where
+        RUNTIME: 'static,
+    {
+        let actions = pipeline
+            .actions()
+            .map(|(id, action)| Ok((id.clone(), self.build_action(id, action)?)))
+            .collect::<Result<_, PipelineError>>()?;
+        Ok(Actions { actions })
+    }
+
+    /// Builds the action `id` from its config.
+    pub fn build_action(
+        &self,
+        id: &ActionId,
+        action: &ActionConfig,
+    ) -> Result<Box<dyn Action<RUNTIME>>, PipelineError>
+    where
+        RUNTIME: 'static,
+    {
+        let invalid = |message: String| PipelineError::InvalidActionConfig {
+            id: id.clone(),
+            type_name: action.type_name.clone(),
+            message,
+        };
+        if action.type_name == COMPOSE_TYPE {
+            let part = |key: &str| {
+                let config = action
+                    .config
+                    .get(key)
+                    .ok_or_else(|| invalid(format!("missing field `{key}`")))?;
+                let config =
+                    ActionConfig::deserialize(config).map_err(|err| invalid(err.to_string()))?;
+                self.build_action(id, &config)
+            };
+            return Ok(Box::new(Compose::new(
+                part(RECOGNIZE_KEY)?,
+                part(EXEC_KEY)?,
+            )));
+        }
+        let factory = self.factories.get(&action.type_name).ok_or_else(|| {
+            PipelineError::UnknownActionType {
+                id: id.clone(),
+                type_name: action.type_name.clone(),
+            }
+        })?;
+        factory(id, action.config.clone()).map_err(invalid)
+    }
+}
+
+impl<RUNTIME: Runtime> Default for ActionFactories<RUNTIME> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Actions built by [`ActionFactories::build`], which tasks borrow through an [`ActionRegistry`].
+pub struct Actions<RUNTIME: Runtime> {
+    actions: BTreeMap<ActionId, Box<dyn Action<RUNTIME>>>,
+}
+
+impl<RUNTIME: Runtime> Actions<RUNTIME> {
+    pub fn get(&self, id: &str) -> Option<&dyn Action<RUNTIME>> {
+        self.actions.get(id).map(|action| action.as_ref())
+    }
+
+    pub fn len(&self) -> usize {
+        self.actions.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.actions.is_empty()
+    }
+
+    /// A registry with these actions only, see [`ActionRegistry::register_all`] to mix them with
+    /// actions built in Rust.
+    pub fn registry(&self) -> ActionRegistry<'_, RUNTIME> {
+        let mut registry = ActionRegistry::new();
+        registry.register_all(self);
+        registry
+    }
+}
+
+impl<'task, RUNTIME: Runtime> ActionRegistry<'task, RUNTIME> {
+    /// Registers every action of `actions` under its id.
+    pub fn register_all(&mut self, actions: &'task Actions<RUNTIME>) -> &mut Self {
+        for (id, action) in &actions.actions {
+            self.register_dyn(id.clone(), action.as_ref());
+        }
+        self
+    }
+}
@@ -0,0 +1,212 @@
+use alloc::collections::btree_map::Entry;
+use alloc::collections::BTreeMap;
+use alloc::string::String;
+use core::fmt;
+
+use serde::de::{self, MapAccess, Visitor};
+use serde::ser::SerializeMap;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use super::action::inline_action_id;
+use super::template::{Resolver, TaskTemplate, TemplateId};
+use super::variables::Variables;
+use super::{Format, Pipeline, PipelineError, NAMESPACE_SEPARATOR};
+use crate::task::TaskId;
+
+/// Key of the templates section of a pipeline document.
+pub const TEMPLATES_KEY: &str = "$templates";
+/// Key of the includes section of a pipeline document.
+pub const INCLUDE_KEY: &str = "$include";
+/// Start of the syntax error reported for a task defined twice in one document.
+pub(super) const DUPLICATE_TASK: &str = "duplicate task";
+/// Start of the syntax error reported for a namespace included twice in one document.
+pub(super) const DUPLICATE_NAMESPACE: &str = "duplicate include namespace";
+
+/// A pipeline document as written, before templates are applied.
+///
+/// Keys starting with `$` are reserved for sections such as [`INCLUDE_KEY`] and [`TEMPLATES_KEY`]
+/// and ignored when unknown, so `"$schema"` can point editors to the JSON Schema.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PipelineDocument {
+    /// Other documents to load, keyed by the namespace their tasks are put in
+    pub includes: BTreeMap<String, String>,
+    pub templates: BTreeMap<TemplateId, TaskTemplate>,
+    pub tasks: BTreeMap<TaskId, TaskTemplate>,
+}
+
+impl PipelineDocument {
+    /// Parses a pipeline document as written, `file` is only used to report errors.
+    pub fn parse(format: Format, file: &str, text: &str) -> Result<Self, PipelineError> {
+        format.parse(file, text, None)
+    }
+
+    /// Parses a pipeline document, substituting `variables` into its placeholders.
+    pub fn parse_with_variables(
+        format: Format,
+        file: &str,
+        text: &str,
+        variables: &Variables,
+    ) -> Result<Self, PipelineError> {
+        format.parse(file, text, Some(variables))
+    }
+
+    /// Applies templates to the tasks of this document, ignoring its includes.
+    pub(super) fn resolve(&self) -> Result<Pipeline, PipelineError> {
+        let mut resolver = Resolver::new(&self.templates);
+        // Broken templates are errors even if no task uses them yet
+        for (name, template) in &self.templates {
+            resolver.template(name, template)?;
+        }
+        let mut pipeline = Pipeline::default();
+        for (id, task) in &self.tasks {
+            if id.contains(NAMESPACE_SEPARATOR) {
+                return Err(PipelineError::InvalidTaskId { id: id.clone() });
+            }
+            let (config, action) = resolver.task_config(id, task)?;
+            if let Some(action) = action {
+                pipeline.actions.insert(config.action_name.clone(), action);
+            }
+            pipeline.tasks.insert(id.clone(), config);
+        }
+        Ok(pipeline)
+    }
+}
+
+/// Converts a single document; includes need [`Pipeline::from_file`] to be resolved.
+impl TryFrom<PipelineDocument> for Pipeline {
+    type Error = PipelineError;
+
+    fn try_from(document: PipelineDocument) -> Result<Self, Self::Error> {
+        if let Some(namespace) = document.includes.keys().next() {
+            return Err(PipelineError::UnresolvedInclude {
+                namespace: namespace.clone(),
+            });
+        }
+        document.resolve()
+    }
+}
+
+impl From<Pipeline> for PipelineDocument {
+    fn from(mut pipeline: Pipeline) -> Self {
+        let tasks = pipeline
+            .tasks
+            .into_iter()
+            .map(|(id, config)| {
+                // Only the task declaring an inline action writes it back inline
+                let action = (config.action_name == inline_action_id(&id))
+                    .then(|| pipeline.actions.remove(&config.action_name))
+                    .flatten();
+                (id, TaskTemplate::from_config(config, action))
+            })
+            .collect();
+        Self {
+            tasks,
+            ..Default::default()
+        }
+    }
+}
+
+impl Serialize for PipelineDocument {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let sections =
+            usize::from(!self.includes.is_empty()) + usize::from(!self.templates.is_empty());
+        let mut map = serializer.serialize_map(Some(self.tasks.len() + sections))?;
+        if !self.includes.is_empty() {
+            map.serialize_entry(INCLUDE_KEY, &self.includes)?;
+        }
+        if !self.templates.is_empty() {
+            map.serialize_entry(TEMPLATES_KEY, &self.templates)?;
+        }
+        for (id, task) in &self.tasks {
+            map.serialize_entry(id, task)?;
+        }
+        map.end()
+    }
+}
+
+impl<'de> Deserialize<'de> for PipelineDocument {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct DocumentVisitor;
+
+        impl<'de> Visitor<'de> for DocumentVisitor {
+            type Value = PipelineDocument;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("a map of task names to tasks")
+            }
+
+            // Entries are deserialized one by one rather than through `#[serde(flatten)]`, which
+            // would buffer them and lose the error positions.
+            fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+                let mut document = PipelineDocument::default();
+                let mut includes = None;
+                while let Some(key) = map.next_key::<TaskId>()? {
+                    if key == INCLUDE_KEY {
+                        if includes.is_some() {
+                            return Err(de::Error::duplicate_field(INCLUDE_KEY));
+                        }
+                        includes = Some(map.next_value::<Includes>()?.0);
+                    } else if key == TEMPLATES_KEY {
+                        document.templates = map.next_value()?;
+                    } else if key.starts_with('$') {
+                        map.next_value::<de::IgnoredAny>()?;
+                    } else {
+                        // Maps would silently keep the last one
+                        match document.tasks.entry(key) {
+                            Entry::Occupied(entry) => {
+                                let key = entry.key();
+                                return Err(de::Error::custom(format_args!(
+                                    "{DUPLICATE_TASK} {key}"
+                                )));
+                            }
+                            Entry::Vacant(entry) => {
+                                entry.insert(map.next_value()?);
+                            }
+                        }
+                    }
+                }
+                document.includes = includes.unwrap_or_default();
+                Ok(document)
+            }
+        }
+
+        deserializer.deserialize_map(DocumentVisitor)
+    }
+}
+
+/// The includes of a document, which can't give a namespace twice.
+struct Includes(BTreeMap<String, String>);
+
+impl<'de> Deserialize<'de> for Includes {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct IncludesVisitor;
+
+        impl<'de> Visitor<'de> for IncludesVisitor {
+            type Value = Includes;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("a map of namespaces to file paths")
+            }
+
+            fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+                let mut includes = BTreeMap::new();
+                while let Some(namespace) = map.next_key::<String>()? {
+                    match includes.entry(namespace) {
+                        Entry::Occupied(entry) => {
+                            let namespace = entry.key();
+                            return Err(de::Error::custom(format_args!(
+                                "{DUPLICATE_NAMESPACE} {namespace}"
+                            )));
+                        }
+                        Entry::Vacant(entry) => {
+                            entry.insert(map.next_value()?);
+                        }
+                    }
+                }
+                Ok(Includes(includes))
+            }
+        }
+
+        deserializer.deserialize_map(IncludesVisitor)
+    }
+}
@@ -0,0 +1,136 @@
+//! Document formats a [`Pipeline`](super::Pipeline) can be written in.
+//!
+//! Every format deserializes into the same [`TaskConfig`](crate::task::TaskConfig) values, and
+//! syntax errors are reported with the [`Location`] where they occurred.
+
+use alloc::string::{String, ToString};
+use core::fmt;
+
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Deserializer};
+
+use super::interpolate::Interpolate;
+use super::variables::Variables;
+use super::PipelineError;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Format {
+    #[cfg(feature = "json")]
+    Json,
+    #[cfg(feature = "yaml")]
+    Yaml,
+    #[cfg(feature = "toml")]
+    Toml,
+}
+
+impl Format {
+    /// Picks the format from a file extension such as `"json"`, `"yaml"`, `"yml"` or `"toml"`.
+    pub fn from_extension(extension: &str) -> Option<Self> {
+        match extension {
+            #[cfg(feature = "json")]
+            "json" => Some(Self::Json),
+            #[cfg(feature = "yaml")]
+            "yaml" | "yml" => Some(Self::Yaml),
+            #[cfg(feature = "toml")]
+            "toml" => Some(Self::Toml),
+            _ => None,
+        }
+    }
+
+    /// Parses `text`, substituting `variables` into it if any.
+    pub(super) fn parse<T: DeserializeOwned>(
+        self,
+        file: &str,
+        text: &str,
+        variables: Option<&Variables>,
+    ) -> Result<T, PipelineError> {
+        match self {
+            #[cfg(feature = "json")]
+            Self::Json => {
+                let mut deserializer = serde_json::Deserializer::from_str(text);
+                deserialize(&mut deserializer, variables)
+                    .and_then(|value| deserializer.end().map(|()| value))
+                    .map_err(|err| {
+                        let location = Location::new(file, err.line(), err.column());
+                        PipelineError::syntax(location, err.to_string())
+                    })
+            }
+            #[cfg(feature = "yaml")]
+            Self::Yaml => {
+                deserialize(serde_yaml::Deserializer::from_str(text), variables).map_err(|err| {
+                    let location = match err.location() {
+                        Some(at) => Location::new(file, at.line(), at.column()),
+                        None => Location::new(file, 0, 0),
+                    };
+                    PipelineError::syntax(location, err.to_string())
+                })
+            }
+            #[cfg(feature = "toml")]
+            Self::Toml => deserialize(toml::Deserializer::new(text), variables).map_err(|err| {
+                let location = match err.span() {
+                    Some(span) => Location::from_offset(file, text, span.start),
+                    None => Location::new(file, 0, 0),
+                };
+                PipelineError::syntax(location, err.message().to_string())
+            }),
+        }
+    }
+}
+
+fn deserialize<'de, T: Deserialize<'de>, D: Deserializer<'de>>(
+    deserializer: D,
+    variables: Option<&Variables>,
+) -> Result<T, D::Error> {
+    match variables {
+        Some(variables) => T::deserialize(Interpolate::new(deserializer, variables)),
+        None => T::deserialize(deserializer),
+    }
+}
+
+/// Position in a pipeline document. `line` and `column` are 1-based, or 0 when unknown.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Location {
+    pub file: String,
+    pub line: usize,
+    pub column: usize,
+}
+
+impl Location {
+    pub fn new(file: impl Into<String>, line: usize, column: usize) -> Self {
+        Self {
+            file: file.into(),
+            line,
+            column,
+        }
+    }
+
+    /// Location of the byte `offset` of `text`.
+    pub fn from_offset(file: impl Into<String>, text: &str, offset: usize) -> Self {
+        let before = &text[..offset.min(text.len())];
+        let line_start = before.rfind('\n').map_or(0, |newline| newline + 1);
+        Self::new(
+            file,
+            before.matches('\n').count() + 1,
+            before[line_start..].chars().count() + 1,
+        )
+    }
+}
+
+impl fmt::Display for Location {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (self.line, self.column) {
+            (0, _) => write!(f, "{}", self.file),
+            (line, 0) => write!(f, "{}:{line}", self.file),
+            (line, column) => write!(f, "{}:{line}:{column}", self.file),
+        }
+    }
+}
+
+/// Removes the ` at line X column Y` suffix parsers append, since [`Location`] already carries it.
+pub(super) fn strip_position(message: &str) -> &str {
+    match message.rfind(" at line ") {
+        Some(at) if message[at..].contains(" column ") => &message[..at],
+        _ => message,
+    }
+}
@@ -0,0 +1,135 @@
+//! Pipelines split over several files.
+//!
+//! A document lists the files it includes under `$include`, keyed by namespace. Tasks of an
+//! included file get the namespace as prefix, so `start` in the file included as `login` becomes
+//! `login::start`:
+//!
+//! ```json
+//! {
+//!   "$include": { "login": "login.json", "shop": "shop/main.yaml" },
+//!   "entry": { "action_name": "open_app", "next_task": ["login::start"] }
+//! }
+//! ```
+//!
+//! Paths are relative to the including file, and includes may be nested. Like a task, a
+//! namespace can only be given once. Task references, guards included, are relative to the
+//! namespace of the file they are written in: `start` in `login.json` means `login::start`, while
+//! a leading `::` starts from the root file, as in `::shop::buy` or `runs["::shop::buy"]`.
+//! Templates are local to their file.
+
+use alloc::collections::btree_map::Entry;
+use alloc::vec::Vec;
+use std::path::{Path, PathBuf};
+
+use super::action::inline_action_id;
+use super::variables::Variables;
+use super::{Format, Pipeline, PipelineDocument, PipelineError, NAMESPACE_SEPARATOR};
+use crate::task::TaskId;
+
+/// Loads the file at `path` and everything it includes.
+pub(super) fn load(path: &Path, variables: Option<&Variables>) -> Result<Pipeline, PipelineError> {
+    let mut loader = Loader {
+        variables,
+        including: Vec::new(),
+        pipeline: Pipeline::default(),
+    };
+    loader.load(path, "")?;
+    Ok(loader.pipeline)
+}
+
+struct Loader<'v> {
+    /// Substituted into every file, which are read as written without them
+    variables: Option<&'v Variables>,
+    /// Canonical paths of the files being loaded, to find include cycles
+    including: Vec<PathBuf>,
+    pipeline: Pipeline,
+}
+
+impl Loader<'_> {
+    /// Loads the file at `path` with `prefix` added to its task ids.
+    fn load(&mut self, path: &Path, prefix: &str) -> Result<(), PipelineError> {
+        let file = path.display().to_string();
+        let io_error = |source| PipelineError::Io {
+            file: file.clone(),
+            source,
+        };
+        let format = path
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .and_then(Format::from_extension)
+            .ok_or_else(|| PipelineError::UnknownFormat { file: file.clone() })?;
+        let canonical = path.canonicalize().map_err(io_error)?;
+        if self.including.contains(&canonical) {
+            return Err(PipelineError::IncludeCycle { file });
+        }
+        let text = std::fs::read_to_string(path).map_err(io_error)?;
+        let document: PipelineDocument = format.parse(&file, &text, self.variables)?;
+        let in_file = |source| PipelineError::InFile {
+            file: file.clone(),
+            source: Box::new(source),
+        };
+
+        let mut local = document.resolve().map_err(in_file)?;
+        for (local_id, mut config) in local.tasks {
+            let id = qualify(prefix, &local_id);
+            if config.action_name == inline_action_id(&local_id) {
+                let action = local.actions.remove(&config.action_name);
+                config.action_name = inline_action_id(&id);
+                self.pipeline
+                    .actions
+                    .extend(action.map(|action| (config.action_name.clone(), action)));
+            }
+            for reference in config
+                .next_task
+                .iter_mut()
+                .chain(config.interrupt_task.iter_mut())
+            {
+                *reference = qualify(prefix, reference);
+            }
+            config.guards = core::mem::take(&mut config.guards)
+                .into_iter()
+                .map(|(target, mut guard)| {
+                    guard
+                        .condition
+                        .map_tasks(|reference| qualify(prefix, reference));
+                    (qualify(prefix, &target), guard)
+                })
+                .collect();
+            config.task_name = id.clone();
+            match self.pipeline.tasks.entry(id) {
+                Entry::Occupied(entry) => {
+                    let id = entry.key().clone();
+                    return Err(in_file(PipelineError::DuplicateTask { id }));
+                }
+                Entry::Vacant(entry) => {
+                    entry.insert(config);
+                }
+            }
+        }
+
+        self.including.push(canonical);
+        let dir = path.parent().unwrap_or(Path::new(""));
+        for (namespace, include) in &document.includes {
+            if namespace.is_empty()
+                || namespace.starts_with('$')
+                || namespace.contains(NAMESPACE_SEPARATOR)
+            {
+                return Err(in_file(PipelineError::InvalidNamespace {
+                    namespace: namespace.clone(),
+                }));
+            }
+            let prefix = format!("{prefix}{namespace}{NAMESPACE_SEPARATOR}");
+            self.load(&dir.join(include), &prefix)?;
+        }
+        self.including.pop();
+        Ok(())
+    }
+}
+
+/// Resolves `reference`, written in the file whose tasks get `prefix`, to a full task id.
+fn qualify(prefix: &str, reference: &str) -> TaskId {
+    match reference.strip_prefix(NAMESPACE_SEPARATOR) {
+        Some(absolute) => absolute.into(),
+        None => format!("{prefix}{reference}"),
+    }
+}
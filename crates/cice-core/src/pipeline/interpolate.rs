@@ -0,0 +1,456 @@
+//! Deserializer adapter substituting [`Variables`] into the strings it reads.
+//!
+//! Substituting while deserializing, rather than in the text or in an intermediate value, keeps
+//! the formats reporting errors at their location in the document.
+
+use alloc::string::{String, ToString};
+use core::fmt;
+
+use serde::de::{self, DeserializeSeed, Deserializer, EnumAccess, MapAccess, SeqAccess};
+use serde::de::{VariantAccess, Visitor};
+use serde_json::Value;
+
+use super::variables::{Substitution, Variables};
+
+/// Wraps a deserializer so the placeholders of its strings are substituted.
+pub(super) struct Interpolate<'v, D> {
+    inner: D,
+    variables: &'v Variables,
+}
+
+impl<'v, D> Interpolate<'v, D> {
+    pub(super) fn new(inner: D, variables: &'v Variables) -> Self {
+        Self { inner, variables }
+    }
+}
+
+/// Type the deserialized value asked for, used to convert a placeholder's value to it.
+#[derive(Clone, Copy)]
+enum Hint {
+    Any,
+    Bool,
+    Unsigned,
+    Signed,
+    Float,
+    Str,
+}
+
+/// Wraps the visitors, seeds and accessors handed to the inner deserializer.
+struct Wrap<'v, X> {
+    inner: X,
+    variables: &'v Variables,
+    hint: Hint,
+}
+
+impl<'v, X> Wrap<'v, X> {
+    fn new(inner: X, variables: &'v Variables, hint: Hint) -> Self {
+        Self {
+            inner,
+            variables,
+            hint,
+        }
+    }
+}
+
+// Strings can only be substituted if the inner deserializer hands them to the visitor, so scalars
+// are read with `deserialize_any`, which every pipeline format supports. Strings are still asked
+// for as such, so YAML reads unquoted scalars such as `2024` or `yes` into string fields.
+macro_rules! forward_scalar {
+    ($($method:ident => $hint:ident,)*) => {
+        $(
+            fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+                self.inner
+                    .deserialize_any(Wrap::new(visitor, self.variables, Hint::$hint))
+            }
+        )*
+    };
+}
+
+impl<'de, D: Deserializer<'de>> Deserializer<'de> for Interpolate<'_, D> {
+    type Error = D::Error;
+
+    forward_scalar! {
+        deserialize_any => Any,
+        deserialize_bool => Bool,
+        deserialize_i8 => Signed,
+        deserialize_i16 => Signed,
+        deserialize_i32 => Signed,
+        deserialize_i64 => Signed,
+        deserialize_i128 => Signed,
+        deserialize_u8 => Unsigned,
+        deserialize_u16 => Unsigned,
+        deserialize_u32 => Unsigned,
+        deserialize_u64 => Unsigned,
+        deserialize_u128 => Unsigned,
+        deserialize_f32 => Float,
+        deserialize_f64 => Float,
+    }
+
+    fn deserialize_char<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        let visitor = Wrap::new(visitor, self.variables, Hint::Str);
+        self.inner.deserialize_char(visitor)
+    }
+
+    fn deserialize_str<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        let visitor = Wrap::new(visitor, self.variables, Hint::Str);
+        self.inner.deserialize_str(visitor)
+    }
+
+    fn deserialize_string<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        let visitor = Wrap::new(visitor, self.variables, Hint::Str);
+        self.inner.deserialize_string(visitor)
+    }
+
+    fn deserialize_bytes<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        let visitor = Wrap::new(visitor, self.variables, Hint::Any);
+        self.inner.deserialize_bytes(visitor)
+    }
+
+    fn deserialize_byte_buf<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        let visitor = Wrap::new(visitor, self.variables, Hint::Any);
+        self.inner.deserialize_byte_buf(visitor)
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        let visitor = Wrap::new(visitor, self.variables, Hint::Any);
+        self.inner.deserialize_option(visitor)
+    }
+
+    fn deserialize_unit<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        let visitor = Wrap::new(visitor, self.variables, Hint::Any);
+        self.inner.deserialize_unit(visitor)
+    }
+
+    fn deserialize_unit_struct<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        let visitor = Wrap::new(visitor, self.variables, Hint::Any);
+        self.inner.deserialize_unit_struct(name, visitor)
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        let visitor = Wrap::new(visitor, self.variables, Hint::Any);
+        self.inner.deserialize_newtype_struct(name, visitor)
+    }
+
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        let visitor = Wrap::new(visitor, self.variables, Hint::Any);
+        self.inner.deserialize_seq(visitor)
+    }
+
+    fn deserialize_tuple<V: Visitor<'de>>(
+        self,
+        len: usize,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        let visitor = Wrap::new(visitor, self.variables, Hint::Any);
+        self.inner.deserialize_tuple(len, visitor)
+    }
+
+    fn deserialize_tuple_struct<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        len: usize,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        let visitor = Wrap::new(visitor, self.variables, Hint::Any);
+        self.inner.deserialize_tuple_struct(name, len, visitor)
+    }
+
+    fn deserialize_map<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        let visitor = Wrap::new(visitor, self.variables, Hint::Any);
+        self.inner.deserialize_map(visitor)
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        let visitor = Wrap::new(visitor, self.variables, Hint::Any);
+        self.inner.deserialize_struct(name, fields, visitor)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        let visitor = Wrap::new(visitor, self.variables, Hint::Any);
+        self.inner.deserialize_enum(name, variants, visitor)
+    }
+
+    fn deserialize_identifier<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        let visitor = Wrap::new(visitor, self.variables, Hint::Str);
+        self.inner.deserialize_identifier(visitor)
+    }
+
+    fn deserialize_ignored_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        self.inner.deserialize_ignored_any(visitor)
+    }
+
+    fn is_human_readable(&self) -> bool {
+        self.inner.is_human_readable()
+    }
+}
+
+impl<'v, V> Wrap<'v, V> {
+    /// Hands `value` to the visitor, converted to the type it asked for when needed.
+    fn visit_value<'de, E>(self, value: &Value) -> Result<V::Value, E>
+    where
+        V: Visitor<'de>,
+        E: de::Error,
+    {
+        let visitor = self.inner;
+        match value {
+            Value::Number(_) | Value::Bool(_) if matches!(self.hint, Hint::Str) => {
+                return visitor.visit_string(value.to_string());
+            }
+            // Strings, e.g. quoted in a profile, still fill numbers and booleans
+            Value::String(text) => match self.hint {
+                Hint::Bool => {
+                    if let Ok(value) = text.parse() {
+                        return visitor.visit_bool(value);
+                    }
+                }
+                Hint::Unsigned => {
+                    if let Ok(value) = text.parse() {
+                        return visitor.visit_u64(value);
+                    }
+                }
+                Hint::Signed => {
+                    if let Ok(value) = text.parse() {
+                        return visitor.visit_i64(value);
+                    }
+                }
+                Hint::Float => {
+                    if let Ok(value) = text.parse() {
+                        return visitor.visit_f64(value);
+                    }
+                }
+                Hint::Any | Hint::Str => {}
+            },
+            _ => {}
+        }
+        value
+            .clone()
+            .deserialize_any(visitor)
+            .map_err(de::Error::custom)
+    }
+
+    fn substitute<'de, E>(
+        self,
+        text: &str,
+        unchanged: impl FnOnce(V) -> Result<V::Value, E>,
+    ) -> Result<V::Value, E>
+    where
+        V: Visitor<'de>,
+        E: de::Error,
+    {
+        match self.variables.substitute(text).map_err(E::custom)? {
+            Substitution::Unchanged => unchanged(self.inner),
+            Substitution::Text(text) => self.inner.visit_string(text),
+            Substitution::Value(value) => self.visit_value(value),
+        }
+    }
+}
+
+macro_rules! forward_visit {
+    ($($method:ident($ty:ty),)*) => {
+        $(
+            fn $method<E: de::Error>(self, value: $ty) -> Result<Self::Value, E> {
+                self.inner.$method(value)
+            }
+        )*
+    };
+}
+
+impl<'de, V: Visitor<'de>> Visitor<'de> for Wrap<'_, V> {
+    type Value = V::Value;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        self.inner.expecting(formatter)
+    }
+
+    forward_visit! {
+        visit_bool(bool),
+        visit_i8(i8),
+        visit_i16(i16),
+        visit_i32(i32),
+        visit_i64(i64),
+        visit_i128(i128),
+        visit_u8(u8),
+        visit_u16(u16),
+        visit_u32(u32),
+        visit_u64(u64),
+        visit_u128(u128),
+        visit_f32(f32),
+        visit_f64(f64),
+        visit_char(char),
+        visit_bytes(&[u8]),
+        visit_byte_buf(alloc::vec::Vec<u8>),
+    }
+
+    fn visit_borrowed_bytes<E: de::Error>(self, value: &'de [u8]) -> Result<Self::Value, E> {
+        self.inner.visit_borrowed_bytes(value)
+    }
+
+    fn visit_str<E: de::Error>(self, value: &str) -> Result<Self::Value, E> {
+        self.substitute(value, |visitor| visitor.visit_str(value))
+    }
+
+    fn visit_borrowed_str<E: de::Error>(self, value: &'de str) -> Result<Self::Value, E> {
+        self.substitute(value, |visitor| visitor.visit_borrowed_str(value))
+    }
+
+    fn visit_string<E: de::Error>(self, value: String) -> Result<Self::Value, E> {
+        match self.variables.substitute(&value).map_err(E::custom)? {
+            Substitution::Unchanged => self.inner.visit_string(value),
+            Substitution::Text(text) => self.inner.visit_string(text),
+            Substitution::Value(value) => self.visit_value(value),
+        }
+    }
+
+    fn visit_none<E: de::Error>(self) -> Result<Self::Value, E> {
+        self.inner.visit_none()
+    }
+
+    fn visit_some<D: Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+        self.inner
+            .visit_some(Interpolate::new(deserializer, self.variables))
+    }
+
+    fn visit_unit<E: de::Error>(self) -> Result<Self::Value, E> {
+        self.inner.visit_unit()
+    }
+
+    fn visit_newtype_struct<D: Deserializer<'de>>(
+        self,
+        deserializer: D,
+    ) -> Result<Self::Value, D::Error> {
+        self.inner
+            .visit_newtype_struct(Interpolate::new(deserializer, self.variables))
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, seq: A) -> Result<Self::Value, A::Error> {
+        self.inner
+            .visit_seq(Wrap::new(seq, self.variables, Hint::Any))
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, map: A) -> Result<Self::Value, A::Error> {
+        self.inner
+            .visit_map(Wrap::new(map, self.variables, Hint::Any))
+    }
+
+    fn visit_enum<A: EnumAccess<'de>>(self, data: A) -> Result<Self::Value, A::Error> {
+        self.inner
+            .visit_enum(Wrap::new(data, self.variables, Hint::Any))
+    }
+}
+
+impl<'de, S: DeserializeSeed<'de>> DeserializeSeed<'de> for Wrap<'_, S> {
+    type Value = S::Value;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+        self.inner
+            .deserialize(Interpolate::new(deserializer, self.variables))
+    }
+}
+
+impl<'de, A: SeqAccess<'de>> SeqAccess<'de> for Wrap<'_, A> {
+    type Error = A::Error;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(
+        &mut self,
+        seed: T,
+    ) -> Result<Option<T::Value>, Self::Error> {
+        self.inner
+            .next_element_seed(Wrap::new(seed, self.variables, Hint::Any))
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        self.inner.size_hint()
+    }
+}
+
+impl<'de, A: MapAccess<'de>> MapAccess<'de> for Wrap<'_, A> {
+    type Error = A::Error;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(
+        &mut self,
+        seed: K,
+    ) -> Result<Option<K::Value>, Self::Error> {
+        self.inner
+            .next_key_seed(Wrap::new(seed, self.variables, Hint::Any))
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(
+        &mut self,
+        seed: V,
+    ) -> Result<V::Value, Self::Error> {
+        self.inner
+            .next_value_seed(Wrap::new(seed, self.variables, Hint::Any))
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        self.inner.size_hint()
+    }
+}
+
+impl<'de, 'v, A: EnumAccess<'de>> EnumAccess<'de> for Wrap<'v, A> {
+    type Error = A::Error;
+    type Variant = Wrap<'v, A::Variant>;
+
+    fn variant_seed<V: DeserializeSeed<'de>>(
+        self,
+        seed: V,
+    ) -> Result<(V::Value, Self::Variant), Self::Error> {
+        let variables = self.variables;
+        let (value, variant) = self
+            .inner
+            .variant_seed(Wrap::new(seed, variables, Hint::Any))?;
+        Ok((value, Wrap::new(variant, variables, Hint::Any)))
+    }
+}
+
+impl<'de, A: VariantAccess<'de>> VariantAccess<'de> for Wrap<'_, A> {
+    type Error = A::Error;
+
+    fn unit_variant(self) -> Result<(), Self::Error> {
+        self.inner.unit_variant()
+    }
+
+    fn newtype_variant_seed<T: DeserializeSeed<'de>>(
+        self,
+        seed: T,
+    ) -> Result<T::Value, Self::Error> {
+        self.inner
+            .newtype_variant_seed(Wrap::new(seed, self.variables, Hint::Any))
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(
+        self,
+        len: usize,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        self.inner
+            .tuple_variant(len, Wrap::new(visitor, self.variables, Hint::Any))
+    }
+
+    fn struct_variant<V: Visitor<'de>>(
+        self,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        self.inner
+            .struct_variant(fields, Wrap::new(visitor, self.variables, Hint::Any))
+    }
+}
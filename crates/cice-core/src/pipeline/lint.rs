@@ -0,0 +1,318 @@
+//! Static checks of pipelines, run without a runtime.
+//!
+//! The [`Linter`] checks the task graph for references to undefined tasks, tasks the entries
+//! never lead to and cycles a run can't leave. Action crates add checks for the configs of the
+//! action types they provide, as they register factories in [`ActionFactories`]:
+//!
+//! ```
+//! # use cice_core::pipeline::lint::{Linter, Rule};
+//! # use cice_core::pipeline::Pipeline;
+//! use cice_core::action::input::{Click, Target};
+//!
+//! let pipeline = Pipeline::from_json(r#"{
+//!     "start": { "action": { "type": "input.click", "at": [2000, 10] }, "next_task": ["end"] }
+//! }"#).unwrap();
+//! let mut linter = Linter::new();
+//! linter.screen(1920, 1080).action("input.click", |lint, config: Click| {
+//!     let Target::Point(x, y) = config.at else {
+//!         return;
+//!     };
+//!     if lint.screen().is_some_and(|(width, height)| x as u64 >= width || y as u64 >= height) {
+//!         lint.report(Rule::OutsideScreen, "click outside the screen");
+//!     }
+//! });
+//! let findings = linter.lint(&pipeline);
+//! assert_eq!(findings[0].rule, Rule::DanglingReference);
+//! assert_eq!(findings[1].rule, Rule::OutsideScreen);
+//! ```
+//!
+//! [`ActionFactories`]: super::action::ActionFactories
+
+use alloc::boxed::Box;
+use alloc::collections::BTreeMap;
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::fmt;
+
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use super::action::{ActionConfig, COMPOSE_TYPE, EXEC_KEY, INLINE_ACTION_PREFIX, RECOGNIZE_KEY};
+use super::document::{DUPLICATE_NAMESPACE, DUPLICATE_TASK};
+use super::{Pipeline, PipelineError};
+use crate::action::ActionId;
+use crate::graph::{EdgeKind, TaskGraph};
+use crate::task::TaskId;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Severity {
+    /// Likely a mistake, but the pipeline can run
+    Warning,
+    /// The pipeline fails or misbehaves when run
+    Error,
+}
+
+/// What a [`Finding`] is about.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Rule {
+    /// The pipeline can't be loaded
+    InvalidPipeline,
+    /// A task or include namespace is defined twice
+    DuplicateId,
+    /// A task or entry refers to an undefined task
+    DanglingReference,
+    /// No entry leads to a task
+    Unreachable,
+    /// Tasks lead to each other without ever reaching a terminal task
+    CycleWithoutExit,
+    /// The config of an action doesn't match its type
+    InvalidAction,
+    /// A file an action reads doesn't exist
+    MissingFile,
+    /// A region or position lies outside the screen
+    OutsideScreen,
+    /// A number is outside the range it is meaningful in
+    OutOfRange,
+}
+
+impl Rule {
+    pub fn severity(self) -> Severity {
+        match self {
+            Self::Unreachable | Self::CycleWithoutExit => Severity::Warning,
+            _ => Severity::Error,
+        }
+    }
+
+    /// Name of the rule, as serialized.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::InvalidPipeline => "invalid-pipeline",
+            Self::DuplicateId => "duplicate-id",
+            Self::DanglingReference => "dangling-reference",
+            Self::Unreachable => "unreachable",
+            Self::CycleWithoutExit => "cycle-without-exit",
+            Self::InvalidAction => "invalid-action",
+            Self::MissingFile => "missing-file",
+            Self::OutsideScreen => "outside-screen",
+            Self::OutOfRange => "out-of-range",
+        }
+    }
+}
+
+impl fmt::Display for Rule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// A problem found in a pipeline.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Finding {
+    pub rule: Rule,
+    pub severity: Severity,
+    /// Task the problem is in, if it is in one
+    pub task: Option<TaskId>,
+    pub message: String,
+}
+
+impl Finding {
+    pub fn new(rule: Rule, task: Option<TaskId>, message: impl Into<String>) -> Self {
+        Self {
+            rule,
+            severity: rule.severity(),
+            task,
+            message: message.into(),
+        }
+    }
+
+    /// The finding for a pipeline that failed to load.
+    pub fn load_error(err: &PipelineError) -> Self {
+        let mut cause = err;
+        while let PipelineError::InFile { source, .. } = cause {
+            cause = source;
+        }
+        let (rule, task) = match cause {
+            PipelineError::DuplicateTask { id } => (Rule::DuplicateId, Some(id.clone())),
+            PipelineError::Syntax { message, .. }
+                if message.starts_with(DUPLICATE_TASK)
+                    || message.starts_with(DUPLICATE_NAMESPACE) =>
+            {
+                (Rule::DuplicateId, None)
+            }
+            _ => (Rule::InvalidPipeline, None),
+        };
+        Self::new(rule, task, err.to_string())
+    }
+}
+
+impl fmt::Display for Finding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let severity = match self.severity {
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        };
+        write!(f, "{severity}[{}]", self.rule)?;
+        if let Some(task) = &self.task {
+            write!(f, " {task}")?;
+        }
+        write!(f, ": {}", self.message)
+    }
+}
+
+/// Collects the findings about one action, given to the checks registered with
+/// [`Linter::action`].
+pub struct ActionLint<'l> {
+    id: &'l ActionId,
+    screen: Option<(u64, u64)>,
+    findings: &'l mut Vec<Finding>,
+}
+
+impl ActionLint<'_> {
+    pub fn id(&self) -> &ActionId {
+        self.id
+    }
+
+    /// Width and height of the screen, when set with [`Linter::screen`].
+    pub fn screen(&self) -> Option<(u64, u64)> {
+        self.screen
+    }
+
+    pub fn report(&mut self, rule: Rule, message: impl Into<String>) {
+        self.findings
+            .push(Finding::new(rule, Some(self.id.clone()), message));
+    }
+}
+
+type Check = dyn Fn(&mut ActionLint<'_>, Map<String, Value>) -> Result<(), String>;
+
+/// Checks pipelines, see the [module documentation](self).
+#[derive(Default)]
+pub struct Linter {
+    entries: Vec<TaskId>,
+    screen: Option<(u64, u64)>,
+    checks: BTreeMap<String, Box<Check>>,
+}
+
+impl Linter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Marks `id` as a task the pipeline is run from; defaults to the tasks nothing leads to.
+    pub fn entry(&mut self, id: impl Into<TaskId>) -> &mut Self {
+        self.entries.push(id.into());
+        self
+    }
+
+    /// Size of the screen the pipeline is run on, for checks of regions and positions.
+    pub fn screen(&mut self, width: u64, height: u64) -> &mut Self {
+        self.screen = Some((width, height));
+        self
+    }
+
+    /// Registers `check` for actions of type `type_name`, configured with a `CONFIG`.
+    ///
+    /// Configs that don't deserialize into `CONFIG` are reported as [`Rule::InvalidAction`].
+    pub fn action<CONFIG>(
+        &mut self,
+        type_name: impl Into<String>,
+        check: impl Fn(&mut ActionLint<'_>, CONFIG) + 'static,
+    ) -> &mut Self
+    where
+        CONFIG: DeserializeOwned,
+    {
+        self.checks.insert(
+            type_name.into(),
+            Box::new(move |lint, config| {
+                let config =
+                    serde_json::from_value(Value::Object(config)).map_err(|err| err.to_string())?;
+                check(lint, config);
+                Ok(())
+            }),
+        );
+        self
+    }
+
+    /// Checks `pipeline`, returning its findings grouped by rule.
+    pub fn lint(&self, pipeline: &Pipeline) -> Vec<Finding> {
+        let mut graph = TaskGraph::from(pipeline);
+        for entry in &self.entries {
+            graph.entry(entry.clone());
+        }
+        let dangling = graph.dangling();
+        let mut findings = Vec::new();
+        for entry in &self.entries {
+            if dangling.contains(entry.as_str()) {
+                let message = format!("entry {entry} is not defined");
+                findings.push(Finding::new(Rule::DanglingReference, None, message));
+            }
+        }
+        for edge in graph.edges() {
+            let field = match edge.kind {
+                EdgeKind::Next => "next_task",
+                EdgeKind::Interrupt => "interrupt_task",
+                EdgeKind::Error => continue,
+            };
+            if dangling.contains(edge.to.as_str()) {
+                let message = format!("{field} refers to undefined task {}", edge.to);
+                findings.push(Finding::new(
+                    Rule::DanglingReference,
+                    Some(edge.from),
+                    message,
+                ));
+            }
+        }
+        for id in graph.unreachable() {
+            let message = "no entry leads to this task";
+            findings.push(Finding::new(Rule::Unreachable, Some(id.into()), message));
+        }
+        for cycle in graph.cycles_without_exit() {
+            let tasks: Vec<&str> = cycle.into_iter().collect();
+            let message = format!(
+                "tasks {} lead to each other but never to a task without next_task",
+                tasks.join(", ")
+            );
+            let task = Some(tasks[0].into());
+            findings.push(Finding::new(Rule::CycleWithoutExit, task, message));
+        }
+        for (id, action) in pipeline.actions() {
+            // Findings about inline actions are reported on their task
+            let task = id.strip_prefix(INLINE_ACTION_PREFIX).unwrap_or(id);
+            self.lint_action(&task.into(), action, &mut findings);
+        }
+        findings
+    }
+
+    fn lint_action(&self, id: &ActionId, action: &ActionConfig, findings: &mut Vec<Finding>) {
+        let invalid = |message: String| {
+            let message = format!("{} action: {message}", action.type_name);
+            Finding::new(Rule::InvalidAction, Some(id.clone()), message)
+        };
+        if action.type_name == COMPOSE_TYPE {
+            for key in [RECOGNIZE_KEY, EXEC_KEY] {
+                match action.config.get(key).map(ActionConfig::deserialize) {
+                    Some(Ok(part)) => self.lint_action(id, &part, findings),
+                    Some(Err(err)) => findings.push(invalid(err.to_string())),
+                    None => findings.push(invalid(format!("missing field `{key}`"))),
+                }
+            }
+            return;
+        }
+        // Types without a check may still be provided by action crates this linter doesn't know
+        let Some(check) = self.checks.get(&action.type_name) else {
+            return;
+        };
+        let mut lint = ActionLint {
+            id,
+            screen: self.screen,
+            findings,
+        };
+        if let Err(message) = check(&mut lint, action.config.clone()) {
+            findings.push(invalid(message));
+        }
+    }
+}
@@ -0,0 +1,270 @@
+//! Pipeline documents: a set of [`TaskConfig`]s keyed by task name.
+//!
+//! ```json
+//! {
+//!   "login": {
+//!     "action_name": "click_login",
+//!     "next_task": ["home"],
+//!     "timeout": "10s"
+//!   },
+//!   "home": { "action_name": "find_home" }
+//! }
+//! ```
+//!
+//! Omitted fields take their defaults, see [`TaskConfig`]. Shared fields can be moved to
+//! templates, see [`template`], and large pipelines split over several files, see [`include`](mod@include).
+//! YAML and TOML documents with the same layout are supported behind the `yaml` and `toml`
+//! features, see [`Format`]. Tasks can also declare their action inline, see [`action`], and
+//! strings can refer to `${name}` variables given when loading, see [`variables`]. Pipelines
+//! are checked for mistakes without running them by [`lint`].
+
+use alloc::collections::BTreeMap;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+
+use serde::{Deserialize, Serialize};
+use snafu::Snafu;
+
+use crate::action::{Action, ActionId};
+use crate::context::{ContextBuilder, ContextError};
+use crate::runtime::Runtime;
+use crate::task::{TaskConfig, TaskId};
+use action::ActionConfig;
+use template::TemplateId;
+use variables::Variables;
+
+pub mod action;
+mod document;
+// Without any format feature, `Format` has no variants and nothing parses
+#[cfg_attr(
+    not(any(feature = "json", feature = "yaml", feature = "toml")),
+    allow(unused)
+)]
+mod format;
+#[cfg(feature = "std")]
+pub mod include;
+mod interpolate;
+pub mod lint;
+#[cfg(feature = "schema")]
+pub mod schema;
+pub mod template;
+pub mod variables;
+
+pub use document::{PipelineDocument, INCLUDE_KEY, TEMPLATES_KEY};
+pub use format::{Format, Location};
+
+/// File name used in diagnostics for documents that were not read from a file.
+#[cfg_attr(
+    not(any(feature = "json", feature = "yaml", feature = "toml")),
+    allow(unused)
+)]
+const INLINE_FILE: &str = "<inline>";
+
+/// Separates the namespaces of a task id, as in `login::start`.
+pub const NAMESPACE_SEPARATOR: &str = "::";
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "PipelineDocument", into = "PipelineDocument")]
+pub struct Pipeline {
+    tasks: BTreeMap<TaskId, TaskConfig>,
+    /// Actions declared inline, keyed by the `action_name` their task got
+    actions: BTreeMap<ActionId, ActionConfig>,
+}
+
+impl Pipeline {
+    /// Parses a pipeline document as written, `file` is only used to report errors.
+    pub fn parse(format: Format, file: &str, text: &str) -> Result<Self, PipelineError> {
+        PipelineDocument::parse(format, file, text)?.try_into()
+    }
+
+    /// Parses a pipeline document, substituting `variables` into its placeholders, see
+    /// [`variables`].
+    pub fn parse_with_variables(
+        format: Format,
+        file: &str,
+        text: &str,
+        variables: &Variables,
+    ) -> Result<Self, PipelineError> {
+        PipelineDocument::parse_with_variables(format, file, text, variables)?.try_into()
+    }
+
+    /// Parses a JSON pipeline document.
+    #[cfg(feature = "json")]
+    pub fn from_json(text: &str) -> Result<Self, PipelineError> {
+        Self::parse(Format::Json, INLINE_FILE, text)
+    }
+
+    /// Parses a YAML pipeline document.
+    #[cfg(feature = "yaml")]
+    pub fn from_yaml(text: &str) -> Result<Self, PipelineError> {
+        Self::parse(Format::Yaml, INLINE_FILE, text)
+    }
+
+    /// Parses a TOML pipeline document.
+    #[cfg(feature = "toml")]
+    pub fn from_toml(text: &str) -> Result<Self, PipelineError> {
+        Self::parse(Format::Toml, INLINE_FILE, text)
+    }
+
+    /// Reads a pipeline file and the files it includes, choosing formats from file extensions.
+    #[cfg(feature = "std")]
+    pub fn from_file(path: impl AsRef<std::path::Path>) -> Result<Self, PipelineError> {
+        include::load(path.as_ref(), None)
+    }
+
+    /// Reads a pipeline file and the files it includes, substituting `variables` into all of them.
+    #[cfg(feature = "std")]
+    pub fn from_file_with_variables(
+        path: impl AsRef<std::path::Path>,
+        variables: &Variables,
+    ) -> Result<Self, PipelineError> {
+        include::load(path.as_ref(), Some(variables))
+    }
+
+    pub fn get(&self, id: &str) -> Option<&TaskConfig> {
+        self.tasks.get(id)
+    }
+
+    pub fn tasks(&self) -> impl Iterator<Item = &TaskConfig> {
+        self.tasks.values()
+    }
+
+    /// Actions declared in the pipeline, keyed by their action id, see [`action`].
+    pub fn actions(&self) -> impl Iterator<Item = (&ActionId, &ActionConfig)> {
+        self.actions.iter()
+    }
+
+    pub fn into_task_configs(self) -> Vec<TaskConfig> {
+        self.tasks.into_values().collect()
+    }
+}
+
+/// Actions that tasks of a [`Pipeline`] can refer to by `action_name`.
+pub struct ActionRegistry<'task, RUNTIME: Runtime> {
+    actions: BTreeMap<ActionId, &'task dyn Action<RUNTIME>>,
+}
+
+impl<'task, RUNTIME: Runtime> ActionRegistry<'task, RUNTIME> {
+    pub fn new() -> Self {
+        Self {
+            actions: BTreeMap::new(),
+        }
+    }
+
+    pub fn register(
+        &mut self,
+        name: impl Into<ActionId>,
+        action: &'task impl Action<RUNTIME>,
+    ) -> &mut Self {
+        self.register_dyn(name, action)
+    }
+
+    pub fn register_dyn(
+        &mut self,
+        name: impl Into<ActionId>,
+        action: &'task dyn Action<RUNTIME>,
+    ) -> &mut Self {
+        self.actions.insert(name.into(), action);
+        self
+    }
+
+    pub fn get(&self, name: &str) -> Option<&'task dyn Action<RUNTIME>> {
+        self.actions.get(name).copied()
+    }
+}
+
+impl<RUNTIME: Runtime> Default for ActionRegistry<'_, RUNTIME> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<'task, RUNTIME: Runtime> ContextBuilder<'task, RUNTIME> {
+    /// Creates a builder with every task of `pipeline`, resolving actions from `registry`.
+    pub fn from_pipeline(
+        runtime: RUNTIME,
+        pipeline: Pipeline,
+        registry: &ActionRegistry<'task, RUNTIME>,
+    ) -> Result<Self, PipelineError> {
+        let mut builder = Self::new(runtime);
+        for config in pipeline.into_task_configs() {
+            let action =
+                registry
+                    .get(&config.action_name)
+                    .ok_or_else(|| PipelineError::UnknownAction {
+                        id: config.task_name.clone(),
+                        action_name: config.action_name.clone(),
+                    })?;
+            builder
+                .add_dyn_task(config, action)
+                .map_err(|err| match err {
+                    ContextError::DuplicateTask { id } => PipelineError::DuplicateTask { id },
+                })?;
+        }
+        Ok(builder)
+    }
+}
+
+#[derive(Debug, Snafu)]
+pub enum PipelineError {
+    #[snafu(display("task {id} declares a different task_name {task_name}"))]
+    TaskNameMismatch { id: TaskId, task_name: TaskId },
+    #[snafu(display("task {id} has no action_name or action, neither set nor inherited"))]
+    MissingAction { id: TaskId },
+    #[snafu(display("{id} extends unknown template {template}"))]
+    UnknownTemplate { id: String, template: TemplateId },
+    #[snafu(display("template {template} is part of an extends cycle"))]
+    TemplateCycle { template: TemplateId },
+    #[snafu(display("task id {id} must not contain {NAMESPACE_SEPARATOR}"))]
+    InvalidTaskId { id: TaskId },
+    #[snafu(display("task {id} is defined twice"))]
+    DuplicateTask { id: TaskId },
+    #[snafu(display("invalid include namespace \"{namespace}\""))]
+    InvalidNamespace { namespace: String },
+    #[snafu(display("include {namespace} can only be resolved when loading from a file"))]
+    UnresolvedInclude { namespace: String },
+    #[snafu(display("{file} includes itself"))]
+    IncludeCycle { file: String },
+    #[snafu(display("task {id} sets both action_name and action"))]
+    ConflictingAction { id: TaskId },
+    #[snafu(display("action {id} has unregistered type {type_name}"))]
+    UnknownActionType { id: ActionId, type_name: String },
+    #[snafu(display("action {id} of type {type_name} has an invalid config: {message}"))]
+    InvalidActionConfig {
+        id: ActionId,
+        type_name: String,
+        message: String,
+    },
+    #[snafu(display("task {id} guards {target}, which is not in its next_task"))]
+    UnknownGuardTarget { id: TaskId, target: TaskId },
+    #[snafu(display("task {id} refers to unregistered action {action_name}"))]
+    UnknownAction { id: TaskId, action_name: ActionId },
+    #[snafu(display("{location}: {message}"))]
+    Syntax { location: Location, message: String },
+    #[snafu(display("{file}: unsupported pipeline format"))]
+    UnknownFormat { file: String },
+    #[cfg(feature = "std")]
+    #[snafu(display("{file}: {source}"))]
+    Io {
+        file: String,
+        source: std::io::Error,
+    },
+    #[snafu(display("{file}: {source}"))]
+    InFile {
+        file: String,
+        source: alloc::boxed::Box<PipelineError>,
+    },
+}
+
+impl PipelineError {
+    #[cfg_attr(
+        not(any(feature = "json", feature = "yaml", feature = "toml")),
+        allow(unused)
+    )]
+    fn syntax(location: Location, message: String) -> Self {
+        Self::Syntax {
+            message: format::strip_position(&message).to_string(),
+            location,
+        }
+    }
+}
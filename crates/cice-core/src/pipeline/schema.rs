@@ -0,0 +1,145 @@
+//! JSON Schema of pipeline documents, so editors can complete and validate them.
+//!
+//! The schema is generated from [`TaskTemplate`] and from the config types of the registered
+//! actions, which are collected as variants of the `ActionConfig` definition, each tagged by its
+//! `"type"`. Write it next to the pipelines and map it in VS Code's `settings.json`:
+//!
+//! ```json
+//! "json.schemas": [{ "fileMatch": ["pipeline/*.json"], "url": "./pipeline.schema.json" }]
+//! ```
+
+use alloc::borrow::Cow;
+use alloc::format;
+use alloc::vec::Vec;
+
+use schemars::generate::{SchemaGenerator, SchemaSettings};
+use schemars::{json_schema, JsonSchema, Schema};
+use serde_json::Value;
+
+use super::action::{ActionConfig, COMPOSE_TYPE, EXEC_KEY, RECOGNIZE_KEY, TYPE_KEY};
+use super::template::TaskTemplate;
+use super::{Pipeline, INCLUDE_KEY, TEMPLATES_KEY};
+
+/// Name of the definition holding the registered action configs.
+pub const ACTION_CONFIG_DEFINITION: &str = "ActionConfig";
+
+impl JsonSchema for Pipeline {
+    fn schema_name() -> Cow<'static, str> {
+        "Pipeline".into()
+    }
+
+    fn json_schema(generator: &mut SchemaGenerator) -> Schema {
+        let task = generator.subschema_for::<TaskTemplate>();
+        json_schema!({
+            "description": "Tasks of a pipeline, keyed by task name",
+            "type": "object",
+            "properties": {
+                "$schema": { "type": "string" },
+                (INCLUDE_KEY): {
+                    "description": "Files to include, keyed by the namespace of their tasks",
+                    "type": "object",
+                    "additionalProperties": { "type": "string" },
+                },
+                (TEMPLATES_KEY): {
+                    "description": "Templates that tasks inherit fields from with `extends`",
+                    "type": "object",
+                    "additionalProperties": task.clone(),
+                },
+            },
+            "additionalProperties": task,
+        })
+    }
+}
+
+/// Any action, [`SchemaBuilder::action`] narrows it down to the registered types.
+impl JsonSchema for ActionConfig {
+    fn schema_name() -> Cow<'static, str> {
+        ACTION_CONFIG_DEFINITION.into()
+    }
+
+    fn json_schema(_: &mut SchemaGenerator) -> Schema {
+        json_schema!({
+            "description": "Action declared inline, configured by the fields next to its type",
+            "type": "object",
+            "properties": {
+                (TYPE_KEY): { "type": "string" },
+            },
+            "required": [TYPE_KEY],
+        })
+    }
+}
+
+/// Builds the JSON Schema of a [`Pipeline`] document.
+///
+/// ```
+/// # use cice_core::pipeline::schema::SchemaBuilder;
+/// let schema = SchemaBuilder::new().build();
+/// assert_eq!(schema.get("title").unwrap(), "Pipeline");
+/// ```
+pub struct SchemaBuilder {
+    generator: SchemaGenerator,
+    actions: Vec<Value>,
+}
+
+impl SchemaBuilder {
+    pub fn new() -> Self {
+        Self {
+            // Draft 7 is the newest draft VS Code fully supports
+            generator: SchemaSettings::draft07().into_generator(),
+            actions: Vec::new(),
+        }
+    }
+
+    /// Adds the config type of the action registered as `type_name`, e.g. `"opencv.template_match"`.
+    pub fn action<CONFIG: JsonSchema>(&mut self, type_name: &str) -> &mut Self {
+        let mut schema = CONFIG::json_schema(&mut self.generator);
+        let object = schema.ensure_object();
+        object.insert("title".into(), type_name.into());
+        let properties = object
+            .entry("properties")
+            .or_insert_with(|| Value::Object(Default::default()));
+        if let Value::Object(properties) = properties {
+            properties.insert(TYPE_KEY.into(), serde_json::json!({ "const": type_name }));
+        }
+        let required = object
+            .entry("required")
+            .or_insert_with(|| Value::Array(Vec::new()));
+        if let Value::Array(required) = required {
+            required.insert(0, TYPE_KEY.into());
+        }
+        self.actions.push(schema.to_value());
+        self
+    }
+
+    pub fn build(&mut self) -> Schema {
+        let mut schema = self.generator.root_schema_for::<Pipeline>();
+        if !self.actions.is_empty() {
+            let pointer = format!("/definitions/{ACTION_CONFIG_DEFINITION}");
+            let reference = format!("#{pointer}");
+            let compose = serde_json::json!({
+                "title": COMPOSE_TYPE,
+                "description": "Recognizes with one action and executes another",
+                "type": "object",
+                "properties": {
+                    (TYPE_KEY): { "const": COMPOSE_TYPE },
+                    (RECOGNIZE_KEY): { "$ref": reference },
+                    (EXEC_KEY): { "$ref": reference },
+                },
+                "required": [TYPE_KEY, RECOGNIZE_KEY, EXEC_KEY],
+                "additionalProperties": false,
+            });
+            let mut variants = self.actions.clone();
+            variants.push(compose);
+            if let Some(definition) = schema.pointer_mut(&pointer) {
+                *definition = serde_json::json!({ "oneOf": variants });
+            }
+        }
+        schema
+    }
+}
+
+impl Default for SchemaBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,290 @@
+//! Task templates: shared fields that tasks inherit with `extends`.
+//!
+//! ```json
+//! {
+//!   "$templates": {
+//!     "patient": { "timeout": "1m", "max_retry": 20, "interrupt_task": ["close_popup"] }
+//!   },
+//!   "login": {
+//!     "extends": "patient",
+//!     "action_name": "click_login",
+//!     "interrupt_task": { "append": ["reconnect"] }
+//!   }
+//! }
+//! ```
+//!
+//! A template may extend another template. Fields set on the child override the inherited ones,
+//! except that `next_task` and `interrupt_task` may also edit the inherited list, see
+//! [`ListPatch`].
+
+use alloc::collections::{BTreeMap, BTreeSet};
+use alloc::string::String;
+// `derive(JsonSchema)` calls `to_string` without importing it, which breaks without std
+#[cfg(feature = "schema")]
+#[allow(unused_imports)]
+use alloc::string::ToString;
+use alloc::vec::Vec;
+use core::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use super::action::{inline_action_id, ActionConfig};
+use super::PipelineError;
+use crate::action::ActionId;
+use crate::guard::Guard;
+use crate::task::{duration, TaskConfig, TaskId};
+
+/// Name of a template in the `$templates` section of a pipeline document.
+pub type TemplateId = String;
+
+/// A task whose fields may be inherited from a template.
+///
+/// Every entry of a pipeline document, task or template, is a `TaskTemplate`. A task must end up
+/// with either an `action_name` or an inline `action` once its templates are applied; other
+/// fields fall back to the [`TaskConfig`] defaults.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(deny_unknown_fields)]
+pub struct TaskTemplate {
+    /// Template to inherit fields from
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub extends: Option<TemplateId>,
+    /// Name of the task, defaults to its key in a pipeline document
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub task_name: Option<TaskId>,
+    /// Name of the action this task recognizes and executes
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub action_name: Option<ActionId>,
+    /// Action declared inline instead of `action_name`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub action: Option<ActionConfig>,
+    /// Tasks to recognize after this one, in priority order
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(feature = "schema", schemars(extend("default" = [])))]
+    pub next_task: Option<ListPatch>,
+    /// Tasks to handle interruptions, such as popups, while waiting for `next_task`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(feature = "schema", schemars(extend("default" = [])))]
+    pub interrupt_task: Option<ListPatch>,
+    /// Conditions on `next_task` edges, keyed by target, see [`crate::guard`]. Inherited guards
+    /// are kept unless the same target is guarded again.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub guards: Option<BTreeMap<TaskId, Guard>>,
+    /// How long to wait for one of `next_task` to be recognized, e.g. `"30s"`
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        with = "duration::option"
+    )]
+    #[cfg_attr(
+        feature = "schema",
+        schemars(
+            with = "Option<String>",
+            regex(pattern = duration::PATTERN),
+            extend("default" = "30s")
+        )
+    )]
+    pub timeout: Option<Duration>,
+    /// Max recognition rounds over `next_task`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(
+        feature = "schema",
+        schemars(extend("default" = TaskConfig::DEFAULT_MAX_RETRY))
+    )]
+    pub max_retry: Option<usize>,
+}
+
+/// Value of a list field, replacing or editing the inherited list.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(untagged, deny_unknown_fields)]
+pub enum ListPatch {
+    /// Replaces the inherited list
+    Replace(Vec<TaskId>),
+    /// Edits the inherited list. Ids listed in `prepend` or `append` are moved rather than
+    /// repeated if they were inherited.
+    Merge {
+        /// Ids to put before the inherited ones
+        #[serde(default)]
+        prepend: Vec<TaskId>,
+        /// Ids to put after the inherited ones
+        #[serde(default)]
+        append: Vec<TaskId>,
+        /// Inherited ids to drop
+        #[serde(default)]
+        remove: Vec<TaskId>,
+    },
+}
+
+impl ListPatch {
+    pub fn apply(&self, inherited: &[TaskId]) -> Vec<TaskId> {
+        match self {
+            Self::Replace(list) => list.clone(),
+            Self::Merge {
+                prepend,
+                append,
+                remove,
+            } => {
+                let kept = inherited
+                    .iter()
+                    .filter(|id| ![prepend, append, remove].iter().any(|ids| ids.contains(id)));
+                prepend.iter().chain(kept).chain(append).cloned().collect()
+            }
+        }
+    }
+}
+
+impl TaskTemplate {
+    /// Applies `self` over `base`, `base` being already resolved.
+    fn inherit(&self, base: &TaskTemplate) -> TaskTemplate {
+        let list = |patch: &Option<ListPatch>, inherited: &Option<ListPatch>| match patch {
+            Some(patch) => {
+                let inherited = inherited
+                    .as_ref()
+                    .map_or(Vec::new(), |list| list.apply(&[]));
+                Some(ListPatch::Replace(patch.apply(&inherited)))
+            }
+            None => inherited.clone(),
+        };
+        // `action_name` and `action` are inherited together, as either replaces the other
+        let action = match self.action_name.is_some() || self.action.is_some() {
+            true => self,
+            false => base,
+        };
+        TaskTemplate {
+            extends: None,
+            task_name: self.task_name.clone(),
+            action_name: action.action_name.clone(),
+            action: action.action.clone(),
+            next_task: list(&self.next_task, &base.next_task),
+            interrupt_task: list(&self.interrupt_task, &base.interrupt_task),
+            guards: match (&self.guards, &base.guards) {
+                (Some(guards), Some(inherited)) => {
+                    let mut merged = inherited.clone();
+                    merged.extend(guards.clone());
+                    Some(merged)
+                }
+                (guards, inherited) => guards.clone().or_else(|| inherited.clone()),
+            },
+            timeout: self.timeout.or(base.timeout),
+            max_retry: self.max_retry.or(base.max_retry),
+        }
+    }
+
+    /// Builds the config of the task `id`, falling back to defaults for unset fields, along with
+    /// its inline action, registered under [`inline_action_id`] of the task.
+    fn into_config(self, id: &TaskId) -> Result<(TaskConfig, Option<ActionConfig>), PipelineError> {
+        let action_name = match (self.action_name, &self.action) {
+            (Some(_), Some(_)) => return Err(PipelineError::ConflictingAction { id: id.clone() }),
+            (Some(action_name), None) => action_name,
+            (None, Some(_)) => inline_action_id(id),
+            (None, None) => return Err(PipelineError::MissingAction { id: id.clone() }),
+        };
+        if let Some(task_name) = self.task_name.filter(|task_name| task_name != id) {
+            return Err(PipelineError::TaskNameMismatch {
+                id: id.clone(),
+                task_name,
+            });
+        }
+        let list = |patch: Option<ListPatch>| patch.map_or(Vec::new(), |list| list.apply(&[]));
+        let next_task = list(self.next_task);
+        let guards = self.guards.unwrap_or_default();
+        if let Some(target) = guards.keys().find(|target| !next_task.contains(target)) {
+            return Err(PipelineError::UnknownGuardTarget {
+                id: id.clone(),
+                target: target.clone(),
+            });
+        }
+        let config = TaskConfig {
+            task_name: id.clone(),
+            action_name,
+            next_task,
+            interrupt_task: list(self.interrupt_task),
+            guards,
+            timeout: self.timeout.unwrap_or(TaskConfig::DEFAULT_TIMEOUT),
+            max_retry: self.max_retry.unwrap_or(TaskConfig::DEFAULT_MAX_RETRY),
+        };
+        Ok((config, self.action))
+    }
+
+    /// The entry of a resolved task, with `action` declared inline if it has one.
+    pub(super) fn from_config(config: TaskConfig, action: Option<ActionConfig>) -> Self {
+        Self {
+            extends: None,
+            task_name: None,
+            action_name: action.is_none().then_some(config.action_name),
+            action,
+            next_task: Some(ListPatch::Replace(config.next_task)),
+            interrupt_task: Some(ListPatch::Replace(config.interrupt_task)),
+            guards: (!config.guards.is_empty()).then_some(config.guards),
+            timeout: Some(config.timeout),
+            max_retry: Some(config.max_retry),
+        }
+    }
+}
+
+/// Resolves `extends` chains, caching every resolved template.
+pub(super) struct Resolver<'doc> {
+    templates: &'doc BTreeMap<TemplateId, TaskTemplate>,
+    resolved: BTreeMap<TemplateId, TaskTemplate>,
+}
+
+impl<'doc> Resolver<'doc> {
+    pub(super) fn new(templates: &'doc BTreeMap<TemplateId, TaskTemplate>) -> Self {
+        Self {
+            templates,
+            resolved: BTreeMap::new(),
+        }
+    }
+
+    pub(super) fn template(
+        &mut self,
+        name: &TemplateId,
+        template: &TaskTemplate,
+    ) -> Result<(), PipelineError> {
+        let mut visiting = BTreeSet::from([name.clone()]);
+        self.resolve(name, template, &mut visiting)?;
+        Ok(())
+    }
+
+    pub(super) fn task_config(
+        &mut self,
+        id: &TaskId,
+        task: &TaskTemplate,
+    ) -> Result<(TaskConfig, Option<ActionConfig>), PipelineError> {
+        let resolved = self.resolve(id, task, &mut BTreeSet::new())?;
+        resolved.into_config(id)
+    }
+
+    /// Resolves `entry`, named `name`; `visiting` holds the templates being resolved to find cycles.
+    fn resolve(
+        &mut self,
+        name: &str,
+        entry: &TaskTemplate,
+        visiting: &mut BTreeSet<TemplateId>,
+    ) -> Result<TaskTemplate, PipelineError> {
+        let Some(parent) = &entry.extends else {
+            return Ok(entry.inherit(&TaskTemplate::default()));
+        };
+        if let Some(base) = self.resolved.get(parent) {
+            return Ok(entry.inherit(base));
+        }
+        let template =
+            self.templates
+                .get(parent)
+                .ok_or_else(|| PipelineError::UnknownTemplate {
+                    id: name.into(),
+                    template: parent.clone(),
+                })?;
+        if !visiting.insert(parent.clone()) {
+            return Err(PipelineError::TemplateCycle {
+                template: parent.clone(),
+            });
+        }
+        let base = self.resolve(parent, template, visiting)?;
+        visiting.remove(parent);
+        let resolved = entry.inherit(&base);
+        self.resolved.insert(parent.clone(), base);
+        Ok(resolved)
+    }
+}
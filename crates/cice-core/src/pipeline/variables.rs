@@ -0,0 +1,180 @@
+//! Variables substituted into pipeline documents when they are loaded.
+//!
+//! Any string of a document, task ids and include paths included, can refer to variables as
+//! `${name}`, so the same pipeline can run against several accounts or servers:
+//!
+//! ```json
+//! {
+//!   "login": {
+//!     "action": { "type": "opencv.template_match", "template_path": "${server}/login.png", "threshold": "${threshold}" },
+//!     "next_task": ["home"]
+//!   }
+//! }
+//! ```
+//!
+//! A string made of a single placeholder takes the type of the variable, so `"${threshold}"`
+//! above becomes a number, while placeholders inside text are formatted into it. Write `$${` for a
+//! literal `${`. A placeholder without a value is an error at its location in the document.
+//!
+//! Placeholders are only substituted by the loaders taking variables, such as
+//! [`Pipeline::parse_with_variables`](super::Pipeline::parse_with_variables) and
+//! [`Pipeline::from_file_with_variables`](super::Pipeline::from_file_with_variables), even when
+//! given no variables. The other loaders read strings as written, `${` and `$${` included.
+//!
+//! Values come from [`Variables::set`], or with `std` from environment variables and profile
+//! files, see [`Variables::from_env`] and [`Variables::from_profile`].
+
+use alloc::collections::BTreeMap;
+use alloc::format;
+use alloc::string::{String, ToString};
+
+use serde_json::Value;
+
+/// Values of the `${name}` placeholders of a pipeline document.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Variables {
+    values: BTreeMap<String, Value>,
+}
+
+impl Variables {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn set(&mut self, name: impl Into<String>, value: impl Into<Value>) -> &mut Self {
+        self.values.insert(name.into(), value.into());
+        self
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Value> {
+        self.values.get(name)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&String, &Value)> {
+        self.values.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    /// Adds the variables of `other`, replacing the ones already set.
+    pub fn merge(&mut self, other: Variables) -> &mut Self {
+        self.values.extend(other.values);
+        self
+    }
+
+    /// Variables from the environment variables starting with `prefix`, which is removed from
+    /// their name: with prefix `"CICE_"`, `CICE_ACCOUNT` is `${ACCOUNT}`.
+    ///
+    /// Values that read as JSON numbers or booleans get that type, the others are strings.
+    #[cfg(feature = "std")]
+    pub fn from_env(prefix: &str) -> Self {
+        let mut variables = Self::new();
+        for (key, value) in std::env::vars() {
+            if let Some(name) = key.strip_prefix(prefix) {
+                let value = match serde_json::from_str(&value) {
+                    Ok(typed @ (Value::Number(_) | Value::Bool(_))) => typed,
+                    _ => Value::String(value),
+                };
+                variables.set(name, value);
+            }
+        }
+        variables
+    }
+
+    /// Reads variables from a profile file in any pipeline [`Format`](super::Format), chosen by
+    /// its extension. Nested tables are flattened, so `{ "server": { "host": "a" } }` defines
+    /// `${server.host}`.
+    #[cfg(feature = "std")]
+    pub fn from_profile(path: impl AsRef<std::path::Path>) -> Result<Self, super::PipelineError> {
+        use super::{Format, PipelineError};
+
+        let path = path.as_ref();
+        let file = path.display().to_string();
+        let format = path
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .and_then(Format::from_extension)
+            .ok_or_else(|| PipelineError::UnknownFormat { file: file.clone() })?;
+        let text = std::fs::read_to_string(path).map_err(|source| PipelineError::Io {
+            file: file.clone(),
+            source,
+        })?;
+        let table: serde_json::Map<String, Value> = format.parse(&file, &text, None)?;
+        let mut variables = Self::new();
+        variables.flatten("", table);
+        Ok(variables)
+    }
+
+    #[cfg(feature = "std")]
+    fn flatten(&mut self, prefix: &str, table: serde_json::Map<String, Value>) {
+        for (key, value) in table {
+            let name = format!("{prefix}{key}");
+            match value {
+                Value::Object(table) => self.flatten(&format!("{name}."), table),
+                value => {
+                    self.set(name, value);
+                }
+            }
+        }
+    }
+
+    /// Substitutes the placeholders of `text`.
+    pub(super) fn substitute<'v>(&'v self, text: &str) -> Result<Substitution<'v>, String> {
+        if !text.contains('$') {
+            return Ok(Substitution::Unchanged);
+        }
+        let mut result = String::new();
+        let mut rest = text;
+        while let Some(dollar) = rest.find('$') {
+            let at_start = dollar == 0 && rest.len() == text.len();
+            result.push_str(&rest[..dollar]);
+            let after = &rest[dollar..];
+            if let Some(escaped) = after.strip_prefix("$${") {
+                result.push_str("${");
+                rest = escaped;
+            } else if let Some(placeholder) = after.strip_prefix("${") {
+                let end = placeholder
+                    .find('}')
+                    .ok_or_else(|| format!("unterminated placeholder in \"{text}\""))?;
+                let name = &placeholder[..end];
+                let value = self
+                    .get(name)
+                    .ok_or_else(|| format!("unbound variable `{name}`"))?;
+                rest = &placeholder[end + 1..];
+                if at_start && rest.is_empty() {
+                    return Ok(Substitution::Value(value));
+                }
+                match value {
+                    Value::String(value) => result.push_str(value),
+                    Value::Number(_) | Value::Bool(_) => result.push_str(&value.to_string()),
+                    _ => {
+                        return Err(format!(
+                            "variable `{name}` is not a string, number or boolean and can't be part of a text"
+                        ))
+                    }
+                }
+            } else {
+                result.push('$');
+                rest = &after[1..];
+            }
+        }
+        result.push_str(rest);
+        Ok(Substitution::Text(result))
+    }
+}
+
+/// The result of [`Variables::substitute`].
+pub(super) enum Substitution<'v> {
+    /// No placeholder
+    Unchanged,
+    /// Placeholders formatted into the text
+    Text(String),
+    /// A single placeholder, replaced by the value with its type
+    Value(&'v Value),
+}
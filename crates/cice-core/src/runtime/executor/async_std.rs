@@ -0,0 +1,45 @@
+use alloc::boxed::Box;
+use core::time::Duration;
+use std::time::Instant;
+
+use async_trait::async_trait;
+use futures::future::BoxFuture;
+
+use super::{Sleeper, Spawner};
+
+/// [`Spawner`] and [`Sleeper`] backed by the global async-std executor.
+#[derive(Debug, Clone, Copy)]
+pub struct AsyncStdExecutor {
+    epoch: Instant,
+}
+
+impl AsyncStdExecutor {
+    pub fn new() -> Self {
+        Self {
+            epoch: Instant::now(),
+        }
+    }
+}
+
+impl Default for AsyncStdExecutor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Spawner for AsyncStdExecutor {
+    fn spawn(&self, future: BoxFuture<'static, ()>) {
+        async_std::task::spawn(future);
+    }
+}
+
+#[async_trait]
+impl Sleeper for AsyncStdExecutor {
+    fn now(&self) -> Duration {
+        self.epoch.elapsed()
+    }
+
+    async fn sleep(&self, duration: Duration) {
+        async_std::task::sleep(duration).await
+    }
+}
@@ -0,0 +1,38 @@
+//! Executor abstraction, so the scheduler does not depend on a specific async runtime.
+//!
+//! Implementations for common executors are available behind the `tokio`, `async-std` and
+//! `smol` features.
+
+use alloc::boxed::Box;
+use core::time::Duration;
+
+use async_trait::async_trait;
+use futures::future::BoxFuture;
+
+#[cfg(feature = "async-std")]
+mod async_std;
+#[cfg(feature = "smol")]
+mod smol;
+#[cfg(feature = "tokio")]
+mod tokio;
+
+#[cfg(feature = "async-std")]
+pub use async_std::AsyncStdExecutor;
+#[cfg(feature = "smol")]
+pub use smol::SmolExecutor;
+#[cfg(feature = "tokio")]
+pub use tokio::TokioExecutor;
+
+/// Runs a future in the background of an async executor.
+pub trait Spawner: Send + Sync {
+    fn spawn(&self, future: BoxFuture<'static, ()>);
+}
+
+/// Timer of an async executor.
+#[async_trait]
+pub trait Sleeper: Send + Sync {
+    /// Monotonic time elapsed since an arbitrary, fixed origin.
+    fn now(&self) -> Duration;
+    /// Completes once `duration` has elapsed according to [`Sleeper::now`].
+    async fn sleep(&self, duration: Duration);
+}
@@ -0,0 +1,45 @@
+use alloc::boxed::Box;
+use core::time::Duration;
+use std::time::Instant;
+
+use async_trait::async_trait;
+use futures::future::BoxFuture;
+
+use super::{Sleeper, Spawner};
+
+/// [`Spawner`] and [`Sleeper`] backed by the global smol executor.
+#[derive(Debug, Clone, Copy)]
+pub struct SmolExecutor {
+    epoch: Instant,
+}
+
+impl SmolExecutor {
+    pub fn new() -> Self {
+        Self {
+            epoch: Instant::now(),
+        }
+    }
+}
+
+impl Default for SmolExecutor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Spawner for SmolExecutor {
+    fn spawn(&self, future: BoxFuture<'static, ()>) {
+        smol::spawn(future).detach();
+    }
+}
+
+#[async_trait]
+impl Sleeper for SmolExecutor {
+    fn now(&self) -> Duration {
+        self.epoch.elapsed()
+    }
+
+    async fn sleep(&self, duration: Duration) {
+        smol::Timer::after(duration).await;
+    }
+}
@@ -0,0 +1,48 @@
+use alloc::boxed::Box;
+use core::time::Duration;
+
+use async_trait::async_trait;
+use futures::future::BoxFuture;
+use tokio::time::Instant;
+
+use super::{Sleeper, Spawner};
+
+/// [`Spawner`] and [`Sleeper`] backed by the ambient tokio runtime.
+///
+/// Both spawning and sleeping must happen inside a tokio runtime with the timer enabled.
+/// Time is read from `tokio::time`, so it follows `tokio::time::pause` in tests.
+#[derive(Debug, Clone, Copy)]
+pub struct TokioExecutor {
+    epoch: Instant,
+}
+
+impl TokioExecutor {
+    pub fn new() -> Self {
+        Self {
+            epoch: Instant::now(),
+        }
+    }
+}
+
+impl Default for TokioExecutor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Spawner for TokioExecutor {
+    fn spawn(&self, future: BoxFuture<'static, ()>) {
+        tokio::spawn(future);
+    }
+}
+
+#[async_trait]
+impl Sleeper for TokioExecutor {
+    fn now(&self) -> Duration {
+        self.epoch.elapsed()
+    }
+
+    async fn sleep(&self, duration: Duration) {
+        tokio::time::sleep(duration).await
+    }
+}
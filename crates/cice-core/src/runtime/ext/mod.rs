@@ -1,8 +1,67 @@
+use alloc::boxed::Box;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::time::Duration;
+
 use async_trait::async_trait;
+use snafu::Snafu;
 
+use crate::runtime::executor::{Sleeper, Spawner};
 use crate::runtime::Runtime;
 
 #[async_trait]
 pub trait ScreenshotExt: Runtime {
     async fn screenshot(&self) -> Option<Vec<u8>>;
 }
+
+/// Mouse and keyboard input, used by the actions of [`crate::action::input`].
+///
+/// Coordinates are in screen pixels from the top left corner; keys are X11 keysyms.
+#[async_trait]
+pub trait InputExt: Runtime {
+    /// Moves the pointer to `(x, y)` and clicks `button`: 1 is left, 2 middle, 3 right, 4 and 5
+    /// scroll up and down.
+    async fn click(&self, x: u32, y: u32, button: u8) -> Result<(), InputError>;
+    async fn move_mouse(&self, x: u32, y: u32) -> Result<(), InputError>;
+    async fn type_text(&self, text: &str) -> Result<(), InputError>;
+    /// Presses and releases `key`.
+    async fn press_key(&self, key: u32) -> Result<(), InputError>;
+}
+
+#[derive(Debug, Snafu)]
+pub enum InputError {
+    #[snafu(display("input failed reason:{reason}"))]
+    InputFailed { reason: String },
+}
+
+/// Timer used by the scheduler to enforce `TaskConfig::timeout` and to pace recognition retries.
+///
+/// The scheduler never reads the system clock itself, so a runtime decides what time means:
+/// a wall clock backed by its async executor, or a virtual clock in tests.
+#[async_trait]
+pub trait TimerExt: Runtime {
+    /// Monotonic time elapsed since an arbitrary, fixed origin.
+    fn now(&self) -> Duration;
+    /// Completes once `duration` has elapsed according to [`TimerExt::now`].
+    async fn sleep(&self, duration: Duration);
+}
+
+/// Runtime driven by an async executor.
+///
+/// Implementing this provides [`TimerExt`] through the executor's [`Sleeper`], and exposes the
+/// executor's [`Spawner`] for [`crate::context::ContextBuilder::spawner`].
+pub trait ExecutorExt: Runtime {
+    type Executor: Spawner + Sleeper;
+    fn executor(&self) -> &Self::Executor;
+}
+
+#[async_trait]
+impl<RUNTIME: ExecutorExt> TimerExt for RUNTIME {
+    fn now(&self) -> Duration {
+        self.executor().now()
+    }
+
+    async fn sleep(&self, duration: Duration) {
+        self.executor().sleep(duration).await
+    }
+}
@@ -1,3 +1,4 @@
+pub mod executor;
 pub mod ext;
 
 pub trait Runtime: Sync + Send {}
@@ -0,0 +1,26 @@
+//! Synchronization that also works without `std`.
+//!
+//! With `std` the lock is a [`std::sync::Mutex`], which parks contending threads instead of
+//! spinning them; without it, the spin lock of the `spin` crate, as there is nothing to park on.
+
+/// A lock for state that is only held for a few map operations, never across an `.await`.
+#[cfg(feature = "std")]
+#[derive(Default)]
+pub(crate) struct Mutex<T>(std::sync::Mutex<T>);
+
+#[cfg(feature = "std")]
+impl<T> Mutex<T> {
+    pub(crate) const fn new(value: T) -> Self {
+        Self(std::sync::Mutex::new(value))
+    }
+
+    pub(crate) fn lock(&self) -> std::sync::MutexGuard<'_, T> {
+        // Nothing holding the lock can leave the state half updated, so poisoning is ignored
+        self.0
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+    }
+}
+
+#[cfg(not(feature = "std"))]
+pub(crate) use spin::Mutex;
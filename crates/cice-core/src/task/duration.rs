@@ -0,0 +1,116 @@
+//! Human-friendly [`Duration`] format for configs, such as `"1500ms"`, `"30s"` or `"1m30s"`.
+//!
+//! Supported units are `h`, `m`, `s`, `ms`, `us` and `ns`. Use it with
+//! `#[serde(with = "cice_core::task::duration")]`.
+
+use alloc::format;
+use alloc::string::String;
+use core::fmt;
+use core::time::Duration;
+
+use serde::de::{self, Visitor};
+use serde::{Deserializer, Serializer};
+
+const UNITS: [(&str, Duration); 6] = [
+    ("h", Duration::from_secs(3600)),
+    ("m", Duration::from_secs(60)),
+    ("s", Duration::from_secs(1)),
+    ("ms", Duration::from_millis(1)),
+    ("us", Duration::from_micros(1)),
+    ("ns", Duration::from_nanos(1)),
+];
+
+/// Regular expression matching the strings accepted by [`parse`].
+pub const PATTERN: &str = r"^\s*([0-9]+(h|m|s|ms|us|ns)\s*)+$";
+
+/// Parses a duration such as `"1500ms"`, `"30s"` or `"1m 30s"`.
+pub fn parse(text: &str) -> Result<Duration, String> {
+    let invalid = || format!("invalid duration \"{text}\", expected e.g. \"1500ms\" or \"30s\"");
+    let mut rest = text.trim();
+    if rest.is_empty() {
+        return Err(invalid());
+    }
+    let mut total = Duration::ZERO;
+    while !rest.is_empty() {
+        let digits = rest
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(rest.len());
+        let value: u32 = rest[..digits].parse().map_err(|_| invalid())?;
+        rest = &rest[digits..];
+        let unit_len = rest
+            .find(|c: char| !c.is_ascii_alphabetic())
+            .unwrap_or(rest.len());
+        let unit = UNITS
+            .iter()
+            .find(|(name, _)| *name == &rest[..unit_len])
+            .map(|(_, unit)| *unit)
+            .ok_or_else(invalid)?;
+        total = unit
+            .checked_mul(value)
+            .and_then(|value| total.checked_add(value))
+            .ok_or_else(invalid)?;
+        rest = rest[unit_len..].trim_start();
+    }
+    Ok(total)
+}
+
+/// Formats `duration` with the largest unit that represents it exactly, e.g. `"30s"`.
+pub fn format(duration: Duration) -> String {
+    if duration.is_zero() {
+        return "0s".into();
+    }
+    let (name, unit) = UNITS
+        .iter()
+        .find(|(_, unit)| duration.as_nanos().is_multiple_of(unit.as_nanos()))
+        .unwrap_or(&UNITS[UNITS.len() - 1]);
+    format!("{}{name}", duration.as_nanos() / unit.as_nanos())
+}
+
+pub fn serialize<S: Serializer>(duration: &Duration, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(&format(*duration))
+}
+
+pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Duration, D::Error> {
+    struct DurationVisitor;
+
+    impl Visitor<'_> for DurationVisitor {
+        type Value = Duration;
+
+        fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+            formatter.write_str("a duration such as \"1500ms\" or \"30s\"")
+        }
+
+        fn visit_str<E: de::Error>(self, value: &str) -> Result<Duration, E> {
+            parse(value).map_err(E::custom)
+        }
+    }
+
+    deserializer.deserialize_str(DurationVisitor)
+}
+
+/// The same format for `Option<Duration>` fields, use with `#[serde(default, with = "...")]`.
+pub mod option {
+    use core::time::Duration;
+
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(
+        duration: &Option<Duration>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        match duration {
+            Some(duration) => super::serialize(duration, serializer),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<Duration>, D::Error> {
+        #[derive(Deserialize)]
+        struct Wrapper(#[serde(with = "super")] Duration);
+
+        let duration = Option::<Wrapper>::deserialize(deserializer)?;
+        Ok(duration.map(|Wrapper(duration)| duration))
+    }
+}
@@ -1,19 +1,32 @@
-use std::time::Duration;
+use core::time::Duration;
 
+use alloc::collections::BTreeMap;
 use alloc::sync::Arc;
 
+use alloc::string::ToString;
 use alloc::{string::String, vec::Vec};
+use futures::future::BoxFuture;
 use futures::FutureExt;
+use serde::{Deserialize, Serialize};
 use snafu::Snafu;
 
 use crate::action::{Action, ActionError, ActionId, ExecError, RecognizeError};
 use crate::context::Context;
+use crate::debug::{Checkpoint, Stage};
+use crate::guard::Guard;
 use crate::message::task::TaskMessage;
 use crate::message::Message;
+use crate::runtime::ext::TimerExt;
 use crate::runtime::Runtime;
+use crate::trace::{self, TraceEvent};
+
+pub mod duration;
 
 pub type TaskId = String;
 
+/// Delay between two recognition rounds of the same task.
+const RETRY_INTERVAL: Duration = Duration::from_millis(500);
+
 /// Task is the basic unit of execution in the system. Each Task is associated with a specific Action
 ///
 /// ## Excecution Flow
@@ -21,7 +34,7 @@ pub type TaskId = String;
 /// 2. Try Recognize: The task attempts to recognize its associated action.
 /// 3. Try Exec: If recognition is successful, the task attempts to execute the action.
 /// 4. Exec Success: If execution is successful, a success message is sent.
-/// 5. Next Tasks: After successful execution, the task checks(use `recognize`) for any next tasks to execute. If there is any next task, it will be entered and goto step 1.
+/// 5. Next Tasks: After successful execution, the task checks(use `recognize`, or the guards of its edges) for any next tasks to execute. If there is any next task, it will be entered and goto step 1.
 ///
 #[repr(transparent)]
 pub struct Task<'task, RUNTIME: Runtime>(Arc<TaskInner<'task, RUNTIME>>);
@@ -37,15 +50,45 @@ pub struct TaskInner<'task, RUNTIME: Runtime> {
     action: &'task dyn Action<RUNTIME>,
 }
 
+/// Configuration of a [`Task`].
+///
+/// In a [`crate::pipeline::Pipeline`] document the task name is the key of the entry, so
+/// `task_name` may be omitted there, and fields may be inherited from templates, see
+/// [`crate::pipeline::template`]. Unknown fields are rejected to catch typos early.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
 pub struct TaskConfig {
+    #[serde(default, skip_serializing_if = "String::is_empty")]
     pub task_name: TaskId,
     pub action_name: ActionId,
+    #[serde(default)]
     pub next_task: Vec<TaskId>,
+    #[serde(default)]
     pub interrupt_task: Vec<TaskId>,
-    pub timeout: Duration, //TODO required a runtime ext to support timer in no_std env
+    /// Conditions on `next_task` edges, keyed by target, see [`crate::guard`]
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub guards: BTreeMap<TaskId, Guard>,
+    /// How long to wait for one of `next_task` to be recognized, e.g. `"30s"`
+    #[serde(default = "TaskConfig::default_timeout", with = "duration")]
+    pub timeout: Duration,
+    /// Max recognition rounds over `next_task`
+    #[serde(default = "TaskConfig::default_max_retry")]
     pub max_retry: usize,
 }
 
+impl TaskConfig {
+    pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(30);
+    pub const DEFAULT_MAX_RETRY: usize = 3;
+
+    fn default_timeout() -> Duration {
+        Self::DEFAULT_TIMEOUT
+    }
+
+    fn default_max_retry() -> usize {
+        Self::DEFAULT_MAX_RETRY
+    }
+}
+
 #[derive(Debug, Clone)]
 pub enum TaskResult {
     Success { id: TaskId },
@@ -64,13 +107,13 @@ pub enum TaskError {
 }
 
 impl<'task, RUNTIME: Runtime> Task<'task, RUNTIME> {
-    pub fn new(config: TaskConfig, action: &'task impl Action<RUNTIME>) -> Self {
+    pub fn new(config: TaskConfig, action: &'task dyn Action<RUNTIME>) -> Self {
         Self(Arc::new(TaskInner::new(config, action)))
     }
 }
 
 impl<'task, RUNTIME: Runtime> TaskInner<'task, RUNTIME> {
-    pub fn new(config: TaskConfig, action: &'task impl Action<RUNTIME>) -> Self {
+    pub fn new(config: TaskConfig, action: &'task dyn Action<RUNTIME>) -> Self {
         Self { config, action }
     }
 }
@@ -81,8 +124,23 @@ impl<RUNTIME: Runtime> TaskInner<'_, RUNTIME> {
     }
 }
 
-impl<'task, RUNTIME: Runtime> Task<'task, RUNTIME> {
-    async fn try_recognize(&self, context: &Context<'task, RUNTIME>) -> Result<(), RecognizeError> {
+impl<RUNTIME: Runtime> Task<'_, RUNTIME> {
+    pub(crate) fn config(&self) -> &TaskConfig {
+        self.0.as_ref().config()
+    }
+
+    fn send_task_message(context: &Context<RUNTIME>, msg: TaskMessage) {
+        if let Err(e) = context.try_send_message(Message::TaskMessage(msg)) {
+            log::error!("Failed to send message {e}");
+        }
+    }
+}
+
+impl<'task, RUNTIME: TimerExt> Task<'task, RUNTIME> {
+    pub(crate) async fn try_recognize(
+        &self,
+        context: &Context<'task, RUNTIME>,
+    ) -> Result<(), RecognizeError> {
         Self::send_task_message(
             context,
             TaskMessage::TryRecognize {
@@ -90,16 +148,42 @@ impl<'task, RUNTIME: Runtime> Task<'task, RUNTIME> {
             },
         );
 
-        self.0.action.recognize(context.get_runtime()).await?;
-
-        Self::send_task_message(
-            context,
-            TaskMessage::ExecSuccess {
-                id: self.config().task_name.clone(),
+        let started = context.get_runtime().now();
+        let (recognition, frame) =
+            trace::seen_by(self.0.action.recognize_scored(context.get_runtime())).await;
+        context.trace(|| TraceEvent::Recognize {
+            at: started,
+            task: self.config().task_name.clone(),
+            duration: context.get_runtime().now().saturating_sub(started),
+            recognized: recognition.result.is_ok(),
+            error: match &recognition.result {
+                Err(err @ RecognizeError::RecognizeFailed { .. }) => Some(err.to_string()),
+                _ => None,
             },
-        );
+            score: recognition.score,
+            frame,
+        });
+        context
+            .blackboard()
+            .record_score(&self.config().task_name, recognition.score);
+        context
+            .blackboard()
+            .record_location(&self.config().task_name, recognition.location);
+        context
+            .blackboard()
+            .record_recognized(&self.config().task_name, recognition.result.is_ok());
+        recognition.result
+    }
 
-        Ok(())
+    /// Recognizes this task on a spawned executor task if the context has a spawner.
+    fn recognize<'a>(
+        &'a self,
+        context: &'a Context<'task, RUNTIME>,
+    ) -> BoxFuture<'a, Result<(), RecognizeError>> {
+        match context.get_spawn_recognize() {
+            Some(spawn_recognize) => spawn_recognize(context, self),
+            None => self.try_recognize(context).boxed(),
+        }
     }
 
     async fn try_exec(&self, context: &Context<'task, RUNTIME>) -> Result<(), ExecError> {
@@ -110,7 +194,16 @@ impl<'task, RUNTIME: Runtime> Task<'task, RUNTIME> {
             },
         );
 
-        self.0.action.exec(context.get_runtime()).await?;
+        let started = context.get_runtime().now();
+        let location = context.blackboard().location(&self.config().task_name);
+        let result = self.0.action.exec_at(context.get_runtime(), location).await;
+        context.trace(|| TraceEvent::Exec {
+            at: started,
+            task: self.config().task_name.clone(),
+            duration: context.get_runtime().now().saturating_sub(started),
+            error: result.as_ref().err().map(ToString::to_string),
+        });
+        result?;
 
         Self::send_task_message(
             context,
@@ -121,19 +214,71 @@ impl<'task, RUNTIME: Runtime> Task<'task, RUNTIME> {
 
         Ok(())
     }
+}
 
-    async fn try_run(&self, context: &Context<'task, RUNTIME>) -> Result<TaskResult, ActionError> {
-        self.try_recognize(context)
-            .await
-            .map_err(Into::<ActionError>::into)?;
+/// What a recognition round does, once the guards of `next_task` were checked.
+enum Round<'a, 'task, RUNTIME: Runtime> {
+    /// Enter a task guarded without recognition whose guard holds
+    Enter(&'a Task<'task, RUNTIME>),
+    /// Recognize the tasks whose guard holds
+    Recognize(Vec<&'a Task<'task, RUNTIME>>),
+}
+
+impl<RUNTIME: Runtime> Round<'_, '_, RUNTIME> {
+    fn candidates(&self) -> Vec<TaskId> {
+        let tasks = match self {
+            Self::Enter(task) => core::slice::from_ref(task),
+            Self::Recognize(tasks) => tasks.as_slice(),
+        };
+        tasks
+            .iter()
+            .map(|task| task.config().task_name.clone())
+            .collect()
+    }
+}
+
+impl<'task, RUNTIME: TimerExt> Task<'task, RUNTIME> {
+    /// Execs this task once it was chosen among the `next_task` of the previous one.
+    async fn enter(&self, context: &Context<'task, RUNTIME>) -> Result<TaskResult, TaskError> {
+        let id = &self.config().task_name;
+        if let Checkpoint::Cancelled = context.checkpoint(Stage::BeforeExec, id, Vec::new()).await {
+            return Ok(TaskResult::TaskCancelled);
+        }
         self.try_exec(context)
             .await
             .map_err(Into::<ActionError>::into)?;
-        Ok(TaskResult::Success {
-            id: self.config().task_name.clone(),
-        })
+        if let Checkpoint::Cancelled = context.checkpoint(Stage::AfterExec, id, Vec::new()).await {
+            return Ok(TaskResult::TaskCancelled);
+        }
+        Ok(TaskResult::Success { id: id.clone() })
+    }
+
+    /// Checks the guards of `next_tasks`, which are checked every round as the blackboard may
+    /// change while waiting.
+    fn round<'a>(
+        &self,
+        context: &Context<'task, RUNTIME>,
+        next_tasks: &'a [Task<'task, RUNTIME>],
+    ) -> Round<'a, 'task, RUNTIME> {
+        let mut candidates = Vec::new();
+        for task in next_tasks {
+            match self.config().guards.get(&task.config().task_name) {
+                Some(guard) if !guard.condition.eval(context.blackboard()) => {}
+                Some(guard) if !guard.recognize => return Round::Enter(task),
+                _ => candidates.push(task),
+            }
+        }
+        Round::Recognize(candidates)
     }
 
+    /// Runs recognition rounds over `next_task` until one of them is recognized, then execs it.
+    ///
+    /// A round races the `recognize` of every candidate whose guard holds against the remaining
+    /// timeout; a candidate guarded without recognition is entered as soon as its guard holds,
+    /// see [`crate::guard`]. Rounds are separated by [`RETRY_INTERVAL`] and limited by
+    /// `max_retry`; running out of either rounds or time yields [`TaskError::TaskTimeOut`].
+    /// Rounds halt at breakpoints and while the context is paused, without using up either, see
+    /// [`crate::debug`].
     pub(crate) async fn run_with_context(
         &self,
         context: &Context<'task, RUNTIME>,
@@ -144,6 +289,11 @@ impl<'task, RUNTIME: Runtime> Task<'task, RUNTIME> {
                 id: self.config().task_name.clone(),
             },
         );
+        context.blackboard().record_run(&self.config().task_name);
+        context.trace(|| TraceEvent::Enter {
+            at: context.get_runtime().now(),
+            task: self.config().task_name.clone(),
+        });
         let inner = self.0.as_ref();
         let next_tasks: Vec<Task<RUNTIME>> = inner
             .config
@@ -163,43 +313,62 @@ impl<'task, RUNTIME: Runtime> Task<'task, RUNTIME> {
         if next_tasks.is_empty() {
             return Ok(TaskResult::NoPendingTask);
         }
+        let timer = context.get_runtime();
+        let mut started = timer.now();
+        let timeout = self.config().timeout;
+        let mut remaining = timeout;
         let mut cancel_signal = context.get_cancel_signal().boxed().fuse();
 
-        let mut task_futures = vec![];
-        next_tasks.iter().for_each(|task| {
-            task_futures.push(task.try_run(context).boxed());
-        });
-        let mut select_futures = futures::future::select_ok(task_futures).fuse();
-        for _retry_count in 0..self.config().max_retry {
-            futures::select! {
-                   res = select_futures => match res {
-                        Ok((result, _remaining)) => {
-                            return Ok(result);
-                        },
-                        Err(_e) => {
-                            //Ignore
-                        }
-                   },
-                   _ = cancel_signal => {
-                       // Cancel signal received
-                       return Ok(TaskResult::TaskCancelled)
+        for retry_count in 0..self.config().max_retry {
+            if retry_count > 0 {
+                futures::select_biased! {
+                    _ = cancel_signal => return Ok(TaskResult::TaskCancelled),
+                    _ = timer.sleep(RETRY_INTERVAL.min(remaining)).fuse() => {},
                 }
             }
+            let halted_at = timer.now();
+            let mut round = self.round(context, &next_tasks);
+            let id = &self.config().task_name;
+            match context
+                .checkpoint(Stage::BeforeRecognize, id, round.candidates())
+                .await
+            {
+                Checkpoint::Passed => {}
+                Checkpoint::Resumed => round = self.round(context, &next_tasks),
+                Checkpoint::Cancelled => return Ok(TaskResult::TaskCancelled),
+            }
+            // Time spent halted doesn't count against the timeout
+            started += timer.now().saturating_sub(halted_at);
+            remaining = timeout.saturating_sub(timer.now().saturating_sub(started));
+            if remaining.is_zero() {
+                break;
+            }
+
+            let candidates = match round {
+                Round::Enter(task) => return task.enter(context).await,
+                Round::Recognize(candidates) if candidates.is_empty() => continue,
+                Round::Recognize(candidates) => candidates,
+            };
+
+            let recognize_round = futures::future::select_ok(candidates.into_iter().map(|task| {
+                task.recognize(context)
+                    .map(move |res| res.map(|()| task))
+                    .boxed()
+            }));
+            futures::select_biased! {
+                _ = cancel_signal => return Ok(TaskResult::TaskCancelled),
+                res = recognize_round.fuse() => {
+                    if let Ok((task, _remaining)) = res {
+                        return task.enter(context).await;
+                    }
+                },
+                _ = timer.sleep(remaining).fuse() => break,
+            }
         }
         Err(TaskError::TaskTimeOut {
             id: self.config().task_name.clone(),
         })
     }
-
-    fn config(&self) -> &TaskConfig {
-        self.0.as_ref().config()
-    }
-
-    fn send_task_message(context: &Context<RUNTIME>, msg: TaskMessage) {
-        if let Err(e) = context.try_send_message(Message::TaskMessage(msg)) {
-            log::error!("Failed to send message {e}");
-        }
-    }
 }
 
 impl From<ActionError> for TaskError {
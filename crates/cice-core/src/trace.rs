@@ -0,0 +1,460 @@
+//! Execution traces: what a run did and what it saw, for inspecting a run after the fact.
+//!
+//! A [`Tracer`] set with [`ContextBuilder::tracer`] receives a [`TraceEvent`] for every task
+//! entered, every recognize and exec call with its result and duration, and the end of the run.
+//! Screenshots are only seen by the runtime, so wrap it in [`Traced`] to also trace each
+//! screenshot taken, with its data. Frames are identified by the hash of their data, so a screen
+//! that doesn't change is stored once.
+//!
+//! With the `std` feature, each [`TraceEvent::Recognize`] also names the last frame its action
+//! took through [`Traced`], so candidates recognizing concurrently are told apart, see
+//! [`Trace::frame_seen_by`].
+//!
+//! With the `std` feature, [`TraceWriter`] writes traces to a file as JSON lines, which
+//! [`Trace::open`] reads back:
+//!
+//! ```no_run
+//! # use cice_core::context::ContextBuilder;
+//! # use cice_core::runtime::ext::{ScreenshotExt, TimerExt};
+//! # use cice_core::trace::{Traced, TraceWriter};
+//! # fn build<RUNTIME: ScreenshotExt + TimerExt>(runtime: RUNTIME) -> std::io::Result<()> {
+//! let writer = TraceWriter::create("run.trace")?;
+//! let mut builder = ContextBuilder::new(Traced::new(runtime, writer.clone()));
+//! builder.tracer(writer);
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! [`ContextBuilder::tracer`]: crate::context::ContextBuilder::tracer
+
+use alloc::boxed::Box;
+use alloc::format;
+use alloc::string::String;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::future::Future;
+use core::time::Duration;
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+
+use crate::runtime::ext::{InputError, InputExt, ScreenshotExt, TimerExt};
+use crate::runtime::Runtime;
+use crate::task::{duration, TaskId};
+
+#[cfg(feature = "std")]
+pub use file::{Trace, TraceError, TraceWriter};
+
+/// Hash of the data of a screenshot, see [`frame_id`].
+pub type FrameId = String;
+
+/// Something that happened during a run, at [`TimerExt::now`] of its runtime.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "event", rename_all = "kebab-case")]
+pub enum TraceEvent {
+    /// A task was entered, and waits for one of its `next_task`
+    Enter {
+        #[serde(with = "duration")]
+        at: Duration,
+        task: TaskId,
+    },
+    /// The action of a task recognized
+    Recognize {
+        #[serde(with = "duration")]
+        at: Duration,
+        task: TaskId,
+        #[serde(with = "duration")]
+        duration: Duration,
+        recognized: bool,
+        /// Why recognition failed, when it didn't merely not recognize
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        error: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        score: Option<f64>,
+        /// The last frame the action took while recognizing, with [`Traced`] and `std`
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        frame: Option<FrameId>,
+    },
+    /// The action of a task was executed
+    Exec {
+        #[serde(with = "duration")]
+        at: Duration,
+        task: TaskId,
+        #[serde(with = "duration")]
+        duration: Duration,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        error: Option<String>,
+    },
+    /// The runtime took a screenshot, see [`Traced`]
+    Screenshot {
+        #[serde(with = "duration")]
+        at: Duration,
+        frame: FrameId,
+    },
+    /// The run ended
+    Finish {
+        #[serde(with = "duration")]
+        at: Duration,
+        #[serde(default, skip_serializing_if = "core::ops::Not::not")]
+        cancelled: bool,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        error: Option<String>,
+    },
+}
+
+impl TraceEvent {
+    /// When the event started.
+    pub fn at(&self) -> Duration {
+        match self {
+            Self::Enter { at, .. }
+            | Self::Recognize { at, .. }
+            | Self::Exec { at, .. }
+            | Self::Screenshot { at, .. }
+            | Self::Finish { at, .. } => *at,
+        }
+    }
+
+    /// Task the event is about, if any.
+    pub fn task(&self) -> Option<&str> {
+        match self {
+            Self::Enter { task, .. } | Self::Recognize { task, .. } | Self::Exec { task, .. } => {
+                Some(task)
+            }
+            Self::Screenshot { .. } | Self::Finish { .. } => None,
+        }
+    }
+}
+
+/// Receives the events of a run, see the [module documentation](self).
+///
+/// Called from the tasks of the scheduler, so it should return quickly.
+pub trait Tracer: Send + Sync {
+    fn record(&self, event: TraceEvent);
+
+    /// Receives the data of a screenshot, just before its [`TraceEvent::Screenshot`]. Frames seen
+    /// before are passed again, so tracers keep the first one.
+    fn frame(&self, frame: &FrameId, data: &[u8]) {
+        let _ = (frame, data);
+    }
+}
+
+impl<TRACER: Tracer + ?Sized> Tracer for Arc<TRACER> {
+    fn record(&self, event: TraceEvent) {
+        (**self).record(event)
+    }
+
+    fn frame(&self, frame: &FrameId, data: &[u8]) {
+        (**self).frame(frame, data)
+    }
+}
+
+/// Identifies a frame by the 64 bit FNV-1a hash of its data, as 16 hex digits.
+pub fn frame_id(data: &[u8]) -> FrameId {
+    let hash = data.iter().fold(0xcbf2_9ce4_8422_2325_u64, |hash, byte| {
+        (hash ^ u64::from(*byte)).wrapping_mul(0x0000_0100_0000_01b3)
+    });
+    format!("{hash:016x}")
+}
+
+/// Runtime that passes the screenshots of `RUNTIME` to a [`Tracer`], and its input through.
+#[derive(Clone)]
+pub struct Traced<RUNTIME> {
+    runtime: RUNTIME,
+    tracer: Arc<dyn Tracer>,
+}
+
+impl<RUNTIME: Runtime> Traced<RUNTIME> {
+    pub fn new(runtime: RUNTIME, tracer: impl Tracer + 'static) -> Self {
+        Self {
+            runtime,
+            tracer: Arc::new(tracer),
+        }
+    }
+
+    pub fn inner(&self) -> &RUNTIME {
+        &self.runtime
+    }
+}
+
+impl<RUNTIME: Runtime> Runtime for Traced<RUNTIME> {}
+
+#[async_trait]
+impl<RUNTIME: TimerExt> TimerExt for Traced<RUNTIME> {
+    fn now(&self) -> Duration {
+        self.runtime.now()
+    }
+
+    async fn sleep(&self, duration: Duration) {
+        self.runtime.sleep(duration).await
+    }
+}
+
+#[async_trait]
+impl<RUNTIME: InputExt> InputExt for Traced<RUNTIME> {
+    async fn click(&self, x: u32, y: u32, button: u8) -> Result<(), InputError> {
+        self.runtime.click(x, y, button).await
+    }
+
+    async fn move_mouse(&self, x: u32, y: u32) -> Result<(), InputError> {
+        self.runtime.move_mouse(x, y).await
+    }
+
+    async fn type_text(&self, text: &str) -> Result<(), InputError> {
+        self.runtime.type_text(text).await
+    }
+
+    async fn press_key(&self, key: u32) -> Result<(), InputError> {
+        self.runtime.press_key(key).await
+    }
+}
+
+#[async_trait]
+impl<RUNTIME: ScreenshotExt + TimerExt> ScreenshotExt for Traced<RUNTIME> {
+    async fn screenshot(&self) -> Option<Vec<u8>> {
+        let data = self.runtime.screenshot().await?;
+        let frame = frame_id(&data);
+        seen::saw(&frame);
+        self.tracer.frame(&frame, &data);
+        self.tracer.record(TraceEvent::Screenshot {
+            at: self.runtime.now(),
+            frame,
+        });
+        Some(data)
+    }
+}
+
+/// Awaits `recognize`, along with the last frame [`Traced`] took while polling it.
+pub(crate) async fn seen_by<F: Future + Unpin>(recognize: F) -> (F::Output, Option<FrameId>) {
+    seen::seen_by(recognize).await
+}
+
+/// Tells which recognition a screenshot was taken for: while a recognition is polled, the
+/// thread points at the slot its frame goes to. Screenshots are taken in the poll of the future
+/// that asked for them, so this holds however candidates are polled or spawned.
+#[cfg(feature = "std")]
+mod seen {
+    use core::cell::RefCell;
+    use core::pin::Pin;
+
+    use super::*;
+    use crate::sync::Mutex;
+
+    type Slot = Arc<Mutex<Option<FrameId>>>;
+
+    std::thread_local! {
+        static SEEING: RefCell<Option<Slot>> = const { RefCell::new(None) };
+    }
+
+    /// Puts back the slot of an enclosing recognition, even if polling panics.
+    struct Restore(Option<Slot>);
+
+    impl Drop for Restore {
+        fn drop(&mut self) {
+            SEEING.with(|seeing| *seeing.borrow_mut() = self.0.take());
+        }
+    }
+
+    pub(super) async fn seen_by<F: Future + Unpin>(
+        mut recognize: F,
+    ) -> (F::Output, Option<FrameId>) {
+        let slot = Slot::default();
+        let output = core::future::poll_fn(|cx| {
+            let _restore = Restore(SEEING.with(|seeing| seeing.replace(Some(slot.clone()))));
+            Pin::new(&mut recognize).poll(cx)
+        })
+        .await;
+        let frame = slot.lock().take();
+        (output, frame)
+    }
+
+    pub(super) fn saw(frame: &FrameId) {
+        SEEING.with(|seeing| {
+            if let Some(slot) = &*seeing.borrow() {
+                *slot.lock() = Some(frame.clone());
+            }
+        });
+    }
+}
+
+/// Without thread locals, screenshots aren't attributed to recognitions.
+#[cfg(not(feature = "std"))]
+mod seen {
+    use super::*;
+
+    pub(super) async fn seen_by<F: Future + Unpin>(recognize: F) -> (F::Output, Option<FrameId>) {
+        (recognize.await, None)
+    }
+
+    pub(super) fn saw(_frame: &FrameId) {}
+}
+
+#[cfg(feature = "std")]
+mod file {
+    use std::collections::{BTreeMap, BTreeSet};
+    use std::fs::File;
+    use std::io::{self, BufRead, BufReader, BufWriter, Write};
+    use std::path::Path;
+    use std::sync::Mutex;
+
+    use base64::prelude::{Engine, BASE64_STANDARD};
+    use serde::{Deserialize, Serialize};
+    use snafu::Snafu;
+
+    use super::*;
+
+    /// A line of a trace file.
+    #[derive(Serialize, Deserialize)]
+    #[serde(untagged)]
+    enum Line {
+        Event(TraceEvent),
+        /// The data of a frame, in base64, written before the first event referring to it
+        Frame {
+            frame: FrameId,
+            data: String,
+        },
+    }
+
+    /// [`Tracer`] writing a trace as JSON lines, see the [module documentation](super).
+    ///
+    /// Every line is flushed as it is written, so the trace of a run that crashes is complete up
+    /// to the crash. Clones write to the same file.
+    #[derive(Clone)]
+    pub struct TraceWriter(Arc<Mutex<WriterState>>);
+
+    struct WriterState {
+        out: Box<dyn Write + Send>,
+        frames: BTreeSet<FrameId>,
+        failed: bool,
+    }
+
+    impl TraceWriter {
+        pub fn create(path: impl AsRef<Path>) -> io::Result<Self> {
+            Ok(Self::new(BufWriter::new(File::create(path)?)))
+        }
+
+        pub fn new(out: impl Write + Send + 'static) -> Self {
+            Self(Arc::new(Mutex::new(WriterState {
+                out: Box::new(out),
+                frames: BTreeSet::new(),
+                failed: false,
+            })))
+        }
+
+        fn write(&self, line: &Line) {
+            let mut state = self.0.lock().unwrap_or_else(|err| err.into_inner());
+            if state.failed {
+                return;
+            }
+            let written = serde_json::to_writer(&mut state.out, line)
+                .map_err(io::Error::from)
+                .and_then(|()| state.out.write_all(b"\n"))
+                .and_then(|()| state.out.flush());
+            if let Err(err) = written {
+                // Once, rather than for every event of the run
+                log::error!("failed to write the trace, later events are dropped: {err}");
+                state.failed = true;
+            }
+        }
+    }
+
+    impl Tracer for TraceWriter {
+        fn record(&self, event: TraceEvent) {
+            self.write(&Line::Event(event));
+        }
+
+        fn frame(&self, frame: &FrameId, data: &[u8]) {
+            let new = self
+                .0
+                .lock()
+                .unwrap_or_else(|err| err.into_inner())
+                .frames
+                .insert(frame.clone());
+            if new {
+                self.write(&Line::Frame {
+                    frame: frame.clone(),
+                    data: BASE64_STANDARD.encode(data),
+                });
+            }
+        }
+    }
+
+    /// A trace read back from a [`TraceWriter`].
+    #[derive(Debug, Clone, Default)]
+    pub struct Trace {
+        pub events: Vec<TraceEvent>,
+        /// Data of the frames of [`TraceEvent::Screenshot`]
+        pub frames: BTreeMap<FrameId, Vec<u8>>,
+    }
+
+    impl Trace {
+        pub fn open(path: impl AsRef<Path>) -> Result<Self, TraceError> {
+            let file = File::open(path).map_err(|source| TraceError::Io { source })?;
+            Self::read(BufReader::new(file))
+        }
+
+        /// Reads a trace; a last line cut short, by a crash while writing it, is ignored.
+        pub fn read(reader: impl BufRead) -> Result<Self, TraceError> {
+            let mut trace = Self::default();
+            let mut lines = reader.lines().enumerate().peekable();
+            while let Some((index, line)) = lines.next() {
+                let line = line.map_err(|source| TraceError::Io { source })?;
+                if line.trim().is_empty() {
+                    continue;
+                }
+                let line = match serde_json::from_str(&line) {
+                    Ok(line) => line,
+                    Err(source) if source.is_eof() && lines.peek().is_none() => break,
+                    Err(source) => {
+                        return Err(TraceError::Syntax {
+                            line: index + 1,
+                            source,
+                        })
+                    }
+                };
+                match line {
+                    Line::Event(event) => trace.events.push(event),
+                    Line::Frame { frame, data } => {
+                        let data = BASE64_STANDARD.decode(data).map_err(|err| {
+                            TraceError::InvalidFrame {
+                                line: index + 1,
+                                reason: err.to_string(),
+                            }
+                        })?;
+                        trace.frames.insert(frame, data);
+                    }
+                }
+            }
+            Ok(trace)
+        }
+
+        /// The frame the event at `index` saw.
+        ///
+        /// A recognition saw the last frame its action took, if any. Other events saw the last
+        /// screenshot taken before they were recorded.
+        pub fn frame_seen_by(&self, index: usize) -> Option<&FrameId> {
+            if let TraceEvent::Recognize { frame, .. } = self.events.get(index)? {
+                return frame.as_ref();
+            }
+            self.events[..=index]
+                .iter()
+                .rev()
+                .find_map(|event| match event {
+                    TraceEvent::Screenshot { frame, .. } => Some(frame),
+                    _ => None,
+                })
+        }
+    }
+
+    #[derive(Debug, Snafu)]
+    pub enum TraceError {
+        #[snafu(display("{source}"))]
+        Io { source: io::Error },
+        #[snafu(display("line {line}: {source}"))]
+        Syntax {
+            line: usize,
+            source: serde_json::Error,
+        },
+        #[snafu(display("line {line}: invalid frame data: {reason}"))]
+        InvalidFrame { line: usize, reason: String },
+    }
+}
@@ -1,8 +1,7 @@
 use cice_core::context::ContextBuilder;
 use cice_core::message::{task::TaskMessage, Message};
-use cice_core::task::TaskConfig;
+use cice_core::pipeline::{ActionRegistry, Pipeline};
 use cice_tests_common::action::{DenyAction, SimpleAction, TestRuntime};
-use cice_tests_common::task::Tasks;
 
 #[tokio::test]
 async fn config() {
@@ -12,18 +11,13 @@ async fn config() {
     // 创建 Action
     let simple_action = SimpleAction::new("simple_action");
 
-    // 创建 Context Builder
-    let mut builder = ContextBuilder::new(runtime);
+    // 注册 Action
+    let mut registry = ActionRegistry::new();
+    registry.register("simple_action", &simple_action);
 
-    // 从 JSON 加载任务配置
-    let task_config = include_str!("task_config/json/base_task.json");
-    let tasks: Tasks = serde_json::from_str(task_config).unwrap();
-    let task_configs: Vec<TaskConfig> = tasks.into();
-
-    // 添加任务
-    for config in task_configs {
-        builder.add_task(config, &simple_action);
-    }
+    // 从 JSON 加载任务配置并创建 Context Builder
+    let pipeline = Pipeline::from_json(include_str!("task_config/json/base_task.json")).unwrap();
+    let builder = ContextBuilder::from_pipeline(runtime, pipeline, &registry).unwrap();
 
     // 构建并运行
     let ret = builder.build().run("test".to_string()).await;
@@ -40,22 +34,16 @@ async fn task_sequence() {
     let accept_action = SimpleAction::new("accept_action");
     let deny_action = DenyAction::new("deny_action");
 
-    // 创建 Context Builder
-    let mut builder = ContextBuilder::new(runtime);
-
-    // 从 JSON 加载任务配置
-    let task_config = include_str!("task_config/json/task_sequence.json");
-    let tasks: Tasks = serde_json::from_str(task_config).unwrap();
-    let task_configs: Vec<TaskConfig> = tasks.into();
-
-    // 添加任务 - 根据 action_name 分配对应的 action
-    for config in task_configs {
-        match config.action_name.as_str() {
-            "accept_action" => builder.add_task(config, &accept_action),
-            "deny_action" => builder.add_task(config, &deny_action),
-            _ => panic!("Unknown action name: {}", config.action_name),
-        };
-    }
+    // 注册 Action - 根据 action_name 分配对应的 action
+    let mut registry = ActionRegistry::new();
+    registry
+        .register("accept_action", &accept_action)
+        .register("deny_action", &deny_action);
+
+    // 从 JSON 加载任务配置并创建 Context Builder
+    let pipeline =
+        Pipeline::from_json(include_str!("task_config/json/task_sequence.json")).unwrap();
+    let builder = ContextBuilder::from_pipeline(runtime, pipeline, &registry).unwrap();
 
     let context = builder.build();
     let handler = context.get_handler();
@@ -107,22 +95,15 @@ async fn simple_image() {
     let simple_action = SimpleAction::new("simple_action");
     let simple_image_action = SimpleAction::new("simple_image_action");
 
-    // 创建 Context Builder
-    let mut builder = ContextBuilder::new(runtime);
-
-    // 从 JSON 加载任务配置
-    let task_config = include_str!("task_config/json/simple_image.json");
-    let tasks: Tasks = serde_json::from_str(task_config).unwrap();
-    let task_configs: Vec<TaskConfig> = tasks.into();
-
-    // 添加任务
-    for config in task_configs {
-        match config.action_name.as_str() {
-            "simple_action" => builder.add_task(config, &simple_action),
-            "simple_image_action" => builder.add_task(config, &simple_image_action),
-            _ => panic!("Unknown action name: {}", config.action_name),
-        };
-    }
+    // 注册 Action - 根据 action_name 分配对应的 action
+    let mut registry = ActionRegistry::new();
+    registry
+        .register("simple_action", &simple_action)
+        .register("simple_image_action", &simple_image_action);
+
+    // 从 JSON 加载任务配置并创建 Context Builder
+    let pipeline = Pipeline::from_json(include_str!("task_config/json/simple_image.json")).unwrap();
+    let builder = ContextBuilder::from_pipeline(runtime, pipeline, &registry).unwrap();
 
     let ret = builder.build().run("entry".to_string()).await;
     println!("{ret:?}");
@@ -138,22 +119,18 @@ async fn controller_input_and_output_action() {
     let simple_action = SimpleAction::new("simple_action");
     let input_output_action = SimpleAction::new("input_output_action");
 
-    // 创建 Context Builder
-    let mut builder = ContextBuilder::new(runtime);
-
-    // 从 JSON 加载任务配置
-    let task_config = include_str!("task_config/json/controller_input_and_output_action.json");
-    let tasks: Tasks = serde_json::from_str(task_config).unwrap();
-    let task_configs: Vec<TaskConfig> = tasks.into();
-
-    // 添加任务
-    for config in task_configs {
-        match config.action_name.as_str() {
-            "simple_action" => builder.add_task(config, &simple_action),
-            "input_output_action" => builder.add_task(config, &input_output_action),
-            _ => panic!("Unknown action name: {}", config.action_name),
-        };
-    }
+    // 注册 Action - 根据 action_name 分配对应的 action
+    let mut registry = ActionRegistry::new();
+    registry
+        .register("simple_action", &simple_action)
+        .register("input_output_action", &input_output_action);
+
+    // 从 JSON 加载任务配置并创建 Context Builder
+    let pipeline = Pipeline::from_json(include_str!(
+        "task_config/json/controller_input_and_output_action.json"
+    ))
+    .unwrap();
+    let builder = ContextBuilder::from_pipeline(runtime, pipeline, &registry).unwrap();
 
     let ret = builder.build().run("entry".to_string()).await;
     println!("{ret:?}");
@@ -169,22 +146,18 @@ async fn recognizer_simple_with_action() {
     let simple_action = SimpleAction::new("simple_action");
     let action_with_area = SimpleAction::new("action_with_area");
 
-    // 创建 Context Builder
-    let mut builder = ContextBuilder::new(runtime);
-
-    // 从 JSON 加载任务配置
-    let task_config = include_str!("task_config/json/recognizer_simple_with_action.json");
-    let tasks: Tasks = serde_json::from_str(task_config).unwrap();
-    let task_configs: Vec<TaskConfig> = tasks.into();
-
-    // 添加任务
-    for config in task_configs {
-        match config.action_name.as_str() {
-            "simple_action" => builder.add_task(config, &simple_action),
-            "action_with_area" => builder.add_task(config, &action_with_area),
-            _ => panic!("Unknown action name: {}", config.action_name),
-        };
-    }
+    // 注册 Action - 根据 action_name 分配对应的 action
+    let mut registry = ActionRegistry::new();
+    registry
+        .register("simple_action", &simple_action)
+        .register("action_with_area", &action_with_area);
+
+    // 从 JSON 加载任务配置并创建 Context Builder
+    let pipeline = Pipeline::from_json(include_str!(
+        "task_config/json/recognizer_simple_with_action.json"
+    ))
+    .unwrap();
+    let builder = ContextBuilder::from_pipeline(runtime, pipeline, &registry).unwrap();
 
     let ret = builder.build().run("entry".to_string()).await;
     println!("{ret:?}");
@@ -0,0 +1,210 @@
+use std::time::Duration;
+
+use cice_core::context::{ContextBuilder, ContextHandler};
+use cice_core::debug::{Breakpoint, Halt, Stage};
+use cice_core::guard::Guard;
+use cice_core::task::{TaskConfig, TaskResult};
+use cice_tests_common::action::{Outcome, ScriptedAction, TestRuntime};
+use cice_tests_common::clock::VirtualClock;
+use cice_tests_common::harness;
+use futures::future::join;
+
+fn task_config(name: &str, next_task: &[&str]) -> TaskConfig {
+    TaskConfig {
+        task_name: name.to_string(),
+        action_name: name.to_string(),
+        next_task: next_task.iter().map(|id| id.to_string()).collect(),
+        interrupt_task: vec![],
+        guards: Default::default(),
+        timeout: Duration::from_secs(10),
+        max_retry: 20,
+    }
+}
+
+/// Waits in virtual time until the context halts.
+async fn halted(handler: &ContextHandler, clock: &VirtualClock) -> Halt {
+    loop {
+        if let Some(halt) = handler.halted() {
+            return halt;
+        }
+        clock.sleep(Duration::from_millis(50)).await;
+    }
+}
+
+fn halt(stage: Stage, task: &str, candidates: &[&str]) -> Halt {
+    Halt {
+        stage,
+        task: task.to_string(),
+        candidates: candidates.iter().map(|id| id.to_string()).collect(),
+    }
+}
+
+#[test]
+fn breakpoints_halt_around_exec() {
+    let runtime = TestRuntime::new();
+    let clock = runtime.clock().clone();
+    let (entry, login, home) = (
+        ScriptedAction::new("entry"),
+        ScriptedAction::new("login"),
+        ScriptedAction::new("home"),
+    );
+    let mut builder = ContextBuilder::new(runtime);
+    builder
+        .add_task(task_config("entry", &["login"]), &entry)
+        .unwrap();
+    builder
+        .add_task(task_config("login", &["home"]), &login)
+        .unwrap();
+    builder.add_task(task_config("home", &[]), &home).unwrap();
+    let context = builder.build();
+    let handler = context.get_handler();
+    handler.set_breakpoint("login", Stage::BeforeExec);
+    handler.set_breakpoint("login", Stage::AfterExec);
+    handler.set_breakpoint("home", Stage::AfterExec);
+    assert!(handler.remove_breakpoint("home", Stage::AfterExec));
+    assert!(!handler.remove_breakpoint("home", Stage::AfterExec));
+    assert_eq!(
+        handler.breakpoints(),
+        [
+            Breakpoint {
+                task: "login".to_string(),
+                stage: Stage::BeforeExec
+            },
+            Breakpoint {
+                task: "login".to_string(),
+                stage: Stage::AfterExec
+            },
+        ]
+    );
+
+    let debug = async {
+        assert_eq!(
+            halted(&handler, &clock).await,
+            halt(Stage::BeforeExec, "login", &[])
+        );
+        assert_eq!(login.exec_count(), 0);
+        handler.resume();
+        clock.sleep(Duration::from_secs(1)).await;
+        assert_eq!(
+            halted(&handler, &clock).await,
+            halt(Stage::AfterExec, "login", &[])
+        );
+        assert_eq!(login.exec_count(), 1);
+        assert_eq!(home.recognize_count(), 0);
+        handler.resume();
+    };
+    let (result, ()) = harness::block_on(&clock, join(context.run("entry".to_string()), debug));
+    assert!(matches!(result, Ok(TaskResult::NoPendingTask)));
+    assert_eq!(home.exec_count(), 1);
+    assert_eq!(handler.halted(), None);
+}
+
+#[test]
+fn steps_go_one_round_or_one_task_at_a_time() {
+    let runtime = TestRuntime::new();
+    let clock = runtime.clock().clone();
+    let (entry, late, home) = (
+        ScriptedAction::new("entry"),
+        ScriptedAction::new("late")
+            .at(Duration::ZERO, Outcome::UnRecognized)
+            .at(Duration::from_secs(1), Outcome::Recognized),
+        ScriptedAction::new("home"),
+    );
+    let mut builder = ContextBuilder::new(runtime);
+    builder
+        .add_task(task_config("entry", &["late"]), &entry)
+        .unwrap();
+    builder
+        .add_task(task_config("late", &["home"]), &late)
+        .unwrap();
+    builder.add_task(task_config("home", &[]), &home).unwrap();
+    let context = builder.build();
+    let handler = context.get_handler();
+    handler.pause();
+
+    let debug = async {
+        let first_round = halt(Stage::BeforeRecognize, "entry", &["late"]);
+        assert_eq!(halted(&handler, &clock).await, first_round);
+        handler.step_round();
+        clock.sleep(Duration::from_millis(10)).await;
+        assert_eq!(halted(&handler, &clock).await, first_round);
+        assert_eq!(late.recognize_count(), 1);
+
+        // Recognized in a later round, then halted before its exec
+        handler.step_task();
+        clock.sleep(Duration::from_millis(10)).await;
+        assert_eq!(
+            halted(&handler, &clock).await,
+            halt(Stage::BeforeExec, "late", &[])
+        );
+        assert_eq!(late.exec_count(), 0);
+        handler.resume();
+    };
+    let (result, ()) = harness::block_on(&clock, join(context.run("entry".to_string()), debug));
+    assert!(matches!(result, Ok(TaskResult::NoPendingTask)));
+    assert_eq!(late.exec_count(), 1);
+    assert_eq!(home.exec_count(), 1);
+}
+
+#[test]
+fn halted_rounds_see_blackboard_changes() {
+    let runtime = TestRuntime::new();
+    let clock = runtime.clock().clone();
+    let (entry, shop, battle) = (
+        ScriptedAction::new("entry"),
+        ScriptedAction::new("shop"),
+        ScriptedAction::new("battle"),
+    );
+    let mut config = task_config("entry", &["shop", "battle"]);
+    for (target, mode) in [("shop", "shop"), ("battle", "battle")] {
+        let guard = Guard::new(format!("vars.mode == '{mode}'").parse().unwrap());
+        config.guards.insert(target.to_string(), guard);
+    }
+    let mut builder = ContextBuilder::new(runtime);
+    builder.add_task(config, &entry).unwrap();
+    builder.add_task(task_config("shop", &[]), &shop).unwrap();
+    builder
+        .add_task(task_config("battle", &[]), &battle)
+        .unwrap();
+    let context = builder.build();
+    let handler = context.get_handler();
+    handler.blackboard().set("mode", "shop");
+    handler.set_breakpoint("shop", Stage::BeforeRecognize);
+
+    let debug = async {
+        assert_eq!(
+            halted(&handler, &clock).await,
+            halt(Stage::BeforeRecognize, "entry", &["shop"])
+        );
+        handler.blackboard().set("mode", "battle");
+        handler.resume();
+    };
+    let (result, ()) = harness::block_on(&clock, join(context.run("entry".to_string()), debug));
+    assert!(matches!(result, Ok(TaskResult::NoPendingTask)));
+    assert_eq!(shop.recognize_count(), 0);
+    assert_eq!(battle.exec_count(), 1);
+}
+
+#[test]
+fn halted_contexts_can_be_cancelled() {
+    let runtime = TestRuntime::new();
+    let clock = runtime.clock().clone();
+    let (entry, login) = (ScriptedAction::new("entry"), ScriptedAction::new("login"));
+    let mut builder = ContextBuilder::new(runtime);
+    builder
+        .add_task(task_config("entry", &["login"]), &entry)
+        .unwrap();
+    builder.add_task(task_config("login", &[]), &login).unwrap();
+    let context = builder.build();
+    let handler = context.get_handler();
+    handler.set_breakpoint("login", Stage::BeforeExec);
+
+    let debug = async {
+        halted(&handler, &clock).await;
+        handler.try_cancel().unwrap();
+    };
+    let (result, ()) = harness::block_on(&clock, join(context.run("entry".to_string()), debug));
+    assert!(matches!(result, Ok(TaskResult::TaskCancelled)));
+    assert_eq!(login.exec_count(), 0);
+    assert_eq!(handler.halted(), None);
+}
@@ -0,0 +1,136 @@
+use std::time::Duration;
+
+use cice_core::context::ContextBuilder;
+use cice_core::graph::{EdgeKind, TaskGraph, ERROR_NODE};
+use cice_core::pipeline::Pipeline;
+use cice_core::task::TaskConfig;
+use cice_tests_common::action::{SimpleAction, TestRuntime};
+
+fn pipeline() -> Pipeline {
+    Pipeline::from_json(
+        r#"{
+            "login": {
+                "action_name": "click_login",
+                "next_task": ["home", "give_up"],
+                "interrupt_task": ["popup"],
+                "guards": { "give_up": { "if": "runs.login > 3", "recognize": false } },
+                "timeout": "10s"
+            },
+            "home": { "action_name": "find_home", "next_task": ["shop"] },
+            "give_up": { "action_name": "quit" },
+            "popup": { "action_name": "close_popup" }
+        }"#,
+    )
+    .unwrap()
+}
+
+#[test]
+fn graph_classifies_tasks_and_edges() {
+    let graph = TaskGraph::from(&pipeline());
+    assert_eq!(graph.entries().into_iter().collect::<Vec<_>>(), ["login"]);
+    assert_eq!(
+        graph.terminals().into_iter().collect::<Vec<_>>(),
+        ["give_up", "popup"]
+    );
+    assert_eq!(graph.dangling().into_iter().collect::<Vec<_>>(), ["shop"]);
+
+    let edges = graph.edges();
+    let kinds = |kind| {
+        edges
+            .iter()
+            .filter(|edge| edge.kind == kind)
+            .map(|edge| (edge.from.as_str(), edge.to.as_str(), edge.label.as_deref()))
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(
+        kinds(EdgeKind::Next),
+        [
+            ("home", "shop", None),
+            ("login", "home", None),
+            ("login", "give_up", Some("runs.login > 3 (no recognize)")),
+        ]
+    );
+    assert_eq!(kinds(EdgeKind::Interrupt), [("login", "popup", None)]);
+    assert_eq!(
+        kinds(EdgeKind::Error),
+        [
+            ("home", ERROR_NODE, Some("timeout 30s")),
+            ("login", ERROR_NODE, Some("timeout 10s")),
+        ]
+    );
+}
+
+#[test]
+fn graph_renders_dot() {
+    let mut graph = TaskGraph::from(&pipeline());
+    graph.entry("login");
+    assert_eq!(
+        graph.to_dot(),
+        r#"digraph tasks {
+    node [shape=box];
+    "give_up" [style="rounded,filled", fillcolor=lightgrey];
+    "home";
+    "login" [penwidth=2, color=blue];
+    "popup" [style="rounded,filled", fillcolor=lightgrey];
+    "shop" [label="shop (undefined)", style=dashed, color=red, fontcolor=red];
+    "$error" [label="error", shape=octagon, color=red];
+    "home" -> "shop" [color=red];
+    "home" -> "$error" [style=dotted, color=red, label="timeout 30s"];
+    "login" -> "home";
+    "login" -> "give_up" [label="runs.login > 3 (no recognize)"];
+    "login" -> "popup" [style=dashed];
+    "login" -> "$error" [style=dotted, color=red, label="timeout 10s"];
+}
+"#
+    );
+}
+
+#[test]
+fn graph_renders_mermaid() {
+    let graph = TaskGraph::from(&pipeline());
+    assert_eq!(
+        graph.to_mermaid(),
+        r#"flowchart TD
+    t0(["give_up"])
+    t1["home"]
+    t2["login"]
+    t3(["popup"])
+    t4["shop (undefined)"]
+    t5{{error}}
+    t1 --> t4
+    t1 -.-x|"timeout 30s"| t5
+    t2 --> t1
+    t2 -->|"runs.login #gt; 3 (no recognize)"| t0
+    t2 -.-> t3
+    t2 -.-x|"timeout 10s"| t5
+    classDef entry stroke:#00f,stroke-width:3px
+    class t2 entry
+    classDef dangling stroke:#f00,stroke-dasharray:4,color:#f00
+    class t4 dangling
+"#
+    );
+}
+
+#[test]
+fn context_exposes_its_task_graph() {
+    let action = SimpleAction::new("action");
+    let mut builder = ContextBuilder::new(TestRuntime::new());
+    builder
+        .add_task(
+            TaskConfig {
+                task_name: "entry".to_string(),
+                action_name: "action".to_string(),
+                next_task: vec!["next".to_string()],
+                interrupt_task: vec![],
+                guards: Default::default(),
+                timeout: Duration::from_secs(30),
+                max_retry: 3,
+            },
+            &action,
+        )
+        .unwrap();
+    let context = builder.build();
+    let graph = context.task_graph();
+    assert_eq!(graph.entries().into_iter().collect::<Vec<_>>(), ["entry"]);
+    assert_eq!(graph.dangling().into_iter().collect::<Vec<_>>(), ["next"]);
+}
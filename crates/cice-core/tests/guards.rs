@@ -0,0 +1,338 @@
+use std::collections::{BTreeMap, VecDeque};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use cice_core::action::{Action, ExecError, Recognition, RecognizeError};
+use cice_core::blackboard::Blackboard;
+use cice_core::context::ContextBuilder;
+use cice_core::guard::{Condition, Guard};
+use cice_core::pipeline::{Pipeline, PipelineError};
+use cice_core::task::{TaskConfig, TaskResult};
+use cice_tests_common::action::{Outcome, ScriptedAction, TestRuntime};
+use cice_tests_common::harness;
+
+fn task_config(name: &str, next_task: &[&str], guards: &[(&str, Guard)]) -> TaskConfig {
+    TaskConfig {
+        task_name: name.to_string(),
+        action_name: name.to_string(),
+        next_task: next_task.iter().map(|id| id.to_string()).collect(),
+        interrupt_task: vec![],
+        guards: guards
+            .iter()
+            .map(|(target, guard)| (target.to_string(), guard.clone()))
+            .collect::<BTreeMap<_, _>>(),
+        timeout: Duration::from_secs(10),
+        max_retry: 20,
+    }
+}
+
+fn condition(source: &str) -> Condition {
+    source.parse().unwrap()
+}
+
+#[test]
+fn conditions_read_the_blackboard() {
+    let blackboard = Blackboard::new();
+    blackboard.set("mode", "shop");
+    blackboard.set("coins", 120);
+
+    assert!(condition("vars.mode == 'shop' && vars.coins >= 100").eval(&blackboard));
+    assert!(condition("!(vars.coins < 100) || vars.mode != \"shop\"").eval(&blackboard));
+    assert!(condition("vars.missing == null && !vars.missing").eval(&blackboard));
+    assert!(condition("runs.login == 0").eval(&blackboard));
+    // A missing score is neither above nor below a threshold
+    assert!(!condition("score.login > 0.5").eval(&blackboard));
+    assert!(!condition("score.login <= 0.5").eval(&blackboard));
+    assert!(!condition("vars.mode > 3").eval(&blackboard));
+}
+
+#[test]
+fn conditions_display_as_they_parse() {
+    for source in [
+        "vars.mode == \"shop\" && (runs.login < 3 || score[\"menu button\"] > 0.8)",
+        "!(runs.login::start >= 2) && vars.server.host != null",
+        "vars.a || vars.b && !vars.c",
+    ] {
+        let parsed = condition(source);
+        assert_eq!(parsed.to_string(), source);
+        assert_eq!(condition(&parsed.to_string()), parsed);
+    }
+    assert_eq!(condition("((vars.a))").to_string(), "vars.a");
+    assert_eq!(
+        condition("runs.a > 1 && score.b > 0.5 || runs.a > 5")
+            .tasks()
+            .collect::<Vec<_>>(),
+        ["a", "b", "a"]
+    );
+}
+
+#[test]
+fn invalid_conditions_are_located() {
+    for (source, message) in [
+        (
+            "runs.login <",
+            "expected a value, found end of condition at column 13",
+        ),
+        (
+            "count > 1",
+            "unknown name `count`, expected `vars`, `runs` or `score` at column 1",
+        ),
+        (
+            "runs.a < 1 < 2",
+            "expected an operator, found `<` at column 12",
+        ),
+        ("vars.a = 1", "unexpected character `=` at column 8"),
+        ("vars['a", "unterminated string at column 6"),
+    ] {
+        let err = source.parse::<Condition>().unwrap_err();
+        assert_eq!(err.to_string(), message, "{source}");
+    }
+}
+
+#[test]
+fn guards_filter_next_tasks_before_recognition() {
+    let runtime = TestRuntime::new();
+    let clock = runtime.clock().clone();
+    let (entry, shop, battle) = (
+        ScriptedAction::new("entry"),
+        ScriptedAction::new("shop"),
+        ScriptedAction::new("battle"),
+    );
+    let mut builder = ContextBuilder::new(runtime);
+    builder
+        .add_task(
+            task_config(
+                "entry",
+                &["shop", "battle"],
+                &[
+                    ("shop", Guard::new(condition("vars.mode == 'shop'"))),
+                    ("battle", Guard::new(condition("vars.mode == 'battle'"))),
+                ],
+            ),
+            &entry,
+        )
+        .unwrap();
+    builder
+        .add_task(task_config("shop", &[], &[]), &shop)
+        .unwrap();
+    builder
+        .add_task(task_config("battle", &[], &[]), &battle)
+        .unwrap();
+    let context = builder.build();
+    context.get_handler().blackboard().set("mode", "battle");
+
+    let run = harness::run(&context, &clock, "entry");
+    assert!(matches!(run.result, Ok(TaskResult::NoPendingTask)));
+    assert_eq!(run.entered(), ["entry", "battle"]);
+    assert_eq!(run.recognize_attempts("shop"), 0);
+    assert_eq!(shop.exec_count(), 0);
+}
+
+#[test]
+fn guards_without_recognition_count_runs() {
+    let runtime = TestRuntime::new();
+    let clock = runtime.clock().clone();
+    let (start, attempt, give_up) = (
+        ScriptedAction::new("start"),
+        ScriptedAction::new("attempt"),
+        ScriptedAction::new("give_up").at(Duration::ZERO, Outcome::UnRecognized),
+    );
+    let mut builder = ContextBuilder::new(runtime);
+    builder
+        .add_task(task_config("start", &["attempt"], &[]), &start)
+        .unwrap();
+    builder
+        .add_task(
+            task_config(
+                "attempt",
+                &["attempt", "give_up"],
+                &[
+                    (
+                        "attempt",
+                        Guard::without_recognize(condition("runs.attempt < 3")),
+                    ),
+                    (
+                        "give_up",
+                        Guard::without_recognize(condition("runs.attempt >= 3")),
+                    ),
+                ],
+            ),
+            &attempt,
+        )
+        .unwrap();
+    builder
+        .add_task(task_config("give_up", &[], &[]), &give_up)
+        .unwrap();
+    let context = builder.build();
+
+    let run = harness::run(&context, &clock, "start");
+    assert!(matches!(run.result, Ok(TaskResult::NoPendingTask)));
+    assert_eq!(
+        run.entered(),
+        ["start", "attempt", "attempt", "attempt", "give_up"]
+    );
+    assert_eq!(run.recognize_attempts("give_up"), 0);
+    assert_eq!(give_up.exec_count(), 1);
+    assert_eq!(context.blackboard().runs("attempt"), 3);
+    assert_eq!(run.elapsed, Duration::ZERO);
+}
+
+#[test]
+fn guards_read_the_last_recognition_score() {
+    let runtime = TestRuntime::new();
+    let clock = runtime.clock().clone();
+    let entry = ScriptedAction::new("entry");
+    let login = ScriptedAction::new("login")
+        .at(Duration::ZERO, Outcome::UnRecognized)
+        .score(0.6);
+    let almost = ScriptedAction::new("almost");
+    let mut builder = ContextBuilder::new(runtime);
+    builder
+        .add_task(
+            task_config(
+                "entry",
+                &["login", "almost"],
+                &[(
+                    "almost",
+                    Guard::without_recognize(condition("score.login > 0.5")),
+                )],
+            ),
+            &entry,
+        )
+        .unwrap();
+    builder
+        .add_task(task_config("login", &[], &[]), &login)
+        .unwrap();
+    builder
+        .add_task(task_config("almost", &[], &[]), &almost)
+        .unwrap();
+    let context = builder.build();
+
+    let run = harness::run(&context, &clock, "entry");
+    assert_eq!(run.entered(), ["entry", "almost"]);
+    // The score is only known after the first round
+    assert_eq!(run.recognize_attempts("login"), 1);
+    assert_eq!(run.elapsed, Duration::from_millis(500));
+    assert_eq!(context.blackboard().score("login"), Some(0.6));
+}
+
+/// Recognizes with the queued outcomes and scores, then is never recognized nor scored.
+struct Queued(Mutex<VecDeque<(bool, Option<f64>)>>);
+
+#[async_trait]
+impl Action<TestRuntime> for Queued {
+    async fn recognize(&self, runtime: &TestRuntime) -> Result<(), RecognizeError> {
+        self.recognize_scored(runtime).await.result
+    }
+
+    async fn recognize_scored(&self, _runtime: &TestRuntime) -> Recognition {
+        let (recognized, score) = self.0.lock().unwrap().pop_front().unwrap_or((false, None));
+        Recognition {
+            result: recognized.then_some(()).ok_or(RecognizeError::UnRecognized),
+            score,
+            location: None,
+        }
+    }
+
+    async fn exec(&self, _runtime: &TestRuntime) -> Result<(), ExecError> {
+        Ok(())
+    }
+}
+
+#[test]
+fn unscored_recognitions_clear_the_last_score() {
+    let runtime = TestRuntime::new();
+    let clock = runtime.clock().clone();
+    let entry = ScriptedAction::new("entry");
+    // Recognized with a high score once, then fails without reporting any
+    let login = Queued(Mutex::new(VecDeque::from([(true, Some(0.9))])));
+    // Scored from the first round on, so `stale` is only checked after `login` failed
+    let tick = ScriptedAction::new("tick")
+        .at(Duration::ZERO, Outcome::UnRecognized)
+        .score(1.0);
+    let stale = ScriptedAction::new("stale");
+    let done = ScriptedAction::new("done")
+        .at(Duration::ZERO, Outcome::UnRecognized)
+        .at(Duration::from_secs(1), Outcome::Recognized);
+    let mut builder = ContextBuilder::new(runtime);
+    builder
+        .add_task(task_config("entry", &["login"], &[]), &entry)
+        .unwrap();
+    builder
+        .add_task(
+            task_config(
+                "login",
+                &["login", "tick", "stale", "done"],
+                &[(
+                    "stale",
+                    Guard::without_recognize(condition("score.login > 0.5 && score.tick > 0")),
+                )],
+            ),
+            &login,
+        )
+        .unwrap();
+    builder
+        .add_task(task_config("tick", &[], &[]), &tick)
+        .unwrap();
+    builder
+        .add_task(task_config("stale", &[], &[]), &stale)
+        .unwrap();
+    builder
+        .add_task(task_config("done", &[], &[]), &done)
+        .unwrap();
+    let context = builder.build();
+
+    let run = harness::run(&context, &clock, "entry");
+    assert!(matches!(run.result, Ok(TaskResult::NoPendingTask)));
+    assert_eq!(run.entered(), ["entry", "login", "done"]);
+    assert_eq!(stale.exec_count(), 0);
+    assert_eq!(context.blackboard().score("login"), None);
+    assert_eq!(context.blackboard().recognized("login"), Some(false));
+}
+
+#[test]
+fn guards_are_read_from_pipelines() {
+    let pipeline = Pipeline::from_json(
+        r#"{
+            "$templates": {
+                "retrying": { "guards": { "retry": "runs.retry < 3" } }
+            },
+            "login": {
+                "extends": "retrying",
+                "action_name": "click_login",
+                "next_task": ["home", "retry", "give_up"],
+                "guards": { "give_up": { "if": "runs.retry >= 3", "recognize": false } }
+            }
+        }"#,
+    )
+    .unwrap();
+    let login = pipeline.get("login").unwrap();
+    assert_eq!(login.guards.len(), 2);
+    assert!(login.guards["retry"].recognize);
+    assert_eq!(
+        login.guards["give_up"],
+        Guard::without_recognize(condition("runs.retry >= 3"))
+    );
+    let round_trip: Pipeline =
+        serde_json::from_value(serde_json::to_value(&pipeline).unwrap()).unwrap();
+    assert_eq!(round_trip, pipeline);
+
+    let err = Pipeline::from_json(
+        r#"{ "login": { "action_name": "a", "next_task": ["home"], "guards": { "hom": "true" } } }"#,
+    )
+    .unwrap_err();
+    assert!(
+        matches!(err, PipelineError::UnknownGuardTarget { ref id, ref target } if id == "login" && target == "hom")
+    );
+
+    let err = Pipeline::from_json(
+        r#"{ "login": { "action_name": "a", "next_task": ["home"], "guards": { "home": "runs.a <" } } }"#,
+    )
+    .unwrap_err();
+    assert!(
+        err.to_string()
+            .ends_with("expected a value, found end of condition at column 9"),
+        "{err}"
+    );
+}
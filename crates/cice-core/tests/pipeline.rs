@@ -0,0 +1,121 @@
+use std::time::Duration;
+
+use cice_core::context::ContextBuilder;
+use cice_core::pipeline::{ActionRegistry, Pipeline, PipelineError};
+use cice_core::task::{duration, TaskResult};
+use cice_tests_common::action::{Outcome, ScriptedAction, TestRuntime};
+use cice_tests_common::harness;
+
+#[test]
+fn omitted_fields_take_defaults() {
+    let pipeline = Pipeline::from_json(r#"{ "entry": { "action_name": "click" } }"#).unwrap();
+    let config = pipeline.get("entry").unwrap();
+    assert_eq!(config.task_name, "entry");
+    assert_eq!(config.action_name, "click");
+    assert!(config.next_task.is_empty());
+    assert!(config.interrupt_task.is_empty());
+    assert_eq!(config.timeout, Duration::from_secs(30));
+    assert_eq!(config.max_retry, 3);
+}
+
+#[test]
+fn durations_are_human_readable() {
+    for (text, expected) in [
+        ("1500ms", Duration::from_millis(1500)),
+        ("30s", Duration::from_secs(30)),
+        ("1m30s", Duration::from_secs(90)),
+        ("1m 30s", Duration::from_secs(90)),
+        ("2h", Duration::from_secs(7200)),
+        ("0s", Duration::ZERO),
+    ] {
+        assert_eq!(duration::parse(text), Ok(expected), "{text}");
+    }
+    for text in ["", "30", "s", "1.5s", "10 minutes", "-1s"] {
+        assert!(duration::parse(text).is_err(), "{text}");
+    }
+
+    assert_eq!(duration::format(Duration::from_millis(1500)), "1500ms");
+    assert_eq!(duration::format(Duration::from_secs(90)), "90s");
+    assert_eq!(duration::format(Duration::from_secs(120)), "2m");
+}
+
+#[test]
+fn serialization_round_trips() {
+    let text = r#"{
+        "entry": { "action_name": "click", "next_task": ["home"], "timeout": "1500ms" },
+        "home": { "action_name": "find_home", "interrupt_task": ["popup"], "max_retry": 10 },
+        "popup": { "action_name": "close", "timeout": "1m" }
+    }"#;
+    let pipeline = Pipeline::from_json(text).unwrap();
+    assert_eq!(
+        pipeline.get("entry").unwrap().timeout,
+        Duration::from_millis(1500)
+    );
+
+    let json = serde_json::to_string(&pipeline).unwrap();
+    assert!(json.contains(r#""timeout":"1500ms""#));
+    assert!(!json.contains("task_name"));
+    assert_eq!(Pipeline::from_json(&json).unwrap(), pipeline);
+}
+
+#[test]
+fn unknown_fields_are_rejected() {
+    let err = Pipeline::from_json(r#"{ "entry": { "action_name": "click", "timeout_secs": 30 } }"#)
+        .unwrap_err();
+    assert!(matches!(err, PipelineError::Syntax { .. }));
+    assert!(err.to_string().contains("timeout_secs"));
+}
+
+#[test]
+fn task_name_must_match_key() {
+    let err =
+        Pipeline::from_json(r#"{ "entry": { "task_name": "other", "action_name": "click" } }"#)
+            .unwrap_err();
+    assert!(err.to_string().contains("other"));
+
+    let pipeline =
+        Pipeline::from_json(r#"{ "entry": { "task_name": "entry", "action_name": "click" } }"#)
+            .unwrap();
+    assert_eq!(pipeline.get("entry").unwrap().task_name, "entry");
+}
+
+#[test]
+fn unregistered_action_is_reported() {
+    let pipeline = Pipeline::from_json(r#"{ "entry": { "action_name": "missing" } }"#).unwrap();
+    let registry = ActionRegistry::new();
+    let result = ContextBuilder::from_pipeline(TestRuntime::new(), pipeline, &registry);
+    assert!(matches!(
+        result,
+        Err(PipelineError::UnknownAction { ref id, ref action_name })
+            if id == "entry" && action_name == "missing"
+    ));
+}
+
+#[test]
+fn pipeline_runs_with_registered_actions() {
+    let runtime = TestRuntime::new();
+    let clock = runtime.clock().clone();
+    let entry = ScriptedAction::new("entry");
+    let popup = ScriptedAction::new("popup")
+        .at(Duration::ZERO, Outcome::UnRecognized)
+        .at(Duration::from_millis(700), Outcome::Recognized);
+
+    let mut registry = ActionRegistry::new();
+    registry.register("entry", &entry).register("popup", &popup);
+
+    let pipeline = Pipeline::from_json(
+        r#"{
+            "entry": { "action_name": "entry", "next_task": ["popup"], "timeout": "2s" },
+            "popup": { "action_name": "popup" }
+        }"#,
+    )
+    .unwrap();
+    let context = ContextBuilder::from_pipeline(runtime, pipeline, &registry)
+        .unwrap()
+        .build();
+
+    let run = harness::run(&context, &clock, "entry");
+    assert!(matches!(run.result, Ok(TaskResult::NoPendingTask)));
+    assert_eq!(run.entered(), vec!["entry", "popup"]);
+    assert_eq!(run.elapsed, Duration::from_secs(1));
+}
@@ -0,0 +1,231 @@
+use std::path::PathBuf;
+use std::time::Duration;
+
+use cice_core::action::ActionId;
+use cice_core::context::ContextBuilder;
+use cice_core::pipeline::action::{ActionConfig, ActionFactories};
+use cice_core::pipeline::{ActionRegistry, Pipeline, PipelineError};
+use cice_core::task::{duration, TaskResult};
+use cice_tests_common::action::{Outcome, ScriptedAction, TestRuntime};
+use cice_tests_common::harness;
+use serde::Deserialize;
+use serde_json::json;
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ScriptedConfig {
+    #[serde(default, with = "duration::option")]
+    recognized_after: Option<Duration>,
+}
+
+fn scripted(id: &ActionId, config: ScriptedConfig) -> ScriptedAction {
+    let action = ScriptedAction::new(id.clone());
+    match config.recognized_after {
+        Some(after) => action
+            .at(Duration::ZERO, Outcome::UnRecognized)
+            .at(after, Outcome::Recognized),
+        None => action,
+    }
+}
+
+fn factories() -> ActionFactories<TestRuntime> {
+    let mut factories = ActionFactories::new();
+    factories.register("test.scripted", scripted);
+    factories
+}
+
+#[test]
+fn inline_actions_are_built_by_type() {
+    let pipeline = Pipeline::from_json(
+        r#"{
+            "entry": { "action_name": "entry", "next_task": ["popup"], "timeout": "2s" },
+            "popup": { "action": { "type": "test.scripted", "recognized_after": "700ms" } }
+        }"#,
+    )
+    .unwrap();
+    assert_eq!(pipeline.get("popup").unwrap().action_name, "$inline::popup");
+
+    let runtime = TestRuntime::new();
+    let clock = runtime.clock().clone();
+    let entry = ScriptedAction::new("entry");
+    let actions = factories().build(&pipeline).unwrap();
+    assert_eq!(actions.len(), 1);
+    let mut registry = ActionRegistry::new();
+    registry.register("entry", &entry).register_all(&actions);
+    let context = ContextBuilder::from_pipeline(runtime, pipeline, &registry)
+        .unwrap()
+        .build();
+
+    let run = harness::run(&context, &clock, "entry");
+    assert!(matches!(run.result, Ok(TaskResult::NoPendingTask)));
+    assert_eq!(run.entered(), vec!["entry", "popup"]);
+    assert_eq!(run.elapsed, Duration::from_secs(1));
+}
+
+#[test]
+fn templates_can_declare_actions() {
+    let pipeline = Pipeline::from_json(
+        r#"{
+            "$templates": { "scripted": { "action": { "type": "test.scripted" } } },
+            "a": { "extends": "scripted" },
+            "b": { "extends": "scripted" }
+        }"#,
+    )
+    .unwrap();
+    let ids: Vec<_> = pipeline.actions().map(|(id, _)| id.as_str()).collect();
+    assert_eq!(ids, ["$inline::a", "$inline::b"]);
+    assert_eq!(pipeline.get("b").unwrap().action_name, "$inline::b");
+}
+
+#[test]
+fn invalid_actions_are_errors() {
+    let err = Pipeline::from_json(
+        r#"{ "entry": { "action_name": "entry", "action": { "type": "test.scripted" } } }"#,
+    )
+    .unwrap_err();
+    assert!(matches!(err, PipelineError::ConflictingAction { ref id } if id == "entry"));
+
+    let pipeline =
+        Pipeline::from_json(r#"{ "entry": { "action": { "type": "test.swipe" } } }"#).unwrap();
+    let err = factories().build(&pipeline).err().unwrap();
+    assert!(matches!(
+        err,
+        PipelineError::UnknownActionType { ref id, ref type_name }
+            if id == "$inline::entry" && type_name == "test.swipe"
+    ));
+
+    let pipeline = Pipeline::from_json(
+        r#"{ "entry": { "action": { "type": "test.scripted", "recognized_after": 3 } } }"#,
+    )
+    .unwrap();
+    let err = factories().build(&pipeline).err().unwrap();
+    assert!(matches!(
+        err,
+        PipelineError::InvalidActionConfig { ref id, .. } if id == "$inline::entry"
+    ));
+
+    let err = Pipeline::from_json(r#"{ "entry": { "action": { "delay": "1s" } } }"#).unwrap_err();
+    match err {
+        PipelineError::Syntax { message, .. } => assert_eq!(message, "missing field `type`"),
+        other => panic!("expected a syntax error, got {other:?}"),
+    }
+}
+
+#[test]
+fn inline_actions_do_not_collide_with_registered_ones() {
+    // `close` refers to the action built in Rust, not to the inline action of the task `popup`
+    let pipeline = Pipeline::from_json(
+        r#"{
+            "popup": { "action": { "type": "test.scripted" }, "next_task": ["close"] },
+            "close": { "action_name": "popup" }
+        }"#,
+    )
+    .unwrap();
+    assert_eq!(pipeline.get("close").unwrap().action_name, "popup");
+    let value = serde_json::to_value(&pipeline).unwrap();
+    assert_eq!(value["close"]["action_name"], "popup");
+    assert_eq!(value["popup"]["action"], json!({ "type": "test.scripted" }));
+
+    let runtime = TestRuntime::new();
+    let clock = runtime.clock().clone();
+    let registered = ScriptedAction::new("registered");
+    let actions = factories().build(&pipeline).unwrap();
+    let mut registry = ActionRegistry::new();
+    registry
+        .register("popup", &registered)
+        .register_all(&actions);
+    let context = ContextBuilder::from_pipeline(runtime, pipeline, &registry)
+        .unwrap()
+        .build();
+    let run = harness::run(&context, &clock, "popup");
+    assert!(matches!(run.result, Ok(TaskResult::NoPendingTask)));
+    assert_eq!(run.entered(), vec!["popup", "close"]);
+    assert_eq!(registered.exec_count(), 1);
+}
+
+#[test]
+fn actions_stay_inline_when_serialized() {
+    let pipeline = Pipeline::from_json(
+        r#"{ "entry": { "action": { "type": "test.scripted", "recognized_after": "1s" } } }"#,
+    )
+    .unwrap();
+    let value = serde_json::to_value(&pipeline).unwrap();
+    assert_eq!(
+        value["entry"]["action"],
+        json!({ "type": "test.scripted", "recognized_after": "1s" })
+    );
+    assert!(value["entry"].get("action_name").is_none());
+
+    let (_, action) = pipeline.actions().next().unwrap();
+    assert_eq!(
+        action,
+        &ActionConfig {
+            type_name: "test.scripted".into(),
+            config: json!({ "recognized_after": "1s" })
+                .as_object()
+                .unwrap()
+                .clone(),
+        }
+    );
+    assert_eq!(serde_json::from_value::<Pipeline>(value).unwrap(), pipeline);
+}
+
+#[test]
+fn included_actions_are_namespaced() {
+    let path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/task_config/include/actions/main.json");
+    let pipeline = Pipeline::from_file(path).unwrap();
+    let ids: Vec<_> = pipeline.actions().map(|(id, _)| id.as_str()).collect();
+    assert_eq!(ids, ["$inline::entry", "$inline::menu::open"]);
+    assert_eq!(
+        pipeline.get("menu::open").unwrap().action_name,
+        "$inline::menu::open"
+    );
+
+    let runtime = TestRuntime::new();
+    let clock = runtime.clock().clone();
+    let actions = factories().build(&pipeline).unwrap();
+    let context = ContextBuilder::from_pipeline(runtime, pipeline, &actions.registry())
+        .unwrap()
+        .build();
+    let run = harness::run(&context, &clock, "entry");
+    assert!(matches!(run.result, Ok(TaskResult::NoPendingTask)));
+    assert_eq!(run.entered(), vec!["entry", "menu::open"]);
+}
+
+#[test]
+fn compose_recognizes_and_executes_with_different_actions() {
+    let pipeline = Pipeline::from_json(
+        r#"{
+            "entry": { "action": { "type": "test.scripted" }, "next_task": ["open"] },
+            "open": {
+                "action": {
+                    "type": "compose",
+                    "recognize": { "type": "test.scripted", "recognized_after": "300ms" },
+                    "exec": { "type": "test.scripted" }
+                }
+            }
+        }"#,
+    )
+    .unwrap();
+    let runtime = TestRuntime::new();
+    let clock = runtime.clock().clone();
+    let actions = factories().build(&pipeline).unwrap();
+    let context = ContextBuilder::from_pipeline(runtime, pipeline, &actions.registry())
+        .unwrap()
+        .build();
+    let run = harness::run(&context, &clock, "entry");
+    assert!(matches!(run.result, Ok(TaskResult::NoPendingTask)));
+    assert_eq!(run.entered(), vec!["entry", "open"]);
+    assert_eq!(run.elapsed, Duration::from_millis(500));
+
+    let pipeline = Pipeline::from_json(
+        r#"{ "entry": { "action": { "type": "compose", "recognize": { "type": "test.scripted" } } } }"#,
+    )
+    .unwrap();
+    let err = factories().build(&pipeline).err().unwrap();
+    assert!(matches!(
+        err,
+        PipelineError::InvalidActionConfig { ref message, .. } if message == "missing field `exec`"
+    ));
+}
@@ -0,0 +1,103 @@
+#![cfg(all(feature = "yaml", feature = "toml"))]
+
+use std::path::PathBuf;
+use std::time::Duration;
+
+use cice_core::pipeline::variables::Variables;
+use cice_core::pipeline::{Format, Location, Pipeline, PipelineError};
+
+fn fixture(path: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/task_config")
+        .join(path)
+}
+
+fn syntax_error(format: Format, text: &str) -> (Location, String) {
+    match Pipeline::parse(format, "login", text) {
+        Err(PipelineError::Syntax { location, message }) => (location, message),
+        other => panic!("expected a syntax error, got {other:?}"),
+    }
+}
+
+#[test]
+fn formats_produce_identical_configs() {
+    let json = Pipeline::from_file(fixture("json/task_sequence.json")).unwrap();
+    let yaml = Pipeline::from_file(fixture("yaml/task_sequence.yaml")).unwrap();
+    let toml = Pipeline::from_file(fixture("toml/task_sequence.toml")).unwrap();
+    assert_eq!(json, yaml);
+    assert_eq!(json, toml);
+    assert_eq!(json.get("Task2_Deny").unwrap().task_name, "Task2_Deny");
+}
+
+#[test]
+fn json_errors_have_location() {
+    let (location, message) = syntax_error(
+        Format::Json,
+        "{\n  \"entry\": {\n    \"action_name\": \"click\",\n    \"timeout_secs\": 30\n  }\n}",
+    );
+    assert_eq!(location, Location::new("login", 4, 18));
+    assert!(
+        message.starts_with("unknown field `timeout_secs`"),
+        "{message}"
+    );
+}
+
+#[test]
+fn yaml_errors_have_location() {
+    let (location, message) = syntax_error(
+        Format::Yaml,
+        "entry:\n  action_name: click\n  timeout: soon\n",
+    );
+    assert_eq!((location.line, location.column), (3, 12));
+    assert!(message.contains("invalid duration \"soon\""), "{message}");
+}
+
+#[test]
+fn toml_errors_have_location() {
+    let (location, message) = syntax_error(
+        Format::Toml,
+        "[entry]\naction_name = \"click\"\nmax_retry = \"three\"\n",
+    );
+    assert_eq!((location.line, location.column), (3, 13));
+    assert!(message.contains("invalid type"), "{message}");
+}
+
+#[test]
+fn file_errors_name_the_file() {
+    let err = Pipeline::from_file(fixture("json/task_sequence.txt")).unwrap_err();
+    assert!(matches!(err, PipelineError::UnknownFormat { .. }));
+
+    let missing = fixture("yaml/missing.yml");
+    let err = Pipeline::from_file(&missing).unwrap_err();
+    assert!(matches!(err, PipelineError::Io { .. }));
+    assert!(err.to_string().starts_with(&missing.display().to_string()));
+}
+
+#[test]
+fn variables_are_substituted_in_every_format() {
+    let mut variables = Variables::from_profile(fixture("variables/staging.toml")).unwrap();
+    assert_eq!(variables.get("server.port"), Some(&serde_json::json!(5900)));
+    variables.set("timeout", "5s");
+
+    let pipeline =
+        Pipeline::from_file_with_variables(fixture("variables/pipeline.yaml"), &variables).unwrap();
+    assert_eq!(
+        pipeline.get("login").unwrap().timeout,
+        Duration::from_secs(5)
+    );
+    let (_, action) = pipeline.actions().next().unwrap();
+    assert_eq!(action.config["account"], "tester");
+    assert_eq!(action.config["address"], "staging.example.com:5900");
+    assert_eq!(action.config["threshold"], 0.85);
+
+    let err =
+        Pipeline::from_file_with_variables(fixture("variables/pipeline.yaml"), &Variables::new())
+            .unwrap_err();
+    match err {
+        PipelineError::Syntax { location, message } => {
+            assert_eq!(location.line, 4);
+            assert!(message.ends_with("unbound variable `account`"), "{message}");
+        }
+        other => panic!("expected a syntax error, got {other:?}"),
+    }
+}
@@ -0,0 +1,107 @@
+use std::path::PathBuf;
+
+use cice_core::pipeline::{Pipeline, PipelineError};
+
+fn fixture(path: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/task_config/include")
+        .join(path)
+}
+
+#[test]
+fn included_tasks_are_namespaced() {
+    let pipeline = Pipeline::from_file(fixture("main.json")).unwrap();
+    let mut ids: Vec<_> = pipeline
+        .tasks()
+        .map(|task| task.task_name.as_str())
+        .collect();
+    ids.sort();
+    assert_eq!(
+        ids,
+        [
+            "entry",
+            "login::done",
+            "login::start",
+            "popup",
+            "shop::checkout::pay",
+            "shop::start"
+        ]
+    );
+
+    assert_eq!(pipeline.get("entry").unwrap().next_task, ["login::start"]);
+    let login = pipeline.get("login::start").unwrap();
+    assert_eq!(login.next_task, ["login::done"]);
+    assert_eq!(login.interrupt_task, ["popup"]);
+    assert_eq!(
+        pipeline.get("login::done").unwrap().next_task,
+        ["shop::start"]
+    );
+    assert_eq!(
+        pipeline.get("shop::start").unwrap().next_task,
+        ["shop::checkout::pay"]
+    );
+}
+
+#[test]
+fn include_cycles_are_reported() {
+    let err = Pipeline::from_file(fixture("cycle/a.json")).unwrap_err();
+    assert!(matches!(err, PipelineError::IncludeCycle { ref file } if file.ends_with("a.json")));
+}
+
+#[test]
+fn duplicate_ids_are_errors() {
+    let err = Pipeline::from_json(
+        r#"{
+            "start": { "action_name": "a" },
+            "start": { "action_name": "b" }
+        }"#,
+    )
+    .unwrap_err();
+    match err {
+        PipelineError::Syntax { location, message } => {
+            assert_eq!(location.line, 3);
+            assert_eq!(message, "duplicate task start");
+        }
+        other => panic!("expected a syntax error, got {other:?}"),
+    }
+
+    let err = Pipeline::from_json(r#"{ "login::start": { "action_name": "a" } }"#).unwrap_err();
+    assert!(matches!(err, PipelineError::InvalidTaskId { .. }));
+
+    let err = Pipeline::from_json(
+        r#"{
+            "$include": { "login": "login.json",
+                          "login": "shop/main.yaml" }
+        }"#,
+    )
+    .unwrap_err();
+    match err {
+        PipelineError::Syntax { location, message } => {
+            assert_eq!(location.line, 3);
+            assert_eq!(message, "duplicate include namespace login");
+        }
+        other => panic!("expected a syntax error, got {other:?}"),
+    }
+
+    let err = Pipeline::from_json(
+        r#"{
+            "$include": { "login": "login.json" },
+            "$include": { "shop": "shop/main.yaml" }
+        }"#,
+    )
+    .unwrap_err();
+    match err {
+        PipelineError::Syntax { message, .. } => {
+            assert_eq!(message, "duplicate field `$include`")
+        }
+        other => panic!("expected a syntax error, got {other:?}"),
+    }
+}
+
+#[test]
+fn includes_need_a_file() {
+    let err = Pipeline::from_json(r#"{ "$include": { "login": "login.json" } }"#).unwrap_err();
+    assert!(
+        matches!(err, PipelineError::UnresolvedInclude { ref namespace } if namespace == "login")
+    );
+}
@@ -0,0 +1,161 @@
+use cice_core::pipeline::lint::{Finding, Linter, Rule, Severity};
+use cice_core::pipeline::{Pipeline, PipelineError};
+use serde::Deserialize;
+
+/// Stands in for the config of a template matching action.
+#[derive(Deserialize)]
+struct RegionConfig {
+    roi: [u64; 4],
+    #[serde(default)]
+    threshold: f64,
+}
+
+fn linter() -> Linter {
+    let mut linter = Linter::new();
+    linter
+        .screen(1280, 720)
+        .action("test.region", |lint, config: RegionConfig| {
+            let [x, y, width, height] = config.roi;
+            if lint.screen().is_some_and(|(screen_width, screen_height)| {
+                x + width > screen_width || y + height > screen_height
+            }) {
+                lint.report(Rule::OutsideScreen, "roi exceeds the screen");
+            }
+            if !(0.0..=1.0).contains(&config.threshold) {
+                lint.report(Rule::OutOfRange, "threshold is outside 0..1");
+            }
+        });
+    linter
+}
+
+fn summary(findings: &[Finding]) -> Vec<(Rule, Option<&str>)> {
+    findings
+        .iter()
+        .map(|finding| (finding.rule, finding.task.as_deref()))
+        .collect()
+}
+
+#[test]
+fn lint_checks_the_task_graph() {
+    let pipeline = Pipeline::from_json(
+        r#"{
+            "start": { "action_name": "a", "next_task": ["menu", "missing"], "interrupt_task": ["popup"] },
+            "menu": { "action_name": "a", "next_task": ["shop"] },
+            "shop": { "action_name": "a", "next_task": ["menu"] },
+            "popup": { "action_name": "a", "interrupt_task": ["gone"] },
+            "orphan": { "action_name": "a", "next_task": ["orphan"] }
+        }"#,
+    )
+    .unwrap();
+    let findings = Linter::new()
+        .entry("start")
+        .entry("nowhere")
+        .lint(&pipeline);
+    assert_eq!(
+        summary(&findings),
+        [
+            (Rule::DanglingReference, None),
+            (Rule::DanglingReference, Some("popup")),
+            (Rule::DanglingReference, Some("start")),
+            (Rule::Unreachable, Some("orphan")),
+            (Rule::CycleWithoutExit, Some("menu")),
+            (Rule::CycleWithoutExit, Some("orphan")),
+        ]
+    );
+    assert_eq!(
+        findings[4].message,
+        "tasks menu, shop lead to each other but never to a task without next_task"
+    );
+    assert_eq!(findings[3].severity, Severity::Warning);
+    assert_eq!(
+        findings[2].to_string(),
+        "error[dangling-reference] start: next_task refers to undefined task missing"
+    );
+}
+
+#[test]
+fn cycles_with_an_exit_are_fine() {
+    let pipeline = Pipeline::from_json(
+        r#"{
+            "start": { "action_name": "a", "next_task": ["retry"] },
+            "retry": { "action_name": "a", "next_task": ["retry", "done"] },
+            "done": { "action_name": "a" }
+        }"#,
+    )
+    .unwrap();
+    assert!(Linter::new().lint(&pipeline).is_empty());
+}
+
+#[test]
+fn lint_runs_the_checks_of_action_types() {
+    let pipeline = Pipeline::from_json(
+        r#"{
+            "inside": { "action": { "type": "test.region", "roi": [0, 0, 1280, 720], "threshold": 0.9 } },
+            "outside": { "action": { "type": "test.region", "roi": [1000, 0, 300, 100], "threshold": 1.5 } },
+            "composed": {
+                "action": {
+                    "type": "compose",
+                    "recognize": { "type": "test.region", "roi": [0, 700, 10, 30] },
+                    "exec": { "type": "input.click" }
+                }
+            },
+            "broken": { "action": { "type": "test.region", "roi": "everywhere" } }
+        }"#,
+    )
+    .unwrap();
+    let findings = linter().lint(&pipeline);
+    assert_eq!(
+        summary(&findings),
+        [
+            (Rule::InvalidAction, Some("broken")),
+            (Rule::OutsideScreen, Some("composed")),
+            (Rule::OutsideScreen, Some("outside")),
+            (Rule::OutOfRange, Some("outside")),
+        ]
+    );
+    assert!(findings[0].message.starts_with("test.region action: "));
+}
+
+#[test]
+fn load_errors_are_findings() {
+    let err = Pipeline::from_json(
+        r#"{ "login": { "action_name": "a" }, "login": { "action_name": "b" } }"#,
+    )
+    .unwrap_err();
+    let finding = Finding::load_error(&err);
+    assert_eq!(finding.rule, Rule::DuplicateId);
+    assert_eq!(finding.message, err.to_string());
+
+    let err =
+        Pipeline::from_json(r#"{ "$include": { "ui": "a.json", "ui": "b.json" } }"#).unwrap_err();
+    assert_eq!(Finding::load_error(&err).rule, Rule::DuplicateId);
+
+    let err = PipelineError::InFile {
+        file: "main.json".into(),
+        source: Box::new(PipelineError::DuplicateTask {
+            id: "ui::login".into(),
+        }),
+    };
+    let finding = Finding::load_error(&err);
+    assert_eq!(
+        (finding.rule, finding.task.as_deref()),
+        (Rule::DuplicateId, Some("ui::login"))
+    );
+
+    let err = Pipeline::from_json(r#"{ "login": {} }"#).unwrap_err();
+    assert_eq!(Finding::load_error(&err).rule, Rule::InvalidPipeline);
+}
+
+#[test]
+fn findings_serialize_for_ci() {
+    let finding = Finding::new(Rule::CycleWithoutExit, Some("menu".into()), "stuck");
+    assert_eq!(
+        serde_json::to_value(&finding).unwrap(),
+        serde_json::json!({
+            "rule": "cycle-without-exit",
+            "severity": "warning",
+            "task": "menu",
+            "message": "stuck"
+        })
+    );
+}
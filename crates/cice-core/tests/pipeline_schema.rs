@@ -0,0 +1,79 @@
+#![cfg(feature = "schema")]
+
+use cice_core::pipeline::schema::{SchemaBuilder, ACTION_CONFIG_DEFINITION};
+use schemars::JsonSchema;
+use serde::Deserialize;
+use serde_json::json;
+
+/// Click at a point
+#[allow(dead_code)]
+#[derive(Deserialize, JsonSchema)]
+struct ClickConfig {
+    x: u32,
+    y: u32,
+    #[serde(default)]
+    double: bool,
+}
+
+#[test]
+fn task_fields_have_defaults() {
+    let schema = SchemaBuilder::new().build();
+    assert_eq!(
+        schema.pointer("/additionalProperties/$ref"),
+        Some(&json!("#/definitions/TaskTemplate"))
+    );
+    assert_eq!(
+        schema.pointer("/properties/$templates/additionalProperties/$ref"),
+        Some(&json!("#/definitions/TaskTemplate"))
+    );
+
+    assert_eq!(
+        schema.pointer("/properties/$include/additionalProperties"),
+        Some(&json!({ "type": "string" }))
+    );
+
+    let task = schema.pointer("/definitions/TaskTemplate").unwrap();
+    assert!(task["properties"]["extends"].is_object());
+    assert_eq!(task["additionalProperties"], json!(false));
+    assert_eq!(task["properties"]["timeout"]["default"], json!("30s"));
+    assert_eq!(task["properties"]["max_retry"]["default"], json!(3));
+    assert_eq!(
+        task["properties"]["action"]["anyOf"][0]["$ref"],
+        json!(format!("#/definitions/{ACTION_CONFIG_DEFINITION}"))
+    );
+
+    // Without registered actions, any type is accepted
+    let action = schema
+        .pointer(&format!("/definitions/{ACTION_CONFIG_DEFINITION}"))
+        .unwrap();
+    assert_eq!(action["required"], json!(["type"]));
+    assert!(action.get("oneOf").is_none());
+}
+
+#[test]
+fn action_configs_are_tagged_by_type() {
+    let schema = SchemaBuilder::new()
+        .action::<ClickConfig>("test.click")
+        .build();
+
+    let variants = schema
+        .pointer(&format!("/definitions/{ACTION_CONFIG_DEFINITION}/oneOf"))
+        .and_then(|variants| variants.as_array())
+        .unwrap();
+    // Registered actions, then the built-in compose
+    assert_eq!(variants.len(), 2);
+    assert_eq!(variants[1]["title"], json!("compose"));
+    assert_eq!(
+        variants[1]["properties"]["exec"]["$ref"],
+        json!(format!("#/definitions/{ACTION_CONFIG_DEFINITION}"))
+    );
+    let click = &variants[0];
+    assert_eq!(click["title"], json!("test.click"));
+    assert_eq!(click["description"], json!("Click at a point"));
+    assert_eq!(
+        click["properties"]["type"],
+        json!({ "const": "test.click" })
+    );
+    assert_eq!(click["properties"]["double"]["default"], json!(false));
+    assert_eq!(click["required"], json!(["type", "x", "y"]));
+}
@@ -0,0 +1,95 @@
+use std::time::Duration;
+
+use cice_core::pipeline::template::ListPatch;
+use cice_core::pipeline::{Pipeline, PipelineError};
+
+fn ids(ids: &[&str]) -> Vec<String> {
+    ids.iter().map(|id| id.to_string()).collect()
+}
+
+#[test]
+fn tasks_inherit_and_override_template_fields() {
+    let pipeline = Pipeline::from_json(
+        r#"{
+            "$schema": "./pipeline.schema.json",
+            "$templates": {
+                "patient": { "timeout": "1m", "max_retry": 20, "interrupt_task": ["close_popup"] }
+            },
+            "login": { "extends": "patient", "action_name": "click_login", "max_retry": 5 },
+            "home": { "action_name": "find_home" }
+        }"#,
+    )
+    .unwrap();
+
+    let login = pipeline.get("login").unwrap();
+    assert_eq!(login.action_name, "click_login");
+    assert_eq!(login.timeout, Duration::from_secs(60));
+    assert_eq!(login.max_retry, 5);
+    assert_eq!(login.interrupt_task, ids(&["close_popup"]));
+    assert!(login.next_task.is_empty());
+
+    // Templates and `$` keys are not tasks
+    assert_eq!(pipeline.tasks().count(), 2);
+    assert_eq!(
+        pipeline.get("home").unwrap().timeout,
+        Duration::from_secs(30)
+    );
+}
+
+#[test]
+fn list_fields_replace_or_edit_inherited_lists() {
+    let inherited = ids(&["a", "b", "c"]);
+    assert_eq!(
+        ListPatch::Replace(ids(&["d"])).apply(&inherited),
+        ids(&["d"])
+    );
+    let merge = ListPatch::Merge {
+        prepend: ids(&["c"]),
+        append: ids(&["d"]),
+        remove: ids(&["a"]),
+    };
+    assert_eq!(merge.apply(&inherited), ids(&["c", "b", "d"]));
+
+    let pipeline = Pipeline::from_json(
+        r#"{
+            "$templates": {
+                "base": { "action_name": "any", "interrupt_task": ["popup"], "next_task": ["home"] },
+                "guarded": { "extends": "base", "interrupt_task": { "append": ["reconnect"] } }
+            },
+            "edited": { "extends": "guarded", "interrupt_task": { "prepend": ["ads"], "remove": ["popup"] } },
+            "replaced": { "extends": "guarded", "next_task": ["login"] }
+        }"#,
+    )
+    .unwrap();
+    let edited = pipeline.get("edited").unwrap();
+    assert_eq!(edited.interrupt_task, ids(&["ads", "reconnect"]));
+    assert_eq!(edited.next_task, ids(&["home"]));
+    let replaced = pipeline.get("replaced").unwrap();
+    assert_eq!(replaced.interrupt_task, ids(&["popup", "reconnect"]));
+    assert_eq!(replaced.next_task, ids(&["login"]));
+}
+
+#[test]
+fn template_errors_are_reported() {
+    let err = Pipeline::from_json(r#"{ "login": { "extends": "missing", "action_name": "a" } }"#)
+        .unwrap_err();
+    assert!(matches!(
+        err,
+        PipelineError::UnknownTemplate { ref id, ref template } if id == "login" && template == "missing"
+    ));
+
+    let err = Pipeline::from_json(
+        r#"{
+            "$templates": { "a": { "extends": "b" }, "b": { "extends": "a" } },
+            "login": { "action_name": "a" }
+        }"#,
+    )
+    .unwrap_err();
+    assert!(matches!(err, PipelineError::TemplateCycle { .. }));
+
+    let err = Pipeline::from_json(
+        r#"{ "$templates": { "slow": { "timeout": "1m" } }, "login": { "extends": "slow" } }"#,
+    )
+    .unwrap_err();
+    assert!(matches!(err, PipelineError::MissingAction { ref id } if id == "login"));
+}
@@ -0,0 +1,181 @@
+use std::path::PathBuf;
+use std::time::Duration;
+
+use cice_core::pipeline::variables::Variables;
+use cice_core::pipeline::{Format, Pipeline, PipelineError};
+use serde_json::json;
+
+fn fixture(path: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/task_config/variables")
+        .join(path)
+}
+
+const LOGIN: &str = r#"{
+    "login_${account}": {
+        "action": {
+            "type": "test.login",
+            "account": "${account}",
+            "address": "${server.host}:${server.port}",
+            "threshold": "${threshold}"
+        },
+        "next_task": ["${next}"],
+        "timeout": "${timeout}",
+        "max_retry": "${retries}"
+    }
+}"#;
+
+fn variables() -> Variables {
+    let mut variables = Variables::from_profile(fixture("staging.json")).unwrap();
+    variables
+        .set("next", "home")
+        .set("timeout", "5s")
+        .set("retries", 2);
+    variables
+}
+
+#[test]
+fn placeholders_are_substituted() {
+    let pipeline =
+        Pipeline::parse_with_variables(Format::Json, "login.json", LOGIN, &variables()).unwrap();
+
+    let login = pipeline.get("login_tester").unwrap();
+    assert_eq!(login.next_task, ["home"]);
+    assert_eq!(login.timeout, Duration::from_secs(5));
+    assert_eq!(login.max_retry, 2);
+
+    let (_, action) = pipeline.actions().next().unwrap();
+    assert_eq!(
+        serde_json::to_value(action).unwrap(),
+        json!({
+            "type": "test.login",
+            "account": "tester",
+            "address": "staging.example.com:5900",
+            // A whole placeholder keeps the type of the variable
+            "threshold": 0.85
+        })
+    );
+}
+
+#[test]
+fn strings_fill_typed_fields() {
+    let mut variables = variables();
+    variables.set("retries", "4").set("account", 42);
+    let pipeline =
+        Pipeline::parse_with_variables(Format::Json, "login.json", LOGIN, &variables).unwrap();
+    assert_eq!(pipeline.get("login_42").unwrap().max_retry, 4);
+}
+
+#[test]
+fn unbound_placeholders_are_located() {
+    let text = LOGIN.replace("${next}", "${missing}");
+    let err = Pipeline::parse_with_variables(Format::Json, "login.json", &text, &variables())
+        .unwrap_err();
+    match err {
+        PipelineError::Syntax { location, message } => {
+            assert_eq!(location.file, "login.json");
+            assert_eq!(location.line, 9);
+            assert_eq!(message, "unbound variable `missing`");
+        }
+        other => panic!("expected a syntax error, got {other:?}"),
+    }
+
+    // Loading with no variables, every placeholder is unbound
+    let err = Pipeline::parse_with_variables(Format::Json, "login.json", LOGIN, &Variables::new())
+        .unwrap_err();
+    assert!(matches!(err, PipelineError::Syntax { .. }));
+}
+
+#[test]
+fn mistyped_values_are_errors() {
+    let mut variables = variables();
+    variables.set("retries", "many");
+    let err =
+        Pipeline::parse_with_variables(Format::Json, "login.json", LOGIN, &variables).unwrap_err();
+    match err {
+        PipelineError::Syntax { location, message } => {
+            assert_eq!(location.line, 11);
+            assert!(
+                message.starts_with("invalid type: string \"many\""),
+                "{message}"
+            );
+        }
+        other => panic!("expected a syntax error, got {other:?}"),
+    }
+
+    variables
+        .set("retries", 2)
+        .set("account", json!(["a", "b"]));
+    let err =
+        Pipeline::parse_with_variables(Format::Json, "login.json", LOGIN, &variables).unwrap_err();
+    assert!(matches!(err, PipelineError::Syntax { .. }));
+}
+
+#[cfg(all(feature = "yaml", feature = "toml"))]
+#[test]
+fn unquoted_scalars_are_read_as_written() {
+    let yaml = r#"
+2024:
+  action_name: 2024
+  max_retry: 3
+true:
+  action_name: 1.50
+  next_task: ["2024"]
+"#;
+    let mut variables = Variables::new();
+    variables.set("threshold", 0.85);
+    for variables in [Variables::new(), variables.clone()] {
+        let pipeline = Pipeline::parse_with_variables(Format::Yaml, "a.yaml", yaml, &variables)
+            .unwrap_or_else(|err| panic!("{err}"));
+        let task = pipeline.get("2024").unwrap();
+        assert_eq!(task.action_name, "2024");
+        assert_eq!(task.max_retry, 3);
+        assert_eq!(pipeline.get("true").unwrap().action_name, "1.50");
+    }
+
+    let toml = r#"
+[pay]
+max_retry = 3
+action = { type = "test.pay", amount = 2024, confirm = true, note = "${note}" }
+"#;
+    variables.set("note", "rent");
+    let pipeline =
+        Pipeline::parse_with_variables(Format::Toml, "a.toml", toml, &variables).unwrap();
+    assert_eq!(pipeline.get("pay").unwrap().max_retry, 3);
+    let (_, action) = pipeline.actions().next().unwrap();
+    assert_eq!(
+        serde_json::Value::Object(action.config.clone()),
+        json!({ "amount": 2024, "confirm": true, "note": "rent" })
+    );
+}
+
+#[test]
+fn dollars_can_be_escaped() {
+    let text = r#"{ "pay": { "action": { "type": "test.type", "text": "$${price} costs $5" } } }"#;
+    let pipeline =
+        Pipeline::parse_with_variables(Format::Json, "pay.json", text, &Variables::new()).unwrap();
+    let (_, action) = pipeline.actions().next().unwrap();
+    assert_eq!(action.config["text"], json!("${price} costs $5"));
+}
+
+#[test]
+fn documents_without_variables_are_read_as_written() {
+    let text =
+        r#"{ "pay": { "action": { "type": "test.type", "text": "${price} or $${price}" } } }"#;
+    let pipeline = Pipeline::from_json(text).unwrap();
+    let (_, action) = pipeline.actions().next().unwrap();
+    assert_eq!(action.config["text"], json!("${price} or $${price}"));
+
+    let pipeline = Pipeline::from_file(fixture("literal.json")).unwrap();
+    assert_eq!(pipeline.get("echo").unwrap().action_name, "print ${HOME}");
+}
+
+#[test]
+fn variables_come_from_env() {
+    std::env::set_var("CICE_TEST_VARIABLES_ACCOUNT", "tester");
+    std::env::set_var("CICE_TEST_VARIABLES_THRESHOLD", "0.9");
+    let variables = Variables::from_env("CICE_TEST_VARIABLES_");
+    assert_eq!(variables.len(), 2);
+    assert_eq!(variables.get("ACCOUNT"), Some(&json!("tester")));
+    assert_eq!(variables.get("THRESHOLD"), Some(&json!(0.9)));
+}
@@ -0,0 +1,314 @@
+use std::time::Duration;
+
+use cice_core::context::{ContextBuilder, ContextError};
+use cice_core::message::task::TaskMessage;
+use cice_core::task::{TaskConfig, TaskError, TaskResult};
+use cice_tests_common::action::{Outcome, ScriptedAction, TestRuntime};
+use cice_tests_common::harness;
+
+fn task_config(name: &str, next_task: &[&str], timeout: Duration, max_retry: usize) -> TaskConfig {
+    TaskConfig {
+        task_name: name.to_string(),
+        action_name: name.to_string(),
+        next_task: next_task.iter().map(|id| id.to_string()).collect(),
+        interrupt_task: vec![],
+        guards: Default::default(),
+        timeout,
+        max_retry,
+    }
+}
+
+#[test]
+fn timeout_elapses_in_virtual_time() {
+    let runtime = TestRuntime::new();
+    let clock = runtime.clock().clone();
+    let entry = ScriptedAction::new("entry");
+    let never = ScriptedAction::new("never").at(Duration::ZERO, Outcome::UnRecognized);
+
+    let mut builder = ContextBuilder::new(runtime);
+    builder
+        .add_task(
+            task_config("entry", &["never"], Duration::from_secs(2), 100),
+            &entry,
+        )
+        .unwrap();
+    builder
+        .add_task(
+            task_config("never", &[], Duration::from_secs(2), 100),
+            &never,
+        )
+        .unwrap();
+    let context = builder.build();
+
+    let run = harness::run(&context, &clock, "entry");
+    assert!(matches!(run.result, Err(TaskError::TaskTimeOut { ref id }) if id == "entry"));
+    assert_eq!(run.elapsed, Duration::from_secs(2));
+    // One round at t=0, then one every 500ms until the timeout is reached
+    assert_eq!(never.recognize_count(), 4);
+    assert_eq!(never.exec_count(), 0);
+}
+
+#[test]
+fn retries_are_exhausted_before_timeout() {
+    let runtime = TestRuntime::new();
+    let clock = runtime.clock().clone();
+    let entry = ScriptedAction::new("entry");
+    let never = ScriptedAction::new("never").at(Duration::ZERO, Outcome::Failed);
+
+    let mut builder = ContextBuilder::new(runtime);
+    builder
+        .add_task(
+            task_config("entry", &["never"], Duration::from_secs(30), 3),
+            &entry,
+        )
+        .unwrap();
+    builder
+        .add_task(
+            task_config("never", &[], Duration::from_secs(30), 3),
+            &never,
+        )
+        .unwrap();
+    let context = builder.build();
+
+    let run = harness::run(&context, &clock, "entry");
+    assert!(matches!(run.result, Err(TaskError::TaskTimeOut { .. })));
+    assert_eq!(run.recognize_attempts("never"), 3);
+    assert_eq!(run.elapsed, Duration::from_secs(1));
+}
+
+#[test]
+fn recognized_after_screen_changes() {
+    let runtime = TestRuntime::new();
+    let clock = runtime.clock().clone();
+    let entry = ScriptedAction::new("entry");
+    let popup = ScriptedAction::new("popup")
+        .at(Duration::ZERO, Outcome::UnRecognized)
+        .at(Duration::from_millis(1200), Outcome::Recognized);
+    let other = ScriptedAction::new("other").at(Duration::ZERO, Outcome::UnRecognized);
+
+    let mut builder = ContextBuilder::new(runtime);
+    builder
+        .add_task(
+            task_config("entry", &["other", "popup"], Duration::from_secs(30), 10),
+            &entry,
+        )
+        .unwrap();
+    builder
+        .add_task(
+            task_config("popup", &[], Duration::from_secs(30), 10),
+            &popup,
+        )
+        .unwrap();
+    builder
+        .add_task(
+            task_config("other", &[], Duration::from_secs(30), 10),
+            &other,
+        )
+        .unwrap();
+    let context = builder.build();
+
+    let run = harness::run(&context, &clock, "entry");
+    assert!(matches!(run.result, Ok(TaskResult::NoPendingTask)));
+    assert_eq!(run.elapsed, Duration::from_millis(1500));
+    assert_eq!(run.entered(), vec!["entry", "popup"]);
+
+    let try_recognize_round = [
+        TaskMessage::TryRecognize {
+            id: "other".to_string(),
+        },
+        TaskMessage::TryRecognize {
+            id: "popup".to_string(),
+        },
+    ];
+    let mut expected = vec![TaskMessage::Enter {
+        id: "entry".to_string(),
+    }];
+    for _ in 0..4 {
+        expected.extend(try_recognize_round.iter().cloned());
+    }
+    expected.extend([
+        TaskMessage::TryExec {
+            id: "popup".to_string(),
+        },
+        TaskMessage::ExecSuccess {
+            id: "popup".to_string(),
+        },
+        TaskMessage::Enter {
+            id: "popup".to_string(),
+        },
+    ]);
+    assert_eq!(run.messages, expected);
+}
+
+#[test]
+fn slow_recognition_is_cut_by_timeout() {
+    let runtime = TestRuntime::new();
+    let clock = runtime.clock().clone();
+    let entry = ScriptedAction::new("entry");
+    let slow = ScriptedAction::new("slow").recognize_cost(Duration::from_secs(5));
+
+    let mut builder = ContextBuilder::new(runtime);
+    builder
+        .add_task(
+            task_config("entry", &["slow"], Duration::from_secs(2), 3),
+            &entry,
+        )
+        .unwrap();
+    builder
+        .add_task(task_config("slow", &[], Duration::from_secs(2), 3), &slow)
+        .unwrap();
+    let context = builder.build();
+
+    let run = harness::run(&context, &clock, "entry");
+    assert!(matches!(run.result, Err(TaskError::TaskTimeOut { .. })));
+    assert_eq!(run.elapsed, Duration::from_secs(2));
+    assert_eq!(slow.exec_count(), 0);
+}
+
+#[test]
+fn exec_failure_is_reported() {
+    let runtime = TestRuntime::new();
+    let clock = runtime.clock().clone();
+    let entry = ScriptedAction::new("entry");
+    let broken = ScriptedAction::new("broken").exec_fails();
+
+    let mut builder = ContextBuilder::new(runtime);
+    builder
+        .add_task(
+            task_config("entry", &["broken"], Duration::from_secs(30), 3),
+            &entry,
+        )
+        .unwrap();
+    builder
+        .add_task(
+            task_config("broken", &[], Duration::from_secs(30), 3),
+            &broken,
+        )
+        .unwrap();
+    let context = builder.build();
+
+    let run = harness::run(&context, &clock, "entry");
+    assert!(matches!(run.result, Err(TaskError::ActionError { .. })));
+    assert_eq!(run.elapsed, Duration::ZERO);
+    assert_eq!(broken.exec_count(), 1);
+}
+
+#[test]
+fn tasks_are_added_once() {
+    let first = ScriptedAction::new("first");
+    let second = ScriptedAction::new("second");
+    let config = task_config("entry", &[], Duration::from_secs(30), 3);
+    let mut builder = ContextBuilder::new(TestRuntime::new());
+    builder.add_task(config.clone(), &first).unwrap();
+    let Err(err) = builder.add_task(config.clone(), &second) else {
+        panic!("the second entry task was added");
+    };
+    assert!(matches!(&err, ContextError::DuplicateTask { id } if id == "entry"));
+    assert_eq!(err.to_string(), "task entry is added twice");
+
+    let mut builder = ContextBuilder::new(TestRuntime::new());
+    let added = builder.add_tasks(vec![(config.clone(), &first), (config, &second)]);
+    assert!(matches!(added, Err(ContextError::DuplicateTask { .. })));
+}
+
+#[test]
+fn cancel_stops_waiting() {
+    let runtime = TestRuntime::new();
+    let clock = runtime.clock().clone();
+    let entry = ScriptedAction::new("entry");
+    let never = ScriptedAction::new("never").at(Duration::ZERO, Outcome::UnRecognized);
+
+    let mut builder = ContextBuilder::new(runtime);
+    builder
+        .add_task(
+            task_config("entry", &["never"], Duration::from_secs(30), 100),
+            &entry,
+        )
+        .unwrap();
+    builder
+        .add_task(
+            task_config("never", &[], Duration::from_secs(30), 100),
+            &never,
+        )
+        .unwrap();
+    let context = builder.build();
+    context.get_handler().try_cancel().unwrap();
+
+    let run = harness::run(&context, &clock, "entry");
+    assert!(matches!(run.result, Ok(TaskResult::TaskCancelled)));
+    assert_eq!(run.elapsed, Duration::ZERO);
+}
+
+#[test]
+fn paused_time_does_not_count_against_timeout() {
+    let runtime = TestRuntime::new();
+    let clock = runtime.clock().clone();
+    let entry = ScriptedAction::new("entry");
+    let late = ScriptedAction::new("late")
+        .at(Duration::ZERO, Outcome::UnRecognized)
+        .at(Duration::from_secs(6), Outcome::Recognized);
+
+    let mut builder = ContextBuilder::new(runtime);
+    builder
+        .add_task(
+            task_config("entry", &["late"], Duration::from_secs(2), 100),
+            &entry,
+        )
+        .unwrap();
+    builder
+        .add_task(task_config("late", &[], Duration::from_secs(2), 100), &late)
+        .unwrap();
+    let context = builder.build();
+    let handler = context.get_handler();
+    handler.pause();
+
+    let resume = async {
+        clock.sleep(Duration::from_secs(5)).await;
+        assert_eq!(late.recognize_count(), 0);
+        handler.resume();
+    };
+    let (result, ()) = harness::block_on(
+        &clock,
+        futures::future::join(context.run("entry".to_string()), resume),
+    );
+    assert!(matches!(result, Ok(TaskResult::NoPendingTask)));
+    assert!(!handler.is_paused());
+    assert_eq!(late.exec_count(), 1);
+    assert_eq!(context.blackboard().recognized("late"), Some(true));
+}
+
+#[test]
+fn paused_runs_can_be_cancelled() {
+    let runtime = TestRuntime::new();
+    let clock = runtime.clock().clone();
+    let entry = ScriptedAction::new("entry");
+    let never = ScriptedAction::new("never");
+
+    let mut builder = ContextBuilder::new(runtime);
+    builder
+        .add_task(
+            task_config("entry", &["never"], Duration::from_secs(30), 100),
+            &entry,
+        )
+        .unwrap();
+    builder
+        .add_task(
+            task_config("never", &[], Duration::from_secs(30), 100),
+            &never,
+        )
+        .unwrap();
+    let context = builder.build();
+    let handler = context.get_handler();
+    handler.pause();
+
+    let cancel = async {
+        clock.sleep(Duration::from_secs(60)).await;
+        handler.try_cancel().unwrap();
+    };
+    let (result, ()) = harness::block_on(
+        &clock,
+        futures::future::join(context.run("entry".to_string()), cancel),
+    );
+    assert!(matches!(result, Ok(TaskResult::TaskCancelled)));
+    assert_eq!(never.recognize_count(), 0);
+}
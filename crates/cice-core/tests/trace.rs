@@ -0,0 +1,285 @@
+use std::io::Write;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use async_trait::async_trait;
+use cice_core::action::{Action, ExecError, Recognition, RecognizeError};
+use cice_core::context::ContextBuilder;
+use cice_core::runtime::ext::{ScreenshotExt, TimerExt};
+use cice_core::runtime::Runtime;
+use cice_core::task::{TaskConfig, TaskError};
+use cice_core::trace::{frame_id, Trace, TraceEvent, TraceWriter, Traced};
+use cice_tests_common::action::TestRuntime;
+use cice_tests_common::harness;
+
+/// Screen showing `screens[i]` from `screens[i].0` on, in virtual time.
+struct ScreenRuntime {
+    runtime: TestRuntime,
+    screens: Vec<(Duration, &'static str)>,
+}
+
+impl Runtime for ScreenRuntime {}
+
+#[async_trait]
+impl TimerExt for ScreenRuntime {
+    fn now(&self) -> Duration {
+        self.runtime.now()
+    }
+
+    async fn sleep(&self, duration: Duration) {
+        self.runtime.sleep(duration).await
+    }
+}
+
+#[async_trait]
+impl ScreenshotExt for ScreenRuntime {
+    async fn screenshot(&self) -> Option<Vec<u8>> {
+        let now = self.now();
+        self.screens
+            .iter()
+            .rev()
+            .find(|(from, _)| *from <= now)
+            .map(|(_, screen)| screen.as_bytes().to_vec())
+    }
+}
+
+/// Recognizes when the screen shows `screen`, taking 100ms to look after `delay`.
+struct LooksFor {
+    screen: &'static str,
+    delay: Duration,
+    exec_fails: bool,
+}
+
+impl LooksFor {
+    fn new(screen: &'static str) -> Self {
+        Self {
+            screen,
+            delay: Duration::ZERO,
+            exec_fails: false,
+        }
+    }
+}
+
+#[async_trait]
+impl<R: ScreenshotExt + TimerExt> Action<R> for LooksFor {
+    async fn recognize(&self, runtime: &R) -> Result<(), RecognizeError> {
+        self.recognize_scored(runtime).await.result
+    }
+
+    async fn recognize_scored(&self, runtime: &R) -> Recognition {
+        runtime.sleep(self.delay).await;
+        let screen = runtime.screenshot().await.unwrap_or_default();
+        runtime.sleep(Duration::from_millis(100)).await;
+        let found = screen == self.screen.as_bytes();
+        Recognition {
+            result: found.then_some(()).ok_or(RecognizeError::UnRecognized),
+            score: Some(if found { 0.9 } else { 0.2 }),
+            location: None,
+        }
+    }
+
+    async fn exec(&self, _runtime: &R) -> Result<(), ExecError> {
+        match self.exec_fails {
+            true => Err(ExecError::ExecFailed {
+                reason: "button gone".to_string(),
+            }),
+            false => Ok(()),
+        }
+    }
+}
+
+/// Output shared with the test once the writer is moved into the context.
+#[derive(Clone, Default)]
+struct Buffer(Arc<Mutex<Vec<u8>>>);
+
+impl Write for Buffer {
+    fn write(&mut self, data: &[u8]) -> std::io::Result<usize> {
+        self.0.lock().unwrap().write(data)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+fn task_config(name: &str, next_task: &[&str]) -> TaskConfig {
+    TaskConfig {
+        task_name: name.to_string(),
+        action_name: name.to_string(),
+        next_task: next_task.iter().map(|id| id.to_string()).collect(),
+        interrupt_task: vec![],
+        guards: Default::default(),
+        timeout: Duration::from_secs(10),
+        max_retry: 20,
+    }
+}
+
+fn run_traced(menu: &LooksFor) -> (Result<(), TaskError>, Trace) {
+    let runtime = TestRuntime::new();
+    let clock = runtime.clock().clone();
+    let screens = vec![(Duration::ZERO, "title"), (Duration::from_secs(1), "menu")];
+    let buffer = Buffer::default();
+    let writer = TraceWriter::new(buffer.clone());
+    let runtime = Traced::new(ScreenRuntime { runtime, screens }, writer.clone());
+    let (entry, done) = (LooksFor::new("title"), LooksFor::new("menu"));
+    let mut builder = ContextBuilder::new(runtime);
+    builder
+        .add_task(task_config("entry", &["menu"]), &entry)
+        .unwrap()
+        .add_task(task_config("menu", &["done"]), menu)
+        .unwrap()
+        .add_task(task_config("done", &[]), &done)
+        .unwrap()
+        .tracer(writer);
+    let context = builder.build();
+    let result = harness::run(&context, &clock, "entry").result;
+    let trace = Trace::read(buffer.0.lock().unwrap().as_slice()).unwrap();
+    (result.map(|_| ()), trace)
+}
+
+#[test]
+fn traces_record_tasks_calls_and_frames() {
+    let (result, trace) = run_traced(&LooksFor::new("menu"));
+    assert!(result.is_ok());
+    let summary: Vec<String> = trace
+        .events
+        .iter()
+        .map(|event| match event {
+            TraceEvent::Enter { at, task } => format!("{at:?} enter {task}"),
+            TraceEvent::Recognize {
+                at,
+                task,
+                recognized,
+                ..
+            } => format!("{at:?} recognize {task} {recognized}"),
+            TraceEvent::Exec { at, task, .. } => format!("{at:?} exec {task}"),
+            TraceEvent::Screenshot { at, .. } => format!("{at:?} screenshot"),
+            TraceEvent::Finish { at, .. } => format!("{at:?} finish"),
+        })
+        .collect();
+    assert_eq!(
+        summary,
+        [
+            "0ns enter entry",
+            "0ns screenshot",
+            "0ns recognize menu false",
+            "600ms screenshot",
+            "600ms recognize menu false",
+            "1.2s screenshot",
+            "1.2s recognize menu true",
+            "1.3s exec menu",
+            "1.3s enter menu",
+            "1.3s screenshot",
+            "1.3s recognize done true",
+            "1.4s exec done",
+            "1.4s enter done",
+            "1.4s finish",
+        ]
+    );
+    assert_eq!(
+        trace.events[6],
+        TraceEvent::Recognize {
+            at: Duration::from_millis(1200),
+            task: "menu".to_string(),
+            duration: Duration::from_millis(100),
+            recognized: true,
+            error: None,
+            score: Some(0.9),
+            frame: Some(frame_id(b"menu")),
+        }
+    );
+
+    // Frames are stored once however many times they were seen
+    assert_eq!(trace.frames.len(), 2);
+    assert_eq!(trace.frames[&frame_id(b"menu")], b"menu");
+    assert_eq!(trace.frame_seen_by(2), Some(&frame_id(b"title")));
+    assert_eq!(trace.frame_seen_by(6), Some(&frame_id(b"menu")));
+    assert_eq!(trace.frame_seen_by(0), None);
+}
+
+#[test]
+fn traces_record_why_runs_failed() {
+    let menu = LooksFor {
+        exec_fails: true,
+        ..LooksFor::new("menu")
+    };
+    let (result, trace) = run_traced(&menu);
+    assert!(matches!(result, Err(TaskError::ActionError { .. })));
+    let [.., exec, finish] = trace.events.as_slice() else {
+        panic!("too few events: {:?}", trace.events);
+    };
+    assert!(matches!(
+        exec,
+        TraceEvent::Exec { task, error: Some(error), .. }
+            if task == "menu" && error.contains("button gone")
+    ));
+    assert!(matches!(
+        finish,
+        TraceEvent::Finish {
+            cancelled: false,
+            error: Some(_),
+            ..
+        }
+    ));
+}
+
+#[test]
+fn concurrent_candidates_see_their_own_frames() {
+    let runtime = TestRuntime::new();
+    let clock = runtime.clock().clone();
+    let screens = vec![
+        (Duration::ZERO, "title"),
+        (Duration::from_millis(50), "menu"),
+    ];
+    let buffer = Buffer::default();
+    let writer = TraceWriter::new(buffer.clone());
+    let runtime = Traced::new(ScreenRuntime { runtime, screens }, writer.clone());
+    // `early` looks at the title and returns after `late` looked at the menu
+    let entry = LooksFor::new("title");
+    let early = LooksFor::new("menu");
+    let late = LooksFor {
+        delay: Duration::from_millis(50),
+        ..LooksFor::new("menu")
+    };
+    let mut builder = ContextBuilder::new(runtime);
+    builder
+        .add_task(task_config("entry", &["early", "late"]), &entry)
+        .unwrap()
+        .add_task(task_config("early", &[]), &early)
+        .unwrap()
+        .add_task(task_config("late", &[]), &late)
+        .unwrap()
+        .tracer(writer);
+    let context = builder.build();
+    let result = harness::run(&context, &clock, "entry").result;
+    assert!(result.is_ok());
+
+    let trace = Trace::read(buffer.0.lock().unwrap().as_slice()).unwrap();
+    let seen: Vec<_> = (0..trace.events.len())
+        .filter_map(|index| match &trace.events[index] {
+            TraceEvent::Recognize { task, .. } => {
+                Some((task.as_str(), trace.frame_seen_by(index).cloned()))
+            }
+            _ => None,
+        })
+        .collect();
+    assert_eq!(
+        seen,
+        [
+            ("early", Some(frame_id(b"title"))),
+            ("late", Some(frame_id(b"menu"))),
+        ]
+    );
+}
+
+#[test]
+fn reading_traces_tolerates_a_cut_off_last_line() {
+    let complete = r#"{"event":"enter","at":"0s","task":"entry"}"#;
+    let trace = Trace::read(format!("{complete}\n{{\"event\":\"ent").as_bytes()).unwrap();
+    assert_eq!(trace.events.len(), 1);
+
+    let err = Trace::read(format!("{{\"event\":\n{complete}\n").as_bytes()).unwrap_err();
+    assert!(err.to_string().starts_with("line 1: "));
+    let err = Trace::read(r#"{"frame":"00","data":"*"}"#.as_bytes()).unwrap_err();
+    assert!(err.to_string().starts_with("line 1: invalid frame data"));
+}
@@ -0,0 +1,117 @@
+use std::error::Error;
+use std::path::PathBuf;
+
+use cice_control::{Command, ControlClient, Endpoint};
+use cice_core::debug::Breakpoint;
+use cice_core::message::Message;
+
+use crate::run::{parse_breakpoint, parse_variable};
+
+#[derive(clap::Args)]
+pub struct AttachArgs {
+    /// Where the run serves the control protocol, as given to `run --control`
+    endpoint: Endpoint,
+    /// Request to send; without one, prints the events of the run until it exits
+    #[command(subcommand)]
+    request: Option<Request>,
+}
+
+#[derive(clap::Subcommand)]
+enum Request {
+    /// Whether the run is paused, where it halted and its breakpoints
+    Status,
+    /// Halt the run at its next checkpoint
+    Pause,
+    /// Run on to the next breakpoint
+    Resume,
+    /// Run a halted context to its next recognition round
+    StepRound,
+    /// Run a halted context to the next task chosen, before it is executed
+    StepTask,
+    /// Cancel the run, halted or not
+    Cancel,
+    /// Print the tasks and edges of the task graph
+    Graph,
+    /// Save a screenshot of the screen the run is on
+    Screenshot {
+        #[arg(short, long, default_value = "screenshot.png")]
+        output: PathBuf,
+    },
+    /// Set a breakpoint, see `run --break`
+    Break {
+        #[arg(value_name = "TASK[:STAGE]", value_parser = parse_breakpoint)]
+        breakpoint: Breakpoint,
+    },
+    /// Remove a breakpoint
+    Clear {
+        #[arg(value_name = "TASK[:STAGE]", value_parser = parse_breakpoint)]
+        breakpoint: Breakpoint,
+    },
+    /// Print a variable of the blackboard
+    Get { name: String },
+    /// Set a variable of the blackboard
+    Set {
+        #[arg(value_name = "NAME=VALUE", value_parser = parse_variable)]
+        variable: (String, serde_json::Value),
+    },
+}
+
+pub fn run(args: AttachArgs) -> Result<(), Box<dyn Error>> {
+    let executor = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()?;
+    executor.block_on(async {
+        let mut client = ControlClient::connect(&args.endpoint).await?;
+        let command = match args.request {
+            None => return follow(&mut client).await,
+            Some(Request::Screenshot { output }) => {
+                std::fs::write(&output, client.screenshot().await?)?;
+                println!("saved {}", output.display());
+                return Ok(());
+            }
+            Some(Request::Status) => Command::Status,
+            Some(Request::Pause) => Command::Pause,
+            Some(Request::Resume) => Command::Resume,
+            Some(Request::StepRound) => Command::StepRound,
+            Some(Request::StepTask) => Command::StepTask,
+            Some(Request::Cancel) => Command::Cancel,
+            Some(Request::Graph) => Command::Graph,
+            Some(Request::Break { breakpoint }) => Command::SetBreakpoint {
+                task: breakpoint.task,
+                stage: breakpoint.stage,
+            },
+            Some(Request::Clear { breakpoint }) => Command::RemoveBreakpoint {
+                task: breakpoint.task,
+                stage: breakpoint.stage,
+            },
+            Some(Request::Get { name }) => Command::GetVar { name },
+            Some(Request::Set {
+                variable: (name, value),
+            }) => Command::SetVar { name, value },
+        };
+        let result = client.request(command).await?;
+        if !result.is_null() {
+            println!("{}", serde_json::to_string_pretty(&result)?);
+        }
+        Ok(())
+    })
+}
+
+/// Prints the events of the run until it exits or Ctrl-C is pressed.
+async fn follow(client: &mut ControlClient) -> Result<(), Box<dyn Error>> {
+    loop {
+        let event = tokio::select! {
+            _ = tokio::signal::ctrl_c() => return Ok(()),
+            event = client.next_event() => event?,
+        };
+        match event {
+            Some(Message::TaskMessage(message)) => println!("{message}"),
+            Some(Message::Halted(halt)) => println!("halted {} {}", halt.stage, halt.task),
+            Some(message) => println!("{message:?}"),
+            None => {
+                println!("the run exited");
+                return Ok(());
+            }
+        }
+    }
+}
@@ -0,0 +1,54 @@
+use std::error::Error;
+use std::fs;
+use std::path::PathBuf;
+
+use cice_core::graph::TaskGraph;
+use cice_core::pipeline::Pipeline;
+
+#[derive(Clone, Copy, clap::ValueEnum)]
+enum GraphFormat {
+    Dot,
+    Mermaid,
+}
+
+#[derive(clap::Args)]
+pub struct GraphArgs {
+    /// Pipeline file to render, with the files it includes
+    pipeline: PathBuf,
+    #[arg(short, long, value_enum, default_value = "dot")]
+    format: GraphFormat,
+    /// Task the pipeline is run from, may be repeated; defaults to the tasks nothing leads to
+    #[arg(short, long)]
+    entry: Vec<String>,
+    /// Write the graph to this file instead of stdout
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+}
+
+pub fn run(args: GraphArgs) -> Result<(), Box<dyn Error>> {
+    let pipeline = Pipeline::from_file(&args.pipeline)?;
+    let mut graph = TaskGraph::from(&pipeline);
+    for entry in args.entry {
+        graph.entry(entry);
+    }
+    warn_dangling(&graph);
+    let rendered = match args.format {
+        GraphFormat::Dot => graph.to_dot(),
+        GraphFormat::Mermaid => graph.to_mermaid(),
+    };
+    match args.output {
+        Some(path) => {
+            fs::write(&path, rendered).map_err(|err| format!("{}: {err}", path.display()).into())
+        }
+        None => {
+            print!("{rendered}");
+            Ok(())
+        }
+    }
+}
+
+pub fn warn_dangling(graph: &TaskGraph) {
+    for id in graph.dangling() {
+        eprintln!("warning: task {id} is referenced but not defined");
+    }
+}
@@ -0,0 +1,97 @@
+use std::error::Error;
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+use cice_core::pipeline::lint::{Finding, Linter, Severity};
+use cice_core::pipeline::Pipeline;
+
+use crate::run::{load_variables, parse_variable};
+
+#[derive(Clone, Copy, clap::ValueEnum)]
+enum LintFormat {
+    Text,
+    /// One object with the findings and their counts, for CI
+    Json,
+}
+
+#[derive(clap::Args)]
+#[command(
+    after_help = "Exit status: 0 when there is no error, 1 otherwise. Checks of \
+                  opencv.template_match actions need the opencv feature."
+)]
+pub struct LintArgs {
+    /// Pipeline file to check, with the files it includes
+    pipeline: PathBuf,
+    /// Task the pipeline is run from, may be repeated; defaults to the tasks nothing leads to
+    #[arg(short, long)]
+    entry: Vec<String>,
+    /// Size of the screen the pipeline is run on, to check regions against
+    #[arg(long, value_name = "WIDTHxHEIGHT", value_parser = parse_screen)]
+    screen: Option<(u64, u64)>,
+    #[arg(short, long, value_enum, default_value = "text")]
+    format: LintFormat,
+    /// Fail on warnings too
+    #[arg(long)]
+    deny_warnings: bool,
+    /// Variable substituted into the pipeline, as `name=value`; may be repeated
+    #[arg(long = "var", value_name = "NAME=VALUE", value_parser = parse_variable)]
+    variables: Vec<(String, serde_json::Value)>,
+    /// File of variables substituted into the pipeline, overridden by `--var`
+    #[arg(long)]
+    profile: Option<PathBuf>,
+}
+
+fn parse_screen(text: &str) -> Result<(u64, u64), String> {
+    text.split_once('x')
+        .and_then(|(width, height)| Some((width.parse().ok()?, height.parse().ok()?)))
+        .ok_or_else(|| format!("expected WIDTHxHEIGHT, found `{text}`"))
+}
+
+pub fn run(args: LintArgs) -> Result<ExitCode, Box<dyn Error>> {
+    let variables = load_variables(args.profile.as_deref(), &args.variables)?;
+    let findings = match Pipeline::from_file_with_variables(&args.pipeline, &variables) {
+        Ok(pipeline) => {
+            let mut linter = Linter::new();
+            #[cfg(feature = "opencv")]
+            cice_action_opencv::register_lints(&mut linter);
+            for entry in args.entry {
+                linter.entry(entry);
+            }
+            if let Some((width, height)) = args.screen {
+                linter.screen(width, height);
+            }
+            linter.lint(&pipeline)
+        }
+        Err(err) => vec![Finding::load_error(&err)],
+    };
+
+    let count = |severity| {
+        findings
+            .iter()
+            .filter(|finding| finding.severity == severity)
+            .count()
+    };
+    let (errors, warnings) = (count(Severity::Error), count(Severity::Warning));
+    match args.format {
+        LintFormat::Text => {
+            for finding in &findings {
+                println!("{finding}");
+            }
+            println!("{errors} error(s), {warnings} warning(s)");
+        }
+        LintFormat::Json => {
+            let report = serde_json::json!({
+                "pipeline": args.pipeline,
+                "errors": errors,
+                "warnings": warnings,
+                "findings": findings,
+            });
+            println!("{}", serde_json::to_string_pretty(&report)?);
+        }
+    }
+    let failed = errors > 0 || (args.deny_warnings && warnings > 0);
+    Ok(match failed {
+        true => ExitCode::FAILURE,
+        false => ExitCode::SUCCESS,
+    })
+}
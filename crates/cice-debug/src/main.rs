@@ -1,3 +1,55 @@
-fn main() {
-    println!("Hello, world!");
+//! Developer tools for cice pipelines.
+
+use std::process::ExitCode;
+
+use clap::{Parser, Subcommand};
+
+mod attach;
+mod graph;
+mod lint;
+mod monitor;
+mod run;
+mod schema;
+mod trace;
+
+#[derive(Parser)]
+#[command(version, about)]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Print the JSON Schema of pipeline documents, for editor completion
+    Schema(schema::SchemaArgs),
+    /// Render the task graph of a pipeline as Graphviz DOT or Mermaid
+    Graph(graph::GraphArgs),
+    /// Check a pipeline for mistakes without running it
+    Lint(lint::LintArgs),
+    /// Run a pipeline against a VNC server or recorded screenshots, printing its events
+    Run(run::RunArgs),
+    /// Follow or steer a run started with `run --control` from another terminal
+    Attach(attach::AttachArgs),
+    /// Browse the trace of a run written with `run --trace`
+    Trace(trace::TraceArgs),
+}
+
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+    let result = match cli.command {
+        Command::Schema(args) => schema::run(args).map(|()| ExitCode::SUCCESS),
+        Command::Graph(args) => graph::run(args).map(|()| ExitCode::SUCCESS),
+        Command::Lint(args) => lint::run(args),
+        Command::Run(args) => run::run(args),
+        Command::Attach(args) => attach::run(args).map(|()| ExitCode::SUCCESS),
+        Command::Trace(args) => trace::run(args).map(|()| ExitCode::SUCCESS),
+    };
+    match result {
+        Ok(code) => code,
+        Err(err) => {
+            eprintln!("error: {err}");
+            ExitCode::FAILURE
+        }
+    }
 }
@@ -0,0 +1,377 @@
+use std::collections::{BTreeMap, VecDeque};
+use std::error::Error;
+use std::future::Future;
+use std::time::{Duration, Instant};
+
+use cice_control::ControlServer;
+use cice_core::blackboard::Blackboard;
+use cice_core::context::ContextHandler;
+use cice_core::message::task::TaskMessage;
+use cice_core::message::Message;
+use cice_core::task::{duration, TaskConfig, TaskError, TaskId, TaskResult};
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
+use ratatui::layout::{Constraint, Layout};
+use ratatui::style::{Color, Modifier, Style, Stylize};
+use ratatui::text::Line;
+use ratatui::widgets::{Block, List, ListItem, Paragraph, Row, Table};
+use ratatui::Frame;
+
+/// Events kept in the timeline.
+const TIMELINE_LEN: usize = 200;
+/// How often the screen is redrawn without new messages, to update timers and the blackboard.
+const REFRESH_INTERVAL: Duration = Duration::from_millis(250);
+
+/// How far the keys `r` and `n` run a halted context.
+enum Step {
+    Round,
+    Task,
+}
+
+enum Status {
+    Running,
+    Cancelling,
+    Finished(String),
+}
+
+/// The task being run and how often each of its `next_task` was tried.
+struct Current {
+    id: TaskId,
+    entered: Instant,
+    attempts: BTreeMap<TaskId, usize>,
+}
+
+/// State of the monitor, built from the messages of the context and its blackboard.
+pub struct Monitor {
+    name: String,
+    tasks: BTreeMap<TaskId, TaskConfig>,
+    handler: ContextHandler,
+    started: Instant,
+    current: Option<Current>,
+    timeline: VecDeque<(Duration, String)>,
+    status: Status,
+    control: Option<ControlServer>,
+}
+
+impl Monitor {
+    pub fn new<'c>(
+        name: impl Into<String>,
+        tasks: impl IntoIterator<Item = &'c TaskConfig>,
+        handler: ContextHandler,
+    ) -> Self {
+        Self {
+            name: name.into(),
+            tasks: tasks
+                .into_iter()
+                .map(|config| (config.task_name.clone(), config.clone()))
+                .collect(),
+            handler,
+            started: Instant::now(),
+            current: None,
+            timeline: VecDeque::new(),
+            status: Status::Running,
+            control: None,
+        }
+    }
+
+    /// Passes the messages of the context on to the clients of `control` too.
+    pub fn control(mut self, control: Option<ControlServer>) -> Self {
+        self.control = control;
+        self
+    }
+
+    fn blackboard(&self) -> &Blackboard {
+        self.handler.blackboard()
+    }
+
+    fn log(&mut self, event: impl Into<String>) {
+        if self.timeline.len() == TIMELINE_LEN {
+            self.timeline.pop_front();
+        }
+        self.timeline
+            .push_back((self.started.elapsed(), event.into()));
+    }
+
+    fn handle(&mut self, message: Message) {
+        if let Some(control) = &self.control {
+            control.publish(&message);
+        }
+        let message = match message {
+            Message::TaskMessage(message) => message,
+            Message::Halted(halt) => {
+                self.log(format!("halted {} {}", halt.stage, halt.task));
+                return;
+            }
+            _ => return,
+        };
+        match &message {
+            TaskMessage::Enter { id } => {
+                self.current = Some(Current {
+                    id: id.clone(),
+                    entered: Instant::now(),
+                    attempts: BTreeMap::new(),
+                });
+            }
+            // Recognitions are shown as counters rather than flooding the timeline
+            TaskMessage::TryRecognize { id } => {
+                if let Some(current) = &mut self.current {
+                    *current.attempts.entry(id.clone()).or_default() += 1;
+                }
+                return;
+            }
+            TaskMessage::TryExec { .. } | TaskMessage::ExecSuccess { .. } => {}
+        }
+        self.log(message.to_string());
+    }
+
+    fn toggle_pause(&mut self) {
+        if self.handler.is_paused() {
+            self.handler.resume();
+            self.log("resumed");
+        } else {
+            self.handler.pause();
+            self.log("paused");
+        }
+    }
+
+    fn step(&mut self, step: Step) {
+        match step {
+            Step::Round => self.handler.step_round(),
+            Step::Task => self.handler.step_task(),
+        }
+    }
+
+    fn cancel(&mut self) {
+        if matches!(self.status, Status::Running) {
+            let _ = self.handler.try_cancel();
+            // A paused context only notices the cancellation between rounds
+            self.handler.resume();
+            self.status = Status::Cancelling;
+            self.log("cancelling");
+        }
+    }
+
+    fn finish(&mut self, result: &Result<TaskResult, TaskError>) {
+        let outcome = match result {
+            Ok(TaskResult::TaskCancelled) => "cancelled".to_string(),
+            Ok(_) => "completed".to_string(),
+            Err(err) => format!("failed: {err}"),
+        };
+        self.log(outcome.clone());
+        self.status = Status::Finished(outcome);
+    }
+
+    fn draw(&self, frame: &mut Frame) {
+        let [header, body, footer] = Layout::vertical([
+            Constraint::Length(1),
+            Constraint::Min(0),
+            Constraint::Length(1),
+        ])
+        .areas(frame.area());
+        let [left, timeline] =
+            Layout::horizontal([Constraint::Percentage(55), Constraint::Percentage(45)])
+                .areas(body);
+        let [task, candidates] =
+            Layout::vertical([Constraint::Length(6), Constraint::Min(0)]).areas(left);
+
+        let (status, color) = match &self.status {
+            Status::Running => match self.handler.halted() {
+                Some(halt) => (
+                    format!("halted {} {}", halt.stage, halt.task),
+                    Color::Yellow,
+                ),
+                None if self.handler.is_paused() => ("pausing".to_string(), Color::Yellow),
+                None => ("running".to_string(), Color::Green),
+            },
+            Status::Cancelling => ("cancelling".to_string(), Color::Yellow),
+            Status::Finished(outcome) => (outcome.clone(), Color::Cyan),
+        };
+        let elapsed = clock(self.started.elapsed());
+        frame.render_widget(
+            Line::from(vec![
+                format!(" {} ", self.name).bold(),
+                format!(" {elapsed} ").into(),
+                format!(" {status} ")
+                    .fg(color)
+                    .add_modifier(Modifier::REVERSED),
+            ]),
+            header,
+        );
+
+        let config = self
+            .current
+            .as_ref()
+            .and_then(|current| self.tasks.get(&current.id));
+        let task_lines = match (&self.current, config) {
+            (Some(current), Some(config)) => {
+                let round = current.attempts.values().copied().max().unwrap_or(0);
+                vec![
+                    Line::from(current.id.clone().bold()),
+                    Line::from(format!("action    {}", config.action_name)),
+                    Line::from(format!(
+                        "waiting   {:.1}s of {}",
+                        current.entered.elapsed().as_secs_f64(),
+                        duration::format(config.timeout)
+                    )),
+                    Line::from(format!("round     {round} of {}", config.max_retry)),
+                ]
+            }
+            _ => vec![Line::from("waiting for the first task")],
+        };
+        frame.render_widget(
+            Paragraph::new(task_lines).block(Block::bordered().title(" Current task ")),
+            task,
+        );
+
+        let rows: Vec<Row> = config
+            .map(|config| {
+                config
+                    .next_task
+                    .iter()
+                    .map(|id| self.candidate_row(id, config))
+                    .collect()
+            })
+            .unwrap_or_default();
+        let table = Table::new(
+            rows,
+            [
+                Constraint::Fill(1),
+                Constraint::Length(8),
+                Constraint::Length(14),
+                Constraint::Length(7),
+                Constraint::Length(6),
+            ],
+        )
+        .header(Row::new(["task", "attempts", "last outcome", "score", "runs"]).bold())
+        .block(Block::bordered().title(" Next tasks "));
+        frame.render_widget(table, candidates);
+
+        let height = usize::from(timeline.height.saturating_sub(2));
+        let items: Vec<ListItem> = self
+            .timeline
+            .iter()
+            .skip(self.timeline.len().saturating_sub(height))
+            .map(|(at, event)| ListItem::new(format!("{:8.3}s  {event}", at.as_secs_f64())))
+            .collect();
+        frame.render_widget(
+            List::new(items).block(Block::bordered().title(" Timeline ")),
+            timeline,
+        );
+
+        let keys = match self.status {
+            Status::Finished(_) => " q quit",
+            _ => " p pause/resume   r step round   n step task   c cancel   q cancel and quit",
+        };
+        frame.render_widget(Line::from(keys).dark_gray(), footer);
+    }
+
+    fn candidate_row(&self, id: &TaskId, config: &TaskConfig) -> Row<'static> {
+        let attempts = self
+            .current
+            .as_ref()
+            .and_then(|current| current.attempts.get(id))
+            .copied()
+            .unwrap_or(0);
+        let (outcome, style) = match self.blackboard().recognized(id) {
+            _ if attempts == 0 && config.guards.contains_key(id) => ("guarded", Style::new()),
+            _ if attempts == 0 => ("-", Style::new()),
+            Some(true) => ("recognized", Style::new().green()),
+            Some(false) => ("not found", Style::new().red()),
+            None => ("recognizing", Style::new().yellow()),
+        };
+        let score = self
+            .blackboard()
+            .score(id)
+            .map_or("-".to_string(), |score| format!("{score:.3}"));
+        Row::new([
+            id.clone(),
+            attempts.to_string(),
+            outcome.to_string(),
+            score,
+            self.blackboard().runs(id).to_string(),
+        ])
+        .style(style)
+    }
+}
+
+/// `elapsed` as `h:mm:ss`.
+fn clock(elapsed: Duration) -> String {
+    let seconds = elapsed.as_secs();
+    format!(
+        "{}:{:02}:{:02}",
+        seconds / 3600,
+        seconds / 60 % 60,
+        seconds % 60
+    )
+}
+
+/// Runs `run` while showing `monitor` in the terminal, until it completes and the user quits.
+pub async fn monitor(
+    mut monitor: Monitor,
+    run: impl Future<Output = Result<TaskResult, TaskError>>,
+) -> Result<Result<TaskResult, TaskError>, Box<dyn Error>> {
+    // Reading the terminal blocks, so keys are forwarded from their own thread
+    let (keys, mut key_events) = tokio::sync::mpsc::unbounded_channel();
+    std::thread::spawn(move || {
+        while let Ok(event) = event::read() {
+            if keys.send(event).is_err() {
+                break;
+            }
+        }
+    });
+
+    let mut terminal = ratatui::try_init()?;
+    let handler = monitor.handler.clone();
+    let mut run = std::pin::pin!(run);
+    let mut result = None;
+    // Quitting while running cancels first, and leaves once the run completes
+    let mut quitting = false;
+    let mut refresh = tokio::time::interval(REFRESH_INTERVAL);
+    let outcome: Result<(), Box<dyn Error>> = loop {
+        if let Err(err) = terminal.draw(|frame| monitor.draw(frame)) {
+            break Err(err.into());
+        }
+        tokio::select! {
+            biased;
+            Some(event) = key_events.recv() => {
+                let Event::Key(key) = event else { continue };
+                if key.kind != KeyEventKind::Press {
+                    continue;
+                }
+                let finished = result.is_some();
+                match key.code {
+                    KeyCode::Char('q') if finished => break Ok(()),
+                    KeyCode::Char('q') => {
+                        quitting = true;
+                        monitor.cancel();
+                    }
+                    KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                        if finished {
+                            break Ok(());
+                        }
+                        monitor.cancel();
+                    }
+                    KeyCode::Char('c') => monitor.cancel(),
+                    KeyCode::Char('p') | KeyCode::Char(' ') if !finished => monitor.toggle_pause(),
+                    KeyCode::Char('r') if !finished => monitor.step(Step::Round),
+                    KeyCode::Char('n') if !finished => monitor.step(Step::Task),
+                    _ => {}
+                }
+            }
+            Ok(message) = handler.recv() => monitor.handle(message),
+            finished = &mut run, if result.is_none() => {
+                while let Ok(message) = handler.try_recv() {
+                    monitor.handle(message);
+                }
+                monitor.finish(&finished);
+                result = Some(finished);
+                if quitting {
+                    break Ok(());
+                }
+            }
+            _ = refresh.tick() => {}
+        }
+    };
+    ratatui::restore();
+    outcome?;
+    Ok(result.expect("the monitor only quits once the run completes"))
+}
@@ -0,0 +1,366 @@
+use std::collections::BTreeSet;
+use std::error::Error;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::time::{Duration, Instant};
+
+use cice_control::{ControlServer, Endpoint, Listener};
+use cice_core::context::ContextBuilder;
+use cice_core::debug::{Breakpoint, Stage};
+use cice_core::graph::TaskGraph;
+use cice_core::message::Message;
+use cice_core::pipeline::action::{
+    ActionConfig, ActionFactories, COMPOSE_TYPE, EXEC_KEY, RECOGNIZE_KEY,
+};
+use cice_core::pipeline::variables::Variables;
+use cice_core::pipeline::{ActionRegistry, Pipeline};
+use cice_core::runtime::ext::{InputExt, ScreenshotExt, TimerExt};
+use cice_core::task::{duration, TaskError, TaskResult};
+use cice_core::trace::{TraceWriter, Traced};
+use cice_runtime_replay::ReplayRuntime;
+use cice_runtime_vnc::VncRuntime;
+
+use crate::monitor::{self, Monitor};
+
+/// Exit codes of `run`, besides 0 when the pipeline completes and 1 when it can't start.
+const EXIT_TIMEOUT: u8 = 2;
+const EXIT_ACTION_FAILED: u8 = 3;
+const EXIT_UNKNOWN_TASK: u8 = 4;
+const EXIT_CANCELLED: u8 = 130;
+
+#[derive(clap::Args)]
+#[command(
+    group(clap::ArgGroup::new("runtime").required(true).args(["vnc", "images", "replay"])),
+    group(clap::ArgGroup::new("replaying").args(["images", "replay"])),
+    group(clap::ArgGroup::new("debugger").multiple(true).args(["tui", "control"])),
+    after_help = "Exit status: 0 when the pipeline completes, 1 when it can't start, 2 when a task \
+                  times out, 3 when an action fails, 4 when a task is unknown and 130 when \
+                  cancelled with Ctrl-C. Only input.* actions are built in; opencv.* actions need \
+                  the opencv feature."
+)]
+pub struct RunArgs {
+    /// Pipeline file to run, with the files it includes
+    pipeline: PathBuf,
+    /// Task to start from; defaults to the only task nothing leads to
+    #[arg(short, long)]
+    entry: Option<String>,
+    /// Run against the VNC server at this address, as `host:port`
+    #[arg(long, value_name = "ADDRESS")]
+    vnc: Option<String>,
+    /// Password of the VNC server
+    #[arg(long, requires = "vnc")]
+    password: Option<String>,
+    /// Replay the numbered screenshots of this directory instead of connecting
+    #[arg(long, value_name = "DIR")]
+    images: Option<PathBuf>,
+    /// Replay the screenshots of a trace written with `--trace` instead of connecting
+    #[arg(long, value_name = "FILE")]
+    replay: Option<PathBuf>,
+    /// How long each replayed screenshot is shown, e.g. `500ms`; defaults to 1s, or to the times
+    /// they were taken at with `--replay`
+    #[arg(long, requires = "replaying", value_parser = duration::parse)]
+    frame_interval: Option<Duration>,
+    /// Show the next replayed screenshot after each click, text or key instead of after a time
+    #[arg(long, requires = "replaying", conflicts_with = "frame_interval")]
+    advance_on_input: bool,
+    /// Variable substituted into the pipeline, as `name=value`; may be repeated
+    #[arg(long = "var", value_name = "NAME=VALUE", value_parser = parse_variable)]
+    variables: Vec<(String, serde_json::Value)>,
+    /// File of variables substituted into the pipeline, overridden by `--var`
+    #[arg(long)]
+    profile: Option<PathBuf>,
+    /// Write a trace of the run to this file, for `cice-debug trace`
+    #[arg(long, value_name = "FILE")]
+    trace: Option<PathBuf>,
+    /// Only print the outcome, not every task event
+    #[arg(short, long)]
+    quiet: bool,
+    /// Follow the run in a terminal UI, where it can be paused and cancelled
+    #[arg(long, conflicts_with = "quiet")]
+    tui: bool,
+    /// Serve the control protocol on `tcp:HOST:PORT` or `unix:PATH`, for `cice-debug attach`
+    #[arg(long, value_name = "ENDPOINT")]
+    control: Option<Endpoint>,
+    /// Halt at a stage of a task, one of `before-recognize`, `before-exec` (the default) or
+    /// `after-exec`; may be repeated
+    #[arg(
+        long = "break",
+        value_name = "TASK[:STAGE]",
+        value_parser = parse_breakpoint,
+        requires = "debugger"
+    )]
+    breakpoints: Vec<Breakpoint>,
+}
+
+/// `name=value`, the value typed like environment variables, see `Variables::from_env`.
+pub fn parse_variable(text: &str) -> Result<(String, serde_json::Value), String> {
+    let (name, value) = text
+        .split_once('=')
+        .ok_or_else(|| format!("expected NAME=VALUE, found `{text}`"))?;
+    let value = match serde_json::from_str(value) {
+        Ok(typed @ (serde_json::Value::Number(_) | serde_json::Value::Bool(_))) => typed,
+        _ => serde_json::Value::String(value.to_string()),
+    };
+    Ok((name.to_string(), value))
+}
+
+/// Variables of `profile`, overridden by the `--var` ones.
+pub fn load_variables(
+    profile: Option<&Path>,
+    overrides: &[(String, serde_json::Value)],
+) -> Result<Variables, Box<dyn Error>> {
+    let mut variables = match profile {
+        Some(profile) => Variables::from_profile(profile)?,
+        None => Variables::new(),
+    };
+    for (name, value) in overrides {
+        variables.set(name.clone(), value.clone());
+    }
+    Ok(variables)
+}
+
+pub fn parse_breakpoint(text: &str) -> Result<Breakpoint, String> {
+    let (task, stage) = match text.rsplit_once(':') {
+        // Namespaced task ids contain `::`
+        Some((task, stage)) if !task.ends_with(':') => {
+            let stage = serde_json::from_value(serde_json::Value::String(stage.to_string()))
+                .map_err(|_| format!("unknown stage `{stage}`"))?;
+            (task, stage)
+        }
+        _ => (text, Stage::BeforeExec),
+    };
+    Ok(Breakpoint {
+        task: task.to_string(),
+        stage,
+    })
+}
+
+pub fn run(args: RunArgs) -> Result<ExitCode, Box<dyn Error>> {
+    let variables = load_variables(args.profile.as_deref(), &args.variables)?;
+    let pipeline = Pipeline::from_file_with_variables(&args.pipeline, &variables)?;
+    check_action_types(&pipeline)?;
+    let graph = TaskGraph::from(&pipeline);
+    crate::graph::warn_dangling(&graph);
+    let entry = match args.entry.clone() {
+        Some(entry) => entry,
+        None => {
+            let entries = graph.entries();
+            match entries.iter().collect::<Vec<_>>().as_slice() {
+                [entry] => entry.to_string(),
+                _ => {
+                    let entries = entries.into_iter().collect::<Vec<_>>().join(", ");
+                    return Err(format!(
+                        "can't tell the entry task among [{entries}], choose one with --entry"
+                    )
+                    .into());
+                }
+            }
+        }
+    };
+
+    let executor = tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .build()?;
+    executor.block_on(async {
+        if let Some(address) = &args.vnc {
+            let runtime = VncRuntime::new(address.clone(), args.password.clone());
+            runtime.connect().await?;
+            let outcome = start(runtime.clone(), pipeline, entry, &args).await;
+            // However the run ended, close the connection before exiting so the server sees
+            // the client leave
+            runtime.disconnect().await?;
+            outcome
+        } else if let Some(dir) = &args.images {
+            let runtime = ReplayRuntime::from_dir(dir)?;
+            start(replaying(runtime, &args), pipeline, entry, &args).await
+        } else if let Some(trace) = &args.replay {
+            let runtime = ReplayRuntime::from_trace(trace)?;
+            start(replaying(runtime, &args), pipeline, entry, &args).await
+        } else {
+            unreachable!("clap requires a runtime")
+        }
+    })
+}
+
+/// Factories of the action types this build can run.
+fn factories<RUNTIME>() -> ActionFactories<RUNTIME>
+where
+    RUNTIME: InputExt + ScreenshotExt + 'static,
+{
+    let mut factories = ActionFactories::new();
+    cice_core::action::input::register(&mut factories);
+    #[cfg(feature = "opencv")]
+    cice_action_opencv::register(&mut factories);
+    factories
+}
+
+/// Fails before connecting when the pipeline uses action types left out of this build.
+fn check_action_types(pipeline: &Pipeline) -> Result<(), Box<dyn Error>> {
+    if cfg!(feature = "opencv") {
+        // Other unknown types are reported when the actions are built
+        return Ok(());
+    }
+    let factories = factories::<ReplayRuntime>();
+    let mut missing = BTreeSet::new();
+    for (_, action) in pipeline.actions() {
+        unknown_types(&factories, action, &mut missing);
+    }
+    if missing.is_empty() {
+        return Ok(());
+    }
+    let missing = missing.into_iter().collect::<Vec<_>>().join(", ");
+    Err(format!(
+        "the pipeline uses action types {missing}, but cice-debug was built without action \
+         types, rebuild with --features opencv"
+    )
+    .into())
+}
+
+/// Adds the types of `action` and of the actions it composes that `factories` can't build.
+fn unknown_types(
+    factories: &ActionFactories<ReplayRuntime>,
+    action: &ActionConfig,
+    missing: &mut BTreeSet<String>,
+) {
+    if action.type_name != COMPOSE_TYPE {
+        if !factories.contains(&action.type_name) {
+            missing.insert(action.type_name.clone());
+        }
+        return;
+    }
+    for key in [RECOGNIZE_KEY, EXEC_KEY] {
+        let part = action.config.get(key).cloned().map(serde_json::from_value);
+        if let Some(Ok(part)) = part {
+            unknown_types(factories, &part, missing);
+        }
+    }
+}
+
+/// Applies the replay options to `runtime`.
+fn replaying(runtime: ReplayRuntime, args: &RunArgs) -> ReplayRuntime {
+    match args.frame_interval {
+        _ if args.advance_on_input => runtime.advance_on_input(),
+        Some(interval) => runtime.frame_interval(interval),
+        None => runtime,
+    }
+}
+
+/// Runs on `runtime`, traced if asked to.
+async fn start<RUNTIME>(
+    runtime: RUNTIME,
+    pipeline: Pipeline,
+    entry: String,
+    args: &RunArgs,
+) -> Result<ExitCode, Box<dyn Error>>
+where
+    RUNTIME: InputExt + ScreenshotExt + TimerExt + Clone + 'static,
+{
+    match &args.trace {
+        Some(path) => {
+            let writer =
+                TraceWriter::create(path).map_err(|err| format!("{}: {err}", path.display()))?;
+            let runtime = Traced::new(runtime, writer.clone());
+            execute(runtime, pipeline, entry, args, Some(writer)).await
+        }
+        None => execute(runtime, pipeline, entry, args, None).await,
+    }
+}
+
+async fn execute<RUNTIME>(
+    runtime: RUNTIME,
+    pipeline: Pipeline,
+    entry: String,
+    args: &RunArgs,
+    trace: Option<TraceWriter>,
+) -> Result<ExitCode, Box<dyn Error>>
+where
+    RUNTIME: InputExt + ScreenshotExt + TimerExt + Clone + 'static,
+{
+    let screen = runtime.clone();
+    let actions = factories().build(&pipeline)?;
+    let mut registry = ActionRegistry::new();
+    registry.register_all(&actions);
+    let tasks = args
+        .tui
+        .then(|| pipeline.tasks().cloned().collect::<Vec<_>>());
+    let mut builder = ContextBuilder::from_pipeline(runtime, pipeline, &registry)?;
+    if let Some(trace) = trace {
+        builder.tracer(trace);
+    }
+    let context = builder.build();
+    let handler = context.get_handler();
+    for breakpoint in &args.breakpoints {
+        handler.set_breakpoint(breakpoint.task.clone(), breakpoint.stage);
+    }
+    let control = match &args.control {
+        Some(endpoint) => {
+            let listener = Listener::bind(endpoint).await?;
+            eprintln!("control protocol on {}", listener.endpoint());
+            let server =
+                ControlServer::new(handler.clone(), context.task_graph()).screenshots(screen);
+            tokio::spawn(server.clone().serve(listener));
+            Some(server)
+        }
+        None => None,
+    };
+
+    let started = Instant::now();
+    if let Some(tasks) = tasks {
+        let name = args.pipeline.display().to_string();
+        let monitor = Monitor::new(name, &tasks, handler).control(control);
+        let result = monitor::monitor(monitor, context.run(entry)).await?;
+        return Ok(outcome(result, started));
+    }
+    let print = |message: Message| {
+        if let Some(control) = &control {
+            control.publish(&message);
+        }
+        if !args.quiet {
+            let elapsed = started.elapsed().as_secs_f64();
+            match message {
+                Message::TaskMessage(message) => println!("[{elapsed:9.3}s] {message}"),
+                message => println!("[{elapsed:9.3}s] {message:?}"),
+            }
+        }
+    };
+    let mut run = std::pin::pin!(context.run(entry));
+    let result = loop {
+        tokio::select! {
+            biased;
+            _ = tokio::signal::ctrl_c() => {
+                eprintln!("cancelling");
+                let _ = handler.try_cancel();
+            }
+            Ok(message) = handler.recv() => print(message),
+            result = &mut run => break result,
+        }
+    };
+    while let Ok(message) = handler.try_recv() {
+        print(message);
+    }
+    Ok(outcome(result, started))
+}
+
+/// Prints how the run ended and returns its exit status.
+fn outcome(result: Result<TaskResult, TaskError>, started: Instant) -> ExitCode {
+    let elapsed = duration::format(started.elapsed());
+    let code = match result {
+        Ok(TaskResult::NoPendingTask | TaskResult::Success { .. }) => {
+            println!("completed in {elapsed}");
+            return ExitCode::SUCCESS;
+        }
+        Ok(TaskResult::TaskCancelled) => {
+            println!("cancelled after {elapsed}");
+            EXIT_CANCELLED
+        }
+        Err(err) => {
+            println!("failed after {elapsed}: {err}");
+            match err {
+                TaskError::TaskTimeOut { .. } => EXIT_TIMEOUT,
+                TaskError::ActionError { .. } => EXIT_ACTION_FAILED,
+                TaskError::UnknownTask { .. } => EXIT_UNKNOWN_TASK,
+            }
+        }
+    };
+    ExitCode::from(code)
+}
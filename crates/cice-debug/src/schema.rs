@@ -0,0 +1,35 @@
+use std::error::Error;
+use std::fs;
+use std::path::PathBuf;
+
+use cice_core::pipeline::schema::SchemaBuilder;
+
+#[derive(clap::Args)]
+pub struct SchemaArgs {
+    /// Write the schema to this file instead of stdout
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+}
+
+/// Schema of pipeline documents with the config of every action built into this binary.
+pub fn pipeline_schema() -> serde_json::Value {
+    #[allow(unused_mut)]
+    let mut builder = SchemaBuilder::new();
+    #[cfg(feature = "opencv")]
+    builder.action::<cice_action_opencv::TemplateMatchConfig>(
+        cice_action_opencv::TemplateMatchAction::TYPE_NAME,
+    );
+    builder.build().to_value()
+}
+
+pub fn run(args: SchemaArgs) -> Result<(), Box<dyn Error>> {
+    let schema = serde_json::to_string_pretty(&pipeline_schema())?;
+    match args.output {
+        Some(path) => fs::write(&path, schema + "\n")
+            .map_err(|err| format!("{}: {err}", path.display()).into()),
+        None => {
+            println!("{schema}");
+            Ok(())
+        }
+    }
+}
@@ -0,0 +1,325 @@
+use std::error::Error;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use cice_core::trace::{FrameId, Trace, TraceEvent};
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind};
+use ratatui::layout::{Constraint, Layout};
+use ratatui::style::{Color, Style, Stylize};
+use ratatui::text::Line;
+use ratatui::widgets::{Block, List, ListItem, ListState, Paragraph};
+use ratatui::Frame;
+
+#[derive(clap::Args)]
+#[command(
+    after_help = "Keys: up/down or j/k move, PgUp/PgDn move by a page, f jumps to the next \
+                  failure, s saves the frame the selected event saw, q quits."
+)]
+pub struct TraceArgs {
+    /// Trace written by `run --trace`
+    trace: PathBuf,
+    /// Print the events instead of browsing them
+    #[arg(long)]
+    print: bool,
+    /// Save every frame of the trace to this directory instead of browsing them
+    #[arg(long, value_name = "DIR")]
+    frames: Option<PathBuf>,
+}
+
+pub fn run(args: TraceArgs) -> Result<(), Box<dyn Error>> {
+    let trace =
+        Trace::open(&args.trace).map_err(|err| format!("{}: {err}", args.trace.display()))?;
+    if let Some(dir) = &args.frames {
+        std::fs::create_dir_all(dir)?;
+        for (frame, data) in &trace.frames {
+            save_frame(dir, frame, data)?;
+        }
+        println!("saved {} frames to {}", trace.frames.len(), dir.display());
+        return Ok(());
+    }
+    let viewer = Viewer::new(args.trace.display().to_string(), trace);
+    if args.print {
+        for index in 0..viewer.trace.events.len() {
+            println!("{}", viewer.summary(index));
+        }
+        return Ok(());
+    }
+    viewer.browse()
+}
+
+/// Extension of an encoded image, from its first bytes.
+fn extension(data: &[u8]) -> &'static str {
+    match data {
+        [0x89, b'P', b'N', b'G', ..] => "png",
+        [0xff, 0xd8, ..] => "jpg",
+        [b'B', b'M', ..] => "bmp",
+        _ => "bin",
+    }
+}
+
+fn save_frame(dir: &Path, frame: &FrameId, data: &[u8]) -> std::io::Result<PathBuf> {
+    let path = dir.join(format!("{frame}.{}", extension(data)));
+    std::fs::write(&path, data)?;
+    Ok(path)
+}
+
+fn failed(event: &TraceEvent) -> bool {
+    matches!(
+        event,
+        TraceEvent::Recognize { error: Some(_), .. }
+            | TraceEvent::Exec { error: Some(_), .. }
+            | TraceEvent::Finish { error: Some(_), .. }
+    )
+}
+
+struct Viewer {
+    name: String,
+    trace: Trace,
+    /// Time of the first event, which times are shown from
+    origin: Duration,
+    list: ListState,
+    status: String,
+}
+
+impl Viewer {
+    fn new(name: String, trace: Trace) -> Self {
+        let origin = trace.events.first().map_or(Duration::ZERO, TraceEvent::at);
+        let mut list = ListState::default();
+        list.select((!trace.events.is_empty()).then_some(0));
+        Self {
+            name,
+            trace,
+            origin,
+            list,
+            status: String::new(),
+        }
+    }
+
+    fn elapsed(&self, event: &TraceEvent) -> f64 {
+        event.at().saturating_sub(self.origin).as_secs_f64()
+    }
+
+    /// One line about the event at `index`.
+    fn summary(&self, index: usize) -> String {
+        let event = &self.trace.events[index];
+        let what = match event {
+            TraceEvent::Enter { task, .. } => format!("enter {task}"),
+            TraceEvent::Recognize {
+                task,
+                duration,
+                recognized,
+                error,
+                score,
+                ..
+            } => {
+                let outcome = match (recognized, error) {
+                    (true, _) => "recognized".to_string(),
+                    (false, Some(error)) => format!("failed: {error}"),
+                    (false, None) => "not found".to_string(),
+                };
+                let score = score.map_or(String::new(), |score| format!(" score {score:.3}"));
+                format!("recognize {task} {outcome}{score} in {duration:?}")
+            }
+            TraceEvent::Exec {
+                task,
+                duration,
+                error,
+                ..
+            } => match error {
+                Some(error) => format!("exec {task} failed in {duration:?}: {error}"),
+                None => format!("exec {task} in {duration:?}"),
+            },
+            TraceEvent::Screenshot { frame, .. } => format!("screenshot {frame}"),
+            TraceEvent::Finish {
+                cancelled, error, ..
+            } => match (cancelled, error) {
+                (true, _) => "cancelled".to_string(),
+                (false, Some(error)) => format!("failed: {error}"),
+                (false, None) => "completed".to_string(),
+            },
+        };
+        format!("{:9.3}s  {what}", self.elapsed(event))
+    }
+
+    fn selected(&self) -> Option<usize> {
+        self.list.selected()
+    }
+
+    fn next_failure(&mut self) {
+        let from = self.selected().map_or(0, |index| index + 1);
+        match (from..self.trace.events.len()).find(|&index| failed(&self.trace.events[index])) {
+            Some(index) => {
+                self.list.select(Some(index));
+                self.status.clear();
+            }
+            None => self.status = "no failure further on".to_string(),
+        }
+    }
+
+    fn save_selected_frame(&mut self) {
+        let frame = self
+            .selected()
+            .and_then(|index| self.trace.frame_seen_by(index));
+        self.status = match frame.and_then(|frame| Some((frame, self.trace.frames.get(frame)?))) {
+            Some((frame, data)) => match save_frame(Path::new("."), frame, data) {
+                Ok(path) => format!("saved {}", path.display()),
+                Err(err) => format!("can't save the frame: {err}"),
+            },
+            None => "no frame was seen yet".to_string(),
+        };
+    }
+
+    fn draw(&mut self, frame: &mut Frame) {
+        let [header, body, footer] = Layout::vertical([
+            Constraint::Length(1),
+            Constraint::Min(0),
+            Constraint::Length(1),
+        ])
+        .areas(frame.area());
+        let [events, details] =
+            Layout::horizontal([Constraint::Percentage(60), Constraint::Percentage(40)])
+                .areas(body);
+
+        let position = self.selected().map_or(String::new(), |index| {
+            format!("{}/{}", index + 1, self.trace.events.len())
+        });
+        frame.render_widget(
+            Line::from(vec![
+                format!(" {} ", self.name).bold(),
+                format!(" {position} ").into(),
+                format!(" {} frames ", self.trace.frames.len()).into(),
+            ]),
+            header,
+        );
+
+        let items: Vec<ListItem> = (0..self.trace.events.len())
+            .map(|index| {
+                let event = &self.trace.events[index];
+                let style = match event {
+                    _ if failed(event) => Style::new().red(),
+                    TraceEvent::Enter { .. } => Style::new().bold(),
+                    TraceEvent::Recognize {
+                        recognized: true, ..
+                    } => Style::new().green(),
+                    TraceEvent::Screenshot { .. } => Style::new().dark_gray(),
+                    _ => Style::new(),
+                };
+                ListItem::new(self.summary(index)).style(style)
+            })
+            .collect();
+        let list = List::new(items)
+            .block(Block::bordered().title(" Events "))
+            .highlight_style(Style::new().bg(Color::DarkGray));
+        frame.render_stateful_widget(list, events, &mut self.list);
+
+        let lines = match self.selected() {
+            Some(index) => self.details(index),
+            None => vec![Line::from("the trace is empty")],
+        };
+        frame.render_widget(
+            Paragraph::new(lines).block(Block::bordered().title(" Details ")),
+            details,
+        );
+
+        let keys = " j/k move   f next failure   s save frame   q quit   ";
+        frame.render_widget(
+            Line::from(vec![keys.dark_gray(), self.status.clone().yellow()]),
+            footer,
+        );
+    }
+
+    fn details(&self, index: usize) -> Vec<Line<'static>> {
+        let event = &self.trace.events[index];
+        let mut lines = vec![Line::from(format!("at        {:.3}s", self.elapsed(event)))];
+        if let Some(task) = event.task() {
+            lines.push(Line::from(format!("task      {task}")));
+        }
+        match event {
+            TraceEvent::Recognize {
+                duration,
+                recognized,
+                error,
+                score,
+                ..
+            } => {
+                lines.push(Line::from(format!("took      {duration:?}")));
+                lines.push(Line::from(format!("result    {recognized}")));
+                if let Some(score) = score {
+                    lines.push(Line::from(format!("score     {score:.3}")));
+                }
+                if let Some(error) = error {
+                    lines.push(Line::from(format!("error     {error}").red()));
+                }
+            }
+            TraceEvent::Exec {
+                duration, error, ..
+            } => {
+                lines.push(Line::from(format!("took      {duration:?}")));
+                if let Some(error) = error {
+                    lines.push(Line::from(format!("error     {error}").red()));
+                }
+            }
+            TraceEvent::Finish {
+                error: Some(error), ..
+            } => lines.push(Line::from(format!("error     {error}").red())),
+            _ => {}
+        }
+        lines.push(Line::from(""));
+        match self.trace.frame_seen_by(index) {
+            Some(frame) => {
+                let size = self.trace.frames.get(frame).map_or(0, Vec::len);
+                let data = self.trace.frames.get(frame).map_or(&[][..], Vec::as_slice);
+                lines.push(Line::from(format!("frame     {frame}")));
+                lines.push(Line::from(format!(
+                    "          {} bytes of {}",
+                    size,
+                    extension(data)
+                )));
+                // Events since that frame was taken, to tell how stale it was
+                let since = (0..index)
+                    .rev()
+                    .take_while(|&earlier| {
+                        !matches!(
+                            &self.trace.events[earlier],
+                            TraceEvent::Screenshot { frame: taken, .. } if taken == frame
+                        )
+                    })
+                    .count();
+                lines.push(Line::from(format!("          {since} events ago")));
+            }
+            None => lines.push(Line::from("no frame seen")),
+        }
+        lines
+    }
+
+    fn browse(mut self) -> Result<(), Box<dyn Error>> {
+        let mut terminal = ratatui::try_init()?;
+        let outcome = loop {
+            if let Err(err) = terminal.draw(|frame| self.draw(frame)) {
+                break Err(err.into());
+            }
+            let key = match event::read() {
+                Ok(Event::Key(key)) if key.kind == KeyEventKind::Press => key,
+                Ok(_) => continue,
+                Err(err) => break Err(err.into()),
+            };
+            let page = terminal
+                .size()
+                .map_or(10, |size| size.height.saturating_sub(4));
+            match key.code {
+                KeyCode::Char('q') | KeyCode::Esc => break Ok(()),
+                KeyCode::Down | KeyCode::Char('j') => self.list.select_next(),
+                KeyCode::Up | KeyCode::Char('k') => self.list.select_previous(),
+                KeyCode::PageDown => self.list.scroll_down_by(page),
+                KeyCode::PageUp => self.list.scroll_up_by(page),
+                KeyCode::Home | KeyCode::Char('g') => self.list.select_first(),
+                KeyCode::End | KeyCode::Char('G') => self.list.select_last(),
+                KeyCode::Char('f') => self.next_failure(),
+                KeyCode::Char('s') => self.save_selected_frame(),
+                _ => {}
+            }
+        };
+        ratatui::restore();
+        outcome
+    }
+}
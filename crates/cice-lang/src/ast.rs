@@ -0,0 +1,83 @@
+//! Syntax tree of a source file, as written.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::time::Duration;
+
+use crate::diagnostic::Span;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct File {
+    pub tasks: Vec<Task>,
+}
+
+/// `task name { clauses }`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Task {
+    pub name: Ident,
+    pub clauses: Vec<Clause>,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Clause {
+    pub kind: ClauseKind,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ClauseKind {
+    /// `when call`: the action recognizing the task
+    When(Call),
+    /// `do call`: the action executed once recognized
+    Do(Call),
+    /// `action name`: an action registered in Rust instead of `when` and `do`
+    Action(Ident),
+    /// `then [a, b]`: the next tasks
+    Then(Vec<Ident>),
+    /// `interrupt [a, b]`: the interrupt tasks
+    Interrupt(Vec<Ident>),
+    /// `timeout 10s`
+    Timeout(Duration),
+    /// `retry 3`
+    Retry(usize),
+}
+
+/// An inline action such as `template("btn.png", threshold = 0.9)`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Call {
+    /// Name of the action, dots included as in `opencv.template_match`
+    pub name: Ident,
+    pub args: Vec<Arg>,
+    pub span: Span,
+}
+
+/// A positional or `name = value` argument of a [`Call`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct Arg {
+    pub name: Option<Ident>,
+    pub value: Expr,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Expr {
+    pub kind: ExprKind,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ExprKind {
+    Str(String),
+    Int(i64),
+    Float(f64),
+    Bool(bool),
+    /// A bare name such as `match`, resolved when lowering
+    Ident(String),
+    List(Vec<Expr>),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Ident {
+    pub name: String,
+    pub span: Span,
+}
@@ -0,0 +1,88 @@
+//! Errors found in a source file, pointing at the text that caused them.
+
+use alloc::format;
+use alloc::string::String;
+use core::fmt;
+use core::ops::Range;
+
+use cice_core::pipeline::Location;
+
+/// Byte range of a source file.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Span {
+    pub fn new(start: usize, end: usize) -> Self {
+        Self { start, end }
+    }
+
+    /// The smallest span covering both `self` and `other`.
+    pub fn to(self, other: Span) -> Self {
+        Self::new(self.start.min(other.start), self.end.max(other.end))
+    }
+}
+
+impl From<Span> for Range<usize> {
+    fn from(span: Span) -> Self {
+        span.start..span.end
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub span: Span,
+    pub message: String,
+}
+
+impl Diagnostic {
+    pub fn new(span: Span, message: impl Into<String>) -> Self {
+        Self {
+            span,
+            message: message.into(),
+        }
+    }
+
+    /// Line and column where the diagnostic starts in `source`.
+    pub fn location(&self, file: &str, source: &str) -> Location {
+        Location::from_offset(file, source, self.span.start)
+    }
+
+    /// Formats the diagnostic with the line it points at, underlining its span:
+    ///
+    /// ```text
+    /// error: unknown task `hom`
+    ///  --> login.cice:3:11
+    ///   |
+    /// 3 |     then [hom, error]
+    ///   |           ^^^
+    /// ```
+    pub fn render(&self, file: &str, source: &str) -> String {
+        let location = self.location(file, source);
+        let start = self.span.start.min(source.len());
+        let line_start = source[..start].rfind('\n').map_or(0, |newline| newline + 1);
+        let line_end = source[start..]
+            .find('\n')
+            .map_or(source.len(), |newline| start + newline);
+        let line = &source[line_start..line_end];
+        let end = self.span.end.clamp(start, line_end);
+        let underline = source[start..end].chars().count().max(1);
+
+        let number = format!("{}", location.line);
+        let gutter = " ".repeat(number.len());
+        let indent = " ".repeat(source[line_start..start].chars().count());
+        format!(
+            "error: {message}\n{gutter}--> {location}\n{gutter} |\n{number} | {line}\n{gutter} | {indent}{carets}\n",
+            message = self.message,
+            carets = "^".repeat(underline),
+        )
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
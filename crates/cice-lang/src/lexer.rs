@@ -0,0 +1,153 @@
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt;
+
+use crate::diagnostic::{Diagnostic, Span};
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum TokenKind {
+    /// Names and keywords
+    Ident(String),
+    Str(String),
+    /// Digits, with the unit of durations such as `1500ms`
+    Number(String),
+    LBrace,
+    RBrace,
+    LParen,
+    RParen,
+    LBracket,
+    RBracket,
+    Comma,
+    Semi,
+    Eq,
+    Dot,
+    Eof,
+}
+
+impl fmt::Display for TokenKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Ident(name) => write!(f, "`{name}`"),
+            Self::Str(_) => f.write_str("string"),
+            Self::Number(number) => write!(f, "`{number}`"),
+            Self::LBrace => f.write_str("`{`"),
+            Self::RBrace => f.write_str("`}`"),
+            Self::LParen => f.write_str("`(`"),
+            Self::RParen => f.write_str("`)`"),
+            Self::LBracket => f.write_str("`[`"),
+            Self::RBracket => f.write_str("`]`"),
+            Self::Comma => f.write_str("`,`"),
+            Self::Semi => f.write_str("`;`"),
+            Self::Eq => f.write_str("`=`"),
+            Self::Dot => f.write_str("`.`"),
+            Self::Eof => f.write_str("end of file"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Token {
+    pub kind: TokenKind,
+    pub span: Span,
+}
+
+/// Splits `source` into tokens, ending with [`TokenKind::Eof`]. Characters that start no token
+/// are reported and skipped.
+pub(crate) fn tokenize(source: &str, diagnostics: &mut Vec<Diagnostic>) -> Vec<Token> {
+    let mut tokens = Vec::new();
+    let mut chars = source.char_indices().peekable();
+    while let Some((start, c)) = chars.next() {
+        let kind = match c {
+            c if c.is_whitespace() => None,
+            // Comments run to the end of the line
+            '#' => {
+                while chars.next_if(|&(_, c)| c != '\n').is_some() {}
+                None
+            }
+            '/' if chars.next_if(|&(_, c)| c == '/').is_some() => {
+                while chars.next_if(|&(_, c)| c != '\n').is_some() {}
+                None
+            }
+            '{' => Some(TokenKind::LBrace),
+            '}' => Some(TokenKind::RBrace),
+            '(' => Some(TokenKind::LParen),
+            ')' => Some(TokenKind::RParen),
+            '[' => Some(TokenKind::LBracket),
+            ']' => Some(TokenKind::RBracket),
+            ',' => Some(TokenKind::Comma),
+            ';' => Some(TokenKind::Semi),
+            '=' => Some(TokenKind::Eq),
+            '.' => Some(TokenKind::Dot),
+            '"' => Some(string(source, start, &mut chars, diagnostics)),
+            c if c.is_ascii_digit()
+                || (c == '-' && chars.peek().is_some_and(|(_, c)| c.is_ascii_digit())) =>
+            {
+                let mut number = String::from(c);
+                while let Some((_, c)) =
+                    chars.next_if(|&(_, c)| c.is_ascii_alphanumeric() || c == '.' || c == '_')
+                {
+                    number.push(c);
+                }
+                Some(TokenKind::Number(number))
+            }
+            c if c.is_alphabetic() || c == '_' => {
+                let mut name = String::from(c);
+                while let Some((_, c)) = chars.next_if(|&(_, c)| c.is_alphanumeric() || c == '_') {
+                    name.push(c);
+                }
+                Some(TokenKind::Ident(name))
+            }
+            c => {
+                diagnostics.push(Diagnostic::new(
+                    Span::new(start, start + c.len_utf8()),
+                    alloc::format!("unexpected character `{c}`"),
+                ));
+                None
+            }
+        };
+        if let Some(kind) = kind {
+            let end = chars.peek().map_or(source.len(), |&(end, _)| end);
+            tokens.push(Token {
+                kind,
+                span: Span::new(start, end),
+            });
+        }
+    }
+    tokens.push(Token {
+        kind: TokenKind::Eof,
+        span: Span::new(source.len(), source.len()),
+    });
+    tokens
+}
+
+/// Reads the rest of a string literal whose opening quote is at `start`.
+fn string(
+    source: &str,
+    start: usize,
+    chars: &mut core::iter::Peekable<core::str::CharIndices>,
+    diagnostics: &mut Vec<Diagnostic>,
+) -> TokenKind {
+    let mut value = String::new();
+    while let Some((at, c)) = chars.next() {
+        match c {
+            '"' => return TokenKind::Str(value),
+            '\\' => match chars.next() {
+                Some((_, 'n')) => value.push('\n'),
+                Some((_, 't')) => value.push('\t'),
+                Some((_, 'r')) => value.push('\r'),
+                Some((_, c @ ('"' | '\\'))) => value.push(c),
+                Some((_, c)) => diagnostics.push(Diagnostic::new(
+                    Span::new(at, at + 1 + c.len_utf8()),
+                    alloc::format!("unknown escape `\\{c}`"),
+                )),
+                None => break,
+            },
+            c => value.push(c),
+        }
+    }
+    diagnostics.push(Diagnostic::new(
+        Span::new(start, source.len()),
+        "unterminated string",
+    ));
+    TokenKind::Str(value)
+}
@@ -1 +1,65 @@
+//! A small language for writing pipelines, lowered to the [`Pipeline`] of `cice-core`.
+//!
+//! ```text
+//! // Click the login button once it is on screen
+//! task login {
+//!     when template("btn.png", 0.9) do click(match)
+//!     then [home, error]
+//!     interrupt popup
+//!     timeout 10s
+//!     retry 5
+//! }
+//!
+//! task home { action find_home }
+//! task error { action report }
+//! task popup { when template("close.png") do click([1200, 80]) }
+//! ```
+//!
+//! Each task is a list of clauses:
+//!
+//! - `when call` recognizes the task and `do call` executes it, as inline actions whose types
+//!   and positional parameters are declared with [`Lowering::action`], see [`lower`]. Arguments
+//!   are constants, except `match` in `do` which is the location `when` recognized at;
+//! - `action name` uses an action registered in Rust instead;
+//! - `then` and `interrupt` list the next and interrupt tasks, brackets being optional for one;
+//! - `timeout` and `retry` set the timeout and the maximum retries.
+//!
+//! Comments start with `//` or `#`. Errors are reported as [`Diagnostic`]s pointing at the source.
+
 #![no_std]
+extern crate alloc;
+
+pub mod ast;
+pub mod diagnostic;
+mod lexer;
+pub mod lower;
+mod parser;
+
+use alloc::vec::Vec;
+
+use cice_core::pipeline::Pipeline;
+
+pub use diagnostic::{Diagnostic, Span};
+pub use lower::{Lowering, Signature};
+
+/// File extension of sources.
+pub const EXTENSION: &str = "cice";
+
+/// Parses `source` into a syntax tree.
+pub fn parse(source: &str) -> Result<ast::File, Vec<Diagnostic>> {
+    let mut diagnostics = Vec::new();
+    let tokens = lexer::tokenize(source, &mut diagnostics);
+    let file = parser::Parser::new(tokens, &mut diagnostics).file();
+    if diagnostics.is_empty() {
+        Ok(file)
+    } else {
+        // Lexer errors come first otherwise
+        diagnostics.sort_by_key(|diagnostic| diagnostic.span.start);
+        Err(diagnostics)
+    }
+}
+
+/// Parses and lowers `source` into a [`Pipeline`].
+pub fn compile(source: &str, lowering: &Lowering) -> Result<Pipeline, Vec<Diagnostic>> {
+    lowering.lower(&parse(source)?)
+}
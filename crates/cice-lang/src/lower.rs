@@ -0,0 +1,277 @@
+//! Lowering of a syntax tree to the [`Pipeline`] it describes.
+//!
+//! `when` and `do` calls become inline [`ActionConfig`]s, built by the
+//! [`ActionFactories`](cice_core::pipeline::action::ActionFactories) of the application: a task
+//! with only one of them uses that action, and a task with both a
+//! [`COMPOSE_TYPE`] action recognizing with `when` and executing `do`.
+//!
+//! Arguments are constants, except `match` in `do`: the location `when` recognized at, lowered to
+//! the [`Target::MATCH`] target of the input actions.
+
+use alloc::collections::BTreeMap;
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+
+use cice_core::action::input::Target;
+use cice_core::pipeline::action::{ActionConfig, COMPOSE_TYPE, EXEC_KEY, RECOGNIZE_KEY, TYPE_KEY};
+use cice_core::pipeline::template::{ListPatch, TaskTemplate};
+use cice_core::pipeline::{Pipeline, PipelineDocument};
+use serde_json::{Map, Number, Value};
+
+use crate::ast::{Call, ClauseKind, Expr, ExprKind, File, Ident, Task};
+use crate::diagnostic::{Diagnostic, Span};
+
+/// The only name arguments can refer to, in `do` calls.
+const MATCH: &str = "match";
+
+/// How the action written `name(...)` is configured.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Signature {
+    /// Type of the action in the [`ActionConfig`]
+    pub type_name: String,
+    /// Config fields the positional arguments are assigned to, in order
+    pub params: Vec<String>,
+}
+
+/// Lowers syntax trees, knowing the signatures of the actions they call.
+///
+/// Calls to an action without signature keep their name as type and only accept named arguments.
+#[derive(Debug, Clone, Default)]
+pub struct Lowering {
+    signatures: BTreeMap<String, Signature>,
+}
+
+impl Lowering {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Declares the action written `name(...)`, configured as `type_name` with its positional
+    /// arguments assigned to `params`:
+    ///
+    /// ```
+    /// # use cice_lang::Lowering;
+    /// let mut lowering = Lowering::new();
+    /// lowering
+    ///     .action("template", "opencv.template_match", &["template_path", "threshold"])
+    ///     .action("click", "input.click", &["at", "button"]);
+    /// ```
+    pub fn action(&mut self, name: &str, type_name: &str, params: &[&str]) -> &mut Self {
+        self.signatures.insert(
+            name.into(),
+            Signature {
+                type_name: type_name.into(),
+                params: params.iter().map(|param| param.to_string()).collect(),
+            },
+        );
+        self
+    }
+
+    pub fn signature(&self, name: &str) -> Option<&Signature> {
+        self.signatures.get(name)
+    }
+
+    pub fn lower(&self, file: &File) -> Result<Pipeline, Vec<Diagnostic>> {
+        let mut diagnostics = Vec::new();
+        let mut names = BTreeMap::new();
+        for task in &file.tasks {
+            if names
+                .insert(task.name.name.as_str(), task.name.span)
+                .is_some()
+            {
+                diagnostics.push(Diagnostic::new(
+                    task.name.span,
+                    format!("task `{}` is defined twice", task.name.name),
+                ));
+            }
+        }
+
+        let mut document = PipelineDocument::default();
+        for task in &file.tasks {
+            let mut lowering = TaskLowering {
+                lowering: self,
+                names: &names,
+                diagnostics: &mut diagnostics,
+            };
+            let template = lowering.task(task);
+            document.tasks.insert(task.name.name.clone(), template);
+        }
+        if !diagnostics.is_empty() {
+            diagnostics.sort_by_key(|diagnostic| diagnostic.span.start);
+            return Err(diagnostics);
+        }
+        // Everything the conversion checks was reported above
+        Pipeline::try_from(document)
+            .map_err(|err| alloc::vec![Diagnostic::new(Span::default(), err.to_string())])
+    }
+}
+
+struct TaskLowering<'l> {
+    lowering: &'l Lowering,
+    names: &'l BTreeMap<&'l str, Span>,
+    diagnostics: &'l mut Vec<Diagnostic>,
+}
+
+impl TaskLowering<'_> {
+    fn task(&mut self, task: &Task) -> TaskTemplate {
+        let mut template = TaskTemplate::default();
+        let mut seen = BTreeMap::new();
+        let (mut when, mut exec, mut action_name) = (None, None, None);
+        for clause in &task.clauses {
+            let keyword = match &clause.kind {
+                ClauseKind::When(_) => "when",
+                ClauseKind::Do(_) => "do",
+                ClauseKind::Action(_) => "action",
+                ClauseKind::Then(_) => "then",
+                ClauseKind::Interrupt(_) => "interrupt",
+                ClauseKind::Timeout(_) => "timeout",
+                ClauseKind::Retry(_) => "retry",
+            };
+            if seen.insert(keyword, clause.span).is_some() {
+                self.error(clause.span, format!("`{keyword}` is set twice"));
+                continue;
+            }
+            match &clause.kind {
+                ClauseKind::When(call) => when = Some(self.call(call, false)),
+                ClauseKind::Do(call) => exec = Some(self.call(call, true)),
+                ClauseKind::Action(name) => action_name = Some(name.name.clone()),
+                ClauseKind::Then(targets) => {
+                    template.next_task = Some(ListPatch::Replace(self.targets(targets)));
+                }
+                ClauseKind::Interrupt(targets) => {
+                    template.interrupt_task = Some(ListPatch::Replace(self.targets(targets)));
+                }
+                ClauseKind::Timeout(timeout) => template.timeout = Some(*timeout),
+                ClauseKind::Retry(retry) => template.max_retry = Some(*retry),
+            }
+        }
+
+        let inline = seen.contains_key("when") || seen.contains_key("do");
+        if let Some(span) = seen.get("action").filter(|_| inline) {
+            self.error(*span, "`action` can't be combined with `when` or `do`");
+        } else if !inline && action_name.is_none() {
+            self.error(
+                task.name.span,
+                format!(
+                    "task `{}` has no action, add `when`, `do` or `action`",
+                    task.name.name
+                ),
+            );
+        }
+        template.action_name = action_name;
+        template.action = match (when, exec) {
+            (Some(when), Some(exec)) => {
+                let mut config = Map::new();
+                config.insert(RECOGNIZE_KEY.into(), action_value(when));
+                config.insert(EXEC_KEY.into(), action_value(exec));
+                Some(ActionConfig {
+                    type_name: COMPOSE_TYPE.into(),
+                    config,
+                })
+            }
+            (when, exec) => when.or(exec),
+        };
+        template
+    }
+
+    /// Lowers a `when` call, or a `do` call if `matched`, where `match` refers to the recognition.
+    fn call(&mut self, call: &Call, matched: bool) -> ActionConfig {
+        let signature = self.lowering.signature(&call.name.name);
+        let mut config = Map::new();
+        let mut positional = 0;
+        for arg in &call.args {
+            let name = match (&arg.name, signature) {
+                (Some(name), _) => name.name.clone(),
+                (None, Some(signature)) if positional < signature.params.len() => {
+                    positional += 1;
+                    signature.params[positional - 1].clone()
+                }
+                (None, Some(signature)) => {
+                    self.error(
+                        arg.value.span,
+                        format!(
+                            "`{}` takes {} positional arguments",
+                            call.name.name,
+                            signature.params.len()
+                        ),
+                    );
+                    continue;
+                }
+                (None, None) => {
+                    self.error(
+                        arg.value.span,
+                        format!(
+                            "`{}` has no declared parameters, name the argument as in `name = value`",
+                            call.name.name
+                        ),
+                    );
+                    continue;
+                }
+            };
+            if config.contains_key(&name) {
+                let span = arg.name.as_ref().map_or(arg.value.span, |name| name.span);
+                self.error(span, format!("argument `{name}` is set twice"));
+                continue;
+            }
+            if let Some(value) = self.value(&arg.value, matched) {
+                config.insert(name, value);
+            }
+        }
+        let type_name = signature.map_or(&call.name.name, |signature| &signature.type_name);
+        ActionConfig {
+            type_name: type_name.clone(),
+            config,
+        }
+    }
+
+    fn targets(&mut self, targets: &[Ident]) -> Vec<String> {
+        for target in targets {
+            if !self.names.contains_key(target.name.as_str()) {
+                self.error(target.span, format!("unknown task `{}`", target.name));
+            }
+        }
+        targets.iter().map(|target| target.name.clone()).collect()
+    }
+
+    fn value(&mut self, expr: &Expr, matched: bool) -> Option<Value> {
+        Some(match &expr.kind {
+            ExprKind::Str(value) => Value::String(value.clone()),
+            ExprKind::Ident(name) if name == MATCH && matched => {
+                Value::String(Target::MATCH.into())
+            }
+            ExprKind::Ident(name) if name == MATCH => {
+                self.error(
+                    expr.span,
+                    "`match` is where `when` recognized, it can only be used in `do`",
+                );
+                return None;
+            }
+            ExprKind::Ident(name) => {
+                self.error(
+                    expr.span,
+                    format!("unknown name `{name}`, quote it to pass a string"),
+                );
+                return None;
+            }
+            ExprKind::Int(value) => Value::Number((*value).into()),
+            // The parser only accepts finite numbers
+            ExprKind::Float(value) => Number::from_f64(*value).map_or(Value::Null, Value::Number),
+            ExprKind::Bool(value) => Value::Bool(*value),
+            ExprKind::List(items) => {
+                let items: Vec<_> = items.iter().map(|item| self.value(item, matched)).collect();
+                Value::Array(items.into_iter().collect::<Option<_>>()?)
+            }
+        })
+    }
+
+    fn error(&mut self, span: Span, message: impl Into<String>) {
+        self.diagnostics.push(Diagnostic::new(span, message));
+    }
+}
+
+fn action_value(action: ActionConfig) -> Value {
+    let mut config = action.config;
+    config.insert(TYPE_KEY.into(), Value::String(action.type_name));
+    Value::Object(config)
+}
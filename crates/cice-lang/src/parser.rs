@@ -0,0 +1,330 @@
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use cice_core::task::duration;
+
+use crate::ast::{Arg, Call, Clause, ClauseKind, Expr, ExprKind, File, Ident, Task};
+use crate::diagnostic::{Diagnostic, Span};
+use crate::lexer::{Token, TokenKind};
+
+/// Words that start a task or a clause, which can't name tasks.
+const KEYWORDS: [&str; 10] = [
+    "task",
+    "when",
+    "do",
+    "action",
+    "then",
+    "interrupt",
+    "timeout",
+    "retry",
+    "true",
+    "false",
+];
+
+/// Marks a parse error already pushed to the diagnostics, see [`Parser::recover`].
+struct Error;
+
+type Result<T> = core::result::Result<T, Error>;
+
+pub(crate) struct Parser<'d> {
+    tokens: Vec<Token>,
+    position: usize,
+    diagnostics: &'d mut Vec<Diagnostic>,
+}
+
+impl<'d> Parser<'d> {
+    pub(crate) fn new(tokens: Vec<Token>, diagnostics: &'d mut Vec<Diagnostic>) -> Self {
+        Self {
+            tokens,
+            position: 0,
+            diagnostics,
+        }
+    }
+
+    pub(crate) fn file(&mut self) -> File {
+        let mut tasks = Vec::new();
+        while self.peek() != &TokenKind::Eof {
+            match self.task() {
+                Ok(task) => tasks.push(task),
+                Err(Error) => self.recover(),
+            }
+        }
+        File { tasks }
+    }
+
+    /// Skips to the next task after an error, so several errors are reported at once.
+    fn recover(&mut self) {
+        let mut depth = 0usize;
+        loop {
+            match self.peek() {
+                TokenKind::Eof => return,
+                TokenKind::Ident(name) if name == "task" && depth == 0 => return,
+                TokenKind::LBrace => depth += 1,
+                TokenKind::RBrace if depth <= 1 => {
+                    self.bump();
+                    return;
+                }
+                TokenKind::RBrace => depth -= 1,
+                _ => {}
+            }
+            self.bump();
+        }
+    }
+
+    fn task(&mut self) -> Result<Task> {
+        let start = self.expect_keyword("task")?;
+        let name = self.name("task name")?;
+        self.expect(TokenKind::LBrace)?;
+        let mut clauses = Vec::new();
+        loop {
+            match self.peek() {
+                TokenKind::RBrace => break,
+                // Leave the next task to be parsed after reporting this one
+                TokenKind::Ident(keyword) if keyword == "task" => {
+                    let span = self.tokens[self.position].span;
+                    return self
+                        .error(span, format!("expected `}}` to close task `{}`", name.name));
+                }
+                TokenKind::Eof => {
+                    let span = self.tokens[self.position].span;
+                    return self
+                        .error(span, format!("expected `}}` to close task `{}`", name.name));
+                }
+                TokenKind::Semi => {
+                    self.bump();
+                }
+                _ => clauses.push(self.clause()?),
+            }
+        }
+        let end = self.bump().span;
+        Ok(Task {
+            name,
+            clauses,
+            span: start.to(end),
+        })
+    }
+
+    fn clause(&mut self) -> Result<Clause> {
+        let token = self.bump();
+        let keyword = match &token.kind {
+            TokenKind::Ident(keyword) => keyword.as_str(),
+            _ => "",
+        };
+        let kind = match keyword {
+            "when" => ClauseKind::When(self.call()?),
+            "do" => ClauseKind::Do(self.call()?),
+            "action" => ClauseKind::Action(self.name("action name")?),
+            "then" => ClauseKind::Then(self.targets()?),
+            "interrupt" => ClauseKind::Interrupt(self.targets()?),
+            "timeout" => ClauseKind::Timeout(self.duration()?),
+            "retry" => ClauseKind::Retry(self.count()?),
+            _ => {
+                return self.error(
+                    token.span,
+                    format!(
+                        "expected `when`, `do`, `action`, `then`, `interrupt`, `timeout`, `retry` or `}}`, found {}",
+                        token.kind
+                    ),
+                )
+            }
+        };
+        let end = self.tokens[self.position - 1].span;
+        Ok(Clause {
+            kind,
+            span: token.span.to(end),
+        })
+    }
+
+    /// `name(args)`, the parentheses being optional without arguments.
+    fn call(&mut self) -> Result<Call> {
+        let mut name = self.name("action")?;
+        while self.peek() == &TokenKind::Dot {
+            self.bump();
+            let part = self.name("action")?;
+            name.name.push('.');
+            name.name.push_str(&part.name);
+            name.span = name.span.to(part.span);
+        }
+        let mut span = name.span;
+        let mut args = Vec::new();
+        if self.peek() == &TokenKind::LParen {
+            self.bump();
+            let end = self.list(TokenKind::RParen, |parser| {
+                let arg_name = match (parser.peek(), parser.peek_at(1)) {
+                    (TokenKind::Ident(_), TokenKind::Eq) => {
+                        let arg_name = parser.name("argument name")?;
+                        parser.bump();
+                        Some(arg_name)
+                    }
+                    _ => None,
+                };
+                args.push(Arg {
+                    name: arg_name,
+                    value: parser.expr()?,
+                });
+                Ok(())
+            })?;
+            span = span.to(end);
+        }
+        Ok(Call { name, args, span })
+    }
+
+    fn expr(&mut self) -> Result<Expr> {
+        let token = self.bump();
+        let kind = match token.kind {
+            TokenKind::Str(value) => ExprKind::Str(value),
+            TokenKind::Ident(name) if name == "true" => ExprKind::Bool(true),
+            TokenKind::Ident(name) if name == "false" => ExprKind::Bool(false),
+            TokenKind::Ident(name) => ExprKind::Ident(name),
+            TokenKind::Number(number) => {
+                if let Ok(value) = number.parse() {
+                    ExprKind::Int(value)
+                } else if let Some(value) =
+                    number.parse().ok().filter(|value: &f64| value.is_finite())
+                {
+                    ExprKind::Float(value)
+                } else {
+                    return self.error(token.span, format!("invalid number `{number}`"));
+                }
+            }
+            TokenKind::LBracket => {
+                let mut items = Vec::new();
+                let end = self.list(TokenKind::RBracket, |parser| {
+                    items.push(parser.expr()?);
+                    Ok(())
+                })?;
+                return Ok(Expr {
+                    kind: ExprKind::List(items),
+                    span: token.span.to(end),
+                });
+            }
+            kind => return self.error(token.span, format!("expected a value, found {kind}")),
+        };
+        Ok(Expr {
+            kind,
+            span: token.span,
+        })
+    }
+
+    /// `[a, b]`, or a single task name.
+    fn targets(&mut self) -> Result<Vec<Ident>> {
+        if self.peek() != &TokenKind::LBracket {
+            return Ok(alloc::vec![self.name("task name")?]);
+        }
+        self.bump();
+        let mut targets = Vec::new();
+        self.list(TokenKind::RBracket, |parser| {
+            targets.push(parser.name("task name")?);
+            Ok(())
+        })?;
+        Ok(targets)
+    }
+
+    fn duration(&mut self) -> Result<core::time::Duration> {
+        let token = self.bump();
+        let text = match &token.kind {
+            TokenKind::Number(text) | TokenKind::Str(text) => text,
+            kind => {
+                return self.error(
+                    token.span,
+                    format!("expected a duration such as `10s`, found {kind}"),
+                )
+            }
+        };
+        duration::parse(text).or_else(|message| self.error(token.span, message))
+    }
+
+    fn count(&mut self) -> Result<usize> {
+        let token = self.bump();
+        match &token.kind {
+            TokenKind::Number(number) => number
+                .parse()
+                .or_else(|_| self.error(token.span, format!("invalid count `{number}`"))),
+            kind => self.error(token.span, format!("expected a count, found {kind}")),
+        }
+    }
+
+    /// Items separated by commas up to `close`, a trailing comma allowed. Returns the span of
+    /// `close`.
+    fn list(
+        &mut self,
+        close: TokenKind,
+        mut item: impl FnMut(&mut Self) -> Result<()>,
+    ) -> Result<Span> {
+        loop {
+            if self.peek() == &close {
+                return Ok(self.bump().span);
+            }
+            item(self)?;
+            match self.peek() {
+                TokenKind::Comma => {
+                    self.bump();
+                }
+                kind if kind == &close => {}
+                _ => {
+                    let token = self.bump();
+                    return self.error(
+                        token.span,
+                        format!("expected `,` or {close}, found {}", token.kind),
+                    );
+                }
+            }
+        }
+    }
+
+    /// A name which is not a keyword; `what` describes it in errors.
+    fn name(&mut self, what: &str) -> Result<Ident> {
+        let token = self.bump();
+        match token.kind {
+            TokenKind::Ident(name) if !KEYWORDS.contains(&name.as_str()) => Ok(Ident {
+                name,
+                span: token.span,
+            }),
+            kind => self.error(token.span, format!("expected {what}, found {kind}")),
+        }
+    }
+
+    fn expect_keyword(&mut self, keyword: &str) -> Result<Span> {
+        let token = self.bump();
+        match &token.kind {
+            TokenKind::Ident(name) if name == keyword => Ok(token.span),
+            kind => self.error(token.span, format!("expected `{keyword}`, found {kind}")),
+        }
+    }
+
+    fn expect(&mut self, expected: TokenKind) -> Result<Span> {
+        let token = self.bump();
+        if token.kind == expected {
+            Ok(token.span)
+        } else {
+            self.error(
+                token.span,
+                format!("expected {expected}, found {}", token.kind),
+            )
+        }
+    }
+
+    fn error<T>(&mut self, span: Span, message: String) -> Result<T> {
+        self.diagnostics.push(Diagnostic::new(span, message));
+        Err(Error)
+    }
+
+    fn peek(&self) -> &TokenKind {
+        self.peek_at(0)
+    }
+
+    fn peek_at(&self, offset: usize) -> &TokenKind {
+        let index = (self.position + offset).min(self.tokens.len() - 1);
+        &self.tokens[index].kind
+    }
+
+    /// Moves to the next token, staying on the final [`TokenKind::Eof`].
+    fn bump(&mut self) -> Token {
+        let token = self.tokens[self.position].clone();
+        if self.position < self.tokens.len() - 1 {
+            self.position += 1;
+        }
+        token
+    }
+}
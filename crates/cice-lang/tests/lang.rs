@@ -0,0 +1,222 @@
+use std::time::Duration;
+
+use cice_core::action::input::{Click, Target};
+use cice_core::context::ContextBuilder;
+use cice_core::pipeline::action::{ActionConfig, ActionFactories};
+use cice_core::task::TaskResult;
+use cice_lang::{ast, Diagnostic, Lowering};
+use cice_tests_common::action::{Outcome, ScriptedAction, TestRuntime};
+use cice_tests_common::harness;
+use serde::Deserialize;
+use serde_json::json;
+
+const LOGIN: &str = r#"
+// Click the login button once it is on screen
+task login {
+    when template("btn.png", 0.9) do click(match)
+    then [home, error]
+    interrupt popup
+    timeout 10s
+    retry 5
+}
+
+task home { action find_home }
+task error { action report; }
+task popup { when template("close.png", threshold = 0.8) do click([1200, 80], button = 3) }
+"#;
+
+fn lowering() -> Lowering {
+    let mut lowering = Lowering::new();
+    lowering
+        .action(
+            "template",
+            "opencv.template_match",
+            &["template_path", "threshold"],
+        )
+        .action("click", "input.click", &["at", "button"]);
+    lowering
+}
+
+fn errors(source: &str) -> Vec<(String, String)> {
+    let diagnostics = cice_lang::compile(source, &lowering()).unwrap_err();
+    diagnostics
+        .iter()
+        .map(|diagnostic| {
+            let text = &source[diagnostic.span.start..diagnostic.span.end];
+            (diagnostic.message.clone(), text.to_string())
+        })
+        .collect()
+}
+
+#[test]
+fn tasks_are_lowered_to_configs() {
+    let pipeline = cice_lang::compile(LOGIN, &lowering()).unwrap();
+
+    let login = pipeline.get("login").unwrap();
+    assert_eq!(login.action_name, "$inline::login");
+    assert_eq!(login.next_task, ["home", "error"]);
+    assert_eq!(login.interrupt_task, ["popup"]);
+    assert_eq!(login.timeout, Duration::from_secs(10));
+    assert_eq!(login.max_retry, 5);
+    assert_eq!(pipeline.get("home").unwrap().action_name, "find_home");
+
+    let actions: Vec<_> = pipeline
+        .actions()
+        .map(|(id, action)| (id.as_str(), serde_json::to_value(action).unwrap()))
+        .collect();
+    assert_eq!(
+        actions,
+        [
+            (
+                "$inline::login",
+                json!({
+                    "type": "compose",
+                    "recognize": {
+                        "type": "opencv.template_match",
+                        "template_path": "btn.png",
+                        "threshold": 0.9
+                    },
+                    "exec": { "type": "input.click", "at": "match" }
+                })
+            ),
+            (
+                "$inline::popup",
+                json!({
+                    "type": "compose",
+                    "recognize": {
+                        "type": "opencv.template_match",
+                        "template_path": "close.png",
+                        "threshold": 0.8
+                    },
+                    "exec": { "type": "input.click", "at": [1200, 80], "button": 3 }
+                })
+            ),
+        ]
+    );
+    // `match` is the target the input actions click the recognized location at
+    let exec: ActionConfig = serde_json::from_value(actions[0].1["exec"].clone()).unwrap();
+    let click: Click = serde_json::from_value(exec.config.into()).unwrap();
+    assert_eq!(click.at, Target::Match);
+}
+
+#[test]
+fn syntax_errors_are_all_reported() {
+    let source = "task a { when }\ntask 2 {}\ntask b { then [a b] }\ntask c { retry 1 ";
+    assert_eq!(
+        errors(source),
+        [
+            ("expected action, found `}`".to_string(), "}".to_string()),
+            ("expected task name, found `2`".into(), "2".into()),
+            ("expected `,` or `]`, found `b`".into(), "b".into()),
+            ("expected `}` to close task `c`".into(), "".into()),
+        ]
+    );
+
+    let file = cice_lang::parse("task a { timeout \"1m 30s\" action b }").unwrap();
+    assert_eq!(
+        file.tasks[0].clauses[0].kind,
+        ast::ClauseKind::Timeout(Duration::from_secs(90))
+    );
+    assert_eq!(
+        errors("task a { timeout 10 action b }"),
+        [(
+            "invalid duration \"10\", expected e.g. \"1500ms\" or \"30s\"".to_string(),
+            "10".to_string()
+        )]
+    );
+}
+
+#[test]
+fn semantic_errors_point_at_the_source() {
+    let source = r#"
+task login {
+    when template("btn.png", 0.9, 3)
+    action login
+    then [hom]
+}
+task login { then login; then login }
+task idle { when swipe(1) }
+task tap { when template(match) do click(left) }
+"#;
+    assert_eq!(
+        errors(source),
+        [
+            (
+                "`template` takes 2 positional arguments".to_string(),
+                "3".to_string()
+            ),
+            (
+                "`action` can't be combined with `when` or `do`".into(),
+                "action login".into()
+            ),
+            ("unknown task `hom`".into(), "hom".into()),
+            ("task `login` is defined twice".into(), "login".into()),
+            (
+                "task `login` has no action, add `when`, `do` or `action`".into(),
+                "login".into()
+            ),
+            ("`then` is set twice".into(), "then login".into()),
+            (
+                "`swipe` has no declared parameters, name the argument as in `name = value`".into(),
+                "1".into()
+            ),
+            (
+                "`match` is where `when` recognized, it can only be used in `do`".into(),
+                "match".into()
+            ),
+            (
+                "unknown name `left`, quote it to pass a string".into(),
+                "left".into()
+            ),
+        ]
+    );
+}
+
+#[test]
+fn diagnostics_render_the_source_line() {
+    let source = "task login {\n    action a\n    then [hom]\n}\n";
+    let diagnostics = cice_lang::compile(source, &lowering()).unwrap_err();
+    let diagnostic: &Diagnostic = &diagnostics[0];
+    assert_eq!(
+        diagnostic.location("login.cice", source).to_string(),
+        "login.cice:3:11"
+    );
+    assert_eq!(
+        diagnostic.render("login.cice", source),
+        "error: unknown task `hom`\n --> login.cice:3:11\n  |\n3 |     then [hom]\n  |           ^^^\n"
+    );
+}
+
+#[derive(Deserialize)]
+struct ScriptedConfig {
+    after_ms: u64,
+}
+
+#[test]
+fn compiled_pipelines_run() {
+    let source = r#"
+task entry { do scripted(0) then popup }
+task popup { when scripted(700) do scripted(0) }
+"#;
+    let mut lowering = Lowering::new();
+    lowering.action("scripted", "test.scripted", &["after_ms"]);
+    let pipeline = cice_lang::compile(source, &lowering).unwrap();
+
+    let mut factories = ActionFactories::new();
+    factories.register("test.scripted", |id, config: ScriptedConfig| {
+        ScriptedAction::new(id.clone())
+            .at(Duration::ZERO, Outcome::UnRecognized)
+            .at(Duration::from_millis(config.after_ms), Outcome::Recognized)
+    });
+    let actions = factories.build(&pipeline).unwrap();
+    let runtime = TestRuntime::new();
+    let clock = runtime.clock().clone();
+    let context = ContextBuilder::from_pipeline(runtime, pipeline, &actions.registry())
+        .unwrap()
+        .build();
+
+    let run = harness::run(&context, &clock, "entry");
+    assert!(matches!(run.result, Ok(TaskResult::NoPendingTask)));
+    assert_eq!(run.entered(), vec!["entry", "popup"]);
+    assert_eq!(run.elapsed, Duration::from_secs(1));
+}
@@ -0,0 +1,459 @@
+//! 回放录制画面的离线 Runtime，无需连接任何 VNC 服务器即可运行 Pipeline。
+
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use async_trait::async_trait;
+use cice_core::runtime::executor::{Sleeper, TokioExecutor};
+use cice_core::runtime::ext::{ExecutorExt, InputError, InputExt, ScreenshotExt};
+use cice_core::runtime::Runtime;
+use cice_core::trace::{FrameId, Trace, TraceError, TraceEvent};
+use snafu::Snafu;
+
+/// 视为画面的图片扩展名
+pub const IMAGE_EXTENSIONS: [&str; 4] = ["png", "jpg", "jpeg", "bmp"];
+
+/// 一帧录制的画面
+#[derive(Debug, Clone)]
+pub struct Frame {
+    /// 画面来源，用于日志
+    pub name: String,
+    /// 编码后的图片，即 `ScreenshotExt::screenshot` 返回的数据
+    pub data: Vec<u8>,
+}
+
+/// 回放收到的一次输入
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Input {
+    Click { x: u32, y: u32, button: u8 },
+    MoveMouse { x: u32, y: u32 },
+    TypeText(String),
+    PressKey(u32),
+}
+
+/// 记录下的一次输入
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InputRecord {
+    /// 收到输入时显示的画面序号
+    pub frame: usize,
+    pub input: Input,
+}
+
+/// 画面前进的方式
+#[derive(Debug, Clone)]
+enum Advance {
+    /// 每隔固定时间前进一帧
+    Interval(Duration),
+    /// 每帧从录制时的时刻起显示，与 `frames` 一一对应
+    Recorded(Arc<Vec<Duration>>),
+    /// 每次点击、输入文本或按键后前进一帧
+    Input,
+}
+
+/// 回放 Runtime，依次提供录制的画面
+///
+/// 默认画面从 Runtime 创建起每隔 `frame_interval` 前进一帧；从 trace 回放时按录制时的时刻前进；
+/// 也可以用 [`ReplayRuntime::advance_on_input`] 改为每次输入后前进一帧。画面停留在最后一帧。
+///
+/// 回放不会真正执行输入：[`ReplayRuntime::click`] 等方法与 `VncRuntime` 的同名方法对应，
+/// 只记录并打印收到的输入，可以用 [`ReplayRuntime::inputs`] 检查 Pipeline 做了什么。
+/// Action 通过 [`InputExt`] 调用这些方法。
+///
+/// ```rust,no_run
+/// use cice_runtime_replay::ReplayRuntime;
+/// use std::time::Duration;
+///
+/// let runtime = ReplayRuntime::from_dir("captures/login")
+///     .unwrap()
+///     .frame_interval(Duration::from_millis(500));
+/// ```
+#[derive(Clone)]
+pub struct ReplayRuntime {
+    frames: Arc<Vec<Frame>>,
+    advance: Advance,
+    /// 收到的输入，所有克隆共享
+    inputs: Arc<Mutex<Vec<InputRecord>>>,
+    /// 异步执行器，调度器通过它计时，回放也按它的时间前进
+    executor: TokioExecutor,
+}
+
+impl ReplayRuntime {
+    /// 默认每帧停留的时间
+    pub const DEFAULT_FRAME_INTERVAL: Duration = Duration::from_secs(1);
+
+    /// 回放给定的画面
+    ///
+    /// # Panics
+    /// `frames` 为空时 panic。
+    pub fn new(frames: Vec<Frame>) -> Self {
+        assert!(!frames.is_empty(), "a replay needs at least one frame");
+        Self {
+            frames: Arc::new(frames),
+            advance: Advance::Interval(Self::DEFAULT_FRAME_INTERVAL),
+            inputs: Arc::default(),
+            executor: TokioExecutor::new(),
+        }
+    }
+
+    /// 回放目录中带编号的图片，如 `0001.png`、`frame_2.jpg`
+    ///
+    /// 图片按文件名中的第一个数字排序，没有数字的排在最后；其他文件被忽略。
+    pub fn from_dir(dir: impl AsRef<Path>) -> Result<Self, ReplayError> {
+        let dir = dir.as_ref();
+        let io_error = |path: &Path| {
+            let path = path.to_path_buf();
+            move |source| ReplayError::Io { path, source }
+        };
+        let mut paths = Vec::new();
+        for entry in std::fs::read_dir(dir).map_err(io_error(dir))? {
+            let path = entry.map_err(io_error(dir))?.path();
+            let is_image = path
+                .extension()
+                .and_then(|extension| extension.to_str())
+                .is_some_and(|extension| {
+                    IMAGE_EXTENSIONS.contains(&extension.to_ascii_lowercase().as_str())
+                });
+            if is_image && path.is_file() {
+                paths.push(path);
+            }
+        }
+        if paths.is_empty() {
+            return Err(ReplayError::NoFrames {
+                path: dir.to_path_buf(),
+            });
+        }
+        paths.sort_by_cached_key(|path| {
+            let name = path.file_name().unwrap_or_default().to_string_lossy();
+            (frame_number(&name).unwrap_or(u64::MAX), name.into_owned())
+        });
+        let frames = paths
+            .iter()
+            .map(|path| {
+                Ok(Frame {
+                    name: path.display().to_string(),
+                    data: std::fs::read(path).map_err(io_error(path))?,
+                })
+            })
+            .collect::<Result<_, ReplayError>>()?;
+        Ok(Self::new(frames))
+    }
+
+    /// 回放 `cice-debug run --trace` 等写下的 trace 中的截图
+    ///
+    /// 画面按截图的顺序回放，连续相同的截图只保留一帧；每帧从录制时相对第一张截图的时刻起显示。
+    pub fn from_trace(path: impl AsRef<Path>) -> Result<Self, ReplayError> {
+        let path = path.as_ref();
+        let trace = Trace::open(path).map_err(|source| ReplayError::Trace {
+            path: path.to_path_buf(),
+            source,
+        })?;
+        let mut frames = Vec::new();
+        let mut times = Vec::new();
+        let mut last: Option<&FrameId> = None;
+        for event in &trace.events {
+            let TraceEvent::Screenshot { at, frame } = event else {
+                continue;
+            };
+            if last == Some(frame) {
+                continue;
+            }
+            let data = trace
+                .frames
+                .get(frame)
+                .ok_or_else(|| ReplayError::MissingFrame {
+                    path: path.to_path_buf(),
+                    frame: frame.clone(),
+                })?;
+            frames.push(Frame {
+                name: format!("{}#{frame}", path.display()),
+                data: data.clone(),
+            });
+            times.push(*at);
+            last = Some(frame);
+        }
+        let Some(&origin) = times.first() else {
+            return Err(ReplayError::NoFrames {
+                path: path.to_path_buf(),
+            });
+        };
+        let times = times.into_iter().map(|at| at - origin).collect();
+        let mut runtime = Self::new(frames);
+        runtime.advance = Advance::Recorded(Arc::new(times));
+        Ok(runtime)
+    }
+
+    /// 每帧停留的时间
+    pub fn frame_interval(mut self, interval: Duration) -> Self {
+        self.advance = Advance::Interval(interval);
+        self
+    }
+
+    /// 不随时间前进，每次点击、输入文本或按键后前进一帧；移动鼠标不前进
+    pub fn advance_on_input(mut self) -> Self {
+        self.advance = Advance::Input;
+        self
+    }
+
+    pub fn frames(&self) -> &[Frame] {
+        &self.frames
+    }
+
+    /// 当前画面的序号
+    pub fn current_frame(&self) -> usize {
+        let index = match &self.advance {
+            Advance::Interval(interval) => {
+                let index = self
+                    .executor
+                    .now()
+                    .as_nanos()
+                    .checked_div(interval.as_nanos())
+                    .unwrap_or(0);
+                usize::try_from(index).unwrap_or(usize::MAX)
+            }
+            Advance::Recorded(times) => {
+                let now = self.executor.now();
+                times.partition_point(|at| *at <= now).saturating_sub(1)
+            }
+            Advance::Input => self
+                .lock_inputs()
+                .iter()
+                .filter(|record| !matches!(record.input, Input::MoveMouse { .. }))
+                .count(),
+        };
+        index.min(self.frames.len() - 1)
+    }
+
+    /// 至今收到的输入
+    pub fn inputs(&self) -> Vec<InputRecord> {
+        self.lock_inputs().clone()
+    }
+
+    fn lock_inputs(&self) -> std::sync::MutexGuard<'_, Vec<InputRecord>> {
+        self.inputs.lock().unwrap_or_else(|err| err.into_inner())
+    }
+
+    fn input(&self, input: Input) {
+        let frame = self.current_frame();
+        log::info!(
+            "replay input {input:?} on frame {}",
+            self.frames[frame].name
+        );
+        self.lock_inputs().push(InputRecord { frame, input });
+    }
+
+    /// 点击屏幕指定位置，button 与 `VncRuntime::click` 相同（1=左键, 2=中键, 3=右键）
+    pub async fn click(&self, x: u32, y: u32, button: u8) {
+        self.input(Input::Click { x, y, button });
+    }
+
+    /// 移动鼠标到指定位置
+    pub async fn move_mouse(&self, x: u32, y: u32) {
+        self.input(Input::MoveMouse { x, y });
+    }
+
+    /// 输入文本
+    pub async fn type_text(&self, text: &str) {
+        self.input(Input::TypeText(text.to_string()));
+    }
+
+    /// 按下键盘按键
+    pub async fn press_key(&self, key: u32) {
+        self.input(Input::PressKey(key));
+    }
+}
+
+/// 文件名中的第一个数字
+fn frame_number(name: &str) -> Option<u64> {
+    let start = name.find(|c: char| c.is_ascii_digit())?;
+    let digits = &name[start..];
+    let end = digits
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(digits.len());
+    digits[..end].parse().ok()
+}
+
+impl Runtime for ReplayRuntime {}
+
+#[async_trait]
+impl ScreenshotExt for ReplayRuntime {
+    async fn screenshot(&self) -> Option<Vec<u8>> {
+        let frame = &self.frames[self.current_frame()];
+        log::debug!("replay frame {}", frame.name);
+        Some(frame.data.clone())
+    }
+}
+
+/// 回放的输入只被记录，总是成功
+#[async_trait]
+impl InputExt for ReplayRuntime {
+    async fn click(&self, x: u32, y: u32, button: u8) -> Result<(), InputError> {
+        ReplayRuntime::click(self, x, y, button).await;
+        Ok(())
+    }
+
+    async fn move_mouse(&self, x: u32, y: u32) -> Result<(), InputError> {
+        ReplayRuntime::move_mouse(self, x, y).await;
+        Ok(())
+    }
+
+    async fn type_text(&self, text: &str) -> Result<(), InputError> {
+        ReplayRuntime::type_text(self, text).await;
+        Ok(())
+    }
+
+    async fn press_key(&self, key: u32) -> Result<(), InputError> {
+        ReplayRuntime::press_key(self, key).await;
+        Ok(())
+    }
+}
+
+/// ReplayRuntime 基于 tokio，调度器的计时由 tokio 提供
+impl ExecutorExt for ReplayRuntime {
+    type Executor = TokioExecutor;
+
+    fn executor(&self) -> &Self::Executor {
+        &self.executor
+    }
+}
+
+/// ReplayRuntime 错误类型
+#[derive(Debug, Snafu)]
+pub enum ReplayError {
+    #[snafu(display("{}: {source}", path.display()))]
+    Io {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    #[snafu(display("{}: no image to replay", path.display()))]
+    NoFrames { path: PathBuf },
+    #[snafu(display("{}: {source}", path.display()))]
+    Trace { path: PathBuf, source: TraceError },
+    #[snafu(display("{}: no data for frame {frame}", path.display()))]
+    MissingFrame { path: PathBuf, frame: FrameId },
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frame(name: &str) -> Frame {
+        Frame {
+            name: name.to_string(),
+            data: name.as_bytes().to_vec(),
+        }
+    }
+
+    #[test]
+    fn test_frame_number() {
+        assert_eq!(frame_number("0012.png"), Some(12));
+        assert_eq!(frame_number("frame_3_b.jpg"), Some(3));
+        assert_eq!(frame_number("cover.png"), None);
+    }
+
+    #[test]
+    fn test_from_dir_sorts_numbered_images() {
+        let dir = std::env::temp_dir().join(format!("cice-replay-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        for name in ["10.png", "2.png", "cover.PNG", "notes.txt"] {
+            std::fs::write(dir.join(name), name).unwrap();
+        }
+
+        let runtime = ReplayRuntime::from_dir(&dir).unwrap();
+        let names: Vec<_> = runtime
+            .frames()
+            .iter()
+            .map(|frame| String::from_utf8(frame.data.clone()).unwrap())
+            .collect();
+        assert_eq!(names, ["2.png", "10.png", "cover.PNG"]);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert!(matches!(
+            ReplayRuntime::from_dir(&dir),
+            Err(ReplayError::Io { .. })
+        ));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_from_trace_follows_recorded_times() {
+        use cice_core::trace::{frame_id, TraceWriter, Tracer};
+
+        let path = std::env::temp_dir().join(format!("cice-replay-{}.trace", std::process::id()));
+        let writer = TraceWriter::create(&path).unwrap();
+        for (at, screen) in [(2000, "a"), (2600, "a"), (3000, "b"), (4000, "a")] {
+            let frame = frame_id(screen.as_bytes());
+            writer.frame(&frame, screen.as_bytes());
+            writer.record(TraceEvent::Screenshot {
+                at: Duration::from_millis(at),
+                frame,
+            });
+        }
+        drop(writer);
+
+        let runtime = ReplayRuntime::from_trace(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        let screens: Vec<_> = runtime
+            .frames()
+            .iter()
+            .map(|frame| &frame.data[..])
+            .collect();
+        assert_eq!(screens, [b"a", b"b", b"a"]);
+        assert_eq!(runtime.screenshot().await.unwrap(), b"a");
+        tokio::time::sleep(Duration::from_millis(999)).await;
+        assert_eq!(runtime.current_frame(), 0);
+        tokio::time::sleep(Duration::from_millis(1)).await;
+        assert_eq!(runtime.screenshot().await.unwrap(), b"b");
+        tokio::time::sleep(Duration::from_secs(5)).await;
+        assert_eq!(runtime.current_frame(), 2);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_frames_advance_on_input() {
+        let runtime =
+            ReplayRuntime::new(vec![frame("a"), frame("b"), frame("c")]).advance_on_input();
+        tokio::time::sleep(Duration::from_secs(5)).await;
+        assert_eq!(runtime.screenshot().await.unwrap(), b"a");
+        runtime.move_mouse(10, 20).await;
+        assert_eq!(runtime.current_frame(), 0);
+        runtime.click(10, 20, 1).await;
+        runtime.clone().type_text("hello").await;
+        runtime.press_key(0xff0d).await;
+        assert_eq!(runtime.screenshot().await.unwrap(), b"c");
+        assert_eq!(
+            runtime.inputs(),
+            [
+                InputRecord {
+                    frame: 0,
+                    input: Input::MoveMouse { x: 10, y: 20 },
+                },
+                InputRecord {
+                    frame: 0,
+                    input: Input::Click {
+                        x: 10,
+                        y: 20,
+                        button: 1,
+                    },
+                },
+                InputRecord {
+                    frame: 1,
+                    input: Input::TypeText("hello".to_string()),
+                },
+                InputRecord {
+                    frame: 2,
+                    input: Input::PressKey(0xff0d),
+                },
+            ]
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_frames_advance_with_time() {
+        let runtime = ReplayRuntime::new(vec![frame("a"), frame("b")])
+            .frame_interval(Duration::from_millis(500));
+        assert_eq!(runtime.screenshot().await.unwrap(), b"a");
+        tokio::time::sleep(Duration::from_millis(500)).await;
+        assert_eq!(runtime.current_frame(), 1);
+        tokio::time::sleep(Duration::from_secs(5)).await;
+        assert_eq!(runtime.screenshot().await.unwrap(), b"b");
+    }
+}
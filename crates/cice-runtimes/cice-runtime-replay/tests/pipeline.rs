@@ -0,0 +1,166 @@
+//! 通过 ContextBuilder 运行 Pipeline，输入 Action 经 InputExt 推动回放的画面
+
+use std::time::Duration;
+
+use async_trait::async_trait;
+use cice_core::action::{input, Action, ExecError, Recognition, RecognizeError};
+use cice_core::context::ContextBuilder;
+use cice_core::pipeline::action::ActionFactories;
+use cice_core::pipeline::Pipeline;
+use cice_core::runtime::ext::ScreenshotExt;
+use cice_core::task::{TaskError, TaskResult};
+use cice_core::trace::{TraceWriter, Traced};
+use cice_runtime_replay::{Frame, Input, InputRecord, ReplayRuntime};
+use serde::Deserialize;
+
+/// 画面为 `screen` 时识别成功，识别位置为 `at`
+#[derive(Deserialize)]
+struct Shows {
+    screen: String,
+    #[serde(default)]
+    at: Option<[u32; 2]>,
+}
+
+#[async_trait]
+impl<R: ScreenshotExt> Action<R> for Shows {
+    async fn recognize(&self, runtime: &R) -> Result<(), RecognizeError> {
+        match runtime.screenshot().await {
+            Some(screen) if screen == self.screen.as_bytes() => Ok(()),
+            _ => Err(RecognizeError::UnRecognized),
+        }
+    }
+
+    async fn recognize_scored(&self, runtime: &R) -> Recognition {
+        let result = self.recognize(runtime).await;
+        Recognition {
+            location: self.at.filter(|_| result.is_ok()).map(|[x, y]| (x, y)),
+            result,
+            score: None,
+        }
+    }
+
+    async fn exec(&self, _runtime: &R) -> Result<(), ExecError> {
+        Ok(())
+    }
+}
+
+const LOGIN: &str = r#"{
+    "start": {
+        "action": { "type": "test.shows", "screen": "title" },
+        "next_task": ["title"],
+        "timeout": "2s"
+    },
+    "title": {
+        "action": {
+            "type": "compose",
+            "recognize": { "type": "test.shows", "screen": "title", "at": [10, 20] },
+            "exec": { "type": "input.click", "at": "match" }
+        },
+        "next_task": ["login"],
+        "timeout": "2s"
+    },
+    "login": {
+        "action": {
+            "type": "compose",
+            "recognize": { "type": "test.shows", "screen": "login" },
+            "exec": { "type": "input.type", "text": "tester" }
+        },
+        "next_task": ["home"],
+        "timeout": "2s"
+    },
+    "home": {
+        "action": { "type": "test.shows", "screen": "home" },
+        "timeout": "2s"
+    }
+}"#;
+
+#[tokio::test(start_paused = true)]
+async fn test_input_actions_advance_frames() {
+    let frames = ["title", "login", "home"]
+        .into_iter()
+        .map(|name| Frame {
+            name: name.to_string(),
+            data: name.as_bytes().to_vec(),
+        })
+        .collect();
+    let replay = ReplayRuntime::new(frames).advance_on_input();
+    // 包装成 Traced 后输入仍然送达回放
+    let runtime = Traced::new(replay.clone(), TraceWriter::new(std::io::sink()));
+
+    let pipeline = Pipeline::from_json(LOGIN).unwrap();
+    let mut factories = ActionFactories::new();
+    input::register(&mut factories);
+    factories.register("test.shows", |_, shows: Shows| shows);
+    let actions = factories.build(&pipeline).unwrap();
+    let context = ContextBuilder::from_pipeline(runtime, pipeline, &actions.registry())
+        .unwrap()
+        .build();
+
+    let result = context.run("start".to_string()).await;
+    assert!(
+        matches!(result, Ok(TaskResult::NoPendingTask)),
+        "{result:?}"
+    );
+    assert_eq!(replay.current_frame(), 2);
+    assert_eq!(
+        replay.inputs(),
+        [
+            InputRecord {
+                frame: 0,
+                input: Input::Click {
+                    x: 10,
+                    y: 20,
+                    button: 1,
+                },
+            },
+            InputRecord {
+                frame: 1,
+                input: Input::TypeText("tester".to_string()),
+            },
+        ]
+    );
+    // 时间流逝不会推动画面
+    tokio::time::sleep(Duration::from_secs(10)).await;
+    assert_eq!(replay.current_frame(), 2);
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_click_at_match_needs_a_location() {
+    let frames = vec![Frame {
+        name: "title".to_string(),
+        data: b"title".to_vec(),
+    }];
+    let replay = ReplayRuntime::new(frames);
+    let pipeline = Pipeline::from_json(
+        r#"{
+            "start": {
+                "action": { "type": "test.shows", "screen": "title" },
+                "next_task": ["title"]
+            },
+            "title": {
+                "action": {
+                    "type": "compose",
+                    "recognize": { "type": "test.shows", "screen": "title" },
+                    "exec": { "type": "input.click", "at": "match" }
+                },
+                "timeout": "2s"
+            }
+        }"#,
+    )
+    .unwrap();
+    let mut factories = ActionFactories::new();
+    input::register(&mut factories);
+    factories.register("test.shows", |_, shows: Shows| shows);
+    let actions = factories.build(&pipeline).unwrap();
+    let context = ContextBuilder::from_pipeline(replay.clone(), pipeline, &actions.registry())
+        .unwrap()
+        .build();
+
+    // 识别没有给出位置，点击失败而不是点在别处
+    let result = context.run("start".to_string()).await;
+    assert!(
+        matches!(result, Err(TaskError::ActionError { .. })),
+        "{result:?}"
+    );
+    assert!(replay.inputs().is_empty());
+}
@@ -0,0 +1,188 @@
+//! 与 VNC 服务器的连接：握手后在后台持续接收帧缓冲更新，维护本地帧缓冲
+
+use std::sync::{Arc, Mutex as StdMutex, PoisonError};
+use std::time::Duration;
+
+use tokio::io::{AsyncWriteExt, BufReader};
+use tokio::net::tcp::{OwnedReadHalf, OwnedWriteHalf};
+use tokio::net::TcpStream;
+use tokio::sync::{watch, Mutex, RwLock};
+use tokio::task::JoinHandle;
+
+use crate::rfb::encoding::{self, Decoder};
+use crate::rfb::framebuffer::{Change, Framebuffer};
+use crate::rfb::{self, io_failed, ServerInit, CLIENT_PIXEL_FORMAT};
+
+/// 等待首次帧缓冲更新的超时时间
+const FIRST_UPDATE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// 帧缓冲的状态
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum Status {
+    /// 还没有收到第一次更新
+    Waiting,
+    /// 帧缓冲已有完整画面
+    Ready,
+    /// 连接已断开，附带原因
+    Closed(String),
+}
+
+/// 与服务器的连接
+pub(crate) struct Connection {
+    pub init: ServerInit,
+    /// 写入端由后台任务（更新请求）和输入事件共用
+    writer: Arc<Mutex<OwnedWriteHalf>>,
+    frames: Frames,
+    /// 接收更新的后台任务，连接释放时终止
+    reader: JoinHandle<()>,
+}
+
+impl Connection {
+    /// 在握手后的连接上设置像素格式和编码，请求完整画面并开始接收更新
+    ///
+    /// 桌面尺寸改变时同步更新 `screen_size`。
+    pub async fn start(
+        stream: TcpStream,
+        init: ServerInit,
+        screen_size: Arc<RwLock<(u32, u32)>>,
+    ) -> Result<Self, String> {
+        let (reader, mut writer) = stream.into_split();
+        writer
+            .write_all(&rfb::set_pixel_format(&CLIENT_PIXEL_FORMAT))
+            .await
+            .map_err(io_failed)?;
+        writer
+            .write_all(&rfb::set_encodings(&encoding::PREFERRED))
+            .await
+            .map_err(io_failed)?;
+        writer
+            .write_all(&rfb::update_request(false, init.width, init.height))
+            .await
+            .map_err(io_failed)?;
+
+        let writer = Arc::new(Mutex::new(writer));
+        let (status, receiver) = watch::channel(Status::Waiting);
+        let frames = Frames {
+            framebuffer: Arc::new(StdMutex::new(Framebuffer::new(init.width, init.height))),
+            status: receiver,
+        };
+        let reader = tokio::spawn(receive_updates(
+            reader,
+            Decoder::new(init.width, init.height),
+            frames.framebuffer.clone(),
+            writer.clone(),
+            status,
+            screen_size,
+        ));
+        Ok(Self {
+            init,
+            writer,
+            frames,
+            reader,
+        })
+    }
+
+    /// 连接是否仍然可用
+    pub fn is_open(&self) -> bool {
+        !matches!(*self.frames.status.borrow(), Status::Closed(_))
+    }
+
+    pub fn frames(&self) -> Frames {
+        self.frames.clone()
+    }
+
+    /// 发送客户端消息，`messages` 可以是连续的多条消息，一次写入
+    pub async fn send(&self, messages: &[u8]) -> Result<(), String> {
+        self.writer
+            .lock()
+            .await
+            .write_all(messages)
+            .await
+            .map_err(io_failed)
+    }
+}
+
+impl Drop for Connection {
+    fn drop(&mut self) {
+        self.reader.abort();
+    }
+}
+
+/// 本地帧缓冲的句柄，截图时不必持有连接的锁
+#[derive(Clone)]
+pub(crate) struct Frames {
+    framebuffer: Arc<StdMutex<Framebuffer>>,
+    status: watch::Receiver<Status>,
+}
+
+impl Frames {
+    /// 当前画面编码成的 PNG
+    ///
+    /// 连接后首次调用时等待服务器发来完整画面。连接已断开时返回断开的原因。
+    pub async fn screenshot(&self) -> Result<Vec<u8>, String> {
+        let mut status = self.status.clone();
+        let status = tokio::time::timeout(
+            FIRST_UPDATE_TIMEOUT,
+            status.wait_for(|status| *status != Status::Waiting),
+        )
+        .await
+        .map_err(|_| format!("no framebuffer update within {FIRST_UPDATE_TIMEOUT:?}"))?
+        .map_err(|_| "the connection was closed".to_string())?
+        .clone();
+        if let Status::Closed(reason) = status {
+            return Err(reason);
+        }
+        // 复制后在阻塞线程中编码，不阻塞接收更新
+        let framebuffer = self
+            .framebuffer
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone();
+        tokio::task::spawn_blocking(move || framebuffer.to_png())
+            .await
+            .map_err(|err| err.to_string())?
+    }
+}
+
+/// 后台任务：接收更新并应用到帧缓冲，每次更新后请求下一次增量更新
+async fn receive_updates(
+    reader: OwnedReadHalf,
+    mut decoder: Decoder,
+    framebuffer: Arc<StdMutex<Framebuffer>>,
+    writer: Arc<Mutex<OwnedWriteHalf>>,
+    status: watch::Sender<Status>,
+    screen_size: Arc<RwLock<(u32, u32)>>,
+) {
+    let mut reader = BufReader::new(reader);
+    let reason = loop {
+        let changes = match rfb::read_message(&mut reader, &mut decoder).await {
+            Ok(Some(changes)) => changes,
+            Ok(None) => continue,
+            Err(reason) => break reason,
+        };
+        let resized = changes
+            .iter()
+            .any(|change| matches!(change, Change::Resize { .. }));
+        // 同一次更新的变化一起应用，截图不会看到更新到一半的画面
+        let applied = {
+            let mut framebuffer = framebuffer.lock().unwrap_or_else(PoisonError::into_inner);
+            framebuffer
+                .apply(changes)
+                .map(|()| (framebuffer.width(), framebuffer.height()))
+        };
+        let (width, height) = match applied {
+            Ok(size) => size,
+            Err(reason) => break reason,
+        };
+        if resized {
+            *screen_size.write().await = (u32::from(width), u32::from(height));
+        }
+        status.send_replace(Status::Ready);
+        let request = rfb::update_request(true, width, height);
+        if let Err(err) = writer.lock().await.write_all(&request).await {
+            break io_failed(err);
+        }
+    };
+    log::warn!("VNC connection closed: {reason}");
+    status.send_replace(Status::Closed(reason));
+}
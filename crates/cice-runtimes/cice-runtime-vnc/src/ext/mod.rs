@@ -1,12 +1,57 @@
 use async_trait::async_trait;
-use cice_core::runtime::ext::ScreenshotExt;
+use cice_core::runtime::executor::TokioExecutor;
+use cice_core::runtime::ext::{ExecutorExt, InputError, InputExt, ScreenshotExt};
 
-use crate::VncRuntime;
+use crate::{VncError, VncRuntime};
 
 /// 为 VncRuntime 实现 ScreenshotExt trait
 #[async_trait]
 impl ScreenshotExt for VncRuntime {
     async fn screenshot(&self) -> Option<Vec<u8>> {
-        self.screenshot().await.ok()
+        self.screenshot()
+            .await
+            .map_err(|err| log::warn!("VNC screenshot failed: {err}"))
+            .ok()
+    }
+}
+
+fn input_failed(err: VncError) -> InputError {
+    InputError::InputFailed {
+        reason: err.to_string(),
+    }
+}
+
+/// 为 VncRuntime 实现 InputExt trait，Action 通过它发送鼠标和键盘事件
+#[async_trait]
+impl InputExt for VncRuntime {
+    async fn click(&self, x: u32, y: u32, button: u8) -> Result<(), InputError> {
+        VncRuntime::click(self, x, y, button)
+            .await
+            .map_err(input_failed)
+    }
+
+    async fn move_mouse(&self, x: u32, y: u32) -> Result<(), InputError> {
+        VncRuntime::move_mouse(self, x, y)
+            .await
+            .map_err(input_failed)
+    }
+
+    async fn type_text(&self, text: &str) -> Result<(), InputError> {
+        VncRuntime::type_text(self, text)
+            .await
+            .map_err(input_failed)
+    }
+
+    async fn press_key(&self, key: u32) -> Result<(), InputError> {
+        VncRuntime::press_key(self, key).await.map_err(input_failed)
+    }
+}
+
+/// VncRuntime 基于 tokio，调度器的计时由 tokio 提供
+impl ExecutorExt for VncRuntime {
+    type Executor = TokioExecutor;
+
+    fn executor(&self) -> &Self::Executor {
+        &self.executor
     }
 }
@@ -0,0 +1,144 @@
+//! X11 keysym：RFB 的 KeyEvent 用 keysym 表示按键，与键盘布局无关
+//!
+//! 可打印的 Latin-1 字符的 keysym 等于其码位，其他 Unicode 字符为 `0x0100_0000 | 码位`；
+//! 功能键和修饰键见下面的常量。
+
+pub const BACKSPACE: u32 = 0xff08;
+pub const TAB: u32 = 0xff09;
+pub const RETURN: u32 = 0xff0d;
+pub const ESCAPE: u32 = 0xff1b;
+pub const DELETE: u32 = 0xffff;
+pub const INSERT: u32 = 0xff63;
+pub const HOME: u32 = 0xff50;
+pub const LEFT: u32 = 0xff51;
+pub const UP: u32 = 0xff52;
+pub const RIGHT: u32 = 0xff53;
+pub const DOWN: u32 = 0xff54;
+pub const PAGE_UP: u32 = 0xff55;
+pub const PAGE_DOWN: u32 = 0xff56;
+pub const END: u32 = 0xff57;
+/// F1，F2 到 F12 依次加 1
+pub const F1: u32 = 0xffbe;
+pub const SHIFT_L: u32 = 0xffe1;
+pub const CONTROL_L: u32 = 0xffe3;
+pub const ALT_L: u32 = 0xffe9;
+pub const SUPER_L: u32 = 0xffeb;
+
+/// 输入字符 `c` 使用的 keysym
+///
+/// 换行、制表符和退格对应相应的功能键，其余控制字符没有 keysym。
+pub fn from_char(c: char) -> Option<u32> {
+    match c {
+        '\n' | '\r' => Some(RETURN),
+        '\t' => Some(TAB),
+        '\u{8}' => Some(BACKSPACE),
+        ' '..='~' | '\u{a0}'..='\u{ff}' => Some(u32::from(c)),
+        _ if c.is_control() => None,
+        _ => Some(0x0100_0000 | u32::from(c)),
+    }
+}
+
+/// 在美式键盘上输入 `c` 是否需要按住 Shift
+///
+/// 服务器按 keysym 查找按键时通常需要客户端同时发送 Shift，否则部分服务器会输入未按 Shift 的字符。
+pub fn needs_shift(c: char) -> bool {
+    c.is_ascii_uppercase() || "~!@#$%^&*()_+{}|:\"<>?".contains(c)
+}
+
+/// 按名称查找按键，不区分大小写
+///
+/// 支持修饰键（`ctrl`、`alt`、`shift`、`super`/`win`/`meta`）、功能键（`enter`、`del`、`f5` 等）
+/// 和单个字符。
+pub fn from_name(name: &str) -> Option<u32> {
+    let lower = name.to_ascii_lowercase();
+    let keysym = match lower.as_str() {
+        "ctrl" | "control" => CONTROL_L,
+        "alt" => ALT_L,
+        "shift" => SHIFT_L,
+        "super" | "win" | "meta" | "cmd" => SUPER_L,
+        "enter" | "return" => RETURN,
+        "tab" => TAB,
+        "esc" | "escape" => ESCAPE,
+        "backspace" => BACKSPACE,
+        "del" | "delete" => DELETE,
+        "ins" | "insert" => INSERT,
+        "home" => HOME,
+        "end" => END,
+        "pageup" | "pgup" => PAGE_UP,
+        "pagedown" | "pgdn" => PAGE_DOWN,
+        "left" => LEFT,
+        "up" => UP,
+        "right" => RIGHT,
+        "down" => DOWN,
+        "space" => u32::from(' '),
+        _ => {
+            if let Some(number) = lower.strip_prefix('f').and_then(|n| n.parse::<u32>().ok()) {
+                return (1..=12).contains(&number).then(|| F1 + number - 1);
+            }
+            let mut chars = name.chars();
+            // 组合键中的字母按小写发送，是否按 Shift 由组合本身决定
+            return match (chars.next(), chars.next()) {
+                (Some(c), None) => from_char(c.to_ascii_lowercase()),
+                _ => None,
+            };
+        }
+    };
+    Some(keysym)
+}
+
+/// 解析 `ctrl+alt+del` 形式的组合键，返回按下顺序的 keysym
+pub fn parse_combo(combo: &str) -> Result<Vec<u32>, String> {
+    // 单独的 `+` 也是一个按键，如 `ctrl++`
+    let names: Vec<&str> = match combo.strip_suffix("++") {
+        Some(rest) => rest.split('+').chain(["+"]).collect(),
+        None if combo == "+" => vec!["+"],
+        None => combo.split('+').collect(),
+    };
+    names
+        .into_iter()
+        .map(|name| {
+            let name = name.trim();
+            from_name(name).ok_or_else(|| format!("unknown key {name:?} in {combo:?}"))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_char() {
+        assert_eq!(from_char('a'), Some(0x61));
+        assert_eq!(from_char('A'), Some(0x41));
+        assert_eq!(from_char('é'), Some(0xe9));
+        assert_eq!(from_char('中'), Some(0x0100_4e2d));
+        assert_eq!(from_char('😀'), Some(0x0101_f600));
+        assert_eq!(from_char('\n'), Some(RETURN));
+        assert_eq!(from_char('\u{7}'), None);
+    }
+
+    #[test]
+    fn test_needs_shift() {
+        assert!(needs_shift('A'));
+        assert!(needs_shift('?'));
+        assert!(!needs_shift('a'));
+        assert!(!needs_shift('/'));
+        assert!(!needs_shift('中'));
+    }
+
+    #[test]
+    fn test_parse_combo() {
+        assert_eq!(
+            parse_combo("Ctrl+Alt+Del"),
+            Ok(vec![CONTROL_L, ALT_L, DELETE])
+        );
+        assert_eq!(parse_combo("ctrl+C"), Ok(vec![CONTROL_L, 0x63]));
+        assert_eq!(parse_combo("ctrl++"), Ok(vec![CONTROL_L, 0x2b]));
+        assert_eq!(parse_combo("alt + F4"), Ok(vec![ALT_L, F1 + 3]));
+        assert_eq!(
+            parse_combo("ctrl+hyper"),
+            Err("unknown key \"hyper\" in \"ctrl+hyper\"".to_string())
+        );
+    }
+}
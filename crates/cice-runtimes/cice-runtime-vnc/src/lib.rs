@@ -1,16 +1,31 @@
+mod connection;
 pub mod ext;
+pub mod keysym;
+pub mod rfb;
 
+use cice_core::runtime::executor::TokioExecutor;
 use cice_core::runtime::Runtime;
 use std::sync::Arc;
-use tokio::sync::RwLock;
+use std::time::Duration;
+use tokio::net::TcpStream;
+use tokio::sync::{Mutex, RwLock};
+
+use crate::connection::Connection;
+use crate::rfb::{PixelFormat, ProtocolVersion};
+
+/// 未指定端口时使用的 VNC 默认端口
+pub const DEFAULT_PORT: u16 = 5900;
+
+/// 连接和握手的超时时间
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
 
 /// VNC Runtime 提供 VNC 连接管理和屏幕控制功能
 ///
 /// # 功能
-/// - VNC 连接管理
-/// - 屏幕截图
-/// - 鼠标点击和移动
-/// - 键盘输入
+/// - VNC 连接管理：RFB 3.3/3.7/3.8 握手，支持无认证和 VNC 密码认证
+/// - 屏幕截图：后台接收帧缓冲更新（Raw、CopyRect、RRE、Hextile、ZRLE、Tight），截图时编码为 PNG
+/// - 鼠标点击和移动：PointerEvent
+/// - 键盘输入：KeyEvent，文本按字符转换为 X11 keysym，支持组合键
 /// - 资源访问
 ///
 /// # 示例
@@ -23,87 +38,159 @@ use tokio::sync::RwLock;
 #[derive(Clone)]
 pub struct VncRuntime {
     /// VNC 服务器地址
-    _address: String,
+    address: String,
     /// VNC 连接密码
-    _password: Option<String>,
-    /// 连接状态
-    connected: Arc<RwLock<bool>>,
-    /// 屏幕尺寸 (width, height)
+    password: Option<String>,
+    /// 已建立的连接，未连接时为 None
+    connection: Arc<Mutex<Option<Connection>>>,
+    /// 屏幕尺寸 (width, height)，连接后为服务器的桌面尺寸
     screen_size: Arc<RwLock<(u32, u32)>>,
+    /// 异步执行器，调度器通过它计时和并行识别
+    executor: TokioExecutor,
 }
 
 impl VncRuntime {
     /// 创建新的 VNC Runtime
     ///
     /// # 参数
-    /// - `address`: VNC 服务器地址，格式为 "host:port"
+    /// - `address`: VNC 服务器地址，格式为 "host:port"，省略端口时使用 5900
     /// - `password`: VNC 连接密码（可选）
     pub fn new(address: impl Into<String>, password: Option<String>) -> Self {
         Self {
-            _address: address.into(),
-            _password: password,
-            connected: Arc::new(RwLock::new(false)),
+            address: address.into(),
+            password,
+            connection: Arc::new(Mutex::new(None)),
             screen_size: Arc::new(RwLock::new((1920, 1080))), // 默认分辨率
+            executor: TokioExecutor::new(),
         }
     }
 
     /// 连接到 VNC 服务器
+    ///
+    /// 完成 RFB 握手和认证，并按服务器的桌面尺寸更新屏幕尺寸，之后在后台接收帧缓冲更新。
+    /// 已连接时什么也不做；之前的连接已断开时重新连接。
+    /// 失败时返回 [`VncError::ConnectionFailed`]，附带地址和具体原因，如服务器拒绝连接的理由、
+    /// 服务器要求密码或认证失败。
     pub async fn connect(&self) -> Result<(), VncError> {
-        let mut connected = self.connected.write().await;
-        if *connected {
+        let mut connection = self.connection.lock().await;
+        if connection.as_ref().is_some_and(Connection::is_open) {
             return Ok(());
         }
 
-        // TODO: 实现实际的 VNC 连接逻辑
-        // 这里暂时只是模拟连接
-        *connected = true;
+        let address = self.socket_address();
+        let established = tokio::time::timeout(CONNECT_TIMEOUT, self.open(&address))
+            .await
+            .unwrap_or_else(|_| Err(format!("timed out after {CONNECT_TIMEOUT:?}")))
+            .map_err(|reason| VncError::ConnectionFailed(format!("{address}: {reason}")))?;
+        self.set_screen_size(
+            u32::from(established.init.width),
+            u32::from(established.init.height),
+        )
+        .await;
+        *connection = Some(established);
         Ok(())
     }
 
-    /// 断开 VNC 连接
-    pub async fn disconnect(&self) -> Result<(), VncError> {
-        let mut connected = self.connected.write().await;
-        if !*connected {
-            return Ok(());
+    /// 建立 TCP 连接并完成握手
+    async fn open(&self, address: &str) -> Result<Connection, String> {
+        let mut stream = TcpStream::connect(address)
+            .await
+            .map_err(|err| err.to_string())?;
+        // 输入事件都很小，不等待合并
+        stream.set_nodelay(true).map_err(|err| err.to_string())?;
+        let init = rfb::handshake(&mut stream, self.password.as_deref()).await?;
+        Connection::start(stream, init, self.screen_size.clone()).await
+    }
+
+    /// 补全默认端口后的地址
+    fn socket_address(&self) -> String {
+        let has_port = self
+            .address
+            .rsplit_once(':')
+            .is_some_and(|(_, port)| port.parse::<u16>().is_ok());
+        if has_port {
+            self.address.clone()
+        } else {
+            format!("{}:{DEFAULT_PORT}", self.address)
         }
+    }
 
-        // TODO: 实现实际的 VNC 断开逻辑
-        *connected = false;
+    /// 断开 VNC 连接
+    pub async fn disconnect(&self) -> Result<(), VncError> {
+        // 关闭 TCP 连接即可，RFB 没有断开消息
+        self.connection.lock().await.take();
         Ok(())
     }
 
-    /// 检查是否已连接
+    /// 检查是否已连接，服务器断开连接后为 false
     pub async fn is_connected(&self) -> bool {
-        *self.connected.read().await
+        let connection = self.connection.lock().await;
+        connection.as_ref().is_some_and(Connection::is_open)
+    }
+
+    /// 协商出的协议版本，未连接时为 None
+    pub async fn protocol_version(&self) -> Option<ProtocolVersion> {
+        let connection = self.connection.lock().await;
+        connection
+            .as_ref()
+            .map(|connection| connection.init.version)
+    }
+
+    /// 服务器的像素格式，未连接时为 None
+    pub async fn pixel_format(&self) -> Option<PixelFormat> {
+        let connection = self.connection.lock().await;
+        connection
+            .as_ref()
+            .map(|connection| connection.init.pixel_format)
+    }
+
+    /// 服务器的桌面名称，未连接时为 None
+    pub async fn desktop_name(&self) -> Option<String> {
+        let connection = self.connection.lock().await;
+        connection
+            .as_ref()
+            .map(|connection| connection.init.name.clone())
     }
 
     /// 获取屏幕截图
     ///
+    /// 连接后首次截图时等待服务器发来完整画面，最多 10 秒；之后直接返回本地帧缓冲的当前内容。
+    ///
     /// # 返回
-    /// 返回屏幕截图的图像数据
+    /// 返回 PNG 编码的屏幕截图，OpenCV 的 `imdecode` 可以直接解码。
+    /// 服务器已断开连接时返回 [`VncError::ConnectionFailed`]，附带断开的原因。
     pub async fn screenshot(&self) -> Result<Vec<u8>, VncError> {
-        if !self.is_connected().await {
-            return Err(VncError::NotConnected);
-        }
-
-        // TODO: 实现实际的截图逻辑
-        // 这里暂时返回空数据
-        Ok(Vec::new())
+        let frames = match self.connection.lock().await.as_ref() {
+            Some(connection) => connection.frames(),
+            None => return Err(VncError::NotConnected),
+        };
+        frames.screenshot().await.map_err(|reason| {
+            VncError::ConnectionFailed(format!("{}: {reason}", self.socket_address()))
+        })
     }
 
     /// 点击屏幕指定位置
     ///
+    /// 先把指针移到该位置，再按下并松开按钮。
+    ///
     /// # 参数
     /// - `x`: X 坐标
     /// - `y`: Y 坐标
-    /// - `button`: 鼠标按钮（1=左键, 2=中键, 3=右键）
-    pub async fn click(&self, _x: u32, _y: u32, _button: u8) -> Result<(), VncError> {
-        if !self.is_connected().await {
-            return Err(VncError::NotConnected);
+    /// - `button`: 鼠标按钮（1=左键, 2=中键, 3=右键, 4=滚轮向上, 5=滚轮向下）
+    pub async fn click(&self, x: u32, y: u32, button: u8) -> Result<(), VncError> {
+        if !(1..=5).contains(&button) {
+            return Err(VncError::InvalidParameter(format!(
+                "mouse button {button}, expected 1 to 5"
+            )));
         }
-
-        // TODO: 实现实际的点击逻辑
-        Ok(())
+        let (x, y) = self.position(x, y).await?;
+        let mask = 1 << (button - 1);
+        let messages = [
+            rfb::pointer_event(0, x, y),
+            rfb::pointer_event(mask, x, y),
+            rfb::pointer_event(0, x, y),
+        ];
+        self.send(&messages.concat()).await
     }
 
     /// 移动鼠标到指定位置
@@ -111,39 +198,83 @@ impl VncRuntime {
     /// # 参数
     /// - `x`: X 坐标
     /// - `y`: Y 坐标
-    pub async fn move_mouse(&self, _x: u32, _y: u32) -> Result<(), VncError> {
-        if !self.is_connected().await {
-            return Err(VncError::NotConnected);
-        }
-
-        // TODO: 实现实际的鼠标移动逻辑
-        Ok(())
+    pub async fn move_mouse(&self, x: u32, y: u32) -> Result<(), VncError> {
+        let (x, y) = self.position(x, y).await?;
+        self.send(&rfb::pointer_event(0, x, y)).await
     }
 
     /// 输入文本
     ///
+    /// 每个字符按下并松开其 keysym（见 [`keysym::from_char`]），包括中文等任意 Unicode 字符；
+    /// 大写字母和需要 Shift 的符号同时按住 Shift。换行输入回车。
+    ///
     /// # 参数
     /// - `text`: 要输入的文本
-    pub async fn type_text(&self, _text: &str) -> Result<(), VncError> {
-        if !self.is_connected().await {
-            return Err(VncError::NotConnected);
+    pub async fn type_text(&self, text: &str) -> Result<(), VncError> {
+        let mut messages = Vec::new();
+        for c in text.chars() {
+            let keysym = keysym::from_char(c)
+                .ok_or_else(|| VncError::InvalidParameter(format!("cannot type {c:?}")))?;
+            let shift = keysym::needs_shift(c);
+            if shift {
+                messages.extend(rfb::key_event(true, keysym::SHIFT_L));
+            }
+            messages.extend(rfb::key_event(true, keysym));
+            messages.extend(rfb::key_event(false, keysym));
+            if shift {
+                messages.extend(rfb::key_event(false, keysym::SHIFT_L));
+            }
         }
-
-        // TODO: 实现实际的文本输入逻辑
-        Ok(())
+        self.send(&messages).await
     }
 
-    /// 按下键盘按键
+    /// 按下并松开键盘按键
     ///
     /// # 参数
-    /// - `key`: 按键代码
-    pub async fn press_key(&self, _key: u32) -> Result<(), VncError> {
+    /// - `key`: X11 keysym，常用按键见 [`keysym`] 模块
+    pub async fn press_key(&self, key: u32) -> Result<(), VncError> {
+        self.press_keys(&[key]).await
+    }
+
+    /// 同时按下多个按键：按顺序按下，再按相反顺序松开，如 `[CONTROL_L, ALT_L, DELETE]`
+    pub async fn press_keys(&self, keys: &[u32]) -> Result<(), VncError> {
+        let pressed = keys.iter().map(|&key| rfb::key_event(true, key));
+        let released = keys.iter().rev().map(|&key| rfb::key_event(false, key));
+        self.send(&pressed.chain(released).collect::<Vec<_>>().concat())
+            .await
+    }
+
+    /// 按下 `ctrl+alt+del`、`ctrl+c` 形式的组合键，按键名称见 [`keysym::from_name`]
+    pub async fn press_combo(&self, combo: &str) -> Result<(), VncError> {
+        let keys = keysym::parse_combo(combo).map_err(VncError::InvalidParameter)?;
+        self.press_keys(&keys).await
+    }
+
+    /// 检查已连接且坐标在屏幕内；未连接时屏幕尺寸没有意义，先报告 [`VncError::NotConnected`]
+    async fn position(&self, x: u32, y: u32) -> Result<(u16, u16), VncError> {
         if !self.is_connected().await {
             return Err(VncError::NotConnected);
         }
+        let (width, height) = self.get_screen_size().await;
+        if x >= width || y >= height {
+            return Err(VncError::InvalidParameter(format!(
+                "({x}, {y}) is outside the {width}x{height} screen"
+            )));
+        }
+        // 屏幕尺寸来自服务器，不超过 u16
+        Ok((x as u16, y as u16))
+    }
 
-        // TODO: 实现实际的按键逻辑
-        Ok(())
+    /// 向服务器发送客户端消息
+    async fn send(&self, messages: &[u8]) -> Result<(), VncError> {
+        let connection = self.connection.lock().await;
+        let connection = connection
+            .as_ref()
+            .filter(|connection| connection.is_open())
+            .ok_or(VncError::NotConnected)?;
+        connection.send(messages).await.map_err(|reason| {
+            VncError::ConnectionFailed(format!("{}: {reason}", self.socket_address()))
+        })
     }
 
     /// 获取屏幕尺寸
@@ -166,7 +297,7 @@ impl Runtime for VncRuntime {}
 pub enum VncError {
     /// 未连接到 VNC 服务器
     NotConnected,
-    /// 连接失败
+    /// 连接失败，附带地址和原因
     ConnectionFailed(String),
     /// 操作失败
     OperationFailed(String),
@@ -190,6 +321,65 @@ impl std::error::Error for VncError {}
 #[cfg(test)]
 mod tests {
     use super::*;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    /// 在本机监听，接受一个连接并完成不需要认证的 RFB 3.8 握手
+    ///
+    /// 收到客户端的 SetPixelFormat、SetEncodings 和首次更新请求后发送 `update`，
+    /// 再保持连接直到客户端断开；`update` 为 None 时立即断开。
+    async fn serve_handshake(update: Option<Vec<u8>>) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            stream.write_all(b"RFB 003.008\n").await.unwrap();
+            let mut reply = [0; 12];
+            stream.read_exact(&mut reply).await.unwrap();
+            stream.write_all(&[1, 1]).await.unwrap();
+            assert_eq!(stream.read_u8().await.unwrap(), 1);
+            stream.write_u32(0).await.unwrap();
+            assert_eq!(stream.read_u8().await.unwrap(), 1);
+            stream.write_all(&[0x04, 0x00, 0x03, 0x00]).await.unwrap();
+            stream
+                .write_all(&[32, 24, 0, 1, 0, 255, 0, 255, 0, 255, 16, 8, 0, 0, 0, 0])
+                .await
+                .unwrap();
+            stream.write_u32(4).await.unwrap();
+            stream.write_all(b"test").await.unwrap();
+
+            let mut set_pixel_format = [0; 20];
+            stream.read_exact(&mut set_pixel_format).await.unwrap();
+            assert_eq!(set_pixel_format[4..], rfb::CLIENT_PIXEL_FORMAT.to_bytes());
+            let mut set_encodings = [0; 4];
+            stream.read_exact(&mut set_encodings).await.unwrap();
+            let count = u16::from_be_bytes([set_encodings[2], set_encodings[3]]);
+            let mut encodings = vec![0; usize::from(count) * 4];
+            stream.read_exact(&mut encodings).await.unwrap();
+            let mut request = [0; 10];
+            stream.read_exact(&mut request).await.unwrap();
+            assert_eq!(request, [3, 0, 0, 0, 0, 0, 4, 0, 3, 0]);
+            let Some(update) = update else {
+                return;
+            };
+            stream.write_all(&update).await.unwrap();
+            // 保持连接直到客户端断开，期间会收到增量更新请求
+            let mut discard = [0; 64];
+            while stream.read(&mut discard).await.is_ok_and(|read| read > 0) {}
+        });
+        address
+    }
+
+    /// 一个 Raw 矩形：(1, 2) 处的两个像素，红和蓝
+    fn raw_update() -> Vec<u8> {
+        [
+            &[0, 0, 0, 1][..],
+            &[0, 1, 0, 2, 0, 2, 0, 1, 0, 0, 0, 0],
+            &[0, 0, 255, 0],
+            &[255, 0, 0, 0],
+        ]
+        .concat()
+    }
 
     #[tokio::test]
     async fn test_vnc_runtime_creation() {
@@ -199,7 +389,7 @@ mod tests {
 
     #[tokio::test]
     async fn test_vnc_connect_disconnect() {
-        let runtime = VncRuntime::new("localhost:5900", None);
+        let runtime = VncRuntime::new(serve_handshake(Some(raw_update())).await, None);
 
         // 初始状态未连接
         assert!(!runtime.is_connected().await);
@@ -207,22 +397,90 @@ mod tests {
         // 连接
         runtime.connect().await.unwrap();
         assert!(runtime.is_connected().await);
+        assert_eq!(runtime.get_screen_size().await, (1024, 768));
+        assert_eq!(
+            runtime.protocol_version().await,
+            Some(ProtocolVersion::V3_8)
+        );
+        assert_eq!(runtime.desktop_name().await.as_deref(), Some("test"));
+        assert_eq!(runtime.pixel_format().await.unwrap().bits_per_pixel, 32);
 
         // 断开连接
         runtime.disconnect().await.unwrap();
         assert!(!runtime.is_connected().await);
     }
 
+    #[tokio::test]
+    async fn test_vnc_screenshot() {
+        let runtime = VncRuntime::new(serve_handshake(Some(raw_update())).await, None);
+        runtime.connect().await.unwrap();
+
+        let png = runtime.screenshot().await.unwrap();
+        let image = image::load_from_memory(&png).unwrap().to_rgb8();
+        assert_eq!(image.dimensions(), (1024, 768));
+        assert_eq!(image.get_pixel(1, 2).0, [255, 0, 0]);
+        assert_eq!(image.get_pixel(2, 2).0, [0, 0, 255]);
+        assert_eq!(image.get_pixel(0, 0).0, [0, 0, 0]);
+    }
+
+    #[tokio::test]
+    async fn test_vnc_screenshot_after_server_closed() {
+        let address = serve_handshake(None).await;
+        let runtime = VncRuntime::new(address.clone(), None);
+        runtime.connect().await.unwrap();
+
+        match runtime.screenshot().await {
+            Err(VncError::ConnectionFailed(reason)) => {
+                assert_eq!(
+                    reason,
+                    format!("{address}: the server closed the connection")
+                )
+            }
+            other => panic!("unexpected {other:?}"),
+        }
+        assert!(!runtime.is_connected().await);
+    }
+
     #[tokio::test]
     async fn test_vnc_operations_require_connection() {
         let runtime = VncRuntime::new("localhost:5900", None);
 
-        // 未连接时操作应该失败
-        assert!(runtime.screenshot().await.is_err());
-        assert!(runtime.click(100, 100, 1).await.is_err());
-        assert!(runtime.move_mouse(100, 100).await.is_err());
-        assert!(runtime.type_text("test").await.is_err());
-        assert!(runtime.press_key(65).await.is_err());
+        // 未连接时操作应该失败，坐标超出默认屏幕时也先报告未连接
+        let not_connected =
+            |result: Result<_, VncError>| matches!(result, Err(VncError::NotConnected));
+        assert!(not_connected(runtime.screenshot().await.map(|_| ())));
+        assert!(not_connected(runtime.click(100, 100, 1).await));
+        assert!(not_connected(runtime.click(100_000, 100_000, 1).await));
+        assert!(not_connected(runtime.move_mouse(100, 100).await));
+        assert!(not_connected(runtime.move_mouse(100_000, 100_000).await));
+        assert!(not_connected(runtime.type_text("test").await));
+        assert!(not_connected(runtime.press_key(65).await));
+    }
+
+    #[tokio::test]
+    async fn test_vnc_connect_failure_has_reason() {
+        // 绑定后立即释放，得到一个没有监听的端口
+        let address = TcpListener::bind("127.0.0.1:0")
+            .await
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .to_string();
+        let runtime = VncRuntime::new(address.clone(), None);
+        match runtime.connect().await {
+            Err(VncError::ConnectionFailed(reason)) => {
+                assert!(reason.starts_with(&address), "{reason}")
+            }
+            other => panic!("unexpected {other:?}"),
+        }
+        assert!(!runtime.is_connected().await);
+    }
+
+    #[test]
+    fn test_default_port() {
+        let address = |address: &str| VncRuntime::new(address, None).socket_address();
+        assert_eq!(address("localhost"), "localhost:5900");
+        assert_eq!(address("localhost:5901"), "localhost:5901");
     }
 
     #[tokio::test]
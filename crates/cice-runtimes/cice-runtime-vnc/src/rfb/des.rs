@@ -0,0 +1,198 @@
+//! VNC 认证使用的 DES 加密
+//!
+//! VNC 认证用密码作为 DES 密钥加密服务器给出的 16 字节挑战。只需加密单个分组，因此这里只实现了
+//! DES 的加密方向，按 FIPS 46-3 的置换表逐位计算，不追求速度。
+
+/// 初始置换 IP
+const IP: [u8; 64] = [
+    58, 50, 42, 34, 26, 18, 10, 2, 60, 52, 44, 36, 28, 20, 12, 4, 62, 54, 46, 38, 30, 22, 14, 6,
+    64, 56, 48, 40, 32, 24, 16, 8, 57, 49, 41, 33, 25, 17, 9, 1, 59, 51, 43, 35, 27, 19, 11, 3, 61,
+    53, 45, 37, 29, 21, 13, 5, 63, 55, 47, 39, 31, 23, 15, 7,
+];
+
+/// 逆初始置换 IP⁻¹
+const FP: [u8; 64] = [
+    40, 8, 48, 16, 56, 24, 64, 32, 39, 7, 47, 15, 55, 23, 63, 31, 38, 6, 46, 14, 54, 22, 62, 30,
+    37, 5, 45, 13, 53, 21, 61, 29, 36, 4, 44, 12, 52, 20, 60, 28, 35, 3, 43, 11, 51, 19, 59, 27,
+    34, 2, 42, 10, 50, 18, 58, 26, 33, 1, 41, 9, 49, 17, 57, 25,
+];
+
+/// 扩展置换 E，32 位扩展为 48 位
+const E: [u8; 48] = [
+    32, 1, 2, 3, 4, 5, 4, 5, 6, 7, 8, 9, 8, 9, 10, 11, 12, 13, 12, 13, 14, 15, 16, 17, 16, 17, 18,
+    19, 20, 21, 20, 21, 22, 23, 24, 25, 24, 25, 26, 27, 28, 29, 28, 29, 30, 31, 32, 1,
+];
+
+/// S 盒输出的置换 P
+const P: [u8; 32] = [
+    16, 7, 20, 21, 29, 12, 28, 17, 1, 15, 23, 26, 5, 18, 31, 10, 2, 8, 24, 14, 32, 27, 3, 9, 19,
+    13, 30, 6, 22, 11, 4, 25,
+];
+
+/// 置换选择 1，64 位密钥去掉校验位得到 56 位
+const PC1: [u8; 56] = [
+    57, 49, 41, 33, 25, 17, 9, 1, 58, 50, 42, 34, 26, 18, 10, 2, 59, 51, 43, 35, 27, 19, 11, 3, 60,
+    52, 44, 36, 63, 55, 47, 39, 31, 23, 15, 7, 62, 54, 46, 38, 30, 22, 14, 6, 61, 53, 45, 37, 29,
+    21, 13, 5, 28, 20, 12, 4,
+];
+
+/// 置换选择 2，56 位得到每轮的 48 位子密钥
+const PC2: [u8; 48] = [
+    14, 17, 11, 24, 1, 5, 3, 28, 15, 6, 21, 10, 23, 19, 12, 4, 26, 8, 16, 7, 27, 20, 13, 2, 41, 52,
+    31, 37, 47, 55, 30, 40, 51, 45, 33, 48, 44, 49, 39, 56, 34, 53, 46, 42, 50, 36, 29, 32,
+];
+
+/// 每轮密钥循环左移的位数
+const SHIFTS: [u32; 16] = [1, 1, 2, 2, 2, 2, 2, 2, 1, 2, 2, 2, 2, 2, 2, 1];
+
+/// 8 个 S 盒，每个 4 行 16 列
+const S: [[u8; 64]; 8] = [
+    [
+        14, 4, 13, 1, 2, 15, 11, 8, 3, 10, 6, 12, 5, 9, 0, 7, 0, 15, 7, 4, 14, 2, 13, 1, 10, 6, 12,
+        11, 9, 5, 3, 8, 4, 1, 14, 8, 13, 6, 2, 11, 15, 12, 9, 7, 3, 10, 5, 0, 15, 12, 8, 2, 4, 9,
+        1, 7, 5, 11, 3, 14, 10, 0, 6, 13,
+    ],
+    [
+        15, 1, 8, 14, 6, 11, 3, 4, 9, 7, 2, 13, 12, 0, 5, 10, 3, 13, 4, 7, 15, 2, 8, 14, 12, 0, 1,
+        10, 6, 9, 11, 5, 0, 14, 7, 11, 10, 4, 13, 1, 5, 8, 12, 6, 9, 3, 2, 15, 13, 8, 10, 1, 3, 15,
+        4, 2, 11, 6, 7, 12, 0, 5, 14, 9,
+    ],
+    [
+        10, 0, 9, 14, 6, 3, 15, 5, 1, 13, 12, 7, 11, 4, 2, 8, 13, 7, 0, 9, 3, 4, 6, 10, 2, 8, 5,
+        14, 12, 11, 15, 1, 13, 6, 4, 9, 8, 15, 3, 0, 11, 1, 2, 12, 5, 10, 14, 7, 1, 10, 13, 0, 6,
+        9, 8, 7, 4, 15, 14, 3, 11, 5, 2, 12,
+    ],
+    [
+        7, 13, 14, 3, 0, 6, 9, 10, 1, 2, 8, 5, 11, 12, 4, 15, 13, 8, 11, 5, 6, 15, 0, 3, 4, 7, 2,
+        12, 1, 10, 14, 9, 10, 6, 9, 0, 12, 11, 7, 13, 15, 1, 3, 14, 5, 2, 8, 4, 3, 15, 0, 6, 10, 1,
+        13, 8, 9, 4, 5, 11, 12, 7, 2, 14,
+    ],
+    [
+        2, 12, 4, 1, 7, 10, 11, 6, 8, 5, 3, 15, 13, 0, 14, 9, 14, 11, 2, 12, 4, 7, 13, 1, 5, 0, 15,
+        10, 3, 9, 8, 6, 4, 2, 1, 11, 10, 13, 7, 8, 15, 9, 12, 5, 6, 3, 0, 14, 11, 8, 12, 7, 1, 14,
+        2, 13, 6, 15, 0, 9, 10, 4, 5, 3,
+    ],
+    [
+        12, 1, 10, 15, 9, 2, 6, 8, 0, 13, 3, 4, 14, 7, 5, 11, 10, 15, 4, 2, 7, 12, 9, 5, 6, 1, 13,
+        14, 0, 11, 3, 8, 9, 14, 15, 5, 2, 8, 12, 3, 7, 0, 4, 10, 1, 13, 11, 6, 4, 3, 2, 12, 9, 5,
+        15, 10, 11, 14, 1, 7, 6, 0, 8, 13,
+    ],
+    [
+        4, 11, 2, 14, 15, 0, 8, 13, 3, 12, 9, 7, 5, 10, 6, 1, 13, 0, 11, 7, 4, 9, 1, 10, 14, 3, 5,
+        12, 2, 15, 8, 6, 1, 4, 11, 13, 12, 3, 7, 14, 10, 15, 6, 8, 0, 5, 9, 2, 6, 11, 13, 8, 1, 4,
+        10, 7, 9, 5, 0, 15, 14, 2, 3, 12,
+    ],
+    [
+        13, 2, 8, 4, 6, 15, 11, 1, 10, 9, 3, 14, 5, 0, 12, 7, 1, 15, 13, 8, 10, 3, 7, 4, 12, 5, 6,
+        11, 0, 14, 9, 2, 7, 11, 4, 1, 9, 12, 14, 2, 0, 6, 10, 13, 15, 3, 5, 8, 2, 1, 14, 7, 4, 10,
+        8, 13, 15, 12, 9, 0, 3, 5, 6, 11,
+    ],
+];
+
+/// 按置换表重排 `input` 的低 `width` 位，表中位置从最高位起以 1 计
+fn permute(input: u64, width: u32, table: &[u8]) -> u64 {
+    table.iter().fold(0, |output, &position| {
+        (output << 1) | ((input >> (width - u32::from(position))) & 1)
+    })
+}
+
+/// 16 轮的子密钥
+fn subkeys(key: u64) -> [u64; 16] {
+    const MASK: u64 = (1 << 28) - 1;
+    let key = permute(key, 64, &PC1);
+    let (mut c, mut d) = (key >> 28, key & MASK);
+    let mut subkeys = [0; 16];
+    for (subkey, shift) in subkeys.iter_mut().zip(SHIFTS) {
+        c = ((c << shift) | (c >> (28 - shift))) & MASK;
+        d = ((d << shift) | (d >> (28 - shift))) & MASK;
+        *subkey = permute((c << 28) | d, 56, &PC2);
+    }
+    subkeys
+}
+
+/// 轮函数 f
+fn feistel(right: u64, subkey: u64) -> u64 {
+    let expanded = permute(right, 32, &E) ^ subkey;
+    let substituted = S.iter().enumerate().fold(0, |output, (index, sbox)| {
+        let chunk = (expanded >> (42 - 6 * index)) & 0x3f;
+        let row = ((chunk >> 4) & 0b10) | (chunk & 1);
+        let column = (chunk >> 1) & 0xf;
+        (output << 4) | u64::from(sbox[(row * 16 + column) as usize])
+    });
+    permute(substituted, 32, &P)
+}
+
+/// 用 `key` 加密一个 8 字节分组
+pub(crate) fn encrypt_block(key: [u8; 8], block: [u8; 8]) -> [u8; 8] {
+    let block = permute(u64::from_be_bytes(block), 64, &IP);
+    let (mut left, mut right) = (block >> 32, block & 0xffff_ffff);
+    for subkey in subkeys(u64::from_be_bytes(key)) {
+        (left, right) = (right, left ^ feistel(right, subkey));
+    }
+    permute((right << 32) | left, 64, &FP).to_be_bytes()
+}
+
+/// VNC 认证对挑战的应答，服务器用同样的计算校验客户端的应答
+///
+/// 密钥为密码的前 8 个字节，不足补 0；与标准 DES 不同，VNC 把密钥的每个字节按位倒序使用。
+pub(crate) fn vnc_response(password: &str, challenge: [u8; 16]) -> [u8; 16] {
+    let mut key = [0; 8];
+    for (byte, password) in key.iter_mut().zip(password.bytes()) {
+        *byte = password.reverse_bits();
+    }
+    let mut response = [0; 16];
+    for (output, input) in response.chunks_exact_mut(8).zip(challenge.chunks_exact(8)) {
+        output.copy_from_slice(&encrypt_block(key, input.try_into().unwrap()));
+    }
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encrypt_block() {
+        // FIPS 81 及常见教材中的测试向量
+        let cases = [
+            (
+                0x1334_5779_9bbc_dff1_u64,
+                0x0123_4567_89ab_cdef_u64,
+                0x85e8_1354_0f0a_b405_u64,
+            ),
+            (0, 0, 0x8ca6_4de9_c1b1_23a7),
+            (
+                0x0123_4567_89ab_cdef,
+                0x4e6f_7720_6973_2074,
+                0x3fa4_0e8a_984d_4815,
+            ),
+        ];
+        for (key, plain, cipher) in cases {
+            assert_eq!(
+                encrypt_block(key.to_be_bytes(), plain.to_be_bytes()),
+                cipher.to_be_bytes(),
+                "key {key:016x}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_vnc_response_reverses_key_bits() {
+        let challenge: [u8; 16] = core::array::from_fn(|index| index as u8);
+        let response = vnc_response("pass", challenge);
+        let key = [b'p', b'a', b's', b's', 0, 0, 0, 0].map(u8::reverse_bits);
+        assert_eq!(
+            response[..8],
+            encrypt_block(key, challenge[..8].try_into().unwrap())
+        );
+        assert_eq!(
+            response[8..],
+            encrypt_block(key, challenge[8..].try_into().unwrap())
+        );
+        // 只使用前 8 个字节
+        assert_eq!(
+            vnc_response("password-long", challenge),
+            vnc_response("password", challenge)
+        );
+    }
+}
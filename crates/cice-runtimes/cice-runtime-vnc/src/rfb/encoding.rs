@@ -0,0 +1,778 @@
+//! FramebufferUpdate 中矩形的编码：Raw、CopyRect、RRE、Hextile、ZRLE、Tight，以及 DesktopSize 伪编码
+//!
+//! 连接后客户端用 SetPixelFormat 要求 [`CLIENT_PIXEL_FORMAT`](super::CLIENT_PIXEL_FORMAT)，
+//! 因此像素固定为 4 字节小端（蓝、绿、红、填充），ZRLE 的 CPIXEL 为其前 3 字节，
+//! Tight 的 TPIXEL 为红、绿、蓝 3 字节。
+
+use flate2::{Decompress, FlushDecompress, Status};
+use image::ImageFormat;
+use tokio::io::{AsyncRead, AsyncReadExt};
+
+use super::framebuffer::{Change, Pixels, Rect};
+use super::io_failed;
+
+pub const RAW: i32 = 0;
+pub const COPY_RECT: i32 = 1;
+pub const RRE: i32 = 2;
+pub const HEXTILE: i32 = 5;
+pub const TIGHT: i32 = 7;
+pub const ZRLE: i32 = 16;
+/// 伪编码：桌面尺寸改变
+pub const DESKTOP_SIZE: i32 = -223;
+
+/// 请求服务器使用的编码，按偏好排列
+///
+/// 不请求 Tight 的 JPEG 质量伪编码，服务器因此不会用有损的 JPEG，以免影响模板匹配；
+/// 服务器仍然发来 JPEG 时也能解码。
+pub const PREFERRED: [i32; 7] = [COPY_RECT, TIGHT, ZRLE, HEXTILE, RRE, RAW, DESKTOP_SIZE];
+
+/// 压缩数据的长度上限，防止异常的长度字段耗尽内存
+const MAX_COMPRESSED_LENGTH: usize = 64 << 20;
+
+/// Tight 中短于该长度的数据不压缩
+const TIGHT_MIN_TO_COMPRESS: usize = 12;
+
+/// 跨矩形保存的解码状态
+///
+/// ZRLE 与 Tight 的 zlib 流在整个连接中延续，必须按顺序解码同一连接的所有矩形。
+pub struct Decoder {
+    width: u16,
+    height: u16,
+    zrle: Decompress,
+    tight: [Decompress; 4],
+}
+
+impl Decoder {
+    /// 帧缓冲为 `width`x`height` 时的解码状态
+    pub fn new(width: u16, height: u16) -> Self {
+        Self {
+            width,
+            height,
+            zrle: Decompress::new(true),
+            tight: std::array::from_fn(|_| Decompress::new(true)),
+        }
+    }
+
+    /// 读取并解码一个矩形
+    pub async fn decode<R>(
+        &mut self,
+        stream: &mut R,
+        rect: Rect,
+        encoding: i32,
+    ) -> Result<Change, String>
+    where
+        R: AsyncRead + Unpin,
+    {
+        if encoding == DESKTOP_SIZE {
+            (self.width, self.height) = (rect.width, rect.height);
+            return Ok(Change::Resize {
+                width: rect.width,
+                height: rect.height,
+            });
+        }
+        let fits = u32::from(rect.x) + u32::from(rect.width) <= u32::from(self.width)
+            && u32::from(rect.y) + u32::from(rect.height) <= u32::from(self.height);
+        if !fits {
+            return Err(format!(
+                "the server updated {}x{} at ({}, {}), outside the {}x{} framebuffer",
+                rect.width, rect.height, rect.x, rect.y, self.width, self.height
+            ));
+        }
+        let pixels = match encoding {
+            RAW => raw(stream, rect.width, rect.height).await?,
+            COPY_RECT => {
+                let from_x = stream.read_u16().await.map_err(io_failed)?;
+                let from_y = stream.read_u16().await.map_err(io_failed)?;
+                return Ok(Change::Copy {
+                    from_x,
+                    from_y,
+                    to: rect,
+                });
+            }
+            RRE => rre(stream, rect).await?,
+            HEXTILE => hextile(stream, rect).await?,
+            ZRLE => self.zrle(stream, rect).await?,
+            TIGHT => self.tight(stream, rect).await?,
+            _ => {
+                return Err(format!(
+                    "the server used encoding {encoding}, which was not requested"
+                ))
+            }
+        };
+        Ok(Change::Pixels {
+            x: rect.x,
+            y: rect.y,
+            pixels,
+        })
+    }
+
+    async fn zrle<R: AsyncRead + Unpin>(
+        &mut self,
+        stream: &mut R,
+        rect: Rect,
+    ) -> Result<Pixels, String> {
+        let length = stream.read_u32().await.map_err(io_failed)? as usize;
+        let compressed = read_bytes(stream, length).await?;
+        let data = inflate(&mut self.zrle, &compressed)?;
+        let mut input = Cursor::new(&data, "ZRLE");
+        let mut pixels = Pixels::filled(rect.width, rect.height, [0; 3]);
+        for y in (0..rect.height).step_by(64) {
+            for x in (0..rect.width).step_by(64) {
+                let tile = zrle_tile(
+                    &mut input,
+                    (rect.width - x).min(64),
+                    (rect.height - y).min(64),
+                )?;
+                pixels.put(x, y, &tile)?;
+            }
+        }
+        Ok(pixels)
+    }
+
+    async fn tight<R: AsyncRead + Unpin>(
+        &mut self,
+        stream: &mut R,
+        rect: Rect,
+    ) -> Result<Pixels, String> {
+        const FILL: u8 = 0b1000;
+        const JPEG: u8 = 0b1001;
+        const READ_FILTER: u8 = 0b0100;
+        const COPY_FILTER: u8 = 0;
+        const PALETTE_FILTER: u8 = 1;
+        const GRADIENT_FILTER: u8 = 2;
+
+        let control = stream.read_u8().await.map_err(io_failed)?;
+        for (index, zlib) in self.tight.iter_mut().enumerate() {
+            if control & (1 << index) != 0 {
+                zlib.reset(true);
+            }
+        }
+        let (width, height) = (rect.width, rect.height);
+        let area = usize::from(width) * usize::from(height);
+        match control >> 4 {
+            FILL => {
+                let mut color = [0; 3];
+                stream.read_exact(&mut color).await.map_err(io_failed)?;
+                Ok(Pixels::filled(width, height, color))
+            }
+            JPEG => {
+                let length = compact_length(stream).await?;
+                let data = read_bytes(stream, length).await?;
+                let image = image::load_from_memory_with_format(&data, ImageFormat::Jpeg)
+                    .map_err(|err| format!("invalid Tight JPEG data: {err}"))?
+                    .to_rgb8();
+                if image.dimensions() != (u32::from(width), u32::from(height)) {
+                    return Err(format!(
+                        "Tight JPEG data is {}x{} for a {width}x{height} rectangle",
+                        image.width(),
+                        image.height()
+                    ));
+                }
+                Ok(Pixels {
+                    width,
+                    height,
+                    data: image.into_raw(),
+                })
+            }
+            kind if kind & 0b1000 == 0 => {
+                let zlib = usize::from(kind & 0b11);
+                let filter = if kind & READ_FILTER != 0 {
+                    stream.read_u8().await.map_err(io_failed)?
+                } else {
+                    COPY_FILTER
+                };
+                let data = match filter {
+                    COPY_FILTER => self.tight_data(stream, zlib, area * 3).await?,
+                    PALETTE_FILTER => {
+                        let colors = usize::from(stream.read_u8().await.map_err(io_failed)?) + 1;
+                        let palette = read_bytes(stream, colors * 3).await?;
+                        let row_length = if colors == 2 {
+                            usize::from(width).div_ceil(8)
+                        } else {
+                            usize::from(width)
+                        };
+                        let indices = self
+                            .tight_data(stream, zlib, row_length * usize::from(height))
+                            .await?;
+                        let bits = if colors == 2 { 1 } else { 8 };
+                        let mut data = Vec::with_capacity(area * 3);
+                        for row in indices.chunks_exact(row_length.max(1)) {
+                            for x in 0..usize::from(width) {
+                                let index = packed_index(row, x, bits);
+                                let color = palette
+                                    .get(index * 3..index * 3 + 3)
+                                    .ok_or_else(|| format!("Tight palette has no color {index}"))?;
+                                data.extend_from_slice(color);
+                            }
+                        }
+                        data
+                    }
+                    GRADIENT_FILTER => {
+                        let mut data = self.tight_data(stream, zlib, area * 3).await?;
+                        undo_gradient(&mut data, usize::from(width));
+                        data
+                    }
+                    _ => return Err(format!("invalid Tight filter {filter}")),
+                };
+                Ok(Pixels {
+                    width,
+                    height,
+                    data,
+                })
+            }
+            _ => Err(format!("invalid Tight compression control {control:#04x}")),
+        }
+    }
+
+    /// Tight 中 `length` 字节的数据，短数据不压缩，其余用第 `zlib` 个 zlib 流压缩
+    async fn tight_data<R: AsyncRead + Unpin>(
+        &mut self,
+        stream: &mut R,
+        zlib: usize,
+        length: usize,
+    ) -> Result<Vec<u8>, String> {
+        if length < TIGHT_MIN_TO_COMPRESS {
+            return read_bytes(stream, length).await;
+        }
+        let compressed_length = compact_length(stream).await?;
+        let compressed = read_bytes(stream, compressed_length).await?;
+        let mut data = inflate(&mut self.tight[zlib], &compressed)?;
+        if data.len() < length {
+            return Err(format!(
+                "Tight data has {} bytes where {length} are needed",
+                data.len()
+            ));
+        }
+        data.truncate(length);
+        Ok(data)
+    }
+}
+
+/// 读取一个像素
+async fn pixel<R: AsyncRead + Unpin>(stream: &mut R) -> Result<[u8; 3], String> {
+    let mut pixel = [0; 4];
+    stream.read_exact(&mut pixel).await.map_err(io_failed)?;
+    Ok([pixel[2], pixel[1], pixel[0]])
+}
+
+async fn read_bytes<R: AsyncRead + Unpin>(
+    stream: &mut R,
+    length: usize,
+) -> Result<Vec<u8>, String> {
+    if length > MAX_COMPRESSED_LENGTH {
+        return Err(format!("the server sent {length} bytes of encoded data"));
+    }
+    let mut data = vec![0; length];
+    stream.read_exact(&mut data).await.map_err(io_failed)?;
+    Ok(data)
+}
+
+async fn raw<R: AsyncRead + Unpin>(
+    stream: &mut R,
+    width: u16,
+    height: u16,
+) -> Result<Pixels, String> {
+    let data = read_bytes(stream, usize::from(width) * usize::from(height) * 4).await?;
+    Ok(Pixels {
+        width,
+        height,
+        data: data
+            .chunks_exact(4)
+            .flat_map(|pixel| [pixel[2], pixel[1], pixel[0]])
+            .collect(),
+    })
+}
+
+async fn rre<R: AsyncRead + Unpin>(stream: &mut R, rect: Rect) -> Result<Pixels, String> {
+    let count = stream.read_u32().await.map_err(io_failed)?;
+    let mut pixels = Pixels::filled(rect.width, rect.height, pixel(stream).await?);
+    for _ in 0..count {
+        let color = pixel(stream).await?;
+        let mut bounds = [0; 4];
+        for value in &mut bounds {
+            *value = stream.read_u16().await.map_err(io_failed)?;
+        }
+        let [x, y, width, height] = bounds;
+        pixels.fill(
+            Rect {
+                x,
+                y,
+                width,
+                height,
+            },
+            color,
+        )?;
+    }
+    Ok(pixels)
+}
+
+async fn hextile<R: AsyncRead + Unpin>(stream: &mut R, rect: Rect) -> Result<Pixels, String> {
+    const RAW_TILE: u8 = 1;
+    const BACKGROUND_SPECIFIED: u8 = 2;
+    const FOREGROUND_SPECIFIED: u8 = 4;
+    const ANY_SUBRECTS: u8 = 8;
+    const SUBRECTS_COLOURED: u8 = 16;
+
+    let mut pixels = Pixels::filled(rect.width, rect.height, [0; 3]);
+    // 背景色与前景色在同一矩形的各个图块间延续
+    let (mut background, mut foreground) = ([0; 3], [0; 3]);
+    for y in (0..rect.height).step_by(16) {
+        for x in (0..rect.width).step_by(16) {
+            let tile = Rect {
+                x,
+                y,
+                width: (rect.width - x).min(16),
+                height: (rect.height - y).min(16),
+            };
+            let subencoding = stream.read_u8().await.map_err(io_failed)?;
+            if subencoding & RAW_TILE != 0 {
+                pixels.put(x, y, &raw(stream, tile.width, tile.height).await?)?;
+                continue;
+            }
+            if subencoding & BACKGROUND_SPECIFIED != 0 {
+                background = pixel(stream).await?;
+            }
+            pixels.fill(tile, background)?;
+            if subencoding & FOREGROUND_SPECIFIED != 0 {
+                foreground = pixel(stream).await?;
+            }
+            if subencoding & ANY_SUBRECTS == 0 {
+                continue;
+            }
+            let count = stream.read_u8().await.map_err(io_failed)?;
+            for _ in 0..count {
+                let color = if subencoding & SUBRECTS_COLOURED != 0 {
+                    pixel(stream).await?
+                } else {
+                    foreground
+                };
+                let position = stream.read_u8().await.map_err(io_failed)?;
+                let size = stream.read_u8().await.map_err(io_failed)?;
+                let subrect = Rect {
+                    x: x + u16::from(position >> 4),
+                    y: y + u16::from(position & 0xf),
+                    width: u16::from(size >> 4) + 1,
+                    height: u16::from(size & 0xf) + 1,
+                };
+                pixels.fill(subrect, color)?;
+            }
+        }
+    }
+    Ok(pixels)
+}
+
+/// 解码一个 ZRLE 图块
+fn zrle_tile(input: &mut Cursor<'_>, width: u16, height: u16) -> Result<Pixels, String> {
+    let area = usize::from(width) * usize::from(height);
+    let subencoding = input.u8()?;
+    let mut data = Vec::with_capacity(area * 3);
+    match subencoding {
+        0 => {
+            for _ in 0..area {
+                data.extend_from_slice(&input.cpixel()?);
+            }
+        }
+        1 => return Ok(Pixels::filled(width, height, input.cpixel()?)),
+        2..=16 => {
+            let palette = input.palette(usize::from(subencoding))?;
+            let bits = match subencoding {
+                2 => 1,
+                3 | 4 => 2,
+                _ => 4,
+            };
+            let row_length = (usize::from(width) * bits).div_ceil(8);
+            for _ in 0..height {
+                let row = input.take(row_length)?;
+                for x in 0..usize::from(width) {
+                    let index = packed_index(row, x, bits);
+                    let color = palette
+                        .get(index)
+                        .ok_or_else(|| format!("ZRLE palette has no color {index}"))?;
+                    data.extend_from_slice(color);
+                }
+            }
+        }
+        128 => {
+            while data.len() < area * 3 {
+                let color = input.cpixel()?;
+                let run = input.run_length()?;
+                push_run(&mut data, color, run, area)?;
+            }
+        }
+        130..=255 => {
+            let palette = input.palette(usize::from(subencoding - 128))?;
+            while data.len() < area * 3 {
+                let index = input.u8()?;
+                let run = if index & 128 != 0 {
+                    input.run_length()?
+                } else {
+                    1
+                };
+                let index = usize::from(index & 127);
+                let color = palette
+                    .get(index)
+                    .ok_or_else(|| format!("ZRLE palette has no color {index}"))?;
+                push_run(&mut data, *color, run, area)?;
+            }
+        }
+        _ => return Err(format!("invalid ZRLE subencoding {subencoding}")),
+    }
+    Ok(Pixels {
+        width,
+        height,
+        data,
+    })
+}
+
+fn push_run(data: &mut Vec<u8>, color: [u8; 3], run: usize, area: usize) -> Result<(), String> {
+    if data.len() / 3 + run > area {
+        return Err("ZRLE run exceeds its tile".to_string());
+    }
+    for _ in 0..run {
+        data.extend_from_slice(&color);
+    }
+    Ok(())
+}
+
+/// 按位打包的行中第 `x` 个索引，高位在前
+fn packed_index(row: &[u8], x: usize, bits: usize) -> usize {
+    let bit = x * bits;
+    let shift = 8 - bits - bit % 8;
+    usize::from((row[bit / 8] >> shift) & ((1 << bits) - 1) as u8)
+}
+
+/// Tight 梯度滤镜：每个分量是以左、上、左上三个像素预测的值与数据之和
+fn undo_gradient(data: &mut [u8], width: usize) {
+    let stride = width * 3;
+    for index in 0..data.len() {
+        let (x, y) = ((index % stride) / 3, index / stride);
+        let left = if x > 0 { i16::from(data[index - 3]) } else { 0 };
+        let up = if y > 0 {
+            i16::from(data[index - stride])
+        } else {
+            0
+        };
+        let up_left = if x > 0 && y > 0 {
+            i16::from(data[index - stride - 3])
+        } else {
+            0
+        };
+        let predicted = (left + up - up_left).clamp(0, 255) as u8;
+        data[index] = predicted.wrapping_add(data[index]);
+    }
+}
+
+/// Tight 的紧凑长度：1 到 3 个字节，前两个字节的最高位表示后面还有字节
+async fn compact_length<R: AsyncRead + Unpin>(stream: &mut R) -> Result<usize, String> {
+    let mut length = 0;
+    for index in 0..3 {
+        let byte = stream.read_u8().await.map_err(io_failed)?;
+        if index == 2 {
+            length |= usize::from(byte) << 14;
+            break;
+        }
+        length |= usize::from(byte & 0x7f) << (7 * index);
+        if byte & 0x80 == 0 {
+            break;
+        }
+    }
+    Ok(length)
+}
+
+/// 解压 `input`，zlib 流的状态保留到下一次
+fn inflate(zlib: &mut Decompress, input: &[u8]) -> Result<Vec<u8>, String> {
+    let mut output = Vec::with_capacity(input.len() * 4 + 1024);
+    let mut consumed = 0;
+    loop {
+        if output.len() == output.capacity() {
+            output.reserve(output.capacity());
+        }
+        let (total_in, total_out) = (zlib.total_in(), zlib.total_out());
+        let status = zlib
+            .decompress_vec(&input[consumed..], &mut output, FlushDecompress::Sync)
+            .map_err(|err| format!("invalid compressed data: {err}"))?;
+        consumed += (zlib.total_in() - total_in) as usize;
+        let progressed = zlib.total_in() != total_in || zlib.total_out() != total_out;
+        let drained = consumed == input.len() && output.len() < output.capacity();
+        if drained || !progressed || status == Status::StreamEnd {
+            return Ok(output);
+        }
+        if output.len() > MAX_COMPRESSED_LENGTH * 16 {
+            return Err("compressed data expands too much".to_string());
+        }
+    }
+}
+
+/// 解压后数据的读取位置
+struct Cursor<'a> {
+    data: &'a [u8],
+    position: usize,
+    /// 编码名称，用于错误信息
+    encoding: &'static str,
+}
+
+impl<'a> Cursor<'a> {
+    fn new(data: &'a [u8], encoding: &'static str) -> Self {
+        Self {
+            data,
+            position: 0,
+            encoding,
+        }
+    }
+
+    fn take(&mut self, length: usize) -> Result<&'a [u8], String> {
+        let bytes = self
+            .data
+            .get(self.position..self.position + length)
+            .ok_or_else(|| format!("{} data ended early", self.encoding))?;
+        self.position += length;
+        Ok(bytes)
+    }
+
+    fn u8(&mut self) -> Result<u8, String> {
+        Ok(self.take(1)?[0])
+    }
+
+    /// ZRLE 的 3 字节像素，依次为蓝、绿、红
+    fn cpixel(&mut self) -> Result<[u8; 3], String> {
+        let pixel = self.take(3)?;
+        Ok([pixel[2], pixel[1], pixel[0]])
+    }
+
+    fn palette(&mut self, size: usize) -> Result<Vec<[u8; 3]>, String> {
+        (0..size).map(|_| self.cpixel()).collect()
+    }
+
+    /// ZRLE 的游程长度：各字节之和加 1，以不为 255 的字节结束
+    fn run_length(&mut self) -> Result<usize, String> {
+        let mut length = 1;
+        loop {
+            let byte = self.u8()?;
+            length += usize::from(byte);
+            if byte != 255 {
+                return Ok(length);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::{Compress, Compression, FlushCompress};
+
+    const RED: [u8; 3] = [255, 0, 0];
+    const GREEN: [u8; 3] = [0, 255, 0];
+    const BLUE: [u8; 3] = [0, 0, 255];
+
+    /// 线上的 4 字节像素
+    fn wire(color: [u8; 3]) -> [u8; 4] {
+        [color[2], color[1], color[0], 0]
+    }
+
+    /// ZRLE 的 CPIXEL
+    fn cpixel(color: [u8; 3]) -> [u8; 3] {
+        [color[2], color[1], color[0]]
+    }
+
+    /// 与服务器一样，用延续的 zlib 流压缩，每段以同步刷新结束
+    fn deflate(zlib: &mut Compress, data: &[u8]) -> Vec<u8> {
+        let mut output = Vec::with_capacity(data.len() + 1024);
+        zlib.compress_vec(data, &mut output, FlushCompress::Sync)
+            .unwrap();
+        output
+    }
+
+    fn rect(x: u16, y: u16, width: u16, height: u16) -> Rect {
+        Rect {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    async fn decode(decoder: &mut Decoder, data: &[u8], rect: Rect, encoding: i32) -> Pixels {
+        let mut stream = data;
+        let change = decoder.decode(&mut stream, rect, encoding).await.unwrap();
+        assert!(stream.is_empty(), "{} bytes left", stream.len());
+        match change {
+            Change::Pixels { pixels, .. } => pixels,
+            change => panic!("unexpected {change:?}"),
+        }
+    }
+
+    fn colors(pixels: &Pixels) -> Vec<[u8; 3]> {
+        pixels
+            .data
+            .chunks_exact(3)
+            .map(|pixel| pixel.try_into().unwrap())
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_raw_and_copy_rect() {
+        let mut decoder = Decoder::new(4, 4);
+        let data = [wire(RED), wire(GREEN)].concat();
+        let pixels = decode(&mut decoder, &data, rect(1, 1, 2, 1), RAW).await;
+        assert_eq!(colors(&pixels), [RED, GREEN]);
+
+        let mut stream = &[0, 1, 0, 2][..];
+        let change = decoder
+            .decode(&mut stream, rect(2, 3, 2, 1), COPY_RECT)
+            .await
+            .unwrap();
+        assert_eq!(
+            change,
+            Change::Copy {
+                from_x: 1,
+                from_y: 2,
+                to: rect(2, 3, 2, 1),
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn test_rre() {
+        let mut decoder = Decoder::new(4, 4);
+        let data = [
+            &[0, 0, 0, 1][..],
+            &wire(BLUE),
+            &wire(RED),
+            &[0, 1, 0, 0, 0, 1, 0, 2],
+        ]
+        .concat();
+        let pixels = decode(&mut decoder, &data, rect(0, 0, 3, 2), RRE).await;
+        assert_eq!(colors(&pixels), [BLUE, RED, BLUE, BLUE, RED, BLUE]);
+    }
+
+    #[tokio::test]
+    async fn test_hextile() {
+        let mut decoder = Decoder::new(20, 2);
+        let data = [
+            // 第一个图块 16x2：背景蓝，前景红，一个 1x2 的子矩形在 (2, 0)
+            &[2 | 4 | 8][..],
+            &wire(BLUE),
+            &wire(RED),
+            &[1, 0x20, 0x01],
+            // 第二个图块 4x2：沿用背景，一个绿色子矩形 2x1 在 (1, 1)
+            &[8 | 16, 1],
+            &wire(GREEN),
+            &[0x11, 0x10],
+        ]
+        .concat();
+        let pixels = decode(&mut decoder, &data, rect(0, 0, 20, 2), HEXTILE).await;
+        let colors = colors(&pixels);
+        assert_eq!(colors[2], RED);
+        assert_eq!(colors[20 + 2], RED);
+        assert_eq!(colors[3], BLUE);
+        assert_eq!(colors[20 + 17..20 + 19], [GREEN, GREEN]);
+        assert_eq!(colors[17], BLUE);
+    }
+
+    #[tokio::test]
+    async fn test_zrle() {
+        let mut decoder = Decoder::new(70, 2);
+        let mut zlib = Compress::new(Compression::default(), true);
+        // 两个图块：64x2 的调色板 RLE，6x2 的 2 色打包调色板
+        let tiles = [
+            &[130][..],
+            &cpixel(RED),
+            &cpixel(GREEN),
+            &[0x80, 63, 0x81, 62, 0],
+            &[2],
+            &cpixel(BLUE),
+            &cpixel(RED),
+            &[0b1000_0000, 0b0000_1000],
+        ]
+        .concat();
+        let compressed = deflate(&mut zlib, &tiles);
+        let data = [&(compressed.len() as u32).to_be_bytes()[..], &compressed].concat();
+        let pixels = decode(&mut decoder, &data, rect(0, 0, 70, 2), ZRLE).await;
+        let rows = colors(&pixels);
+        assert_eq!(rows[..64], [RED; 64]);
+        assert_eq!(rows[64..70], [RED, BLUE, BLUE, BLUE, BLUE, BLUE]);
+        assert_eq!(rows[70..70 + 63], [GREEN; 63]);
+        assert_eq!(rows[70 + 63], RED);
+        assert_eq!(rows[70 + 64..], [BLUE, BLUE, BLUE, BLUE, RED, BLUE]);
+
+        // zlib 流在矩形之间延续：下一个矩形的数据依赖前面的字典
+        let solid = [&[1][..], &cpixel(GREEN)].concat();
+        let compressed = deflate(&mut zlib, &solid);
+        let data = [&(compressed.len() as u32).to_be_bytes()[..], &compressed].concat();
+        let pixels = decode(&mut decoder, &data, rect(0, 0, 3, 1), ZRLE).await;
+        assert_eq!(colors(&pixels), [GREEN; 3]);
+    }
+
+    #[tokio::test]
+    async fn test_tight() {
+        let mut decoder = Decoder::new(8, 8);
+
+        // Fill
+        let data = [&[0x80][..], &RED].concat();
+        let pixels = decode(&mut decoder, &data, rect(0, 0, 2, 2), TIGHT).await;
+        assert_eq!(colors(&pixels), [RED; 4]);
+
+        // 2 色调色板，短数据不压缩
+        let data = [
+            &[0x40, 1, 1][..],
+            &BLUE,
+            &GREEN,
+            &[0b0100_0000, 0b1000_0000],
+        ]
+        .concat();
+        let pixels = decode(&mut decoder, &data, rect(0, 0, 3, 2), TIGHT).await;
+        assert_eq!(colors(&pixels), [BLUE, GREEN, BLUE, GREEN, BLUE, BLUE]);
+
+        // 复制滤镜，用第 1 个 zlib 流压缩
+        let mut zlib = Compress::new(Compression::default(), true);
+        let raw = [RED, GREEN, BLUE, RED, GREEN, BLUE].concat();
+        let compressed = deflate(&mut zlib, &raw);
+        let data = [&[0x10, compressed.len() as u8][..], &compressed].concat();
+        let pixels = decode(&mut decoder, &data, rect(0, 0, 3, 2), TIGHT).await;
+        assert_eq!(colors(&pixels), [RED, GREEN, BLUE, RED, GREEN, BLUE]);
+
+        // 梯度滤镜，数据是与预测值的差
+        let mut zlib = Compress::new(Compression::default(), true);
+        let differences = [10, 20, 30, 5, 5, 5, 1, 1, 1, 0, 0, 0];
+        let compressed = deflate(&mut zlib, &differences);
+        // 重置第 2 个 zlib 流后使用它
+        let data = [&[0x64, 2, compressed.len() as u8][..], &compressed].concat();
+        let pixels = decode(&mut decoder, &data, rect(0, 0, 2, 2), TIGHT).await;
+        assert_eq!(
+            colors(&pixels),
+            [[10, 20, 30], [15, 25, 35], [11, 21, 31], [16, 26, 36]]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_rejects_rectangles_outside_the_framebuffer() {
+        let mut decoder = Decoder::new(4, 4);
+        let mut stream = &[][..];
+        let err = decoder
+            .decode(&mut stream, rect(3, 0, 2, 1), RAW)
+            .await
+            .unwrap_err();
+        assert_eq!(
+            err,
+            "the server updated 2x1 at (3, 0), outside the 4x4 framebuffer"
+        );
+
+        let change = decoder
+            .decode(&mut stream, rect(0, 0, 8, 8), DESKTOP_SIZE)
+            .await
+            .unwrap();
+        assert_eq!(
+            change,
+            Change::Resize {
+                width: 8,
+                height: 8
+            }
+        );
+        assert!(decoder
+            .decode(&mut stream, rect(3, 0, 2, 1), 99)
+            .await
+            .unwrap_err()
+            .contains("encoding 99"));
+    }
+}
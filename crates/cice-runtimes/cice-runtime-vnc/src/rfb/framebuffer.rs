@@ -0,0 +1,260 @@
+//! 客户端保存的帧缓冲，由服务器的 FramebufferUpdate 更新
+
+use image::codecs::png::{CompressionType, FilterType, PngEncoder};
+use image::{ExtendedColorType, ImageEncoder};
+
+/// 帧缓冲中的矩形区域
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rect {
+    pub x: u16,
+    pub y: u16,
+    pub width: u16,
+    pub height: u16,
+}
+
+impl Rect {
+    fn area(&self) -> usize {
+        usize::from(self.width) * usize::from(self.height)
+    }
+}
+
+/// 按行排列的 RGB 像素
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Pixels {
+    pub width: u16,
+    pub height: u16,
+    pub data: Vec<u8>,
+}
+
+impl Pixels {
+    /// 填满同一颜色
+    pub fn filled(width: u16, height: u16, color: [u8; 3]) -> Self {
+        Self {
+            width,
+            height,
+            data: color.repeat(usize::from(width) * usize::from(height)),
+        }
+    }
+
+    /// 填充其中的区域，`rect` 相对于左上角
+    pub fn fill(&mut self, rect: Rect, color: [u8; 3]) -> Result<(), String> {
+        self.check(rect)?;
+        let stride = usize::from(self.width) * 3;
+        for row in rect.y..rect.y + rect.height {
+            let start = usize::from(row) * stride + usize::from(rect.x) * 3;
+            let end = start + usize::from(rect.width) * 3;
+            for pixel in self.data[start..end].chunks_exact_mut(3) {
+                pixel.copy_from_slice(&color);
+            }
+        }
+        Ok(())
+    }
+
+    /// 用 `pixels` 覆盖其中的区域
+    pub fn put(&mut self, x: u16, y: u16, pixels: &Pixels) -> Result<(), String> {
+        self.check(Rect {
+            x,
+            y,
+            width: pixels.width,
+            height: pixels.height,
+        })?;
+        let stride = usize::from(self.width) * 3;
+        let row_length = usize::from(pixels.width) * 3;
+        if row_length == 0 {
+            return Ok(());
+        }
+        for (row, source) in pixels.data.chunks_exact(row_length).enumerate() {
+            let start = (usize::from(y) + row) * stride + usize::from(x) * 3;
+            self.data[start..start + row_length].copy_from_slice(source);
+        }
+        Ok(())
+    }
+
+    fn check(&self, rect: Rect) -> Result<(), String> {
+        let fits = u32::from(rect.x) + u32::from(rect.width) <= u32::from(self.width)
+            && u32::from(rect.y) + u32::from(rect.height) <= u32::from(self.height);
+        if fits {
+            Ok(())
+        } else {
+            Err(format!(
+                "rectangle {}x{} at ({}, {}) exceeds the {}x{} area",
+                rect.width, rect.height, rect.x, rect.y, self.width, self.height
+            ))
+        }
+    }
+}
+
+/// 一次 FramebufferUpdate 中的一个矩形带来的变化
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Change {
+    /// 矩形内的新像素
+    Pixels { x: u16, y: u16, pixels: Pixels },
+    /// 从帧缓冲的另一处复制（CopyRect）
+    Copy { from_x: u16, from_y: u16, to: Rect },
+    /// 桌面尺寸改变（DesktopSize 伪编码），内容清空
+    Resize { width: u16, height: u16 },
+}
+
+/// 客户端保存的帧缓冲
+#[derive(Debug, Clone)]
+pub struct Framebuffer {
+    pixels: Pixels,
+}
+
+impl Framebuffer {
+    /// 全黑的帧缓冲
+    pub fn new(width: u16, height: u16) -> Self {
+        Self {
+            pixels: Pixels::filled(width, height, [0; 3]),
+        }
+    }
+
+    pub fn width(&self) -> u16 {
+        self.pixels.width
+    }
+
+    pub fn height(&self) -> u16 {
+        self.pixels.height
+    }
+
+    /// `(x, y)` 处的 RGB 颜色
+    pub fn pixel(&self, x: u16, y: u16) -> Option<[u8; 3]> {
+        if x >= self.width() || y >= self.height() {
+            return None;
+        }
+        let start = (usize::from(y) * usize::from(self.width()) + usize::from(x)) * 3;
+        self.pixels.data[start..start + 3].try_into().ok()
+    }
+
+    /// 应用一次更新的全部变化
+    pub fn apply(&mut self, changes: Vec<Change>) -> Result<(), String> {
+        for change in changes {
+            match change {
+                Change::Pixels { x, y, pixels } => self.pixels.put(x, y, &pixels)?,
+                Change::Copy { from_x, from_y, to } => {
+                    let from = Rect {
+                        x: from_x,
+                        y: from_y,
+                        ..to
+                    };
+                    self.pixels.check(from)?;
+                    let copied = self.crop(from);
+                    self.pixels.put(to.x, to.y, &copied)?;
+                }
+                Change::Resize { width, height } => *self = Self::new(width, height),
+            }
+        }
+        Ok(())
+    }
+
+    /// 复制其中的区域，`rect` 须在帧缓冲内
+    fn crop(&self, rect: Rect) -> Pixels {
+        let stride = usize::from(self.width()) * 3;
+        let mut data = Vec::with_capacity(rect.area() * 3);
+        for row in rect.y..rect.y + rect.height {
+            let start = usize::from(row) * stride + usize::from(rect.x) * 3;
+            data.extend_from_slice(&self.pixels.data[start..start + usize::from(rect.width) * 3]);
+        }
+        Pixels {
+            width: rect.width,
+            height: rect.height,
+            data,
+        }
+    }
+
+    /// 编码为 PNG，OpenCV 的 `imdecode` 可以直接解码
+    pub fn to_png(&self) -> Result<Vec<u8>, String> {
+        let mut png = Vec::new();
+        // 截图频繁，优先编码速度
+        PngEncoder::new_with_quality(&mut png, CompressionType::Fast, FilterType::Sub)
+            .write_image(
+                &self.pixels.data,
+                u32::from(self.width()),
+                u32::from(self.height()),
+                ExtendedColorType::Rgb8,
+            )
+            .map_err(|err| format!("Failed to encode the screenshot: {err}"))?;
+        Ok(png)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RED: [u8; 3] = [255, 0, 0];
+    const BLUE: [u8; 3] = [0, 0, 255];
+
+    #[test]
+    fn test_apply_changes() {
+        let mut framebuffer = Framebuffer::new(4, 3);
+        framebuffer
+            .apply(vec![
+                Change::Pixels {
+                    x: 1,
+                    y: 1,
+                    pixels: Pixels::filled(2, 1, RED),
+                },
+                Change::Copy {
+                    from_x: 1,
+                    from_y: 1,
+                    to: Rect {
+                        x: 2,
+                        y: 2,
+                        width: 2,
+                        height: 1,
+                    },
+                },
+            ])
+            .unwrap();
+        assert_eq!(framebuffer.pixel(0, 1), Some([0; 3]));
+        assert_eq!(framebuffer.pixel(2, 1), Some(RED));
+        assert_eq!(framebuffer.pixel(3, 2), Some(RED));
+        assert_eq!(framebuffer.pixel(4, 0), None);
+
+        let outside = framebuffer.apply(vec![Change::Pixels {
+            x: 3,
+            y: 0,
+            pixels: Pixels::filled(2, 1, BLUE),
+        }]);
+        assert_eq!(
+            outside,
+            Err("rectangle 2x1 at (3, 0) exceeds the 4x3 area".to_string())
+        );
+
+        framebuffer
+            .apply(vec![Change::Resize {
+                width: 8,
+                height: 2,
+            }])
+            .unwrap();
+        assert_eq!((framebuffer.width(), framebuffer.height()), (8, 2));
+        assert_eq!(framebuffer.pixel(2, 1), Some([0; 3]));
+    }
+
+    #[test]
+    fn test_to_png() {
+        let mut framebuffer = Framebuffer::new(3, 2);
+        let mut pixels = Pixels::filled(3, 2, BLUE);
+        pixels
+            .fill(
+                Rect {
+                    x: 1,
+                    y: 0,
+                    width: 1,
+                    height: 2,
+                },
+                RED,
+            )
+            .unwrap();
+        framebuffer
+            .apply(vec![Change::Pixels { x: 0, y: 0, pixels }])
+            .unwrap();
+
+        let png = framebuffer.to_png().unwrap();
+        let image = image::load_from_memory(&png).unwrap().to_rgb8();
+        assert_eq!(image.dimensions(), (3, 2));
+        assert_eq!(image.get_pixel(0, 1).0, BLUE);
+        assert_eq!(image.get_pixel(1, 1).0, RED);
+    }
+}
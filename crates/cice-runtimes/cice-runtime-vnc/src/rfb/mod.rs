@@ -0,0 +1,648 @@
+//! RFB 协议（VNC 使用的远程帧缓冲协议，RFC 6143）客户端
+//!
+//! 支持 3.3、3.7、3.8 三个版本，安全类型支持 None 与 VNC Authentication；
+//! 帧缓冲更新支持 Raw、CopyRect、RRE、Hextile、ZRLE、Tight 编码。
+
+pub(crate) mod des;
+pub(crate) mod encoding;
+pub mod framebuffer;
+
+use std::fmt;
+use std::io;
+
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+use self::encoding::Decoder;
+use self::framebuffer::{Change, Rect};
+
+/// 服务器发来的字符串（失败原因、桌面名称）的最大长度，防止异常的长度字段耗尽内存
+const MAX_STRING_LENGTH: u32 = 64 * 1024;
+
+/// 协议版本
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ProtocolVersion {
+    V3_3,
+    V3_7,
+    V3_8,
+}
+
+impl ProtocolVersion {
+    /// 根据服务器的 ProtocolVersion 消息选择双方都支持的最高版本
+    ///
+    /// 按 RFC 6143，未知的 3.x 版本（如部分服务器使用的 3.5）视为 3.3，高于 3.8 的版本使用 3.8。
+    fn negotiate(message: &[u8; 12]) -> Result<Self, String> {
+        let invalid = || {
+            format!(
+                "not an RFB server, it sent {:?}",
+                String::from_utf8_lossy(message)
+            )
+        };
+        let text = std::str::from_utf8(message).map_err(|_| invalid())?;
+        let version = text
+            .strip_prefix("RFB ")
+            .and_then(|version| version.strip_suffix('\n'))
+            .ok_or_else(invalid)?;
+        let (major, minor) = version.split_once('.').ok_or_else(invalid)?;
+        let major: u32 = major.parse().map_err(|_| invalid())?;
+        let minor: u32 = minor.parse().map_err(|_| invalid())?;
+        match (major, minor) {
+            (3, 8..) | (4.., _) => Ok(Self::V3_8),
+            (3, 7) => Ok(Self::V3_7),
+            (3, 3..) => Ok(Self::V3_3),
+            _ => Err(format!(
+                "unsupported protocol version {major}.{minor}, 3.3 or later is required"
+            )),
+        }
+    }
+
+    fn message(self) -> &'static [u8; 12] {
+        match self {
+            Self::V3_3 => b"RFB 003.003\n",
+            Self::V3_7 => b"RFB 003.007\n",
+            Self::V3_8 => b"RFB 003.008\n",
+        }
+    }
+}
+
+impl fmt::Display for ProtocolVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::V3_3 => write!(f, "3.3"),
+            Self::V3_7 => write!(f, "3.7"),
+            Self::V3_8 => write!(f, "3.8"),
+        }
+    }
+}
+
+/// 像素格式，描述帧缓冲中每个像素的编码
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PixelFormat {
+    pub bits_per_pixel: u8,
+    pub depth: u8,
+    pub big_endian: bool,
+    /// 为 false 时像素值是颜色表的索引
+    pub true_colour: bool,
+    pub red_max: u16,
+    pub green_max: u16,
+    pub blue_max: u16,
+    pub red_shift: u8,
+    pub green_shift: u8,
+    pub blue_shift: u8,
+}
+
+impl PixelFormat {
+    /// 按线上格式解码，忽略末尾 3 字节的填充
+    pub fn from_bytes(bytes: &[u8; 16]) -> Self {
+        Self {
+            bits_per_pixel: bytes[0],
+            depth: bytes[1],
+            big_endian: bytes[2] != 0,
+            true_colour: bytes[3] != 0,
+            red_max: u16::from_be_bytes([bytes[4], bytes[5]]),
+            green_max: u16::from_be_bytes([bytes[6], bytes[7]]),
+            blue_max: u16::from_be_bytes([bytes[8], bytes[9]]),
+            red_shift: bytes[10],
+            green_shift: bytes[11],
+            blue_shift: bytes[12],
+        }
+    }
+
+    /// 按线上格式编码，末尾 3 字节为填充
+    pub fn to_bytes(&self) -> [u8; 16] {
+        let [red_high, red_low] = self.red_max.to_be_bytes();
+        let [green_high, green_low] = self.green_max.to_be_bytes();
+        let [blue_high, blue_low] = self.blue_max.to_be_bytes();
+        [
+            self.bits_per_pixel,
+            self.depth,
+            u8::from(self.big_endian),
+            u8::from(self.true_colour),
+            red_high,
+            red_low,
+            green_high,
+            green_low,
+            blue_high,
+            blue_low,
+            self.red_shift,
+            self.green_shift,
+            self.blue_shift,
+            0,
+            0,
+            0,
+        ]
+    }
+}
+
+/// 握手完成后服务器描述的桌面
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ServerInit {
+    pub version: ProtocolVersion,
+    pub width: u16,
+    pub height: u16,
+    pub pixel_format: PixelFormat,
+    /// 桌面名称
+    pub name: String,
+}
+
+/// 客户端要求服务器使用的像素格式：32 位小端真彩色，内存中依次为蓝、绿、红、填充
+pub const CLIENT_PIXEL_FORMAT: PixelFormat = PixelFormat {
+    bits_per_pixel: 32,
+    depth: 24,
+    big_endian: false,
+    true_colour: true,
+    red_max: 255,
+    green_max: 255,
+    blue_max: 255,
+    red_shift: 16,
+    green_shift: 8,
+    blue_shift: 0,
+};
+
+/// 安全类型
+const SECURITY_INVALID: u8 = 0;
+const SECURITY_NONE: u8 = 1;
+const SECURITY_VNC_AUTH: u8 = 2;
+
+/// 完成版本协商、认证和初始化，返回服务器描述的桌面
+///
+/// 失败时返回具体原因，如服务器拒绝连接的理由或认证失败。
+pub(crate) async fn handshake<S>(
+    stream: &mut S,
+    password: Option<&str>,
+) -> Result<ServerInit, String>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    let mut message = [0; 12];
+    stream.read_exact(&mut message).await.map_err(io_failed)?;
+    let version = ProtocolVersion::negotiate(&message)?;
+    stream
+        .write_all(version.message())
+        .await
+        .map_err(io_failed)?;
+
+    let security = match version {
+        // 3.3 由服务器决定安全类型
+        ProtocolVersion::V3_3 => {
+            let security = stream.read_u32().await.map_err(io_failed)?;
+            match u8::try_from(security) {
+                Ok(SECURITY_INVALID) => {
+                    let reason = read_string(stream).await?;
+                    return Err(format!("the server refused the connection: {reason}"));
+                }
+                Ok(security @ (SECURITY_NONE | SECURITY_VNC_AUTH)) => security,
+                _ => return Err(format!("unsupported security type {security}")),
+            }
+        }
+        ProtocolVersion::V3_7 | ProtocolVersion::V3_8 => {
+            let count = stream.read_u8().await.map_err(io_failed)?;
+            if count == 0 {
+                let reason = read_string(stream).await?;
+                return Err(format!("the server refused the connection: {reason}"));
+            }
+            let mut offered = vec![0; usize::from(count)];
+            stream.read_exact(&mut offered).await.map_err(io_failed)?;
+            let security = choose_security(&offered, password.is_some())?;
+            stream.write_u8(security).await.map_err(io_failed)?;
+            security
+        }
+    };
+
+    if security == SECURITY_VNC_AUTH {
+        let password = password.ok_or("the server requires a password")?;
+        let mut challenge = [0; 16];
+        stream.read_exact(&mut challenge).await.map_err(io_failed)?;
+        stream
+            .write_all(&des::vnc_response(password, challenge))
+            .await
+            .map_err(io_failed)?;
+    }
+    // 3.8 之前，None 没有 SecurityResult
+    if security == SECURITY_VNC_AUTH || version == ProtocolVersion::V3_8 {
+        match stream.read_u32().await.map_err(io_failed)? {
+            0 => {}
+            _ if version == ProtocolVersion::V3_8 => {
+                let reason = read_string(stream).await?;
+                return Err(format!("authentication failed: {reason}"));
+            }
+            _ => return Err("authentication failed".to_string()),
+        }
+    }
+
+    // ClientInit：共享桌面，不断开其他客户端
+    stream.write_u8(1).await.map_err(io_failed)?;
+    let width = stream.read_u16().await.map_err(io_failed)?;
+    let height = stream.read_u16().await.map_err(io_failed)?;
+    let mut pixel_format = [0; 16];
+    stream
+        .read_exact(&mut pixel_format)
+        .await
+        .map_err(io_failed)?;
+    let name = read_string(stream).await?;
+    Ok(ServerInit {
+        version,
+        width,
+        height,
+        pixel_format: PixelFormat::from_bytes(&pixel_format),
+        name,
+    })
+}
+
+/// 在服务器按偏好排列的安全类型中选第一个可用的；VNC Authentication 需要密码
+fn choose_security(offered: &[u8], has_password: bool) -> Result<u8, String> {
+    offered
+        .iter()
+        .copied()
+        .find(|&security| {
+            security == SECURITY_NONE || (security == SECURITY_VNC_AUTH && has_password)
+        })
+        .ok_or_else(|| {
+            if offered.contains(&SECURITY_VNC_AUTH) {
+                "the server requires a password".to_string()
+            } else {
+                format!("no supported security type among {offered:?}, only None (1) and VNC Authentication (2) are supported")
+            }
+        })
+}
+
+/// 客户端消息类型
+const SET_PIXEL_FORMAT: u8 = 0;
+const SET_ENCODINGS: u8 = 2;
+const FRAMEBUFFER_UPDATE_REQUEST: u8 = 3;
+const KEY_EVENT: u8 = 4;
+const POINTER_EVENT: u8 = 5;
+
+/// 服务器消息类型
+const FRAMEBUFFER_UPDATE: u8 = 0;
+const SET_COLOUR_MAP_ENTRIES: u8 = 1;
+const BELL: u8 = 2;
+const SERVER_CUT_TEXT: u8 = 3;
+
+/// SetPixelFormat 消息
+pub(crate) fn set_pixel_format(format: &PixelFormat) -> Vec<u8> {
+    [&[SET_PIXEL_FORMAT, 0, 0, 0][..], &format.to_bytes()].concat()
+}
+
+/// SetEncodings 消息，`encodings` 按偏好排列
+pub(crate) fn set_encodings(encodings: &[i32]) -> Vec<u8> {
+    let count = u16::try_from(encodings.len()).unwrap_or(u16::MAX);
+    let mut message = vec![SET_ENCODINGS, 0];
+    message.extend_from_slice(&count.to_be_bytes());
+    for encoding in &encodings[..usize::from(count)] {
+        message.extend_from_slice(&encoding.to_be_bytes());
+    }
+    message
+}
+
+/// 请求整个帧缓冲的 FramebufferUpdateRequest 消息
+///
+/// `incremental` 为 true 时服务器只发送变化的部分，并且可以等到画面变化时才回复。
+pub(crate) fn update_request(incremental: bool, width: u16, height: u16) -> [u8; 10] {
+    let [width_high, width_low] = width.to_be_bytes();
+    let [height_high, height_low] = height.to_be_bytes();
+    [
+        FRAMEBUFFER_UPDATE_REQUEST,
+        u8::from(incremental),
+        0,
+        0,
+        0,
+        0,
+        width_high,
+        width_low,
+        height_high,
+        height_low,
+    ]
+}
+
+/// KeyEvent 消息，`keysym` 为 X11 keysym
+pub(crate) fn key_event(down: bool, keysym: u32) -> [u8; 8] {
+    let [a, b, c, d] = keysym.to_be_bytes();
+    [KEY_EVENT, u8::from(down), 0, 0, a, b, c, d]
+}
+
+/// PointerEvent 消息，`buttons` 的第 0 到 7 位对应按键 1 到 8 是否按下
+pub(crate) fn pointer_event(buttons: u8, x: u16, y: u16) -> [u8; 6] {
+    let [x_high, x_low] = x.to_be_bytes();
+    let [y_high, y_low] = y.to_be_bytes();
+    [POINTER_EVENT, buttons, x_high, x_low, y_high, y_low]
+}
+
+/// 读取服务器的下一条消息
+///
+/// FramebufferUpdate 返回其中各矩形带来的变化，应一起应用；其他消息读完后忽略，返回 None。
+pub(crate) async fn read_message<S>(
+    stream: &mut S,
+    decoder: &mut Decoder,
+) -> Result<Option<Vec<Change>>, String>
+where
+    S: AsyncRead + Unpin,
+{
+    match stream.read_u8().await.map_err(io_failed)? {
+        FRAMEBUFFER_UPDATE => {
+            stream.read_u8().await.map_err(io_failed)?;
+            let count = stream.read_u16().await.map_err(io_failed)?;
+            let mut changes = Vec::with_capacity(usize::from(count));
+            for _ in 0..count {
+                let mut bounds = [0; 4];
+                for value in &mut bounds {
+                    *value = stream.read_u16().await.map_err(io_failed)?;
+                }
+                let [x, y, width, height] = bounds;
+                let encoding = stream.read_i32().await.map_err(io_failed)?;
+                let rect = Rect {
+                    x,
+                    y,
+                    width,
+                    height,
+                };
+                changes.push(decoder.decode(stream, rect, encoding).await?);
+            }
+            Ok(Some(changes))
+        }
+        // 使用真彩色像素格式，颜色表无用
+        SET_COLOUR_MAP_ENTRIES => {
+            let mut header = [0; 5];
+            stream.read_exact(&mut header).await.map_err(io_failed)?;
+            let count = u16::from_be_bytes([header[3], header[4]]);
+            skip(stream, u64::from(count) * 6).await?;
+            Ok(None)
+        }
+        BELL => Ok(None),
+        SERVER_CUT_TEXT => {
+            let mut padding = [0; 3];
+            stream.read_exact(&mut padding).await.map_err(io_failed)?;
+            let length = stream.read_u32().await.map_err(io_failed)?;
+            skip(stream, u64::from(length)).await?;
+            Ok(None)
+        }
+        message => Err(format!("unknown server message type {message}")),
+    }
+}
+
+/// 丢弃 `length` 字节
+async fn skip<S: AsyncRead + Unpin>(stream: &mut S, length: u64) -> Result<(), String> {
+    let skipped = tokio::io::copy(&mut stream.take(length), &mut tokio::io::sink())
+        .await
+        .map_err(io_failed)?;
+    if skipped < length {
+        return Err(io_failed(io::ErrorKind::UnexpectedEof.into()));
+    }
+    Ok(())
+}
+
+/// 读取长度为 u32 的字符串
+async fn read_string<S: AsyncRead + Unpin>(stream: &mut S) -> Result<String, String> {
+    let length = stream.read_u32().await.map_err(io_failed)?;
+    if length > MAX_STRING_LENGTH {
+        return Err(format!("the server sent a string of {length} bytes"));
+    }
+    let mut bytes = vec![0; length as usize];
+    stream.read_exact(&mut bytes).await.map_err(io_failed)?;
+    Ok(String::from_utf8_lossy(&bytes).into_owned())
+}
+
+pub(crate) fn io_failed(err: io::Error) -> String {
+    match err.kind() {
+        io::ErrorKind::UnexpectedEof
+        | io::ErrorKind::BrokenPipe
+        | io::ErrorKind::ConnectionReset => "the server closed the connection".to_string(),
+        _ => err.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::DuplexStream;
+
+    /// 在内存中与 `server` 握手
+    async fn handshake_with<F, Fut>(password: Option<&str>, server: F) -> Result<ServerInit, String>
+    where
+        F: FnOnce(DuplexStream) -> Fut,
+        Fut: std::future::Future<Output = ()> + Send + 'static,
+    {
+        let (mut client, server_end) = tokio::io::duplex(1024);
+        let server = tokio::spawn(server(server_end));
+        let result = handshake(&mut client, password).await;
+        drop(client);
+        server.await.unwrap();
+        result
+    }
+
+    async fn expect(stream: &mut DuplexStream, expected: &[u8]) {
+        let mut received = vec![0; expected.len()];
+        stream.read_exact(&mut received).await.unwrap();
+        assert_eq!(received, expected);
+    }
+
+    async fn server_init(stream: &mut DuplexStream) {
+        expect(stream, &[1]).await;
+        stream.write_all(&[0x05, 0x00, 0x03, 0x20]).await.unwrap();
+        stream
+            .write_all(&CLIENT_PIXEL_FORMAT.to_bytes())
+            .await
+            .unwrap();
+        stream.write_u32(4).await.unwrap();
+        stream.write_all(b"desk").await.unwrap();
+    }
+
+    #[test]
+    fn test_negotiate() {
+        let negotiate = |message: &[u8; 12]| ProtocolVersion::negotiate(message);
+        assert_eq!(negotiate(b"RFB 003.003\n"), Ok(ProtocolVersion::V3_3));
+        assert_eq!(negotiate(b"RFB 003.005\n"), Ok(ProtocolVersion::V3_3));
+        assert_eq!(negotiate(b"RFB 003.007\n"), Ok(ProtocolVersion::V3_7));
+        assert_eq!(negotiate(b"RFB 003.889\n"), Ok(ProtocolVersion::V3_8));
+        assert_eq!(negotiate(b"RFB 004.001\n"), Ok(ProtocolVersion::V3_8));
+        assert!(negotiate(b"RFB 002.000\n")
+            .unwrap_err()
+            .contains("unsupported protocol version 2.0"));
+        assert!(negotiate(b"SSH-2.0-Open")
+            .unwrap_err()
+            .starts_with("not an RFB server"));
+    }
+
+    #[test]
+    fn test_choose_security() {
+        assert_eq!(choose_security(&[2, 1], true), Ok(2));
+        assert_eq!(choose_security(&[2, 1], false), Ok(1));
+        assert_eq!(
+            choose_security(&[2], false),
+            Err("the server requires a password".to_string())
+        );
+        assert!(choose_security(&[16, 19], true)
+            .unwrap_err()
+            .starts_with("no supported security type among [16, 19]"));
+    }
+
+    #[tokio::test]
+    async fn test_handshake_3_8_with_password() {
+        let init = handshake_with(Some("secret"), |mut stream| async move {
+            stream.write_all(b"RFB 003.008\n").await.unwrap();
+            expect(&mut stream, b"RFB 003.008\n").await;
+            stream.write_all(&[2, 2, 1]).await.unwrap();
+            expect(&mut stream, &[2]).await;
+            let challenge = [7; 16];
+            stream.write_all(&challenge).await.unwrap();
+            expect(&mut stream, &des::vnc_response("secret", challenge)).await;
+            stream.write_u32(0).await.unwrap();
+            server_init(&mut stream).await;
+        })
+        .await
+        .unwrap();
+        assert_eq!(
+            init,
+            ServerInit {
+                version: ProtocolVersion::V3_8,
+                width: 1280,
+                height: 800,
+                pixel_format: CLIENT_PIXEL_FORMAT,
+                name: "desk".to_string(),
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn test_handshake_3_3_without_security() {
+        let init = handshake_with(None, |mut stream| async move {
+            stream.write_all(b"RFB 003.003\n").await.unwrap();
+            expect(&mut stream, b"RFB 003.003\n").await;
+            stream.write_u32(1).await.unwrap();
+            // 3.3 的 None 没有 SecurityResult
+            server_init(&mut stream).await;
+        })
+        .await
+        .unwrap();
+        assert_eq!(
+            (init.version, init.width, init.height),
+            (ProtocolVersion::V3_3, 1280, 800)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_handshake_3_7_without_security() {
+        let init = handshake_with(Some("unused"), |mut stream| async move {
+            stream.write_all(b"RFB 003.007\n").await.unwrap();
+            expect(&mut stream, b"RFB 003.007\n").await;
+            stream.write_all(&[1, 1]).await.unwrap();
+            expect(&mut stream, &[1]).await;
+            server_init(&mut stream).await;
+        })
+        .await
+        .unwrap();
+        assert_eq!(init.version, ProtocolVersion::V3_7);
+    }
+
+    #[tokio::test]
+    async fn test_handshake_reports_why_it_failed() {
+        let refused = handshake_with(None, |mut stream| async move {
+            stream.write_all(b"RFB 003.008\n").await.unwrap();
+            expect(&mut stream, b"RFB 003.008\n").await;
+            stream.write_all(&[0, 0, 0, 0, 8]).await.unwrap();
+            stream.write_all(b"too many").await.unwrap();
+        })
+        .await;
+        assert_eq!(
+            refused,
+            Err("the server refused the connection: too many".to_string())
+        );
+
+        let wrong_password = handshake_with(Some("wrong"), |mut stream| async move {
+            stream.write_all(b"RFB 003.008\n").await.unwrap();
+            expect(&mut stream, b"RFB 003.008\n").await;
+            stream.write_all(&[1, 2]).await.unwrap();
+            expect(&mut stream, &[2]).await;
+            stream.write_all(&[0; 16]).await.unwrap();
+            expect(&mut stream, &des::vnc_response("wrong", [0; 16])).await;
+            stream.write_all(&[0, 0, 0, 1, 0, 0, 0, 14]).await.unwrap();
+            stream.write_all(b"wrong password").await.unwrap();
+        })
+        .await;
+        assert_eq!(
+            wrong_password,
+            Err("authentication failed: wrong password".to_string())
+        );
+
+        let no_password = handshake_with(None, |mut stream| async move {
+            stream.write_all(b"RFB 003.003\n").await.unwrap();
+            expect(&mut stream, b"RFB 003.003\n").await;
+            stream.write_u32(2).await.unwrap();
+        })
+        .await;
+        assert_eq!(
+            no_password,
+            Err("the server requires a password".to_string())
+        );
+
+        let closed = handshake_with(None, |mut stream| async move {
+            stream.write_all(b"RFB 003.008\n").await.unwrap();
+        })
+        .await;
+        assert_eq!(closed, Err("the server closed the connection".to_string()));
+    }
+
+    #[test]
+    fn test_client_messages() {
+        assert_eq!(
+            set_encodings(&[encoding::RAW, encoding::DESKTOP_SIZE]),
+            [2, 0, 0, 2, 0, 0, 0, 0, 0xff, 0xff, 0xff, 0x21]
+        );
+        assert_eq!(
+            update_request(true, 1024, 768),
+            [3, 1, 0, 0, 0, 0, 4, 0, 3, 0]
+        );
+        assert_eq!(
+            set_pixel_format(&CLIENT_PIXEL_FORMAT)[..8],
+            [0, 0, 0, 0, 32, 24, 0, 1]
+        );
+        assert_eq!(key_event(true, 0xffe3), [4, 1, 0, 0, 0, 0, 0xff, 0xe3]);
+        assert_eq!(pointer_event(4, 300, 2), [5, 4, 1, 44, 0, 2]);
+    }
+
+    #[tokio::test]
+    async fn test_read_message() {
+        let mut decoder = Decoder::new(4, 4);
+        let data = [
+            // Bell、ServerCutText 与 SetColourMapEntries 被跳过
+            &[BELL][..],
+            &[SERVER_CUT_TEXT, 0, 0, 0, 0, 0, 0, 2, b'h', b'i'],
+            &[SET_COLOUR_MAP_ENTRIES, 0, 0, 0, 0, 1, 1, 2, 3, 4, 5, 6],
+            // 两个矩形：Raw 的一个像素和 DesktopSize
+            &[FRAMEBUFFER_UPDATE, 0, 0, 2],
+            &[0, 1, 0, 2, 0, 1, 0, 1, 0, 0, 0, 0],
+            &[0x30, 0x20, 0x10, 0],
+            &[0, 0, 0, 0, 0, 8, 0, 6, 0xff, 0xff, 0xff, 0x21],
+        ]
+        .concat();
+        let mut stream = &data[..];
+        for _ in 0..3 {
+            assert_eq!(read_message(&mut stream, &mut decoder).await, Ok(None));
+        }
+        let changes = read_message(&mut stream, &mut decoder)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            changes,
+            [
+                Change::Pixels {
+                    x: 1,
+                    y: 2,
+                    pixels: framebuffer::Pixels {
+                        width: 1,
+                        height: 1,
+                        data: vec![0x10, 0x20, 0x30],
+                    },
+                },
+                Change::Resize {
+                    width: 8,
+                    height: 6,
+                },
+            ]
+        );
+        assert_eq!(
+            read_message(&mut stream, &mut decoder).await,
+            Err("the server closed the connection".to_string())
+        );
+    }
+}
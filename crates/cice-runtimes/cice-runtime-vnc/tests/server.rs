@@ -0,0 +1,340 @@
+//! 对进程内 RFB 服务器（`cice_tests_common::vnc`）的端到端测试
+
+use std::time::Duration;
+
+use cice_runtime_vnc::rfb::ProtocolVersion;
+use cice_runtime_vnc::{keysym, VncError, VncRuntime};
+use cice_tests_common::vnc::{self, Encoding, InputEvent, PixelFormat, VncServer};
+use image::{Rgb, RgbImage};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+
+/// 同时包含渐变、纯色块和两色棋盘的画面，覆盖各编码的不同分支
+fn pattern(width: u32, height: u32) -> RgbImage {
+    RgbImage::from_fn(width, height, |x, y| {
+        if x < width / 3 {
+            Rgb([(x * 7) as u8, (y * 5) as u8, ((x + y) * 3) as u8])
+        } else if x < width * 2 / 3 {
+            Rgb([30, 140, 200])
+        } else if (x / 3 + y / 2) % 2 == 0 {
+            Rgb([255, 255, 255])
+        } else {
+            Rgb([0, 0, 0])
+        }
+    })
+}
+
+async fn screenshot(runtime: &VncRuntime) -> RgbImage {
+    let png = runtime.screenshot().await.unwrap();
+    image::load_from_memory(&png).unwrap().to_rgb8()
+}
+
+#[tokio::test]
+async fn test_screenshot_with_each_encoding() {
+    let frame = pattern(150, 70);
+    for encoding in Encoding::ALL {
+        let server = VncServer::builder()
+            .frame(frame.clone())
+            .encodings([encoding])
+            .start()
+            .await
+            .unwrap();
+        let runtime = VncRuntime::new(server.address(), None);
+        runtime.connect().await.unwrap();
+
+        assert_eq!(screenshot(&runtime).await, frame, "{encoding:?}");
+        assert_eq!(server.last_encoding(), Some(encoding));
+    }
+}
+
+#[tokio::test]
+async fn test_screenshot_with_server_pixel_format() {
+    // 服务器的原生格式为 16 位大端，客户端要求 32 位后像素按客户端的格式编码
+    let server = VncServer::builder()
+        .frame(pattern(40, 20))
+        .pixel_format(PixelFormat {
+            bits_per_pixel: 16,
+            depth: 16,
+            big_endian: true,
+            true_colour: true,
+            red_max: 31,
+            green_max: 63,
+            blue_max: 31,
+            red_shift: 11,
+            green_shift: 5,
+            blue_shift: 0,
+        })
+        .start()
+        .await
+        .unwrap();
+    let runtime = VncRuntime::new(server.address(), None);
+    runtime.connect().await.unwrap();
+
+    assert_eq!(runtime.pixel_format().await.unwrap().bits_per_pixel, 16);
+    assert_eq!(screenshot(&runtime).await, pattern(40, 20));
+}
+
+#[tokio::test]
+async fn test_screenshot_follows_frame_changes() {
+    let server = VncServer::builder()
+        .frame(RgbImage::from_pixel(32, 24, Rgb([255, 0, 0])))
+        .start()
+        .await
+        .unwrap();
+    let runtime = VncRuntime::new(server.address(), None);
+    runtime.connect().await.unwrap();
+    assert_eq!(screenshot(&runtime).await.get_pixel(0, 0).0, [255, 0, 0]);
+
+    server.set_frame(RgbImage::from_pixel(32, 24, Rgb([0, 0, 255])));
+    wait_for(&runtime, |image| image.get_pixel(0, 0).0 == [0, 0, 255]).await;
+
+    // 尺寸改变时服务器先发送 DesktopSize
+    server.set_frame(pattern(48, 30));
+    wait_for(&runtime, |image| *image == pattern(48, 30)).await;
+    assert_eq!(runtime.get_screen_size().await, (48, 30));
+}
+
+/// 等待截图满足条件
+async fn wait_for(runtime: &VncRuntime, condition: impl Fn(&RgbImage) -> bool) {
+    for _ in 0..100 {
+        if condition(&screenshot(runtime).await) {
+            return;
+        }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+    panic!("the screenshot did not change");
+}
+
+#[tokio::test]
+async fn test_handshake_with_each_version() {
+    for (served, version) in [
+        (vnc::ProtocolVersion::V3_3, ProtocolVersion::V3_3),
+        (vnc::ProtocolVersion::V3_7, ProtocolVersion::V3_7),
+        (vnc::ProtocolVersion::V3_8, ProtocolVersion::V3_8),
+    ] {
+        let server = VncServer::builder()
+            .version(served)
+            .name("desk")
+            .start()
+            .await
+            .unwrap();
+        let runtime = VncRuntime::new(server.address(), None);
+        runtime.connect().await.unwrap();
+        assert_eq!(runtime.protocol_version().await, Some(version));
+        assert_eq!(runtime.desktop_name().await.as_deref(), Some("desk"));
+        assert_eq!(runtime.get_screen_size().await, (640, 480));
+    }
+}
+
+#[tokio::test]
+async fn test_password() {
+    let server = VncServer::builder()
+        .password("secret")
+        .start()
+        .await
+        .unwrap();
+
+    let runtime = VncRuntime::new(server.address(), Some("secret".to_string()));
+    runtime.connect().await.unwrap();
+    assert!(runtime.is_connected().await);
+
+    // 只有前 8 个字节参与认证
+    let truncated = VncServer::builder()
+        .password("passw0rd!")
+        .start()
+        .await
+        .unwrap();
+    let runtime = VncRuntime::new(truncated.address(), Some("passw0rd?".to_string()));
+    runtime.connect().await.unwrap();
+    assert!(runtime.is_connected().await);
+
+    let runtime = VncRuntime::new(server.address(), Some("wrong".to_string()));
+    match runtime.connect().await {
+        Err(VncError::ConnectionFailed(reason)) => {
+            assert!(
+                reason.ends_with("authentication failed: wrong password"),
+                "{reason}"
+            )
+        }
+        other => panic!("unexpected {other:?}"),
+    }
+
+    let runtime = VncRuntime::new(server.address(), None);
+    match runtime.connect().await {
+        Err(VncError::ConnectionFailed(reason)) => {
+            assert!(
+                reason.ends_with("the server requires a password"),
+                "{reason}"
+            )
+        }
+        other => panic!("unexpected {other:?}"),
+    }
+}
+
+#[tokio::test]
+async fn test_records_input_events() {
+    let server = VncServer::builder().start().await.unwrap();
+    // 手写的最小客户端：3.8、无认证
+    let mut stream = TcpStream::connect(server.address()).await.unwrap();
+    let mut buffer = [0; 12];
+    stream.read_exact(&mut buffer).await.unwrap();
+    stream.write_all(b"RFB 003.008\n").await.unwrap();
+    stream.read_exact(&mut buffer[..2]).await.unwrap();
+    stream.write_u8(1).await.unwrap();
+    stream.read_u32().await.unwrap();
+    stream.write_u8(1).await.unwrap();
+    let mut server_init = [0; 20];
+    stream.read_exact(&mut server_init).await.unwrap();
+    let mut name = vec![0; stream.read_u32().await.unwrap() as usize];
+    stream.read_exact(&mut name).await.unwrap();
+
+    stream.write_all(&[5, 1, 0, 10, 0, 20]).await.unwrap();
+    stream
+        .write_all(&[4, 1, 0, 0, 0, 0, 0, 0x61])
+        .await
+        .unwrap();
+    stream
+        .write_all(&[4, 0, 0, 0, 0, 0, 0, 0x61])
+        .await
+        .unwrap();
+
+    assert_eq!(
+        server.wait_for_events(3).await,
+        [
+            InputEvent::Pointer {
+                x: 10,
+                y: 20,
+                buttons: 1
+            },
+            InputEvent::Key {
+                keysym: 0x61,
+                down: true
+            },
+            InputEvent::Key {
+                keysym: 0x61,
+                down: false
+            },
+        ]
+    );
+    server.clear_events();
+    assert!(server.events().is_empty());
+}
+
+async fn connected(server: &VncServer) -> VncRuntime {
+    let runtime = VncRuntime::new(server.address(), None);
+    runtime.connect().await.unwrap();
+    runtime
+}
+
+fn key(keysym: u32, down: bool) -> InputEvent {
+    InputEvent::Key { keysym, down }
+}
+
+/// 按下并松开
+fn tap(keysym: u32) -> [InputEvent; 2] {
+    [key(keysym, true), key(keysym, false)]
+}
+
+#[tokio::test]
+async fn test_pointer_events() {
+    let server = VncServer::builder().start().await.unwrap();
+    let runtime = connected(&server).await;
+
+    runtime.move_mouse(5, 6).await.unwrap();
+    runtime.click(10, 20, 1).await.unwrap();
+    runtime.click(30, 40, 3).await.unwrap();
+    runtime.click(30, 40, 5).await.unwrap();
+    let pointer = |x, y, buttons| InputEvent::Pointer { x, y, buttons };
+    assert_eq!(
+        server.wait_for_events(10).await,
+        [
+            pointer(5, 6, 0),
+            pointer(10, 20, 0),
+            pointer(10, 20, 1),
+            pointer(10, 20, 0),
+            pointer(30, 40, 0),
+            pointer(30, 40, 4),
+            pointer(30, 40, 0),
+            pointer(30, 40, 0),
+            pointer(30, 40, 16),
+            pointer(30, 40, 0),
+        ]
+    );
+
+    assert!(matches!(
+        runtime.click(640, 0, 1).await,
+        Err(VncError::InvalidParameter(_))
+    ));
+    assert!(matches!(
+        runtime.click(0, 0, 6).await,
+        Err(VncError::InvalidParameter(_))
+    ));
+}
+
+#[tokio::test]
+async fn test_type_text() {
+    let server = VncServer::builder().start().await.unwrap();
+    let runtime = connected(&server).await;
+
+    runtime.type_text("aB?é中\n").await.unwrap();
+    let shifted = |keysym| {
+        [
+            key(keysym::SHIFT_L, true),
+            key(keysym, true),
+            key(keysym, false),
+            key(keysym::SHIFT_L, false),
+        ]
+    };
+    let expected = [
+        &tap(0x61)[..],
+        &shifted(0x42),
+        &shifted(0x3f),
+        &tap(0xe9),
+        &tap(0x0100_4e2d),
+        &tap(keysym::RETURN),
+    ]
+    .concat();
+    assert_eq!(server.wait_for_events(expected.len()).await, expected);
+
+    assert!(matches!(
+        runtime.type_text("\u{7}").await,
+        Err(VncError::InvalidParameter(_))
+    ));
+}
+
+#[tokio::test]
+async fn test_key_combos() {
+    let server = VncServer::builder().start().await.unwrap();
+    let runtime = connected(&server).await;
+
+    runtime.press_key(keysym::ESCAPE).await.unwrap();
+    runtime.press_combo("Ctrl+Alt+Del").await.unwrap();
+    runtime
+        .press_keys(&[keysym::CONTROL_L, u32::from('c')])
+        .await
+        .unwrap();
+    let expected = [
+        &tap(keysym::ESCAPE)[..],
+        &[
+            key(keysym::CONTROL_L, true),
+            key(keysym::ALT_L, true),
+            key(keysym::DELETE, true),
+            key(keysym::DELETE, false),
+            key(keysym::ALT_L, false),
+            key(keysym::CONTROL_L, false),
+        ],
+        &[
+            key(keysym::CONTROL_L, true),
+            key(0x63, true),
+            key(0x63, false),
+            key(keysym::CONTROL_L, false),
+        ],
+    ]
+    .concat();
+    assert_eq!(server.wait_for_events(expected.len()).await, expected);
+
+    assert!(matches!(
+        runtime.press_combo("ctrl+nope").await,
+        Err(VncError::InvalidParameter(_))
+    ));
+}
@@ -1 +1,15 @@
+#![no_std]
+//! Cice facade crate.
+//!
+//! Re-exports `cice-core`. Pick the async executor used by the scheduler with one of the
+//! `tokio`, `async-std` or `smol` features, see [`runtime::executor`]. The `lang` feature adds
+//! the pipeline language of `cice-lang` as `cice::lang`, and the `control` feature the remote
+//! control protocol of `cice-control` as `cice::control`.
 
+pub use cice_core::*;
+
+#[cfg(feature = "lang")]
+pub use cice_lang as lang;
+
+#[cfg(feature = "control")]
+pub use cice_control as control;
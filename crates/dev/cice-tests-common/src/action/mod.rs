@@ -1,7 +1,12 @@
 use async_trait::async_trait;
-use cice_core::action::{Action, ExecError, RecognizeError};
+use cice_core::action::{Action, ExecError, Recognition, RecognizeError};
+use cice_core::runtime::ext::TimerExt;
 use cice_core::runtime::Runtime;
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::Arc;
+use std::time::Duration;
+
+use crate::clock::VirtualClock;
 
 /// 测试用的简单 Runtime 实现
 #[derive(Clone)]
@@ -10,15 +15,25 @@ pub struct TestRuntime {
 }
 
 struct TestRuntimeInner {
-    // 可以在这里添加共享状态
+    clock: VirtualClock,
 }
 
 impl TestRuntime {
     pub fn new() -> Self {
+        Self::with_clock(VirtualClock::new())
+    }
+
+    /// 使用指定的虚拟时钟创建 Runtime
+    pub fn with_clock(clock: VirtualClock) -> Self {
         Self {
-            _inner: Arc::new(TestRuntimeInner {}),
+            _inner: Arc::new(TestRuntimeInner { clock }),
         }
     }
+
+    /// Runtime 使用的虚拟时钟
+    pub fn clock(&self) -> &VirtualClock {
+        &self._inner.clock
+    }
 }
 
 impl Default for TestRuntime {
@@ -29,6 +44,17 @@ impl Default for TestRuntime {
 
 impl Runtime for TestRuntime {}
 
+#[async_trait]
+impl TimerExt for TestRuntime {
+    fn now(&self) -> Duration {
+        self.clock().now()
+    }
+
+    async fn sleep(&self, duration: Duration) {
+        self.clock().sleep(duration).await
+    }
+}
+
 /// 简单的 Action 实现 - 总是成功
 pub struct SimpleAction {
     name: String,
@@ -114,3 +140,131 @@ impl Action<TestRuntime> for ConfigurableAction {
         }
     }
 }
+
+/// [`ScriptedAction`] 在某个虚拟时间点之后的识别结果
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Outcome {
+    Recognized,
+    UnRecognized,
+    /// 识别出错，即 `RecognizeError::RecognizeFailed`
+    Failed,
+}
+
+/// 识别结果随虚拟时间变化的 Action
+///
+/// 识别结果由调用 `recognize` 时的虚拟时间决定，默认从 0 时刻起总是识别成功。
+///
+/// ```rust
+/// use cice_tests_common::action::{Outcome, ScriptedAction};
+/// use std::time::Duration;
+///
+/// // 前 5 秒识别不到，之后识别成功，每次识别耗时 200ms
+/// let action = ScriptedAction::new("popup")
+///     .at(Duration::ZERO, Outcome::UnRecognized)
+///     .at(Duration::from_secs(5), Outcome::Recognized)
+///     .recognize_cost(Duration::from_millis(200));
+/// ```
+pub struct ScriptedAction {
+    name: String,
+    script: Vec<(Duration, Outcome)>,
+    recognize_cost: Duration,
+    score: Option<f64>,
+    exec_fails: bool,
+    recognize_count: AtomicUsize,
+    exec_count: AtomicUsize,
+}
+
+impl ScriptedAction {
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            script: vec![(Duration::ZERO, Outcome::Recognized)],
+            recognize_cost: Duration::ZERO,
+            score: None,
+            exec_fails: false,
+            recognize_count: AtomicUsize::new(0),
+            exec_count: AtomicUsize::new(0),
+        }
+    }
+
+    /// 从虚拟时间 `at` 起，识别结果变为 `outcome`
+    pub fn at(mut self, at: Duration, outcome: Outcome) -> Self {
+        self.script.retain(|(time, _)| *time != at);
+        self.script.push((at, outcome));
+        self.script.sort_by_key(|(time, _)| *time);
+        self
+    }
+
+    /// 每次识别消耗的虚拟时间
+    pub fn recognize_cost(mut self, cost: Duration) -> Self {
+        self.recognize_cost = cost;
+        self
+    }
+
+    /// 每次识别报告的得分，即 `recognize_scored` 返回的 `score`
+    pub fn score(mut self, score: f64) -> Self {
+        self.score = Some(score);
+        self
+    }
+
+    /// 让 `exec` 总是失败
+    pub fn exec_fails(mut self) -> Self {
+        self.exec_fails = true;
+        self
+    }
+
+    /// `recognize` 被调用的次数
+    pub fn recognize_count(&self) -> usize {
+        self.recognize_count.load(Ordering::SeqCst)
+    }
+
+    /// `exec` 被调用的次数
+    pub fn exec_count(&self) -> usize {
+        self.exec_count.load(Ordering::SeqCst)
+    }
+
+    fn outcome_at(&self, now: Duration) -> Outcome {
+        self.script
+            .iter()
+            .take_while(|(time, _)| *time <= now)
+            .last()
+            .map_or(Outcome::UnRecognized, |(_, outcome)| *outcome)
+    }
+}
+
+#[async_trait]
+impl Action<TestRuntime> for ScriptedAction {
+    async fn recognize(&self, runtime: &TestRuntime) -> Result<(), RecognizeError> {
+        self.recognize_count.fetch_add(1, Ordering::SeqCst);
+        let outcome = self.outcome_at(runtime.now());
+        runtime.sleep(self.recognize_cost).await;
+        log::debug!("ScriptedAction {} recognize - {outcome:?}", self.name);
+        match outcome {
+            Outcome::Recognized => Ok(()),
+            Outcome::UnRecognized => Err(RecognizeError::UnRecognized),
+            Outcome::Failed => Err(RecognizeError::RecognizeFailed {
+                reason: "scripted failure".to_string(),
+            }),
+        }
+    }
+
+    async fn recognize_scored(&self, runtime: &TestRuntime) -> Recognition {
+        Recognition {
+            result: self.recognize(runtime).await,
+            score: self.score,
+            location: None,
+        }
+    }
+
+    async fn exec(&self, _runtime: &TestRuntime) -> Result<(), ExecError> {
+        self.exec_count.fetch_add(1, Ordering::SeqCst);
+        log::debug!("ScriptedAction {} exec", self.name);
+        if self.exec_fails {
+            Err(ExecError::ExecFailed {
+                reason: "scripted failure".to_string(),
+            })
+        } else {
+            Ok(())
+        }
+    }
+}
@@ -0,0 +1,130 @@
+use std::collections::BTreeMap;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, Waker};
+use std::time::Duration;
+
+/// 虚拟时钟，实现确定性的、瞬时完成的计时
+///
+/// 时间只会在以下情况下前进：
+/// - 调用 [`VirtualClock::advance`]
+/// - 由 [`crate::harness::block_on`] 驱动时，被驱动的 future 阻塞且仍有等待中的 [`Sleep`]，
+///   时钟直接跳到最早的截止时间
+/// - 未被驱动时（例如在 `#[tokio::test]` 中使用），[`Sleep`] 会立即把时钟推进到自己的截止时间
+#[derive(Clone, Default)]
+pub struct VirtualClock(Arc<Mutex<ClockState>>);
+
+#[derive(Default)]
+struct ClockState {
+    now: Duration,
+    driven: bool,
+    next_id: u64,
+    timers: BTreeMap<(Duration, u64), Waker>,
+}
+
+impl VirtualClock {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 当前虚拟时间
+    pub fn now(&self) -> Duration {
+        self.0.lock().unwrap().now
+    }
+
+    /// 创建一个在 `duration` 虚拟时间后完成的 future
+    pub fn sleep(&self, duration: Duration) -> Sleep {
+        let mut state = self.0.lock().unwrap();
+        let id = state.next_id;
+        state.next_id += 1;
+        Sleep {
+            clock: self.clone(),
+            deadline: state.now + duration,
+            id,
+            yielded: false,
+        }
+    }
+
+    /// 推进虚拟时间，并唤醒所有到期的 [`Sleep`]
+    pub fn advance(&self, duration: Duration) {
+        let target = self.now() + duration;
+        self.advance_to(target);
+    }
+
+    /// 跳到最早的等待中 [`Sleep`] 的截止时间，没有等待中的 [`Sleep`] 时返回 `false`
+    pub(crate) fn advance_to_next_timer(&self) -> bool {
+        let next = self
+            .0
+            .lock()
+            .unwrap()
+            .timers
+            .keys()
+            .next()
+            .map(|(at, _)| *at);
+        match next {
+            Some(deadline) => {
+                self.advance_to(deadline);
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub(crate) fn set_driven(&self, driven: bool) {
+        self.0.lock().unwrap().driven = driven;
+    }
+
+    fn advance_to(&self, target: Duration) {
+        let due = {
+            let mut state = self.0.lock().unwrap();
+            let now = state.now.max(target);
+            state.now = now;
+            let pending = state.timers.split_off(&(now, u64::MAX));
+            std::mem::replace(&mut state.timers, pending)
+        };
+        due.into_values().for_each(Waker::wake);
+    }
+}
+
+/// [`VirtualClock::sleep`] 返回的 future
+///
+/// 第一次 poll 总会让出一次执行权，使同一执行器上的其他 future（例如消息收集）有机会运行
+pub struct Sleep {
+    clock: VirtualClock,
+    deadline: Duration,
+    id: u64,
+    yielded: bool,
+}
+
+impl Future for Sleep {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        if !self.yielded {
+            self.yielded = true;
+            cx.waker().wake_by_ref();
+            return Poll::Pending;
+        }
+        let mut state = self.clock.0.lock().unwrap();
+        if !state.driven {
+            state.now = state.now.max(self.deadline);
+        }
+        if state.now >= self.deadline {
+            state.timers.remove(&(self.deadline, self.id));
+            return Poll::Ready(());
+        }
+        state
+            .timers
+            .insert((self.deadline, self.id), cx.waker().clone());
+        Poll::Pending
+    }
+}
+
+impl Drop for Sleep {
+    fn drop(&mut self) {
+        if let Ok(mut state) = self.clock.0.lock() {
+            state.timers.remove(&(self.deadline, self.id));
+        }
+    }
+}
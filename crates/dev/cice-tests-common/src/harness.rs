@@ -0,0 +1,123 @@
+use std::future::Future;
+use std::pin::pin;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::task::{Context as PollContext, Poll};
+use std::time::Duration;
+
+use cice_core::context::Context;
+use cice_core::message::{task::TaskMessage, Message};
+use cice_core::runtime::ext::TimerExt;
+use cice_core::task::{TaskError, TaskId, TaskResult};
+use futures::task::{waker, ArcWake};
+use futures::FutureExt;
+
+use crate::clock::VirtualClock;
+
+/// 在虚拟时间下运行 future 直到完成
+///
+/// 每当 future 阻塞且没有被唤醒时，时钟直接跳到最早的等待中 `Sleep` 的截止时间，
+/// 因此超时、重试等待都会瞬间完成，且结果是确定的。
+///
+/// # Panics
+/// future 阻塞但没有任何等待中的 `Sleep` 时（即永远无法继续）会 panic。
+pub fn block_on<F: Future>(clock: &VirtualClock, future: F) -> F::Output {
+    struct Flag(AtomicBool);
+
+    impl ArcWake for Flag {
+        fn wake_by_ref(arc_self: &Arc<Self>) {
+            arc_self.0.store(true, Ordering::SeqCst);
+        }
+    }
+
+    let flag = Arc::new(Flag(AtomicBool::new(false)));
+    let waker = waker(flag.clone());
+    let mut cx = PollContext::from_waker(&waker);
+    let mut future = pin!(future);
+
+    clock.set_driven(true);
+    let output = loop {
+        flag.0.store(false, Ordering::SeqCst);
+        if let Poll::Ready(output) = future.as_mut().poll(&mut cx) {
+            break output;
+        }
+        if !flag.0.load(Ordering::SeqCst) && !clock.advance_to_next_timer() {
+            clock.set_driven(false);
+            panic!(
+                "future is blocked at {:?} without any pending timer",
+                clock.now()
+            );
+        }
+    };
+    clock.set_driven(false);
+    output
+}
+
+/// 一次虚拟时间运行的记录
+#[derive(Debug)]
+pub struct Recording {
+    pub result: Result<TaskResult, TaskError>,
+    /// 按发送顺序记录的全部 `TaskMessage`
+    pub messages: Vec<TaskMessage>,
+    /// 运行所消耗的虚拟时间
+    pub elapsed: Duration,
+}
+
+impl Recording {
+    /// 按顺序返回所有进入过的任务
+    pub fn entered(&self) -> Vec<TaskId> {
+        self.messages
+            .iter()
+            .filter_map(|msg| match msg {
+                TaskMessage::Enter { id } => Some(id.clone()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// 统计某个任务被尝试识别的次数
+    pub fn recognize_attempts(&self, id: &str) -> usize {
+        self.messages
+            .iter()
+            .filter(|msg| matches!(msg, TaskMessage::TryRecognize { id: task } if task == id))
+            .count()
+    }
+}
+
+/// 在虚拟时间下运行 `context`，同时收集它发出的全部消息
+///
+/// `clock` 必须是 `context` 所用 Runtime 的时钟，例如 `TestRuntime::clock`。
+pub fn run<RUNTIME: TimerExt>(
+    context: &Context<'_, RUNTIME>,
+    clock: &VirtualClock,
+    entry: &str,
+) -> Recording {
+    let handler = context.get_handler();
+    let started = clock.now();
+    let mut messages = vec![];
+
+    let result = block_on(clock, async {
+        let mut run = pin!(context.run(entry.to_string()).fuse());
+        loop {
+            futures::select_biased! {
+                msg = handler.recv().fuse() => {
+                    if let Ok(Message::TaskMessage(msg)) = msg {
+                        messages.push(msg);
+                    }
+                },
+                res = run => break res,
+            }
+        }
+    });
+    while let Ok(msg) = handler.try_recv() {
+        if let Message::TaskMessage(msg) = msg {
+            messages.push(msg);
+        }
+    }
+
+    Recording {
+        result,
+        messages,
+        elapsed: clock.now() - started,
+    }
+}
@@ -1,3 +1,6 @@
 pub mod action;
+pub mod clock;
+pub mod harness;
 pub mod resource;
-pub mod task;
+#[cfg(feature = "vnc")]
+pub mod vnc;
@@ -0,0 +1,949 @@
+//! 进程内的 RFB 服务器，用于端到端测试 `cice-runtime-vnc`
+//!
+//! 服务器只监听 `127.0.0.1` 的随机端口，不需要网络或桌面环境：
+//! - 提供脚本化的帧缓冲，测试中可随时用 [`VncServer::set_frame`] 换成新画面，
+//!   客户端请求增量更新时收到新画面；尺寸改变且客户端支持时先发送 DesktopSize
+//! - 按顺序记录客户端发来的指针和按键事件
+//! - 可配置协议版本、VNC 密码认证，以及服务器愿意使用的编码
+//!
+//! 每次更新都发送整个画面，编码从客户端 SetEncodings 的列表中按客户端的偏好选第一个服务器启用的，
+//! 都不可用时使用 Raw。像素按客户端 SetPixelFormat 要求的格式编码，只支持真彩色。
+//!
+//! ```ignore
+//! let server = VncServer::builder()
+//!     .frame(RgbImage::from_pixel(64, 48, Rgb([255, 0, 0])))
+//!     .encodings([Encoding::Zrle])
+//!     .start()
+//!     .await
+//!     .unwrap();
+//! let runtime = VncRuntime::new(server.address(), None);
+//! runtime.connect().await.unwrap();
+//! runtime.click(10, 20, 1).await.unwrap();
+//! let events = server.wait_for_events(2).await;
+//! ```
+
+use std::io;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use des::cipher::{BlockEncrypt, KeyInit};
+use des::Des;
+use flate2::{Compress, Compression, FlushCompress};
+use image::RgbImage;
+use tokio::io::{AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::net::tcp::OwnedReadHalf;
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::{mpsc, watch};
+use tokio::task::{JoinHandle, JoinSet};
+
+/// 服务器发出的协议版本
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ProtocolVersion {
+    V3_3,
+    V3_7,
+    V3_8,
+}
+
+/// 像素格式，字段与 RFB 线上格式一一对应
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PixelFormat {
+    pub bits_per_pixel: u8,
+    pub depth: u8,
+    pub big_endian: bool,
+    pub true_colour: bool,
+    pub red_max: u16,
+    pub green_max: u16,
+    pub blue_max: u16,
+    pub red_shift: u8,
+    pub green_shift: u8,
+    pub blue_shift: u8,
+}
+
+impl PixelFormat {
+    /// 解码 SetPixelFormat 中的格式，忽略末尾 3 字节的填充
+    fn from_bytes(bytes: &[u8; 16]) -> Self {
+        Self {
+            bits_per_pixel: bytes[0],
+            depth: bytes[1],
+            big_endian: bytes[2] != 0,
+            true_colour: bytes[3] != 0,
+            red_max: u16::from_be_bytes([bytes[4], bytes[5]]),
+            green_max: u16::from_be_bytes([bytes[6], bytes[7]]),
+            blue_max: u16::from_be_bytes([bytes[8], bytes[9]]),
+            red_shift: bytes[10],
+            green_shift: bytes[11],
+            blue_shift: bytes[12],
+        }
+    }
+
+    /// 编码为 ServerInit 中的格式，末尾 3 字节为填充
+    fn to_bytes(self) -> [u8; 16] {
+        let mut bytes = [0; 16];
+        bytes[0] = self.bits_per_pixel;
+        bytes[1] = self.depth;
+        bytes[2] = u8::from(self.big_endian);
+        bytes[3] = u8::from(self.true_colour);
+        bytes[4..6].copy_from_slice(&self.red_max.to_be_bytes());
+        bytes[6..8].copy_from_slice(&self.green_max.to_be_bytes());
+        bytes[8..10].copy_from_slice(&self.blue_max.to_be_bytes());
+        bytes[10] = self.red_shift;
+        bytes[11] = self.green_shift;
+        bytes[12] = self.blue_shift;
+        bytes
+    }
+}
+
+/// ServerInit 默认的像素格式：32 位小端真彩色，内存中依次为蓝、绿、红、填充，与常见 VNC 服务器一致
+pub const DEFAULT_PIXEL_FORMAT: PixelFormat = PixelFormat {
+    bits_per_pixel: 32,
+    depth: 24,
+    big_endian: false,
+    true_colour: true,
+    red_max: 255,
+    green_max: 255,
+    blue_max: 255,
+    red_shift: 16,
+    green_shift: 8,
+    blue_shift: 0,
+};
+
+/// [`VncServer::wait_for_events`] 的等待上限
+const EVENT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// DesktopSize 伪编码
+const DESKTOP_SIZE: i32 = -223;
+
+/// 服务器可以使用的编码
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Encoding {
+    Raw,
+    Rre,
+    Hextile,
+    Zrle,
+    Tight,
+}
+
+impl Encoding {
+    pub const ALL: [Encoding; 5] = [
+        Encoding::Raw,
+        Encoding::Rre,
+        Encoding::Hextile,
+        Encoding::Zrle,
+        Encoding::Tight,
+    ];
+
+    /// SetEncodings 和矩形头中的编码号
+    pub fn code(self) -> i32 {
+        match self {
+            Encoding::Raw => 0,
+            Encoding::Rre => 2,
+            Encoding::Hextile => 5,
+            Encoding::Tight => 7,
+            Encoding::Zrle => 16,
+        }
+    }
+}
+
+/// 客户端发来的输入事件
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InputEvent {
+    /// PointerEvent，`buttons` 的第 0/1/2 位依次为左键、中键、右键，第 3/4 位为滚轮上下
+    Pointer { x: u16, y: u16, buttons: u8 },
+    /// KeyEvent，`keysym` 为 X11 keysym
+    Key { keysym: u32, down: bool },
+}
+
+/// [`VncServer`] 的配置
+pub struct VncServerBuilder {
+    frame: RgbImage,
+    name: String,
+    version: ProtocolVersion,
+    password: Option<String>,
+    encodings: Vec<Encoding>,
+    pixel_format: PixelFormat,
+}
+
+impl Default for VncServerBuilder {
+    fn default() -> Self {
+        Self {
+            frame: RgbImage::new(640, 480),
+            name: "cice-test".to_string(),
+            version: ProtocolVersion::V3_8,
+            password: None,
+            encodings: Encoding::ALL.to_vec(),
+            pixel_format: DEFAULT_PIXEL_FORMAT,
+        }
+    }
+}
+
+impl VncServerBuilder {
+    /// 初始画面，默认为 640x480 的黑屏
+    pub fn frame(mut self, frame: RgbImage) -> Self {
+        self.frame = frame;
+        self
+    }
+
+    /// 桌面名称
+    pub fn name(mut self, name: impl Into<String>) -> Self {
+        self.name = name.into();
+        self
+    }
+
+    /// 服务器发出的协议版本，默认 3.8
+    pub fn version(mut self, version: ProtocolVersion) -> Self {
+        self.version = version;
+        self
+    }
+
+    /// 要求 VNC 密码认证，默认不认证
+    pub fn password(mut self, password: impl Into<String>) -> Self {
+        self.password = Some(password.into());
+        self
+    }
+
+    /// 服务器愿意使用的编码，默认全部；Raw 总是可用
+    pub fn encodings(mut self, encodings: impl IntoIterator<Item = Encoding>) -> Self {
+        self.encodings = encodings.into_iter().collect();
+        self
+    }
+
+    /// ServerInit 中的像素格式，客户端未发送 SetPixelFormat 时按它编码
+    pub fn pixel_format(mut self, pixel_format: PixelFormat) -> Self {
+        self.pixel_format = pixel_format;
+        self
+    }
+
+    /// 在 `127.0.0.1` 的随机端口开始监听
+    pub async fn start(self) -> io::Result<VncServer> {
+        check_size(&self.frame);
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let address = listener.local_addr()?;
+        let (frame, _) = watch::channel(Arc::new(self.frame));
+        let (events, _) = watch::channel(Vec::new());
+        let shared = Arc::new(Shared {
+            name: self.name,
+            version: self.version,
+            password: self.password,
+            encodings: self.encodings,
+            pixel_format: self.pixel_format,
+            frame,
+            events,
+            last_encoding: Mutex::new(None),
+        });
+        let accept = tokio::spawn(accept(listener, shared.clone()));
+        Ok(VncServer {
+            address,
+            shared,
+            accept,
+        })
+    }
+}
+
+/// 进程内的 RFB 服务器，释放时关闭监听和所有连接
+pub struct VncServer {
+    address: SocketAddr,
+    shared: Arc<Shared>,
+    accept: JoinHandle<()>,
+}
+
+/// 各连接共享的状态
+struct Shared {
+    name: String,
+    version: ProtocolVersion,
+    password: Option<String>,
+    encodings: Vec<Encoding>,
+    pixel_format: PixelFormat,
+    frame: watch::Sender<Arc<RgbImage>>,
+    events: watch::Sender<Vec<InputEvent>>,
+    last_encoding: Mutex<Option<Encoding>>,
+}
+
+impl VncServer {
+    pub fn builder() -> VncServerBuilder {
+        VncServerBuilder::default()
+    }
+
+    /// "host:port" 形式的监听地址
+    pub fn address(&self) -> String {
+        self.address.to_string()
+    }
+
+    /// 换成新画面，已连接的客户端在下一次更新中收到
+    pub fn set_frame(&self, frame: RgbImage) {
+        check_size(&frame);
+        self.shared.frame.send_replace(Arc::new(frame));
+    }
+
+    /// 当前画面
+    pub fn frame(&self) -> Arc<RgbImage> {
+        self.shared.frame.borrow().clone()
+    }
+
+    /// 至今收到的全部输入事件
+    pub fn events(&self) -> Vec<InputEvent> {
+        self.shared.events.borrow().clone()
+    }
+
+    /// 清空已记录的输入事件
+    pub fn clear_events(&self) {
+        self.shared.events.send_replace(Vec::new());
+    }
+
+    /// 等待收到至少 `count` 个输入事件，返回全部事件
+    ///
+    /// # Panics
+    /// 5 秒内没有收到足够的事件时 panic。
+    pub async fn wait_for_events(&self, count: usize) -> Vec<InputEvent> {
+        let mut events = self.shared.events.subscribe();
+        let waited = tokio::time::timeout(
+            EVENT_TIMEOUT,
+            events.wait_for(|events| events.len() >= count),
+        )
+        .await;
+        match waited {
+            Ok(Ok(events)) => events.clone(),
+            _ => panic!(
+                "expected {count} input events within {EVENT_TIMEOUT:?}, got {:?}",
+                self.events()
+            ),
+        }
+    }
+
+    /// 最近一次更新使用的编码，还没有发送过更新时为 None
+    pub fn last_encoding(&self) -> Option<Encoding> {
+        *self.shared.last_encoding.lock().unwrap()
+    }
+}
+
+impl Drop for VncServer {
+    fn drop(&mut self) {
+        self.accept.abort();
+    }
+}
+
+fn check_size(frame: &RgbImage) {
+    assert!(
+        frame.width() <= u32::from(u16::MAX) && frame.height() <= u32::from(u16::MAX),
+        "the frame is {}x{}, larger than RFB allows",
+        frame.width(),
+        frame.height()
+    );
+}
+
+/// 接受连接，监听任务终止时 `clients` 随之释放，所有连接一起关闭
+async fn accept(listener: TcpListener, shared: Arc<Shared>) {
+    let mut clients = JoinSet::new();
+    while let Ok((stream, _)) = listener.accept().await {
+        let shared = shared.clone();
+        clients.spawn(async move {
+            if let Err(err) = serve(stream, shared).await {
+                log::debug!("VNC test server closed a connection: {err}");
+            }
+        });
+    }
+}
+
+/// 客户端发来的、需要连接主循环处理的消息
+enum ClientMessage {
+    SetPixelFormat(PixelFormat),
+    SetEncodings(Vec<i32>),
+    UpdateRequest { incremental: bool },
+}
+
+async fn serve(mut stream: TcpStream, shared: Arc<Shared>) -> io::Result<()> {
+    stream.set_nodelay(true)?;
+    let mut frame = shared.frame.subscribe();
+    let (mut width, mut height) = size(&frame.borrow());
+    if !handshake(&mut stream, &shared, width, height).await? {
+        return Ok(());
+    }
+
+    let (reader, mut writer) = stream.into_split();
+    let (sender, mut messages) = mpsc::unbounded_channel();
+    let reader = tokio::spawn(read_messages(reader, sender, shared.clone()));
+    let mut client = Client::new(shared.pixel_format);
+    // 客户端请求了更新但还没有回复；为 true 时立即回复，否则等画面变化
+    let mut pending = None;
+    let result = loop {
+        tokio::select! {
+            message = messages.recv() => match message {
+                Some(ClientMessage::SetPixelFormat(format)) => client.format = format,
+                Some(ClientMessage::SetEncodings(encodings)) => client.encodings = encodings,
+                Some(ClientMessage::UpdateRequest { incremental }) => {
+                    pending = Some(pending.unwrap_or(false) || !incremental);
+                }
+                None => break Ok(()),
+            },
+            changed = frame.changed(), if pending.is_some() => {
+                if changed.is_err() {
+                    break Ok(());
+                }
+                pending = Some(true);
+            }
+        }
+        if pending != Some(true) {
+            continue;
+        }
+        pending = None;
+        let image = frame.borrow_and_update().clone();
+        let resize = size(&image) != (width, height);
+        if resize && !client.encodings.contains(&DESKTOP_SIZE) {
+            log::debug!("the client does not support DesktopSize, the new frame is cropped");
+        }
+        let (update, encoding) = client.update(&image, &shared.encodings, resize, (width, height));
+        if resize && client.encodings.contains(&DESKTOP_SIZE) {
+            (width, height) = size(&image);
+        }
+        *shared.last_encoding.lock().unwrap() = Some(encoding);
+        if let Err(err) = writer.write_all(&update).await {
+            break Err(err);
+        }
+    };
+    reader.abort();
+    result
+}
+
+fn size(image: &RgbImage) -> (u16, u16) {
+    (image.width() as u16, image.height() as u16)
+}
+
+/// 完成版本协商、认证和初始化；认证失败返回 false
+async fn handshake(
+    stream: &mut TcpStream,
+    shared: &Shared,
+    width: u16,
+    height: u16,
+) -> io::Result<bool> {
+    stream.write_all(version_message(shared.version)).await?;
+    let mut reply = [0; 12];
+    stream.read_exact(&mut reply).await?;
+    let version = [
+        ProtocolVersion::V3_3,
+        ProtocolVersion::V3_7,
+        ProtocolVersion::V3_8,
+    ]
+    .into_iter()
+    .find(|&version| *version_message(version) == reply && version <= shared.version)
+    .ok_or_else(|| invalid(format!("unexpected version reply {reply:?}")))?;
+
+    let security = if shared.password.is_some() { 2 } else { 1 };
+    if version == ProtocolVersion::V3_3 {
+        stream.write_u32(u32::from(security)).await?;
+    } else {
+        stream.write_all(&[1, security]).await?;
+        let chosen = stream.read_u8().await?;
+        if chosen != security {
+            return Err(invalid(format!("the client chose security type {chosen}")));
+        }
+    }
+
+    if let Some(password) = &shared.password {
+        let challenge = challenge();
+        stream.write_all(&challenge).await?;
+        let mut response = [0; 16];
+        stream.read_exact(&mut response).await?;
+        if response != vnc_response(password, challenge) {
+            stream.write_u32(1).await?;
+            if version == ProtocolVersion::V3_8 {
+                write_string(stream, "wrong password").await?;
+            }
+            return Ok(false);
+        }
+        stream.write_u32(0).await?;
+    } else if version == ProtocolVersion::V3_8 {
+        stream.write_u32(0).await?;
+    }
+
+    // ClientInit 的共享标志不影响测试服务器
+    stream.read_u8().await?;
+    stream.write_u16(width).await?;
+    stream.write_u16(height).await?;
+    stream.write_all(&shared.pixel_format.to_bytes()).await?;
+    write_string(stream, &shared.name).await?;
+    Ok(true)
+}
+
+fn version_message(version: ProtocolVersion) -> &'static [u8; 12] {
+    match version {
+        ProtocolVersion::V3_3 => b"RFB 003.003\n",
+        ProtocolVersion::V3_7 => b"RFB 003.007\n",
+        ProtocolVersion::V3_8 => b"RFB 003.008\n",
+    }
+}
+
+/// 每次不同的认证挑战
+fn challenge() -> [u8; 16] {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_nanos();
+    nanos.to_le_bytes()
+}
+
+/// 客户端对挑战的正确应答：用 `des` crate 以密码为密钥分两组加密挑战
+///
+/// 密钥为密码的前 8 个字节，不足补 0；VNC 把密钥的每个字节按位倒序使用。
+fn vnc_response(password: &str, challenge: [u8; 16]) -> [u8; 16] {
+    let mut key = [0; 8];
+    for (byte, password) in key.iter_mut().zip(password.bytes()) {
+        *byte = password.reverse_bits();
+    }
+    let cipher = Des::new(&key.into());
+    let mut response = challenge;
+    for block in response.chunks_exact_mut(8) {
+        cipher.encrypt_block(block.into());
+    }
+    response
+}
+
+async fn write_string(stream: &mut TcpStream, text: &str) -> io::Result<()> {
+    stream.write_u32(text.len() as u32).await?;
+    stream.write_all(text.as_bytes()).await
+}
+
+fn invalid(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+/// 读取客户端消息：输入事件直接记录，其余交给连接主循环
+async fn read_messages(
+    reader: OwnedReadHalf,
+    messages: mpsc::UnboundedSender<ClientMessage>,
+    shared: Arc<Shared>,
+) -> io::Result<()> {
+    let mut reader = BufReader::new(reader);
+    loop {
+        let message = match reader.read_u8().await? {
+            0 => {
+                let mut bytes = [0; 19];
+                reader.read_exact(&mut bytes).await?;
+                let format: [u8; 16] = bytes[3..].try_into().unwrap();
+                ClientMessage::SetPixelFormat(PixelFormat::from_bytes(&format))
+            }
+            2 => {
+                reader.read_u8().await?;
+                let count = reader.read_u16().await?;
+                let mut encodings = Vec::with_capacity(usize::from(count));
+                for _ in 0..count {
+                    encodings.push(reader.read_i32().await?);
+                }
+                ClientMessage::SetEncodings(encodings)
+            }
+            3 => {
+                let incremental = reader.read_u8().await? != 0;
+                // 总是发送整个画面，忽略请求的区域
+                let mut region = [0; 8];
+                reader.read_exact(&mut region).await?;
+                ClientMessage::UpdateRequest { incremental }
+            }
+            4 => {
+                let down = reader.read_u8().await? != 0;
+                reader.read_u16().await?;
+                let keysym = reader.read_u32().await?;
+                record(&shared, InputEvent::Key { keysym, down });
+                continue;
+            }
+            5 => {
+                let buttons = reader.read_u8().await?;
+                let x = reader.read_u16().await?;
+                let y = reader.read_u16().await?;
+                record(&shared, InputEvent::Pointer { x, y, buttons });
+                continue;
+            }
+            6 => {
+                let mut padding = [0; 3];
+                reader.read_exact(&mut padding).await?;
+                let length = reader.read_u32().await?;
+                let mut text = vec![0; length as usize];
+                reader.read_exact(&mut text).await?;
+                continue;
+            }
+            message => return Err(invalid(format!("unknown client message {message}"))),
+        };
+        if messages.send(message).is_err() {
+            return Ok(());
+        }
+    }
+}
+
+fn record(shared: &Shared, event: InputEvent) {
+    shared.events.send_modify(|events| events.push(event));
+}
+
+/// 一个连接的编码状态
+struct Client {
+    format: PixelFormat,
+    /// 客户端按偏好排列的编码
+    encodings: Vec<i32>,
+    /// ZRLE 与 Tight 的 zlib 流在整个连接中延续
+    zrle: Compress,
+    tight: [Compress; 2],
+}
+
+impl Client {
+    fn new(format: PixelFormat) -> Self {
+        Self {
+            format,
+            encodings: Vec::new(),
+            zrle: Compress::new(Compression::fast(), true),
+            tight: std::array::from_fn(|_| Compress::new(Compression::fast(), true)),
+        }
+    }
+
+    /// 发送整个画面的 FramebufferUpdate
+    ///
+    /// `resize` 时客户端支持 DesktopSize 则先发送新尺寸，否则把画面裁剪到客户端的尺寸 `size`。
+    fn update(
+        &mut self,
+        image: &RgbImage,
+        enabled: &[Encoding],
+        resize: bool,
+        client_size: (u16, u16),
+    ) -> (Vec<u8>, Encoding) {
+        let encoding = self
+            .encodings
+            .iter()
+            .find_map(|&code| {
+                enabled
+                    .iter()
+                    .copied()
+                    .find(|encoding| encoding.code() == code)
+            })
+            .unwrap_or(Encoding::Raw);
+        let desktop_size = resize && self.encodings.contains(&DESKTOP_SIZE);
+        let (width, height) = if resize && !desktop_size {
+            let (image_width, image_height) = size(image);
+            (
+                client_size.0.min(image_width),
+                client_size.1.min(image_height),
+            )
+        } else {
+            size(image)
+        };
+
+        let mut message = vec![0, 0];
+        message.extend_from_slice(&(1 + u16::from(desktop_size)).to_be_bytes());
+        if desktop_size {
+            rect_header(&mut message, [0, 0, width, height], DESKTOP_SIZE);
+        }
+        rect_header(&mut message, [0, 0, width, height], encoding.code());
+        let region = Region {
+            image,
+            width,
+            height,
+        };
+        match encoding {
+            Encoding::Raw => self.raw(&region, &mut message),
+            Encoding::Rre => self.rre(&region, &mut message),
+            Encoding::Hextile => self.hextile(&region, &mut message),
+            Encoding::Zrle => self.zrle(&region, &mut message),
+            Encoding::Tight => self.tight(&region, &mut message),
+        }
+        (message, encoding)
+    }
+
+    /// 按客户端的像素格式编码一个像素
+    fn pixel(&self, color: [u8; 3], output: &mut Vec<u8>) {
+        let format = &self.format;
+        let scale = |value: u8, max: u16| u32::from(value) * u32::from(max) / 255;
+        let value = (scale(color[0], format.red_max) << format.red_shift)
+            | (scale(color[1], format.green_max) << format.green_shift)
+            | (scale(color[2], format.blue_max) << format.blue_shift);
+        match (format.bits_per_pixel, format.big_endian) {
+            (8, _) => output.push(value as u8),
+            (16, true) => output.extend_from_slice(&(value as u16).to_be_bytes()),
+            (16, false) => output.extend_from_slice(&(value as u16).to_le_bytes()),
+            (_, true) => output.extend_from_slice(&value.to_be_bytes()),
+            (_, false) => output.extend_from_slice(&value.to_le_bytes()),
+        }
+    }
+
+    /// ZRLE 的 CPIXEL：32 位且颜色只占低 3 字节时省去空闲的字节
+    fn cpixel(&self, color: [u8; 3], output: &mut Vec<u8>) {
+        let start = output.len();
+        self.pixel(color, output);
+        if self.format.bits_per_pixel == 32 && self.format.depth <= 24 {
+            if self.format.big_endian {
+                output.remove(start);
+            } else {
+                output.pop();
+            }
+        }
+    }
+
+    /// Tight 的 TPIXEL：32 位 24 位色深时为红、绿、蓝 3 字节
+    fn tpixel(&self, color: [u8; 3], output: &mut Vec<u8>) {
+        let format = &self.format;
+        let rgb888 = format.bits_per_pixel == 32
+            && format.depth == 24
+            && [format.red_max, format.green_max, format.blue_max] == [255; 3];
+        if rgb888 {
+            output.extend_from_slice(&color);
+        } else {
+            self.pixel(color, output);
+        }
+    }
+
+    fn raw(&self, region: &Region<'_>, output: &mut Vec<u8>) {
+        for color in region.colors(0, 0, region.width, region.height) {
+            self.pixel(color, output);
+        }
+    }
+
+    /// 背景取左上角的颜色，其余像素按行合并成横向的子矩形
+    fn rre(&self, region: &Region<'_>, output: &mut Vec<u8>) {
+        let background = region.color(0, 0);
+        let runs = region.runs(0, 0, region.width, region.height, background);
+        output.extend_from_slice(&(runs.len() as u32).to_be_bytes());
+        self.pixel(background, output);
+        for (color, [x, y, width, height]) in runs {
+            self.pixel(color, output);
+            for value in [x, y, width, height] {
+                output.extend_from_slice(&value.to_be_bytes());
+            }
+        }
+    }
+
+    /// 单色图块只发背景；两色图块用前景色子矩形；其余发送原始像素
+    fn hextile(&self, region: &Region<'_>, output: &mut Vec<u8>) {
+        const RAW: u8 = 1;
+        const BACKGROUND_SPECIFIED: u8 = 2;
+        const FOREGROUND_SPECIFIED: u8 = 4;
+        const ANY_SUBRECTS: u8 = 8;
+
+        for y in (0..region.height).step_by(16) {
+            for x in (0..region.width).step_by(16) {
+                let (width, height) = ((region.width - x).min(16), (region.height - y).min(16));
+                let palette = region.palette(x, y, width, height, 3);
+                match palette[..] {
+                    [background] => {
+                        output.push(BACKGROUND_SPECIFIED);
+                        self.pixel(background, output);
+                    }
+                    [background, foreground] => {
+                        let runs = region.runs(x, y, width, height, background);
+                        if runs.len() > usize::from(u8::MAX) {
+                            output.push(RAW);
+                            for color in region.colors(x, y, width, height) {
+                                self.pixel(color, output);
+                            }
+                            continue;
+                        }
+                        output.push(BACKGROUND_SPECIFIED | FOREGROUND_SPECIFIED | ANY_SUBRECTS);
+                        self.pixel(background, output);
+                        self.pixel(foreground, output);
+                        output.push(runs.len() as u8);
+                        for (_, [run_x, run_y, run_width, run_height]) in runs {
+                            output.push((((run_x - x) as u8) << 4) | (run_y - y) as u8);
+                            output.push((((run_width - 1) as u8) << 4) | (run_height - 1) as u8);
+                        }
+                    }
+                    _ => {
+                        output.push(RAW);
+                        for color in region.colors(x, y, width, height) {
+                            self.pixel(color, output);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// 单色图块用纯色，不超过 16 色用打包调色板，其余发送原始 CPIXEL
+    fn zrle(&mut self, region: &Region<'_>, output: &mut Vec<u8>) {
+        let mut data = Vec::new();
+        for y in (0..region.height).step_by(64) {
+            for x in (0..region.width).step_by(64) {
+                let (width, height) = ((region.width - x).min(64), (region.height - y).min(64));
+                let palette = region.palette(x, y, width, height, 17);
+                match palette.len() {
+                    1 => {
+                        data.push(1);
+                        self.cpixel(palette[0], &mut data);
+                    }
+                    2..=16 => {
+                        data.push(palette.len() as u8);
+                        for &color in &palette {
+                            self.cpixel(color, &mut data);
+                        }
+                        let bits = match palette.len() {
+                            2 => 1,
+                            3 | 4 => 2,
+                            _ => 4,
+                        };
+                        for row in y..y + height {
+                            let colors = region.colors(x, row, width, 1);
+                            pack(&colors, &palette, bits, &mut data);
+                        }
+                    }
+                    _ => {
+                        data.push(0);
+                        for color in region.colors(x, y, width, height) {
+                            self.cpixel(color, &mut data);
+                        }
+                    }
+                }
+            }
+        }
+        let compressed = deflate(&mut self.zrle, &data);
+        output.extend_from_slice(&(compressed.len() as u32).to_be_bytes());
+        output.extend_from_slice(&compressed);
+    }
+
+    /// 单色用 Fill；不超过 256 色用调色板滤镜（第 1 个 zlib 流）；其余用复制滤镜（第 0 个 zlib 流）
+    fn tight(&mut self, region: &Region<'_>, output: &mut Vec<u8>) {
+        const FILL: u8 = 0x80;
+        const PALETTE: u8 = 0x50;
+        const PALETTE_FILTER: u8 = 1;
+
+        let colors = region.colors(0, 0, region.width, region.height);
+        let palette = region.palette(0, 0, region.width, region.height, 257);
+        match palette.len() {
+            1 => {
+                output.push(FILL);
+                output.extend_from_slice(&palette[0]);
+            }
+            2..=256 => {
+                output.extend_from_slice(&[PALETTE, PALETTE_FILTER, (palette.len() - 1) as u8]);
+                for &color in &palette {
+                    self.tpixel(color, output);
+                }
+                let mut indices = Vec::new();
+                if palette.len() == 2 {
+                    for row in colors.chunks_exact(usize::from(region.width)) {
+                        pack(row, &palette, 1, &mut indices);
+                    }
+                } else {
+                    indices.extend(colors.iter().map(|color| index(&palette, *color)));
+                }
+                self.tight_data(1, &indices, output);
+            }
+            _ => {
+                output.push(0);
+                let mut data = Vec::new();
+                for color in colors {
+                    self.tpixel(color, &mut data);
+                }
+                self.tight_data(0, &data, output);
+            }
+        }
+    }
+
+    /// 短于 12 字节的数据不压缩，其余写入紧凑长度和压缩数据
+    fn tight_data(&mut self, stream: usize, data: &[u8], output: &mut Vec<u8>) {
+        if data.len() < 12 {
+            output.extend_from_slice(data);
+            return;
+        }
+        let compressed = deflate(&mut self.tight[stream], data);
+        let length = compressed.len();
+        output.push((length & 0x7f) as u8 | if length > 0x7f { 0x80 } else { 0 });
+        if length > 0x7f {
+            output.push(((length >> 7) & 0x7f) as u8 | if length > 0x3fff { 0x80 } else { 0 });
+        }
+        if length > 0x3fff {
+            output.push((length >> 14) as u8);
+        }
+        output.extend_from_slice(&compressed);
+    }
+}
+
+fn rect_header(output: &mut Vec<u8>, bounds: [u16; 4], encoding: i32) {
+    for value in bounds {
+        output.extend_from_slice(&value.to_be_bytes());
+    }
+    output.extend_from_slice(&encoding.to_be_bytes());
+}
+
+/// 要发送的画面，宽高可能因裁剪小于图像
+struct Region<'a> {
+    image: &'a RgbImage,
+    width: u16,
+    height: u16,
+}
+
+impl Region<'_> {
+    fn color(&self, x: u16, y: u16) -> [u8; 3] {
+        self.image.get_pixel(u32::from(x), u32::from(y)).0
+    }
+
+    /// 区域内按行排列的颜色
+    fn colors(&self, x: u16, y: u16, width: u16, height: u16) -> Vec<[u8; 3]> {
+        (y..y + height)
+            .flat_map(|row| (x..x + width).map(move |column| (column, row)))
+            .map(|(column, row)| self.color(column, row))
+            .collect()
+    }
+
+    /// 区域内按首次出现排列的颜色，最多 `limit` 个
+    fn palette(&self, x: u16, y: u16, width: u16, height: u16, limit: usize) -> Vec<[u8; 3]> {
+        let mut palette = Vec::new();
+        for color in self.colors(x, y, width, height) {
+            if !palette.contains(&color) {
+                palette.push(color);
+                if palette.len() == limit {
+                    break;
+                }
+            }
+        }
+        palette
+    }
+
+    /// 区域内不是 `background` 的像素按行合并成的横向矩形，坐标相对于整个画面
+    fn runs(
+        &self,
+        x: u16,
+        y: u16,
+        width: u16,
+        height: u16,
+        background: [u8; 3],
+    ) -> Vec<([u8; 3], [u16; 4])> {
+        let mut runs = Vec::new();
+        for row in y..y + height {
+            let mut column = x;
+            while column < x + width {
+                let color = self.color(column, row);
+                let start = column;
+                while column < x + width && self.color(column, row) == color {
+                    column += 1;
+                }
+                if color != background {
+                    runs.push((color, [start, row, column - start, 1]));
+                }
+            }
+        }
+        runs
+    }
+}
+
+fn index(palette: &[[u8; 3]], color: [u8; 3]) -> u8 {
+    palette.iter().position(|&entry| entry == color).unwrap() as u8
+}
+
+/// 把一行颜色按调色板索引打包，高位在前，行末补齐到整字节
+fn pack(colors: &[[u8; 3]], palette: &[[u8; 3]], bits: usize, output: &mut Vec<u8>) {
+    for chunk in colors.chunks(8 / bits) {
+        let mut byte = 0;
+        for (position, &color) in chunk.iter().enumerate() {
+            byte |= index(palette, color) << (8 - bits * (position + 1));
+        }
+        output.push(byte);
+    }
+}
+
+/// 用延续的 zlib 流压缩，以同步刷新结束，客户端可以立即解压
+fn deflate(zlib: &mut Compress, input: &[u8]) -> Vec<u8> {
+    let mut output = Vec::with_capacity(input.len() / 2 + 64);
+    let start = zlib.total_in();
+    loop {
+        output.reserve(1024);
+        let consumed = (zlib.total_in() - start) as usize;
+        zlib.compress_vec(&input[consumed..], &mut output, FlushCompress::Sync)
+            .expect("zlib compression does not fail on valid input");
+        let done = (zlib.total_in() - start) as usize == input.len();
+        if done && output.len() < output.capacity() {
+            return output;
+        }
+    }
+}
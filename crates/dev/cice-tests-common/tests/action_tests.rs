@@ -17,41 +17,50 @@ async fn test_simple_action_sequence() {
     let mut builder = ContextBuilder::new(runtime);
 
     // 添加任务
-    builder.add_task(
-        TaskConfig {
-            task_name: "task1".to_string(),
-            action_name: "action1".to_string(),
-            next_task: vec!["task2".to_string()],
-            interrupt_task: vec![],
-            timeout: Duration::from_secs(30),
-            max_retry: 3,
-        },
-        &action1,
-    );
-
-    builder.add_task(
-        TaskConfig {
-            task_name: "task2".to_string(),
-            action_name: "action2".to_string(),
-            next_task: vec!["task3".to_string()],
-            interrupt_task: vec![],
-            timeout: Duration::from_secs(30),
-            max_retry: 3,
-        },
-        &action2,
-    );
-
-    builder.add_task(
-        TaskConfig {
-            task_name: "task3".to_string(),
-            action_name: "action3".to_string(),
-            next_task: vec![],
-            interrupt_task: vec![],
-            timeout: Duration::from_secs(30),
-            max_retry: 3,
-        },
-        &action3,
-    );
+    builder
+        .add_task(
+            TaskConfig {
+                task_name: "task1".to_string(),
+                action_name: "action1".to_string(),
+                next_task: vec!["task2".to_string()],
+                interrupt_task: vec![],
+                guards: Default::default(),
+                timeout: Duration::from_secs(30),
+                max_retry: 3,
+            },
+            &action1,
+        )
+        .unwrap();
+
+    builder
+        .add_task(
+            TaskConfig {
+                task_name: "task2".to_string(),
+                action_name: "action2".to_string(),
+                next_task: vec!["task3".to_string()],
+                interrupt_task: vec![],
+                guards: Default::default(),
+                timeout: Duration::from_secs(30),
+                max_retry: 3,
+            },
+            &action2,
+        )
+        .unwrap();
+
+    builder
+        .add_task(
+            TaskConfig {
+                task_name: "task3".to_string(),
+                action_name: "action3".to_string(),
+                next_task: vec![],
+                interrupt_task: vec![],
+                guards: Default::default(),
+                timeout: Duration::from_secs(30),
+                max_retry: 3,
+            },
+            &action3,
+        )
+        .unwrap();
 
     // 构建并运行
     let context = builder.build();
@@ -70,41 +79,50 @@ async fn test_action_with_deny() {
     let mut builder = ContextBuilder::new(runtime);
 
     // 添加一个会失败的任务和一个会成功的任务
-    builder.add_task(
-        TaskConfig {
-            task_name: "entry".to_string(),
-            action_name: "entry_action".to_string(),
-            next_task: vec!["task_deny".to_string(), "task_accept".to_string()],
-            interrupt_task: vec![],
-            timeout: Duration::from_secs(30),
-            max_retry: 3,
-        },
-        &action_accept,
-    );
-
-    builder.add_task(
-        TaskConfig {
-            task_name: "task_deny".to_string(),
-            action_name: "deny_action".to_string(),
-            next_task: vec![],
-            interrupt_task: vec![],
-            timeout: Duration::from_secs(30),
-            max_retry: 3,
-        },
-        &action_deny,
-    );
-
-    builder.add_task(
-        TaskConfig {
-            task_name: "task_accept".to_string(),
-            action_name: "accept_action".to_string(),
-            next_task: vec![],
-            interrupt_task: vec![],
-            timeout: Duration::from_secs(30),
-            max_retry: 3,
-        },
-        &action_accept,
-    );
+    builder
+        .add_task(
+            TaskConfig {
+                task_name: "entry".to_string(),
+                action_name: "entry_action".to_string(),
+                next_task: vec!["task_deny".to_string(), "task_accept".to_string()],
+                interrupt_task: vec![],
+                guards: Default::default(),
+                timeout: Duration::from_secs(30),
+                max_retry: 3,
+            },
+            &action_accept,
+        )
+        .unwrap();
+
+    builder
+        .add_task(
+            TaskConfig {
+                task_name: "task_deny".to_string(),
+                action_name: "deny_action".to_string(),
+                next_task: vec![],
+                interrupt_task: vec![],
+                guards: Default::default(),
+                timeout: Duration::from_secs(30),
+                max_retry: 3,
+            },
+            &action_deny,
+        )
+        .unwrap();
+
+    builder
+        .add_task(
+            TaskConfig {
+                task_name: "task_accept".to_string(),
+                action_name: "accept_action".to_string(),
+                next_task: vec![],
+                interrupt_task: vec![],
+                guards: Default::default(),
+                timeout: Duration::from_secs(30),
+                max_retry: 3,
+            },
+            &action_accept,
+        )
+        .unwrap();
 
     let context = builder.build();
     let result = context.run("entry".to_string()).await;
@@ -122,41 +140,50 @@ async fn test_configurable_action() {
 
     let mut builder = ContextBuilder::new(runtime);
 
-    builder.add_task(
-        TaskConfig {
-            task_name: "entry".to_string(),
-            action_name: "entry_action".to_string(),
-            next_task: vec!["task_fail".to_string(), "task_success".to_string()],
-            interrupt_task: vec![],
-            timeout: Duration::from_secs(30),
-            max_retry: 3,
-        },
-        &action_success,
-    );
-
-    builder.add_task(
-        TaskConfig {
-            task_name: "task_fail".to_string(),
-            action_name: "fail_action".to_string(),
-            next_task: vec![],
-            interrupt_task: vec![],
-            timeout: Duration::from_secs(30),
-            max_retry: 3,
-        },
-        &action_fail,
-    );
-
-    builder.add_task(
-        TaskConfig {
-            task_name: "task_success".to_string(),
-            action_name: "success_action".to_string(),
-            next_task: vec![],
-            interrupt_task: vec![],
-            timeout: Duration::from_secs(30),
-            max_retry: 3,
-        },
-        &action_success,
-    );
+    builder
+        .add_task(
+            TaskConfig {
+                task_name: "entry".to_string(),
+                action_name: "entry_action".to_string(),
+                next_task: vec!["task_fail".to_string(), "task_success".to_string()],
+                interrupt_task: vec![],
+                guards: Default::default(),
+                timeout: Duration::from_secs(30),
+                max_retry: 3,
+            },
+            &action_success,
+        )
+        .unwrap();
+
+    builder
+        .add_task(
+            TaskConfig {
+                task_name: "task_fail".to_string(),
+                action_name: "fail_action".to_string(),
+                next_task: vec![],
+                interrupt_task: vec![],
+                guards: Default::default(),
+                timeout: Duration::from_secs(30),
+                max_retry: 3,
+            },
+            &action_fail,
+        )
+        .unwrap();
+
+    builder
+        .add_task(
+            TaskConfig {
+                task_name: "task_success".to_string(),
+                action_name: "success_action".to_string(),
+                next_task: vec![],
+                interrupt_task: vec![],
+                guards: Default::default(),
+                timeout: Duration::from_secs(30),
+                max_retry: 3,
+            },
+            &action_success,
+        )
+        .unwrap();
 
     let context = builder.build();
     let result = context.run("entry".to_string()).await;
@@ -0,0 +1,117 @@
+use std::time::Duration;
+
+use async_trait::async_trait;
+use cice_core::action::{Action, ExecError, RecognizeError};
+use cice_core::context::ContextBuilder;
+use cice_core::runtime::executor::{
+    AsyncStdExecutor, Sleeper, SmolExecutor, Spawner, TokioExecutor,
+};
+use cice_core::runtime::ext::ExecutorExt;
+use cice_core::runtime::Runtime;
+use cice_core::task::{TaskConfig, TaskError, TaskResult};
+
+/// 由任意执行器驱动的 Runtime
+struct ExecutorRuntime<E>(E);
+
+impl<E: Spawner + Sleeper> Runtime for ExecutorRuntime<E> {}
+
+impl<E: Spawner + Sleeper> ExecutorExt for ExecutorRuntime<E> {
+    type Executor = E;
+
+    fn executor(&self) -> &Self::Executor {
+        &self.0
+    }
+}
+
+/// 固定识别结果的 Action，可用于任意 Runtime
+struct FixedAction(bool);
+
+#[async_trait]
+impl<R: Runtime> Action<R> for FixedAction {
+    async fn recognize(&self, _runtime: &R) -> Result<(), RecognizeError> {
+        if self.0 {
+            Ok(())
+        } else {
+            Err(RecognizeError::UnRecognized)
+        }
+    }
+
+    async fn exec(&self, _runtime: &R) -> Result<(), ExecError> {
+        Ok(())
+    }
+}
+
+static ACCEPT: FixedAction = FixedAction(true);
+static DENY: FixedAction = FixedAction(false);
+
+fn task_config(name: &str, next_task: &[&str], timeout: Duration) -> TaskConfig {
+    TaskConfig {
+        task_name: name.to_string(),
+        action_name: name.to_string(),
+        next_task: next_task.iter().map(|id| id.to_string()).collect(),
+        interrupt_task: vec![],
+        guards: Default::default(),
+        timeout,
+        max_retry: 100,
+    }
+}
+
+/// 使用执行器并行识别：entry -> [deny, accept]
+async fn parallel_recognition<E: Spawner + Sleeper + Clone + 'static>(executor: E) {
+    let mut builder = ContextBuilder::new(ExecutorRuntime(executor.clone()));
+    builder.spawner(executor);
+    builder
+        .add_task(
+            task_config("entry", &["deny", "accept"], Duration::from_secs(5)),
+            &ACCEPT,
+        )
+        .unwrap();
+    builder
+        .add_task(task_config("deny", &[], Duration::from_secs(5)), &DENY)
+        .unwrap();
+    builder
+        .add_task(task_config("accept", &[], Duration::from_secs(5)), &ACCEPT)
+        .unwrap();
+
+    let result = builder.build().run("entry".to_string()).await;
+    assert!(matches!(result, Ok(TaskResult::NoPendingTask)));
+}
+
+/// 超时由执行器计时：entry -> [deny]
+async fn timeout<E: Spawner + Sleeper + Clone + 'static>(executor: E) {
+    let started = executor.now();
+    let mut builder = ContextBuilder::new(ExecutorRuntime(executor.clone()));
+    builder
+        .add_task(
+            task_config("entry", &["deny"], Duration::from_millis(50)),
+            &ACCEPT,
+        )
+        .unwrap();
+    builder
+        .add_task(task_config("deny", &[], Duration::from_millis(50)), &DENY)
+        .unwrap();
+
+    let result = builder.build().run("entry".to_string()).await;
+    assert!(matches!(result, Err(TaskError::TaskTimeOut { .. })));
+    assert!(executor.now() - started >= Duration::from_millis(50));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn tokio_executor() {
+    parallel_recognition(TokioExecutor::new()).await;
+    timeout(TokioExecutor::new()).await;
+}
+
+#[async_std::test]
+async fn async_std_executor() {
+    parallel_recognition(AsyncStdExecutor::new()).await;
+    timeout(AsyncStdExecutor::new()).await;
+}
+
+#[test]
+fn smol_executor() {
+    smol::block_on(async {
+        parallel_recognition(SmolExecutor::new()).await;
+        timeout(SmolExecutor::new()).await;
+    });
+}
@@ -25,6 +25,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
             template_path: "templates/login_button.png".to_string(),
             threshold: 0.8, // 匹配阈值
             roi: None,      // 全屏搜索
+            debug: None,
         },
     );
 
@@ -34,6 +35,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
             template_path: "templates/app_icon.png".to_string(),
             threshold: 0.9,              // 匹配阈值
             roi: Some([0, 0, 800, 600]), // 只在左上角区域搜索
+            debug: None,
         },
     );
 
@@ -48,11 +50,12 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
             action_name: "find_login_button".to_string(),
             next_task: vec!["find_app_icon".to_string()],
             interrupt_task: vec![],
+            guards: Default::default(),
             timeout: Duration::from_secs(30),
             max_retry: 5,
         },
         &find_button_action,
-    );
+    )?;
 
     // 添加任务：查找应用图标
     builder.add_task(
@@ -61,11 +64,12 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
             action_name: "find_app_icon".to_string(),
             next_task: vec![],
             interrupt_task: vec![],
+            guards: Default::default(),
             timeout: Duration::from_secs(30),
             max_retry: 5,
         },
         &find_icon_action,
-    );
+    )?;
 
     // 5. 运行任务
     println!("5. 运行任务...\n");